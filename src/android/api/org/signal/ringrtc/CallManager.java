@@ -1554,7 +1554,19 @@ public class CallManager {
     RECEIVED_OFFER_WITH_GLARE,
 
     /** Received an offer on a linked device from one that doesn't support multi-ring. */
-    IGNORE_CALLS_FROM_NON_MULTIRING_CALLERS;
+    IGNORE_CALLS_FROM_NON_MULTIRING_CALLERS,
+
+    /** The remote peer has put the call on hold. */
+    REMOTE_HOLD_ENABLE,
+
+    /** The remote peer has taken the call off hold. */
+    REMOTE_HOLD_DISABLE,
+
+    /** The remote peer moved the call to another of their devices. */
+    REMOTE_TRANSFERRED,
+
+    /** The call ended because another of the user's devices took it over. */
+    ENDED_TRANSFERRED_TO_ANOTHER_DEVICE;
 
     @CalledByNative
     static CallEvent fromNativeIndex(int nativeIndex) {
@@ -1601,7 +1613,10 @@ public class CallManager {
     BUSY,
 
     /** Call needed permission on a different device. */
-    NEED_PERMISSION;
+    NEED_PERMISSION,
+
+    /** Call moved to a different device of the same user. */
+    TRANSFERRED;
 
     @CalledByNative
     static HangupType fromNativeIndex(int nativeIndex) {
@@ -68,6 +68,14 @@ public enum CallManagerEvent: Int32 {
     case receivedOfferWithGlare
     /// Received an offer on a linked device from one that doesn't support multi-ring.
     case ignoreCallsFromNonMultiringCallers
+    /// The remote side has put the call on hold.
+    case remoteHoldEnable
+    /// The remote side has taken the call off hold.
+    case remoteHoldDisable
+    /// The remote side moved the call to another of their devices.
+    case remoteTransferred
+    /// The call ended because another of the user's devices took it over.
+    case endedTransferredToAnotherDevice
 }
 
 // In sync with WebRTC's PeerConnection.AdapterType.
@@ -138,6 +146,8 @@ public enum HangupType: Int32 {
     case busy = 3
     /// Call needed permission on a different device.
     case needPermission = 4
+    /// Call moved to a different device of the same user.
+    case transferred = 5
 }
 
 public enum CallMessageUrgency: Int32 {
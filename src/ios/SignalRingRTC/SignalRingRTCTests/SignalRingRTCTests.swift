@@ -297,6 +297,15 @@ final class TestDelegate: CallManagerDelegate {
         case .ignoreCallsFromNonMultiringCallers:
             Logger.debug("TestDelegate:ignoreCallsFromNonMultiringCallers")
             eventIgnoreCallsFromNonMultiringCallers = true
+
+        case .remoteHoldEnable:
+            Logger.debug("TestDelegate:remoteHoldEnable")
+        case .remoteHoldDisable:
+            Logger.debug("TestDelegate:remoteHoldDisable")
+        case .remoteTransferred:
+            Logger.debug("TestDelegate:remoteTransferred")
+        case .endedTransferredToAnotherDevice:
+            Logger.debug("TestDelegate:endedTransferredToAnotherDevice")
         }
     }
 
@@ -468,6 +477,8 @@ final class TestDelegate: CallManagerDelegate {
                 case .needPermission:
                     self.shouldSendHangupNeedPermissionInvoked = true
                     self.hangupDeviceId = deviceId
+                case .transferred:
+                    self.hangupDeviceId = deviceId
                 }
 
                 if !self.doFailSendHangup {
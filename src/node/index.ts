@@ -15,7 +15,9 @@ export {
   CallState,
   CallingMessage,
   ConnectionState,
+  ControlRequest,
   DeviceId,
+  EscalationUpdate,
   GroupCall,
   GroupCallEndReason,
   GroupCallObserver,
@@ -24,13 +26,21 @@ export {
   HangupType,
   HttpMethod,
   JoinState,
+  KeyRotationMetrics,
+  KeyRotationPolicy,
+  Layout,
+  LayoutConfig,
   LocalDeviceState,
   OfferType,
   OpaqueMessage,
   PeekInfo,
+  PendingDevice,
+  PinKind,
+  RecipientRingStatus,
   RemoteDeviceState,
   RingCancelReason,
   RingRTCType,
+  RingStatus,
   RingUpdate,
   UserId,
   VideoCapturer,
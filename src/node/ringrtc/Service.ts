@@ -43,8 +43,11 @@ class NativeCallManager {
 // Mirror methods onto NativeCallManager.
 // This is done through direct assignment rather than wrapper methods to avoid indirection.
 (NativeCallManager.prototype as any).setSelfUuid = Native.cm_setSelfUuid;
+(NativeCallManager.prototype as any).setRingStorePath =
+  Native.cm_setRingStorePath;
 (NativeCallManager.prototype as any).createOutgoingCall =
   Native.cm_createOutgoingCall;
+(NativeCallManager.prototype as any).takeOverCall = Native.cm_takeOverCall;
 (NativeCallManager.prototype as any).proceed = Native.cm_proceed;
 (NativeCallManager.prototype as any).accept = Native.cm_accept;
 (NativeCallManager.prototype as any).ignore = Native.cm_ignore;
@@ -84,8 +87,15 @@ class NativeCallManager {
   Native.cm_createGroupCallClient;
 (NativeCallManager.prototype as any).deleteGroupCallClient =
   Native.cm_deleteGroupCallClient;
+(NativeCallManager.prototype as any).requestEscalation =
+  Native.cm_requestEscalation;
+(NativeCallManager.prototype as any).acceptEscalation =
+  Native.cm_acceptEscalation;
+(NativeCallManager.prototype as any).declineEscalation =
+  Native.cm_declineEscalation;
 (NativeCallManager.prototype as any).connect = Native.cm_connect;
 (NativeCallManager.prototype as any).join = Native.cm_join;
+(NativeCallManager.prototype as any).resume = Native.cm_resume;
 (NativeCallManager.prototype as any).leave = Native.cm_leave;
 (NativeCallManager.prototype as any).disconnect = Native.cm_disconnect;
 (NativeCallManager.prototype as any).groupRing = Native.cm_groupRing;
@@ -96,6 +106,30 @@ class NativeCallManager {
 (NativeCallManager.prototype as any).setOutgoingGroupCallVideoIsScreenShare =
   Native.cm_setOutgoingGroupCallVideoIsScreenShare;
 (NativeCallManager.prototype as any).setPresenting = Native.cm_setPresenting;
+(NativeCallManager.prototype as any).setHandRaised = Native.cm_setHandRaised;
+(NativeCallManager.prototype as any).sendReaction = Native.cm_sendReaction;
+(NativeCallManager.prototype as any).sendMessage = Native.cm_sendMessage;
+(NativeCallManager.prototype as any).sendControlRequest =
+  Native.cm_sendControlRequest;
+(NativeCallManager.prototype as any).approvePendingDevice =
+  Native.cm_approvePendingDevice;
+(NativeCallManager.prototype as any).denyPendingDevice =
+  Native.cm_denyPendingDevice;
+(NativeCallManager.prototype as any).setAudioLevelsConfig =
+  Native.cm_setAudioLevelsConfig;
+(NativeCallManager.prototype as any).setResumptionKey =
+  Native.cm_setResumptionKey;
+(NativeCallManager.prototype as any).setLayoutConfig =
+  Native.cm_setLayoutConfig;
+(NativeCallManager.prototype as any).setPinned = Native.cm_setPinned;
+(NativeCallManager.prototype as any).setWaitForSlotWhenFull =
+  Native.cm_setWaitForSlotWhenFull;
+(NativeCallManager.prototype as any).setKeyRotationPolicy =
+  Native.cm_setKeyRotationPolicy;
+(NativeCallManager.prototype as any).rotateMediaSendKey =
+  Native.cm_rotateMediaSendKey;
+(NativeCallManager.prototype as any).getKeyRotationMetrics =
+  Native.cm_getKeyRotationMetrics;
 (NativeCallManager.prototype as any).resendMediaKeys =
   Native.cm_resendMediaKeys;
 (NativeCallManager.prototype as any).setBandwidthMode =
@@ -129,6 +163,12 @@ export class PeekInfo {
   }
 }
 
+// A device waiting in the lobby for a joined device to approve it.
+export interface PendingDevice {
+  demuxId: number; // UInt32
+  userId?: GroupCallUserId;
+}
+
 // In sync with WebRTC's PeerConnection.AdapterType.
 // Despite how it looks, this is not an option set.
 // A network adapter type can only be one of the listed values.
@@ -241,6 +281,9 @@ export class RingRTCType {
         update: RingUpdate
       ) => void)
     | null = null;
+  handleEscalationUpdate:
+    | ((callId: CallId, groupId: Buffer, update: EscalationUpdate) => void)
+    | null = null;
 
   constructor() {
     this.callManager = new NativeCallManager() as unknown as CallManager;
@@ -268,6 +311,16 @@ export class RingRTCType {
     this.callManager.setSelfUuid(uuid)
   }
 
+  // Called by UX
+  // Keeps outstanding group rings in the given file so that they survive
+  // restarts, and so that rings replayed after a restart don't ring again.
+  // Rings restored from the file are reported again through
+  // handleGroupCallRingUpdate.  Should be called before any call messages are
+  // received.
+  setRingStorePath(path: string): void {
+    this.callManager.setRingStorePath(path);
+  }
+
   // Called by UX
   startOutgoingCall(
     remoteUserId: UserId,
@@ -297,6 +350,39 @@ export class RingRTCType {
     return call;
   }
 
+  // Called by UX
+  // Moves a call that another of the user's devices is in to this device.
+  // The call is connected when its state becomes Accepted, and the other
+  // device's call ends with TransferredToAnotherDevice.
+  takeOverCall(
+    remoteUserId: UserId,
+    callId: CallId,
+    isVideoCall: boolean,
+    localDeviceId: DeviceId,
+    settings: CallSettings
+  ): Call {
+    this.callManager.takeOverCall(
+      remoteUserId,
+      callId,
+      isVideoCall,
+      localDeviceId
+    );
+    const isIncoming = false;
+    const call = new Call(
+      this.callManager,
+      remoteUserId,
+      callId,
+      isIncoming,
+      isVideoCall,
+      settings,
+      CallState.Prering
+    );
+    this._call = call;
+    call.outgoingAudioEnabled = true;
+    call.outgoingVideoEnabled = isVideoCall;
+    return call;
+  }
+
   // Called by UX
   cancelGroupRing(groupId: GroupId, ringId: bigint, reason: RingCancelReason | null): void {
     silly_deadlock_protection(() => {
@@ -453,6 +539,30 @@ export class RingRTCType {
     }
   }
 
+  onRemoteHold(remoteUserId: UserId, held: boolean): void {
+    const call = this._call;
+    if (!call || call.remoteUserId !== remoteUserId) {
+      return;
+    }
+
+    call.remoteHeld = held;
+    if (call.handleRemoteHold) {
+      call.handleRemoteHold();
+    }
+  }
+
+  // Called by Rust
+  onRemoteTransferred(remoteUserId: UserId): void {
+    const call = this._call;
+    if (!call || call.remoteUserId !== remoteUserId) {
+      return;
+    }
+
+    if (call.handleRemoteTransferred) {
+      call.handleRemoteTransferred();
+    }
+  }
+
   onNetworkRouteChanged(remoteUserId: UserId, localNetworkAdapterType: NetworkAdapterType): void {
     const call = this._call;
     if (!call || call.remoteUserId !== remoteUserId) {
@@ -681,6 +791,86 @@ export class RingRTCType {
     return groupCall;
   }
 
+  // Called by UX
+  // Asks the other user of the active direct call to move to the group call
+  // for groupId.  The group call connects right away, and joins once the other
+  // user accepts; the direct call is hung up once the other user's media
+  // arrives through the group call.  Progress comes back via
+  // handleEscalationUpdate.
+  requestEscalation(
+    call: Call,
+    remoteUuid: Buffer,
+    groupId: Buffer,
+    sfuUrl: string,
+    observer: GroupCallObserver
+  ): GroupCall | undefined {
+    let clientId;
+    try {
+      clientId = this.callManager.requestEscalation(
+        call.callId,
+        remoteUuid,
+        groupId,
+        sfuUrl,
+        !call.outgoingAudioEnabled
+      );
+    } catch (e) {
+      console.log(`requestEscalation(): ${e}`);
+      return undefined;
+    }
+    return this.addEscalatedGroupCall(clientId, groupId, sfuUrl, observer);
+  }
+
+  // Called by UX
+  // Accepts an EscalationUpdate.Requested, joining the group call for groupId.
+  acceptEscalation(
+    call: Call,
+    groupId: Buffer,
+    sfuUrl: string,
+    observer: GroupCallObserver
+  ): GroupCall | undefined {
+    let clientId;
+    try {
+      clientId = this.callManager.acceptEscalation(
+        call.callId,
+        sfuUrl,
+        !call.outgoingAudioEnabled
+      );
+    } catch (e) {
+      console.log(`acceptEscalation(): ${e}`);
+      return undefined;
+    }
+    return this.addEscalatedGroupCall(clientId, groupId, sfuUrl, observer);
+  }
+
+  // Called by UX
+  // Declines an EscalationUpdate.Requested; the direct call goes on as before.
+  declineEscalation(call: Call): void {
+    try {
+      this.callManager.declineEscalation(call.callId);
+    } catch (e) {
+      console.log(`declineEscalation(): ${e}`);
+    }
+  }
+
+  private addEscalatedGroupCall(
+    clientId: GroupCallClientId,
+    groupId: Buffer,
+    sfuUrl: string,
+    observer: GroupCallObserver
+  ): GroupCall {
+    const groupCall = new GroupCall(
+      this.callManager,
+      groupId,
+      sfuUrl,
+      observer,
+      clientId
+    );
+
+    this._groupCallByClientId.set(groupCall.clientId, groupCall);
+
+    return groupCall;
+  }
+
   // Called by UX
   // Returns a list of user IDs
   peekGroupCall(
@@ -826,6 +1016,207 @@ export class RingRTCType {
     });
   }
 
+  // Called by Rust
+  handleReaction(
+    clientId: GroupCallClientId,
+    demuxId: number,
+    value: string
+  ): void {
+    silly_deadlock_protection(() => {
+      let groupCall = this._groupCallByClientId.get(clientId);
+      if (!groupCall) {
+        this.onLogMessage(
+          CallLogLevel.Error,
+          'Service.ts',
+          0,
+          'handleReaction(): GroupCall not found in map!'
+        );
+        return;
+      }
+
+      groupCall.handleReaction(demuxId, value);
+    });
+  }
+
+  // Called by Rust
+  handleMessageReceived(
+    clientId: GroupCallClientId,
+    demuxId: number,
+    messageId: string,
+    text: string
+  ): void {
+    silly_deadlock_protection(() => {
+      let groupCall = this._groupCallByClientId.get(clientId);
+      if (!groupCall) {
+        this.onLogMessage(
+          CallLogLevel.Error,
+          'Service.ts',
+          0,
+          'handleMessageReceived(): GroupCall not found in map!'
+        );
+        return;
+      }
+
+      groupCall.handleMessageReceived(demuxId, messageId, text);
+    });
+  }
+
+  // Called by Rust
+  handleControlRequest(
+    clientId: GroupCallClientId,
+    senderDemuxId: number,
+    request: ControlRequest
+  ): void {
+    silly_deadlock_protection(() => {
+      let groupCall = this._groupCallByClientId.get(clientId);
+      if (!groupCall) {
+        this.onLogMessage(
+          CallLogLevel.Error,
+          'Service.ts',
+          0,
+          'handleControlRequest(): GroupCall not found in map!'
+        );
+        return;
+      }
+
+      groupCall.handleControlRequest(senderDemuxId, request);
+    });
+  }
+
+  // Called by Rust
+  handleAudioLevels(
+    clientId: GroupCallClientId,
+    capturedLevel: number,
+    receivedLevels: Array<ReceivedAudioLevel>
+  ): void {
+    silly_deadlock_protection(() => {
+      let groupCall = this._groupCallByClientId.get(clientId);
+      if (!groupCall) {
+        this.onLogMessage(
+          CallLogLevel.Error,
+          'Service.ts',
+          0,
+          'handleAudioLevels(): GroupCall not found in map!'
+        );
+        return;
+      }
+
+      groupCall.handleAudioLevels(capturedLevel, receivedLevels);
+    });
+  }
+
+  // Called by Rust
+  handleResumptionSnapshot(
+    clientId: GroupCallClientId,
+    snapshot: Buffer
+  ): void {
+    silly_deadlock_protection(() => {
+      let groupCall = this._groupCallByClientId.get(clientId);
+      if (!groupCall) {
+        this.onLogMessage(
+          CallLogLevel.Error,
+          'Service.ts',
+          0,
+          'handleResumptionSnapshot(): GroupCall not found in map!'
+        );
+        return;
+      }
+
+      groupCall.handleResumptionSnapshot(snapshot);
+    });
+  }
+
+  // Called by Rust
+  handleLayoutChanged(clientId: GroupCallClientId, layout: Layout): void {
+    silly_deadlock_protection(() => {
+      let groupCall = this._groupCallByClientId.get(clientId);
+      if (!groupCall) {
+        this.onLogMessage(
+          CallLogLevel.Error,
+          'Service.ts',
+          0,
+          'handleLayoutChanged(): GroupCall not found in map!'
+        );
+        return;
+      }
+
+      groupCall.handleLayoutChanged(layout);
+    });
+  }
+
+  // Called by Rust
+  handlePinnedVideosNotForwarded(
+    clientId: GroupCallClientId,
+    demuxIds: Array<number>
+  ): void {
+    silly_deadlock_protection(() => {
+      let groupCall = this._groupCallByClientId.get(clientId);
+      if (!groupCall) {
+        this.onLogMessage(
+          CallLogLevel.Error,
+          'Service.ts',
+          0,
+          'handlePinnedVideosNotForwarded(): GroupCall not found in map!'
+        );
+        return;
+      }
+
+      groupCall.handlePinnedVideosNotForwarded(demuxIds);
+    });
+  }
+
+  // Called by Rust
+  handleRingStatusesChanged(
+    clientId: GroupCallClientId,
+    statuses: Array<{
+      userId: GroupCallUserId;
+      ringId: string;
+      status: RingStatus;
+    }>
+  ): void {
+    silly_deadlock_protection(() => {
+      let groupCall = this._groupCallByClientId.get(clientId);
+      if (!groupCall) {
+        this.onLogMessage(
+          CallLogLevel.Error,
+          'Service.ts',
+          0,
+          'handleRingStatusesChanged(): GroupCall not found in map!'
+        );
+        return;
+      }
+
+      groupCall.handleRingStatusesChanged(
+        statuses.map(({ userId, ringId, status }) => ({
+          userId,
+          ringId: BigInt(ringId),
+          status,
+        }))
+      );
+    });
+  }
+
+  // Called by Rust
+  handlePendingDevicesChanged(
+    clientId: GroupCallClientId,
+    pendingDevices: Array<PendingDevice>
+  ): void {
+    silly_deadlock_protection(() => {
+      let groupCall = this._groupCallByClientId.get(clientId);
+      if (!groupCall) {
+        this.onLogMessage(
+          CallLogLevel.Error,
+          'Service.ts',
+          0,
+          'handlePendingDevicesChanged(): GroupCall not found in map!'
+        );
+        return;
+      }
+
+      groupCall.handlePendingDevicesChanged(pendingDevices);
+    });
+  }
+
   // Called by Rust
   handlePeekChanged(clientId: GroupCallClientId, info: PeekInfo): void {
     silly_deadlock_protection(() => {
@@ -897,6 +1288,21 @@ export class RingRTCType {
     });
   }
 
+  // Called by Rust
+  escalationUpdate(
+    callId: CallId,
+    groupId: GroupId,
+    update: EscalationUpdate
+  ): void {
+    silly_deadlock_protection(() => {
+      if (this.handleEscalationUpdate) {
+        this.handleEscalationUpdate(callId, groupId, update);
+      } else {
+        console.log('RingRTC.handleEscalationUpdate is not set!');
+      }
+    });
+  }
+
   // Called by Rust
   onLogMessage(
     level: number,
@@ -1303,6 +1709,8 @@ export class Call {
   private _outgoingVideoIsScreenShare: boolean = false;
   private _remoteVideoEnabled: boolean = false;
   remoteSharingScreen: boolean = false;
+  // Whether the remote user has put this call on hold to take another call.
+  remoteHeld: boolean = false;
   networkRoute: NetworkRoute = new NetworkRoute();
   private _videoCapturer: VideoCapturer | null = null;
   private _videoRenderer: VideoRenderer | null = null;
@@ -1312,6 +1720,9 @@ export class Call {
   handleStateChanged?: () => void;
   handleRemoteVideoEnabled?: () => void;
   handleRemoteSharingScreen?: () => void;
+  handleRemoteHold?: () => void;
+  // The remote user moved the call to another of their devices.
+  handleRemoteTransferred?: () => void;
   handleNetworkRouteChanged?: () => void;
 
   // This callback should be set by the VideoCapturer,
@@ -1550,6 +1961,10 @@ export enum JoinState {
   NotJoined = 0,
   Joining = 1,
   Joined = 2,
+  // Waiting in the lobby for a joined device to approve joining
+  Pending = 3,
+  // Waiting for a device to leave a full call (see setWaitForSlotWhenFull)
+  WaitingForSlot = 4,
 }
 
 // If not ended purposely by the user, gives the reason why a group call ended.
@@ -1572,6 +1987,21 @@ export enum GroupCallEndReason {
   IceFailedAfterConnected = 12,
   ServerChangedDemuxId = 13,
   HasMaxDevices = 14,
+  DeniedRequestToJoinCall = 15,
+}
+
+// Something a device of the call creator can ask another device to do.
+export enum ControlRequest {
+  Mute = 0,
+  LowerHand = 1,
+  Remove = 2,
+}
+
+// Pinned videos are forwarded by the SFU before the rest, and the spotlight
+// (only one at a time) before everything.
+export enum PinKind {
+  Pinned = 0,
+  Spotlight = 1,
 }
 
 export enum CallMessageUrgency {
@@ -1596,6 +2026,22 @@ export enum RingUpdate {
   CancelledByRinger,
 }
 
+// Progress of moving a direct call to a group call.
+export enum EscalationUpdate {
+  /// The other user asked to move to a group call.
+  /// Answer with acceptEscalation or declineEscalation.
+  Requested = 0,
+  /// The other user accepted, so the group call is being joined.
+  Accepted,
+  /// The other user declined, so the direct call goes on as before.
+  Declined,
+  /// The other user's media is arriving through the group call, so the
+  /// direct call was hung up.
+  Completed,
+  /// The direct call or the group call ended before the move was complete.
+  Failed,
+}
+
 // HTTP request methods.
 export enum HttpMethod {
   Get = 0,
@@ -1612,7 +2058,11 @@ export class LocalDeviceState {
   videoMuted: boolean;
   presenting: boolean;
   sharingScreen: boolean;
+  handRaised: boolean;
   networkRoute: NetworkRoute;
+  // From 0 (silence) to 32767 (the loudest possible); only updated once enabled
+  // with GroupCall.setAudioLevelsConfig.
+  audioLevel: number;
 
   constructor() {
     this.connectionState = ConnectionState.NotConnected;
@@ -1622,7 +2072,9 @@ export class LocalDeviceState {
     this.videoMuted = true;
     this.presenting = false;
     this.sharingScreen = false;
+    this.handRaised = false;
     this.networkRoute = new NetworkRoute();
+    this.audioLevel = 0;
   }
 }
 
@@ -1640,6 +2092,10 @@ export class RemoteDeviceState {
   addedTime: string | undefined; // unix millis (to be converted to a numeric type)
   speakerTime: string | undefined; // unix millis; 0 if they've never spoken (to be converted to a numeric type)
   forwardingVideo: boolean | undefined;
+  handRaised: boolean | undefined;
+  // From 0 (silence) to 32767 (the loudest possible); only updated once enabled
+  // with GroupCall.setAudioLevelsConfig.
+  audioLevel: number | undefined;
 
   constructor(demuxId: number, userId: Buffer, mediaKeysReceived: boolean) {
     this.demuxId = demuxId;
@@ -1648,6 +2104,28 @@ export class RemoteDeviceState {
   }
 }
 
+export interface ReceivedAudioLevel {
+  demuxId: number; // UInt32
+  level: number; // UInt16
+}
+
+// The media send key is always rotated when someone leaves.  These add
+// rotations even when no one does.
+export interface KeyRotationPolicy {
+  // How long a new key is sent out before it is used.  Defaults to 3000.
+  applyDelayMillis?: number;
+  maxKeyAgeMillis?: number;
+  maxFramesPerKey?: number;
+}
+
+export interface KeyRotationMetrics {
+  rotations: number;
+  // How long someone who left could still decrypt media, for the latest
+  // rotation caused by someone leaving and the largest one so far.
+  lastExposureWindowMillis?: number;
+  maxExposureWindowMillis?: number;
+}
+
 // Used to communicate the group membership to RingRTC for a group call.
 export class GroupMemberInfo {
   userId: Buffer;
@@ -1680,12 +2158,76 @@ export class VideoRequest {
   }
 }
 
+// Passed to GroupCall.setLayoutConfig to have RingRTC order the remote devices
+// into pages of a grid and request their video to match.
+export interface LayoutConfig {
+  viewportWidth: number; // UInt16
+  viewportHeight: number; // UInt16
+  tilesPerPage: number;
+  pageIndex: number;
+  // These go first, in this order, if they are in the call.
+  pinnedDemuxIds: Array<number>;
+}
+
+export interface Layout {
+  // Every remote device, pinned ones first.  The rest keep their places, except
+  // that a speaker who isn't on the first page swaps with the least recent
+  // speaker on it.
+  demuxIds: Array<number>;
+  // Clamped to the last page.
+  pageIndex: number;
+  pageCount: number;
+  tilesPerPage: number;
+  columns: number;
+  rows: number;
+  tileWidth: number;
+  tileHeight: number;
+}
+
 export interface GroupCallObserver {
   requestMembershipProof(groupCall: GroupCall): void;
   requestGroupMembers(groupCall: GroupCall): void;
   onLocalDeviceStateChanged(groupCall: GroupCall): void;
   onRemoteDeviceStatesChanged(groupCall: GroupCall): void;
   onPeekChanged(groupCall: GroupCall): void;
+  // Only called while joined; see GroupCall.getPendingDevices.
+  onPendingDevicesChanged(groupCall: GroupCall): void;
+  // Reactions are delivered once each and are not part of the RemoteDeviceState.
+  onReaction(groupCall: GroupCall, demuxId: number, value: string): void;
+  // Chat messages are end-to-end encrypted and delivered once each, but
+  // messages from the same device may arrive out of order.
+  onMessageReceived(
+    groupCall: GroupCall,
+    demuxId: number,
+    messageId: string, // UInt64
+    text: string
+  ): void;
+  // Only requests from a device of the call creator are passed on, and only
+  // with a MAC from that device's control key, which it sent with its media
+  // key.  Honor them with setOutgoingAudioMuted(true), setHandRaised(false),
+  // or leave().
+  onControlRequest(
+    groupCall: GroupCall,
+    senderDemuxId: number,
+    request: ControlRequest
+  ): void;
+  // Audio levels are updated in the LocalDeviceState and RemoteDeviceStates.
+  onAudioLevels(groupCall: GroupCall): void;
+  // Only called once enabled with GroupCall.setResumptionKey.  Keep only the
+  // latest snapshot, and delete it once the call is left or ended.
+  onResumptionSnapshot(groupCall: GroupCall, snapshot: Buffer): void;
+  // Only called once enabled with GroupCall.setLayoutConfig.  The devices on
+  // the page are the tilesPerPage starting at pageIndex * tilesPerPage.
+  onLayoutChanged(groupCall: GroupCall, layout: Layout): void;
+  // The pinned devices whose video is requested but not being forwarded,
+  // whenever that changes, including when none are left.
+  onPinnedVideosNotForwarded(
+    groupCall: GroupCall,
+    demuxIds: Array<number>
+  ): void;
+  // Called whenever the status of any recipient of GroupCall.ringAll or
+  // GroupCall.ring changes; see GroupCall.getRingStatuses.
+  onRingStatusesChanged(groupCall: GroupCall): void;
   onEnded(groupCall: GroupCall, reason: GroupCallEndReason): void;
 }
 
@@ -1702,20 +2244,30 @@ export class GroupCall {
   private _remoteDeviceStates: Array<RemoteDeviceState> | undefined;
 
   private _peekInfo: PeekInfo | undefined; // uuid
+  private _pendingDevices: Array<PendingDevice>;
+  private _ringStatuses: Array<RecipientRingStatus>;
 
   // Called by UI via RingRTC object
   constructor(
     callManager: CallManager,
     groupId: Buffer,
     sfuUrl: string,
-    observer: GroupCallObserver
+    observer: GroupCallObserver,
+    // Set if the client was already created, as when moving a direct call
+    // to a group call.
+    clientId?: GroupCallClientId
   ) {
     this._callManager = callManager;
     this._observer = observer;
 
     this._localDeviceState = new LocalDeviceState();
+    this._pendingDevices = [];
+    this._ringStatuses = [];
 
-    this._clientId = this._callManager.createGroupCallClient(groupId, sfuUrl);
+    this._clientId =
+      clientId !== undefined
+        ? clientId
+        : this._callManager.createGroupCallClient(groupId, sfuUrl);
   }
 
   // Called by UI
@@ -1728,6 +2280,15 @@ export class GroupCall {
     this._callManager.join(this._clientId);
   }
 
+  // Called by UI
+  // Like join(), but rejoins as the same device with a snapshot from
+  // onResumptionSnapshot saved before the app restarted, so that others don't
+  // see this device leave and join again.  Call setResumptionKey first.  If the
+  // snapshot can't be used, this joins as a new device.
+  resume(snapshot: Buffer): void {
+    this._callManager.resume(this._clientId, snapshot);
+  }
+
   // Called by UI
   leave(): void {
     this._callManager.leave(this._clientId);
@@ -1753,6 +2314,21 @@ export class GroupCall {
     return this._peekInfo;
   }
 
+  // Called by UI
+  // Based on the last peek, so it may be out of date.
+  isFull(): boolean {
+    const maxDevices = this._peekInfo?.maxDevices;
+    return (
+      maxDevices !== undefined &&
+      (this._peekInfo?.deviceCount ?? 0) >= maxDevices
+    );
+  }
+
+  // Called by UI
+  getPendingDevices(): Array<PendingDevice> {
+    return this._pendingDevices;
+  }
+
   // Called by UI
   setOutgoingAudioMuted(muted: boolean): void {
     this._localDeviceState.audioMuted = muted;
@@ -1784,11 +2360,108 @@ export class GroupCall {
     this._observer.onLocalDeviceStateChanged(this);
   }
 
+  // Called by UI
+  setHandRaised(raised: boolean): void {
+    this._localDeviceState.handRaised = raised;
+    this._callManager.setHandRaised(this._clientId, raised);
+    this._observer.onLocalDeviceStateChanged(this);
+  }
+
+  // Called by UI
+  sendReaction(value: string): void {
+    this._callManager.sendReaction(this._clientId, value);
+  }
+
+  // Called by UI
+  sendMessage(text: string): void {
+    this._callManager.sendMessage(this._clientId, text);
+  }
+
+  // Called by UI
+  // Only works for the call creator.
+  sendControlRequest(targetDemuxId: number, request: ControlRequest): void {
+    this._callManager.sendControlRequest(
+      this._clientId,
+      targetDemuxId,
+      request
+    );
+  }
+
+  // Called by UI
+  // Lets a device waiting in the lobby into the call.
+  approvePendingDevice(demuxId: number): void {
+    this._callManager.approvePendingDevice(this._clientId, demuxId);
+  }
+
+  // Called by UI
+  // Turns away a device waiting in the lobby.
+  denyPendingDevice(demuxId: number): void {
+    this._callManager.denyPendingDevice(this._clientId, demuxId);
+  }
+
+  // Called by UI
+  // Audio levels are passed on every intervalMillis once enabled, and smoothing
+  // (from 0 up to, but not including, 1) keeps them from jumping around as much.
+  // An undefined intervalMillis disables them again.
+  setAudioLevelsConfig(
+    intervalMillis: number | undefined,
+    smoothing: number = 0.5
+  ): void {
+    this._callManager.setAudioLevelsConfig(
+      this._clientId,
+      intervalMillis,
+      smoothing
+    );
+  }
+
+  // Called by UI
+  // Once set, encrypted snapshots for resume() are passed to
+  // onResumptionSnapshot.  The key must be 32 bytes and should be stored more
+  // safely than the snapshots.  An undefined key stops the snapshots.
+  setResumptionKey(key: Buffer | undefined): void {
+    this._callManager.setResumptionKey(this._clientId, key);
+  }
+
+  // Called by UI
+  setKeyRotationPolicy(policy: KeyRotationPolicy): void {
+    this._callManager.setKeyRotationPolicy(
+      this._clientId,
+      policy.applyDelayMillis ?? 3000,
+      policy.maxKeyAgeMillis,
+      policy.maxFramesPerKey
+    );
+  }
+
+  // Called by UI
+  // Replaces the media send key as if someone had left the call.
+  rotateMediaSendKey(): void {
+    this._callManager.rotateMediaSendKey(this._clientId);
+  }
+
+  // Called by UI
+  getKeyRotationMetrics(): KeyRotationMetrics | undefined {
+    return this._callManager.getKeyRotationMetrics(this._clientId);
+  }
+
   // Called by UI
   ringAll(): void {
     this._callManager.groupRing(this._clientId, undefined);
   }
 
+  // Called by UI
+  // Rings one member again, such as one who hasn't responded to ringAll.
+  // Members who accepted or declined aren't rung again.
+  ring(recipient: GroupCallUserId): void {
+    this._callManager.groupRing(this._clientId, recipient);
+  }
+
+  // Called by UI
+  // Only includes the group members set with setGroupMembers and those who
+  // responded.
+  getRingStatuses(): Array<RecipientRingStatus> {
+    return this._ringStatuses;
+  }
+
   // Called by UI
   resendMediaKeys(): void {
     this._callManager.resendMediaKeys(this._clientId);
@@ -1804,6 +2477,36 @@ export class GroupCall {
     this._callManager.requestVideo(this._clientId, resolutions);
   }
 
+  // Called by UI
+  // Once set, video is requested for the layout, replacing requestVideo, and
+  // it is updated as devices come and go and speakers change.  An undefined
+  // config stops updating it.
+  setLayoutConfig(config: LayoutConfig | undefined): void {
+    this._callManager.setLayoutConfig(
+      this._clientId,
+      config?.viewportWidth,
+      config?.viewportHeight ?? 0,
+      config?.tilesPerPage ?? 0,
+      config?.pageIndex ?? 0,
+      config?.pinnedDemuxIds ?? []
+    );
+  }
+
+  // Called by UI
+  // An undefined pin unpins the device.  Pinned devices also go first in the
+  // layout (see setLayoutConfig).
+  setPinned(demuxId: number, pin: PinKind | undefined): void {
+    this._callManager.setPinned(this._clientId, demuxId, pin);
+  }
+
+  // Called by UI
+  // If set, joining a full call waits for a slot to open rather than ending
+  // with GroupCallEndReason.HasMaxDevices.  Unsetting it while waiting ends the
+  // call that way.
+  setWaitForSlotWhenFull(wait: boolean): void {
+    this._callManager.setWaitForSlotWhenFull(this._clientId, wait);
+  }
+
   // Called by UI
   setGroupMembers(members: Array<GroupMemberInfo>): void {
     this._callManager.setGroupMembers(this._clientId, members);
@@ -1834,6 +2537,10 @@ export class GroupCall {
   // Called by Rust via RingRTC object
   handleJoinStateChanged(joinState: JoinState): void {
     this._localDeviceState.joinState = joinState;
+    // Pending devices are only kept up to date while joined.
+    if (joinState !== JoinState.Joined) {
+      this._pendingDevices = [];
+    }
 
     this._observer.onLocalDeviceStateChanged(this);
   }
@@ -1855,6 +2562,7 @@ export class GroupCall {
         old => old.demuxId == noo.demuxId
       );
       noo.videoAspectRatio = old?.videoAspectRatio;
+      noo.audioLevel = old?.audioLevel;
     }
 
     this._remoteDeviceStates = remoteDeviceStates;
@@ -1862,6 +2570,54 @@ export class GroupCall {
     this._observer.onRemoteDeviceStatesChanged(this);
   }
 
+  // Called by Rust via RingRTC object
+  handleReaction(demuxId: number, value: string): void {
+    this._observer.onReaction(this, demuxId, value);
+  }
+
+  // Called by Rust via RingRTC object
+  handleMessageReceived(demuxId: number, messageId: string, text: string): void {
+    this._observer.onMessageReceived(this, demuxId, messageId, text);
+  }
+
+  // Called by Rust via RingRTC object
+  handleControlRequest(senderDemuxId: number, request: ControlRequest): void {
+    this._observer.onControlRequest(this, senderDemuxId, request);
+  }
+
+  // Called by Rust via RingRTC object
+  handleAudioLevels(
+    capturedLevel: number,
+    receivedLevels: Array<ReceivedAudioLevel>
+  ): void {
+    this._localDeviceState.audioLevel = capturedLevel;
+    for (const received of receivedLevels) {
+      const remoteDeviceState = this._remoteDeviceStates?.find(
+        state => state.demuxId === received.demuxId
+      );
+      if (remoteDeviceState) {
+        remoteDeviceState.audioLevel = received.level;
+      }
+    }
+
+    this._observer.onAudioLevels(this);
+  }
+
+  // Called by Rust via RingRTC object
+  handleResumptionSnapshot(snapshot: Buffer): void {
+    this._observer.onResumptionSnapshot(this, snapshot);
+  }
+
+  // Called by Rust via RingRTC object
+  handleLayoutChanged(layout: Layout): void {
+    this._observer.onLayoutChanged(this, layout);
+  }
+
+  // Called by Rust via RingRTC object
+  handlePinnedVideosNotForwarded(demuxIds: Array<number>): void {
+    this._observer.onPinnedVideosNotForwarded(this, demuxIds);
+  }
+
   // Called by Rust via RingRTC object
   handlePeekChanged(info: PeekInfo): void {
     this._peekInfo = info;
@@ -1869,6 +2625,20 @@ export class GroupCall {
     this._observer.onPeekChanged(this);
   }
 
+  // Called by Rust via RingRTC object
+  handleRingStatusesChanged(statuses: Array<RecipientRingStatus>): void {
+    this._ringStatuses = statuses;
+
+    this._observer.onRingStatusesChanged(this);
+  }
+
+  // Called by Rust via RingRTC object
+  handlePendingDevicesChanged(pendingDevices: Array<PendingDevice>): void {
+    this._pendingDevices = pendingDevices;
+
+    this._observer.onPendingDevicesChanged(this);
+  }
+
   // Called by Rust via RingRTC object
   handleEnded(reason: GroupCallEndReason): void {
     this._observer.onEnded(this, reason);
@@ -2012,6 +2782,7 @@ export enum HangupType {
   Declined = 2,
   Busy = 3,
   NeedPermission = 4,
+  Transferred = 5,
 }
 
 export enum BandwidthMode {
@@ -2020,6 +2791,26 @@ export enum BandwidthMode {
   Normal = 2,
 }
 
+// Where a ring sent with GroupCall.ringAll or GroupCall.ring stands for one
+// of its recipients.
+export enum RingStatus {
+  Ringing = 0,
+  // The recipient joined the call.
+  Accepted,
+  Declined,
+  // The recipient was already in another call.
+  Busy,
+  // The recipient didn't respond in time.
+  Expired,
+}
+
+export interface RecipientRingStatus {
+  userId: GroupCallUserId;
+  // Recipients rung again with GroupCall.ring have their own ring ID.
+  ringId: bigint;
+  status: RingStatus;
+}
+
 /// Describes why a ring was cancelled.
 export enum RingCancelReason {
   /// The user explicitly clicked "Decline".
@@ -2031,11 +2822,18 @@ export enum RingCancelReason {
 export interface CallManager {
   setConfig(config: Config): void;
   setSelfUuid(uuid: Buffer): void;
+  setRingStorePath(path: string): void;
   createOutgoingCall(
     remoteUserId: UserId,
     isVideoCall: boolean,
     localDeviceId: DeviceId
   ): CallId;
+  takeOverCall(
+    remoteUserId: UserId,
+    callId: CallId,
+    isVideoCall: boolean,
+    localDeviceId: DeviceId
+  ): void;
   proceed(
     callId: CallId,
     iceServerUsername: string,
@@ -2110,8 +2908,22 @@ export interface CallManager {
 
   createGroupCallClient(groupId: Buffer, sfuUrl: string): GroupCallClientId;
   deleteGroupCallClient(clientId: GroupCallClientId): void;
+  requestEscalation(
+    callId: CallId,
+    remoteUuid: Buffer,
+    groupId: Buffer,
+    sfuUrl: string,
+    audioMuted: boolean
+  ): GroupCallClientId;
+  acceptEscalation(
+    callId: CallId,
+    sfuUrl: string,
+    audioMuted: boolean
+  ): GroupCallClientId;
+  declineEscalation(callId: CallId): void;
   connect(clientId: GroupCallClientId): void;
   join(clientId: GroupCallClientId): void;
+  resume(clientId: GroupCallClientId, snapshot: Buffer): void;
   leave(clientId: GroupCallClientId): void;
   disconnect(clientId: GroupCallClientId): void;
   setOutgoingAudioMuted(clientId: GroupCallClientId, muted: boolean): void;
@@ -2121,6 +2933,32 @@ export interface CallManager {
     clientId: GroupCallClientId,
     isScreenShare: boolean
   ): void;
+  setHandRaised(clientId: GroupCallClientId, raised: boolean): void;
+  sendReaction(clientId: GroupCallClientId, value: string): void;
+  sendMessage(clientId: GroupCallClientId, text: string): void;
+  sendControlRequest(
+    clientId: GroupCallClientId,
+    targetDemuxId: number,
+    request: ControlRequest
+  ): void;
+  approvePendingDevice(clientId: GroupCallClientId, demuxId: number): void;
+  denyPendingDevice(clientId: GroupCallClientId, demuxId: number): void;
+  setAudioLevelsConfig(
+    clientId: GroupCallClientId,
+    intervalMillis: number | undefined,
+    smoothing: number
+  ): void;
+  setResumptionKey(clientId: GroupCallClientId, key: Buffer | undefined): void;
+  setKeyRotationPolicy(
+    clientId: GroupCallClientId,
+    applyDelayMillis: number,
+    maxKeyAgeMillis: number | undefined,
+    maxFramesPerKey: number | undefined
+  ): void;
+  rotateMediaSendKey(clientId: GroupCallClientId): void;
+  getKeyRotationMetrics(
+    clientId: GroupCallClientId
+  ): KeyRotationMetrics | undefined;
   groupRing(clientId: GroupCallClientId, recipient: Buffer | undefined): void;
   resendMediaKeys(clientId: GroupCallClientId): void;
   setBandwidthMode(
@@ -2131,6 +2969,20 @@ export interface CallManager {
     clientId: GroupCallClientId,
     resolutions: Array<VideoRequest>
   ): void;
+  setLayoutConfig(
+    clientId: GroupCallClientId,
+    viewportWidth: number | undefined,
+    viewportHeight: number,
+    tilesPerPage: number,
+    pageIndex: number,
+    pinnedDemuxIds: Array<number>
+  ): void;
+  setPinned(
+    clientId: GroupCallClientId,
+    demuxId: number,
+    pin: PinKind | undefined
+  ): void;
+  setWaitForSlotWhenFull(clientId: GroupCallClientId, wait: boolean): void;
   setGroupMembers(
     clientId: GroupCallClientId,
     members: Array<GroupMemberInfo>
@@ -2168,6 +3020,8 @@ export interface CallManagerCallbacks {
   onCallEnded(remoteUserId: UserId, endedReason: CallEndedReason): void;
   onRemoteVideoEnabled(remoteUserId: UserId, enabled: boolean): void;
   onRemoteSharingScreen(remoteUserId: UserId, enabled: boolean): void;
+  onRemoteHold(remoteUserId: UserId, held: boolean): void;
+  onRemoteTransferred(remoteUserId: UserId): void;
   onSendOffer(
     remoteUserId: UserId,
     remoteDeviceId: DeviceId,
@@ -2246,9 +3100,53 @@ export interface CallManagerCallbacks {
     clientId: GroupCallClientId,
     remoteDeviceStates: Array<RemoteDeviceState>
   ): void;
+  handleReaction(
+    clientId: GroupCallClientId,
+    demuxId: number,
+    value: string
+  ): void;
+  handleMessageReceived(
+    clientId: GroupCallClientId,
+    demuxId: number,
+    messageId: string,
+    text: string
+  ): void;
+  handleControlRequest(
+    clientId: GroupCallClientId,
+    senderDemuxId: number,
+    request: ControlRequest
+  ): void;
+  handleAudioLevels(
+    clientId: GroupCallClientId,
+    capturedLevel: number,
+    receivedLevels: Array<ReceivedAudioLevel>
+  ): void;
+  handleResumptionSnapshot(clientId: GroupCallClientId, snapshot: Buffer): void;
+  handleLayoutChanged(clientId: GroupCallClientId, layout: Layout): void;
+  handlePinnedVideosNotForwarded(
+    clientId: GroupCallClientId,
+    demuxIds: Array<number>
+  ): void;
+  handleRingStatusesChanged(
+    clientId: GroupCallClientId,
+    statuses: Array<{
+      userId: GroupCallUserId;
+      ringId: string;
+      status: RingStatus;
+    }>
+  ): void;
+  handlePendingDevicesChanged(
+    clientId: GroupCallClientId,
+    pendingDevices: Array<PendingDevice>
+  ): void;
   handlePeekChanged(clientId: GroupCallClientId, info: PeekInfo): void;
   handlePeekResponse(request_id: number, info: PeekInfo): void;
   handleEnded(clientId: GroupCallClientId, reason: GroupCallEndReason): void;
+  escalationUpdate(
+    callId: CallId,
+    groupId: Buffer,
+    update: EscalationUpdate
+  ): void;
 
   onLogMessage(
     level: number,
@@ -2284,6 +3182,7 @@ export enum CallEndedReason {
   DeclinedOnAnotherDevice = 'DeclinedOnAnotherDevice',
   BusyOnAnotherDevice = 'BusyOnAnotherDevice',
   CallerIsNotMultiring = 'CallerIsNotMultiring',
+  TransferredToAnotherDevice = 'TransferredToAnotherDevice',
 }
 
 export enum CallLogLevel {
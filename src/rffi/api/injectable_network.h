@@ -18,6 +18,7 @@ namespace rffi {
 typedef struct {
   rust_object object;
   int (*SendUdp)(rust_object, IpPort source, IpPort dest, const uint8_t*, size_t);
+  int (*SendTcp)(rust_object, IpPort source, IpPort dest, const uint8_t*, size_t);
   int (*Release)(rust_object);
 } InjectableNetworkSender;
 
@@ -40,6 +41,10 @@ class InjectableNetwork {
   virtual void RemoveInterface(const char* name) = 0;
   virtual void ReceiveUdp(
     IpPort source, IpPort dest, const uint8_t* data, size_t size) = 0;
+  // Each call is one packet sent over a TCP connection.
+  // The framing of what was sent is kept.
+  virtual void ReceiveTcp(
+    IpPort source, IpPort dest, const uint8_t* data, size_t size) = 0;
 
   // These are more for internal use, not external, which is why the types
   // aren't the external types.
@@ -49,6 +54,15 @@ class InjectableNetwork {
     const uint8_t* data,
     size_t size) = 0;
   virtual void ForgetUdp(const rtc::SocketAddress& local_address) = 0;
+  virtual int SendTcp(
+    const rtc::SocketAddress& local_address,
+    const rtc::SocketAddress& remote_address,
+    const uint8_t* data,
+    size_t size) = 0;
+  virtual void ForgetTcp(
+    const rtc::SocketAddress& local_address,
+    const rtc::SocketAddress& remote_address) = 0;
+  virtual void ForgetTcpServer(const rtc::SocketAddress& local_address) = 0;
 };
 
 std::unique_ptr<InjectableNetwork> CreateInjectableNetwork(rtc::Thread* network_thread);
@@ -74,6 +88,13 @@ RUSTEXPORT void Rust_InjectableNetwork_ReceiveUdp(
   const uint8_t* data,
   size_t size);
 
+RUSTEXPORT void Rust_InjectableNetwork_ReceiveTcp(
+  InjectableNetwork* network,
+  IpPort source,
+  IpPort dest,
+  const uint8_t* data,
+  size_t size);
+
 }  // namespace rffi
 
 }  // namespace webrtc
@@ -127,6 +127,192 @@ class InjectableUdpSocket : public rtc::AsyncPacketSocket {
   int last_error_ = 0;
 };
 
+// A connection either made with CreateClientTcpSocket or accepted by an
+// InjectableTcpServerSocket.  There's no handshake: a client socket
+// connects right away and the first packet it sends creates the
+// accepted socket on the other side.
+class InjectableTcpSocket : public rtc::AsyncPacketSocket {
+ public:
+  InjectableTcpSocket(InjectableNetwork* network,
+                      const rtc::SocketAddress& local_address,
+                      const rtc::SocketAddress& remote_address,
+                      State state)
+      : network_(network),
+        local_address_(local_address),
+        remote_address_(remote_address),
+        state_(state) {
+  }
+  ~InjectableTcpSocket() override {
+    network_->ForgetTcp(local_address_, remote_address_);
+  }
+
+  // As rtc::AsyncPacketSocket
+  rtc::SocketAddress GetLocalAddress() const override {
+    return local_address_;
+  }
+
+  // As rtc::AsyncPacketSocket
+  rtc::SocketAddress GetRemoteAddress() const override {
+    return remote_address_;
+  }
+
+  // As rtc::AsyncPacketSocket
+  int Send(const void* data,
+           size_t data_size,
+           const rtc::PacketOptions& options) override {
+    int result = network_->SendTcp(local_address_, remote_address_, static_cast<const uint8_t*>(data), data_size);
+    if (result < 0) {
+      last_error_ = result;
+      return result;
+    }
+
+    // Ends up going to Call::OnSentPacket for congestion control purposes.
+    SignalSentPacket(this, rtc::SentPacket(options.packet_id, rtc::TimeMillis()));
+    return result;
+  }
+
+  // As rtc::AsyncPacketSocket
+  int SendTo(const void* data,
+             size_t data_size,
+             const rtc::SocketAddress& remote_address,
+             const rtc::PacketOptions& options) override {
+    // Like rtc::AsyncTCPSocket, ignore the address and use the connection.
+    return Send(data, data_size, options);
+  }
+
+  void Connect() {
+    state_ = rtc::AsyncPacketSocket::STATE_CONNECTED;
+    SignalConnect(this);
+  }
+
+  void ReceiveFrom(const uint8_t* data, size_t data_size) {
+    RTC_LOG(LS_VERBOSE) << "InjectableTcpSocket::ReceiveFrom()"
+                        << " from " << remote_address_.ToString()
+                        << " to " << local_address_.ToString();
+    auto now = rtc::TimeMicros();
+    SignalReadPacket(this, reinterpret_cast<const char*>(data), data_size, remote_address_, now);
+  }
+
+  // As rtc::AsyncPacketSocket
+  int Close() override {
+    state_ = rtc::AsyncPacketSocket::STATE_CLOSED;
+    return 0;
+  }
+
+  // As rtc::AsyncPacketSocket
+  State GetState() const override {
+    return state_;
+  }
+
+  // As rtc::AsyncPacketSocket
+  int GetOption(rtc::Socket::Option option, int* value) override {
+    return -1;
+  }
+
+  // As rtc::AsyncPacketSocket
+  int SetOption(rtc::Socket::Option option, int value) override {
+    // See InjectableUdpSocket::SetOption.
+    // Pretend it worked.
+    return 1;
+  }
+
+  // As rtc::AsyncPacketSocket
+  int GetError() const override {
+    return last_error_;
+  }
+
+  // As rtc::AsyncPacketSocket
+  void SetError(int error) override {
+  }
+
+ private:
+  InjectableNetwork* network_;
+  rtc::SocketAddress local_address_;
+  rtc::SocketAddress remote_address_;
+  State state_;
+  int last_error_ = 0;
+};
+
+// Listens for connections, which are signaled with SignalNewConnection.
+class InjectableTcpServerSocket : public rtc::AsyncPacketSocket {
+ public:
+  InjectableTcpServerSocket(InjectableNetwork* network, const rtc::SocketAddress& local_address)
+      : network_(network), local_address_(local_address) {
+  }
+  ~InjectableTcpServerSocket() override {
+    network_->ForgetTcpServer(local_address_);
+  }
+
+  // As rtc::AsyncPacketSocket
+  rtc::SocketAddress GetLocalAddress() const override {
+    return local_address_;
+  }
+
+  // As rtc::AsyncPacketSocket
+  rtc::SocketAddress GetRemoteAddress() const override {
+    return rtc::SocketAddress();
+  }
+
+  // As rtc::AsyncPacketSocket
+  int Send(const void* data,
+           size_t data_size,
+           const rtc::PacketOptions& options) override {
+    // Only the accepted sockets can send.
+    return -1;
+  }
+
+  // As rtc::AsyncPacketSocket
+  int SendTo(const void* data,
+             size_t data_size,
+             const rtc::SocketAddress& remote_address,
+             const rtc::PacketOptions& options) override {
+    // Only the accepted sockets can send.
+    return -1;
+  }
+
+  // Whoever is connected to SignalNewConnection takes ownership of the socket.
+  void Accept(InjectableTcpSocket* socket) {
+    RTC_LOG(LS_INFO) << "InjectableTcpServerSocket::Accept()"
+                     << " from " << socket->GetRemoteAddress().ToString()
+                     << " to " << local_address_.ToString();
+    SignalNewConnection(this, socket);
+  }
+
+  // As rtc::AsyncPacketSocket
+  int Close() override {
+    return 0;
+  }
+
+  // As rtc::AsyncPacketSocket
+  State GetState() const override {
+    return rtc::AsyncPacketSocket::STATE_BOUND;
+  }
+
+  // As rtc::AsyncPacketSocket
+  int GetOption(rtc::Socket::Option option, int* value) override {
+    return -1;
+  }
+
+  // As rtc::AsyncPacketSocket
+  int SetOption(rtc::Socket::Option option, int value) override {
+    // Pretend it worked.
+    return 1;
+  }
+
+  // As rtc::AsyncPacketSocket
+  int GetError() const override {
+    return 0;
+  }
+
+  // As rtc::AsyncPacketSocket
+  void SetError(int error) override {
+  }
+
+ private:
+  InjectableNetwork* network_;
+  rtc::SocketAddress local_address_;
+};
+
 class InjectableNetworkImpl : public InjectableNetwork, public rtc::NetworkManager, public rtc::PacketSocketFactory {
  public:
   InjectableNetworkImpl(rtc::Thread* network_thread) : network_thread_(network_thread) {
@@ -205,6 +391,38 @@ class InjectableNetworkImpl : public InjectableNetwork, public rtc::NetworkManag
     });
   }
 
+  void ReceiveTcp(IpPort source,
+                  IpPort dest,
+                  const uint8_t* data,
+                  size_t size) override {
+    // The network stack expects everything to happen on the network thread.
+    // Make sure to copy the data!
+    network_thread_->PostTask(RTC_FROM_HERE,
+        [this, source, dest, data{std::vector<uint8_t>(data, data+size)}, size] {
+      auto local_address = IpPortToRtcSocketAddress(dest);
+      auto remote_address = IpPortToRtcSocketAddress(source);
+      RTC_LOG(LS_VERBOSE) << "InjectableNetworkImpl::ReceiveTcp()"
+                          << " from " << remote_address.ToString()
+                          << " to " << local_address.ToString()
+                          << " size: " << size;
+      auto addresses = std::make_pair(local_address, remote_address);
+      auto tcp_socket = tcp_socket_by_addresses_.find(addresses);
+      if (tcp_socket == tcp_socket_by_addresses_.end()) {
+        // The first packet of a connection to a listening socket.
+        auto server_socket = tcp_server_socket_by_local_address_.find(local_address);
+        if (server_socket == tcp_server_socket_by_local_address_.end()) {
+          RTC_LOG(LS_WARNING) << "Received TCP packet for unknown connection.";
+          return;
+        }
+        auto accepted_socket = new InjectableTcpSocket(
+            this, local_address, remote_address, rtc::AsyncPacketSocket::STATE_CONNECTED);
+        tcp_socket = tcp_socket_by_addresses_.insert({addresses, accepted_socket}).first;
+        server_socket->second->Accept(accepted_socket);
+      }
+      tcp_socket->second->ReceiveFrom(data.data(), data.size());
+    });
+  }
+
   int SendUdp(const rtc::SocketAddress& local_address,
               const rtc::SocketAddress& remote_address,
               const uint8_t* data,
@@ -230,6 +448,40 @@ class InjectableNetworkImpl : public InjectableNetwork, public rtc::NetworkManag
     });
   }
 
+  int SendTcp(const rtc::SocketAddress& local_address,
+              const rtc::SocketAddress& remote_address,
+              const uint8_t* data,
+              size_t size) override {
+    if (!sender_.object) {
+      RTC_LOG(LS_WARNING) << "Dropping packet because no sender set.";
+      return -1;
+    }
+    IpPort local = RtcSocketAddressToIpPort(local_address);
+    IpPort remote = RtcSocketAddressToIpPort(remote_address);
+    sender_.SendTcp(sender_.object, local, remote, data, size);
+    return size;
+  }
+
+  void ForgetTcp(const rtc::SocketAddress& local_address,
+                 const rtc::SocketAddress& remote_address) override {
+    // We need to access tcp_socket_by_addresses_ on the network_thread_.
+    // Erase synchronously so that a ReceiveTcp task queued after the socket
+    // is deleted can't find it, and so that a late erase can't remove a newer
+    // socket with the same addresses.
+    network_thread_->Invoke<void>(RTC_FROM_HERE, [this, local_address, remote_address] {
+      tcp_socket_by_addresses_.erase(std::make_pair(local_address, remote_address));
+    });
+  }
+
+  void ForgetTcpServer(const rtc::SocketAddress& local_address) override {
+    // We need to access tcp_server_socket_by_local_address_ on the network_thread_.
+    // Like ForgetTcp, erase synchronously so a queued ReceiveTcp can't accept
+    // a connection on a deleted server socket.
+    network_thread_->Invoke<void>(RTC_FROM_HERE, [this, local_address] {
+      tcp_server_socket_by_local_address_.erase(local_address);
+    });
+  }
+
   // As NetworkManager
   void StartUpdating() override {
     RTC_DCHECK(network_thread_->IsCurrent());
@@ -298,24 +550,45 @@ class InjectableNetworkImpl : public InjectableNetwork, public rtc::NetworkManag
   }
 
   // As PacketSocketFactory
-  rtc::AsyncPacketSocket* CreateServerTcpSocket(const rtc::SocketAddress& local_address,
+  rtc::AsyncPacketSocket* CreateServerTcpSocket(const rtc::SocketAddress& local_address_without_port,
                                            uint16_t min_port,
                                            uint16_t max_port,
                                            int opts) override {
-    // We never plan to support TCP ICE (other than through TURN),
-    // So we'll never implement this.
-    return nullptr;
+    RTC_DCHECK(network_thread_->IsCurrent());
+    RTC_LOG(LS_INFO) << "InjectableNetworkImpl::CreateServerTcpSocket() ip: " << local_address_without_port.ip();
+    // Used for passive ICE-TCP candidates.
+    // The packets keep their framing, so the opts (such as OPT_STUN) can be ignored.
+    rtc::SocketAddress local_address(local_address_without_port.ipaddr(), next_tcp_port_++);
+    auto server_socket = new InjectableTcpServerSocket(this, local_address);
+    tcp_server_socket_by_local_address_.insert({local_address, server_socket});
+    return server_socket;
   }
 
   // As PacketSocketFactory
   rtc::AsyncPacketSocket* CreateClientTcpSocket(
-      const rtc::SocketAddress& local_address,
+      const rtc::SocketAddress& local_address_without_port,
       const rtc::SocketAddress& remote_address,
       const rtc::ProxyInfo& proxy_info,
       const std::string& user_agent,
       const rtc::PacketSocketTcpOptions& tcp_options) override {
-    // TODO: Support TCP for TURN
-    return nullptr;
+    RTC_DCHECK(network_thread_->IsCurrent());
+    RTC_LOG(LS_INFO) << "InjectableNetworkImpl::CreateClientTcpSocket() ip: " << local_address_without_port.ip()
+                     << " remote: " << remote_address.ToString();
+    // Used for TURN over TCP and active ICE-TCP candidates.
+    rtc::SocketAddress local_address(local_address_without_port.ipaddr(), next_tcp_port_++);
+    auto tcp_socket = new InjectableTcpSocket(
+        this, local_address, remote_address, rtc::AsyncPacketSocket::STATE_CONNECTING);
+    auto addresses = std::make_pair(local_address, remote_address);
+    tcp_socket_by_addresses_.insert({addresses, tcp_socket});
+    // Connect once the caller has had a chance to listen to SignalConnect.
+    // The socket may be gone by then.
+    network_thread_->PostTask(RTC_FROM_HERE, [this, addresses] {
+      auto tcp_socket = tcp_socket_by_addresses_.find(addresses);
+      if (tcp_socket != tcp_socket_by_addresses_.end()) {
+        tcp_socket->second->Connect();
+      }
+    });
+    return tcp_socket;
   }
 
   // As PacketSocketFactory
@@ -329,9 +602,15 @@ class InjectableNetworkImpl : public InjectableNetwork, public rtc::NetworkManag
   rtc::Thread* network_thread_;
   std::map<std::string, std::unique_ptr<rtc::Network>> interface_by_name_;
   std::map<rtc::SocketAddress, InjectableUdpSocket*> udp_socket_by_local_address_;
+  // Keyed by (local address, remote address)
+  std::map<std::pair<rtc::SocketAddress, rtc::SocketAddress>, InjectableTcpSocket*> tcp_socket_by_addresses_;
+  std::map<rtc::SocketAddress, InjectableTcpServerSocket*> tcp_server_socket_by_local_address_;
   // The ICE stack does not like ports below 1024.
   // Give it a nice even number to count up from.
   uint16_t next_udp_port_ = 2001;
+  // Simulated NATs and servers don't tell UDP and TCP apart by port,
+  // so keep TCP ports far from the UDP ones.
+  uint16_t next_tcp_port_ = 20001;
   InjectableNetworkSender sender_ = {};
 };
 
@@ -369,6 +648,15 @@ RUSTEXPORT void Rust_InjectableNetwork_ReceiveUdp(
   network->ReceiveUdp(local, remote, data, size);
 }
 
+RUSTEXPORT void Rust_InjectableNetwork_ReceiveTcp(
+    InjectableNetwork* network,
+    IpPort local,
+    IpPort remote,
+    const uint8_t* data,
+    size_t size) {
+  network->ReceiveTcp(local, remote, data, size);
+}
+
 }  // namespace rffi
 
 }  // namespace webrtc
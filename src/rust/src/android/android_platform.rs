@@ -28,6 +28,7 @@ use crate::common::{
 use crate::core::bandwidth_mode::BandwidthMode;
 use crate::core::call::Call;
 use crate::core::connection::{Connection, ConnectionType};
+use crate::core::escalation::EscalationUpdate;
 use crate::core::platform::{Platform, PlatformItem};
 use crate::core::{group_call, signaling};
 use crate::webrtc::media::{MediaStream, VideoTrack};
@@ -1050,6 +1051,19 @@ impl Platform for AndroidPlatform {
         }
     }
 
+    fn handle_escalation_update(
+        &self,
+        call_id: CallId,
+        _group_id: group_call::GroupId,
+        update: EscalationUpdate,
+    ) {
+        // Not passed on to the app yet.
+        info!(
+            "handle_escalation_update(): call_id: {} update: {:?}",
+            call_id, update
+        );
+    }
+
     fn handle_peek_response(
         &self,
         request_id: u32,
@@ -1207,7 +1221,11 @@ impl Platform for AndroidPlatform {
 
         let join_state = match join_state {
             group_call::JoinState::NotJoined(_) => 0,
-            group_call::JoinState::Joining => 1,
+            // The app doesn't know about the lobby or waiting for a slot yet, so both look like
+            // joining.
+            group_call::JoinState::Joining
+            | group_call::JoinState::Pending(_, _)
+            | group_call::JoinState::WaitingForSlot(_) => 1,
             group_call::JoinState::Joined(_, _) => 2,
         };
 
@@ -1411,6 +1429,76 @@ impl Platform for AndroidPlatform {
         }
     }
 
+    fn handle_reaction(&self, client_id: group_call::ClientId, reaction: group_call::Reaction) {
+        // Not passed on to the app yet.
+        info!(
+            "handle_reaction(): id: {} demux_id: {}",
+            client_id, reaction.demux_id
+        );
+    }
+
+    fn handle_message_received(
+        &self,
+        client_id: group_call::ClientId,
+        message: group_call::ChatMessage,
+    ) {
+        // Not passed on to the app yet.
+        info!(
+            "handle_message_received(): id: {} demux_id: {} message_id: {}",
+            client_id, message.demux_id, message.message_id
+        );
+    }
+
+    fn handle_control_request(
+        &self,
+        client_id: group_call::ClientId,
+        sender_demux_id: group_call::DemuxId,
+        request: group_call::ControlRequest,
+    ) {
+        // Not passed on to the app yet.
+        info!(
+            "handle_control_request(): id: {} sender_demux_id: {} request: {:?}",
+            client_id, sender_demux_id, request
+        );
+    }
+
+    fn handle_audio_levels(
+        &self,
+        _client_id: group_call::ClientId,
+        _captured_level: group_call::AudioLevel,
+        _received_levels: Vec<group_call::ReceivedAudioLevel>,
+    ) {
+        // Not passed on to the app yet (and not logged because it's called so often).
+    }
+
+    fn handle_resumption_snapshot(&self, client_id: group_call::ClientId, _snapshot: Vec<u8>) {
+        // Not passed on to the app yet.
+        info!("handle_resumption_snapshot(): id: {}", client_id);
+    }
+
+    fn handle_layout_changed(&self, client_id: group_call::ClientId, _layout: group_call::Layout) {
+        // Not passed on to the app yet.
+        info!("handle_layout_changed(): id: {}", client_id);
+    }
+
+    fn handle_pinned_videos_not_forwarded(
+        &self,
+        client_id: group_call::ClientId,
+        _demux_ids: Vec<group_call::DemuxId>,
+    ) {
+        // Not passed on to the app yet.
+        info!("handle_pinned_videos_not_forwarded(): id: {}", client_id);
+    }
+
+    fn handle_ring_statuses_changed(
+        &self,
+        client_id: group_call::ClientId,
+        _statuses: Vec<group_call::RecipientRingStatus>,
+    ) {
+        // Not passed on to the app yet.
+        info!("handle_ring_statuses_changed(): id: {}", client_id);
+    }
+
     fn handle_peek_changed(
         &self,
         client_id: group_call::ClientId,
@@ -1511,6 +1599,19 @@ impl Platform for AndroidPlatform {
         });
     }
 
+    fn handle_pending_devices_changed(
+        &self,
+        client_id: group_call::ClientId,
+        pending_devices: &[group_call::PeekDeviceInfo],
+    ) {
+        // Not passed on to the app yet.
+        info!(
+            "handle_pending_devices_changed(): id: {} count: {}",
+            client_id,
+            pending_devices.len()
+        );
+    }
+
     fn handle_ended(&self, client_id: group_call::ClientId, reason: group_call::EndReason) {
         info!("handle_ended():");
 
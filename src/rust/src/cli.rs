@@ -3,7 +3,7 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
-use log::{debug, info};
+use log::{debug, info, warn};
 
 use ringrtc::{
     common::{
@@ -16,7 +16,13 @@ use ringrtc::{
         HttpMethod,
         Result,
     },
-    core::{bandwidth_mode::BandwidthMode, call_manager::CallManager, group_call, signaling},
+    core::{
+        bandwidth_mode::BandwidthMode,
+        call_manager::CallManager,
+        group_call,
+        signaling,
+        util::ClockPauseGuard,
+    },
     native::{
         CallState,
         CallStateHandler,
@@ -29,19 +35,39 @@ use ringrtc::{
         SignalingSender,
     },
     simnet::{
+        clock::SimClock,
+        nat::NatConfig,
         router,
-        router::{LinkConfig, Router},
+        router::{DelayDistribution, LinkConfig, LinkSchedule, LossModel, Router},
+        scenario::{
+            Action,
+            BandwidthModeName,
+            CallStateKind,
+            InterfaceType,
+            Scenario,
+            ScenarioReport,
+            StateChange,
+            StateCheck,
+        },
+        stun::StunServer,
+        turn::{TurnServer, TurnServerConfig},
     },
     webrtc::{
         injectable_network,
         injectable_network::InjectableNetwork,
-        media::{VideoFrame, VideoSink, VideoSource},
+        media::{AudioTrack, VideoFrame, VideoSink, VideoSource},
         network::NetworkInterfaceType,
         peer_connection_factory::{self as pcf, Certificate, IceServer, PeerConnectionFactory},
         peer_connection_observer::NetworkRoute,
     },
 };
-use std::{collections::HashMap, thread, time::Duration};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
 
 fn main() {
     log::set_logger(&LOG).expect("set logger");
@@ -54,29 +80,58 @@ fn main() {
     #[cfg(not(debug_assertions))]
     ringrtc::webrtc::logging::set_logger(log::LevelFilter::Warn);
 
+    // Pass a path to a scenario file, such as scenarios/tunnel.json,
+    // to run it instead of the built-in call.
+    match std::env::args().nth(1) {
+        Some(scenario_path) => {
+            let scenario = Scenario::load(&scenario_path).expect("Load scenario");
+            let report = run_scenario(&scenario);
+            println!("{}", report);
+            if !report.passed() {
+                std::process::exit(1);
+            }
+        }
+        None => run_built_in_call(),
+    }
+}
+
+fn run_built_in_call() {
     let hide_ip = false;
-    // TODO: Real STUN/TURN servers.
-    let ice_server = IceServer::none();
     let stopper = Stopper::new();
     let signaling_server = SignalingServer::start(&stopper).expect("Start signaling server");
-    let router = Router::start(&stopper).expect("Start router");
+    let clock = SimClock::real();
+    let router = Router::start_with_clock(&stopper, clock.clone()).expect("Start router");
+    start_pcap_if_requested(&router);
     let good_link = LinkConfig {
-        delay_min:                 Duration::from_millis(10),
-        delay_max:                 Duration::from_millis(20),
-        loss_probabilty:           0.00,
-        repeated_loss_probability: 0.00,
-        rate:                      DataRate::from_mbps(5),
-        queue_size:                DataRate::from_mbps(5) * Duration::from_millis(500),
+        delay:                 DelayDistribution::Uniform {
+            min: Duration::from_millis(10),
+            max: Duration::from_millis(20),
+        },
+        loss:                  LossModel::none(),
+        reorder_probability:   0.0,
+        duplicate_probability: 0.0,
+        rate:                  DataRate::from_mbps(5),
+        queue_size:            DataRate::from_mbps(5) * Duration::from_millis(500),
     };
     let bad_link = LinkConfig {
-        delay_min:                 Duration::from_millis(100),
-        delay_max:                 Duration::from_millis(200),
-        loss_probabilty:           0.005,
-        repeated_loss_probability: 0.70,
-        rate:                      DataRate::from_kbps(256),
-        queue_size:                DataRate::from_kbps(256) * Duration::from_secs(500),
+        delay:                 DelayDistribution::Uniform {
+            min: Duration::from_millis(100),
+            max: Duration::from_millis(200),
+        },
+        loss:                  LossModel::GilbertElliott {
+            good_to_bad_probability: 0.005,
+            bad_to_good_probability: 0.30,
+            good_loss_probability:   0.0,
+            bad_loss_probability:    1.0,
+        },
+        reorder_probability:   0.0,
+        duplicate_probability: 0.0,
+        rate:                  DataRate::from_kbps(256),
+        queue_size:            DataRate::from_kbps(256) * Duration::from_secs(500),
     };
 
+    let ice_server = add_servers(&router, &stopper, &good_link);
+
     let caller = CallEndpoint::start(
         "caller",
         1 as DeviceId,
@@ -84,6 +139,7 @@ fn main() {
         &ice_server,
         &signaling_server,
         &router,
+        &clock,
         &stopper,
     )
     .expect("Start caller");
@@ -111,6 +167,7 @@ fn main() {
         &ice_server,
         &signaling_server,
         &router,
+        &clock,
         &stopper,
     )
     .expect("Start callee");
@@ -141,6 +198,7 @@ fn main() {
                 &ice_server,
                 &signaling_server,
                 &router,
+                &clock,
                 &stopper,
             )
             .expect("Start ignored callee");
@@ -186,20 +244,279 @@ fn main() {
     // Then let that settle before ending.
     thread::sleep(Duration::from_secs(1));
 
+    log_interface_stats(&router, &["1.1.0.1", "2.1.0.1", "2.1.0.2"]);
+    router.stop_pcap();
+
+    stopper.stop_all_and_join();
+}
+
+// Set SIMNET_PCAP to a path to capture the packets for Wireshark.
+fn start_pcap_if_requested(router: &Router) {
+    if let Some(pcap_path) = std::env::var_os("SIMNET_PCAP") {
+        router
+            .start_pcap_file(pcap_path)
+            .expect("Start pcap capture");
+    }
+}
+
+// Adds a STUN server and a TURN server to the router.
+// Returns the IceServer that endpoints use to reach them.
+fn add_servers(router: &Router, stopper: &Stopper, link: &LinkConfig) -> IceServer {
+    let stun_address: SocketAddr = "9.0.0.1:3478".parse().expect("Parse STUN address");
+    StunServer::add_to_router(router, stun_address, link.clone()).expect("Add STUN server");
+    let turn_config = TurnServerConfig {
+        address:  "9.0.0.2:3478".parse().expect("Parse TURN address"),
+        username: "simnet".to_string(),
+        password: "simnet".to_string(),
+        realm:    "simnet".to_string(),
+    };
+    let turn_server = TurnServer::start(stopper, turn_config.clone()).expect("Start TURN server");
+    turn_server
+        .add_to_router(router, link.clone())
+        .expect("Add TURN server");
+    IceServer::new(
+        turn_config.username,
+        turn_config.password,
+        vec![
+            format!("stun:{}", stun_address),
+            format!("turn:{}", turn_config.address),
+            format!("turn:{}?transport=tcp", turn_config.address),
+        ],
+    )
+}
+
+fn log_interface_stats(router: &Router, ips: &[&str]) {
+    for ip in ips {
+        if let Some(stats) = router.interface_stats(ip.parse().expect("Parse IP")) {
+            info!("Link stats for {}:", ip);
+            info!("  send:    {}", stats.send);
+            info!("  receive: {}", stats.receive);
+        }
+    }
+}
+
+fn run_scenario(scenario: &Scenario) -> ScenarioReport {
+    info!("Running scenario {:?}", scenario.name);
+    let hide_ip = false;
+    let stopper = Stopper::new();
+    let signaling_server = SignalingServer::start(&stopper).expect("Start signaling server");
+    let clock = if scenario.virtual_time {
+        SimClock::new_virtual()
+    } else {
+        SimClock::real()
+    };
+    let router = match scenario.seed {
+        Some(seed) => Router::start_with_seed_and_clock(&stopper, seed, clock.clone()),
+        None => Router::start_with_clock(&stopper, clock.clone()),
+    }
+    .expect("Start router");
+    start_pcap_if_requested(&router);
+    // The servers aren't what's being tested, so they always get a good link.
+    let server_link = LinkConfig {
+        delay:                 DelayDistribution::Uniform {
+            min: Duration::from_millis(10),
+            max: Duration::from_millis(20),
+        },
+        loss:                  LossModel::none(),
+        reorder_probability:   0.0,
+        duplicate_probability: 0.0,
+        rate:                  DataRate::from_mbps(100),
+        queue_size:            DataRate::from_mbps(100) * Duration::from_millis(500),
+    };
+    let ice_server = add_servers(&router, &stopper, &server_link);
+
+    let mut endpoints: HashMap<&str, CallEndpoint> = HashMap::new();
+    for config in &scenario.endpoints {
+        let endpoint = CallEndpoint::start(
+            &config.name,
+            config.device_id,
+            hide_ip,
+            &ice_server,
+            &signaling_server,
+            &router,
+            &clock,
+            &stopper,
+        )
+        .expect("Start scenario endpoint");
+        for interface in &config.interfaces {
+            let link = scenario.links[&interface.link].to_link_config();
+            let typ = match interface.typ {
+                InterfaceType::Ethernet => NetworkInterfaceType::Ethernet,
+                InterfaceType::Wifi => NetworkInterfaceType::Wifi,
+                InterfaceType::Cellular => NetworkInterfaceType::Cellular,
+                InterfaceType::Vpn => NetworkInterfaceType::Vpn,
+            };
+            endpoint.add_network_interface(
+                &interface.name,
+                typ,
+                &interface.ip.to_string(),
+                interface.preference,
+                &link,
+                &link,
+            );
+            if !interface.up {
+                endpoint.set_network_interface_up(&interface.name, false);
+            }
+            if let Some(nat) = &interface.nat {
+                router.set_interface_nat(
+                    interface.ip,
+                    Some(NatConfig {
+                        nat_type:  nat.nat_type,
+                        public_ip: nat.public_ip,
+                    }),
+                );
+            }
+        }
+        endpoints.insert(&config.name, endpoint);
+    }
+
+    // With virtual time, the CallManagers' clocks move forward with the router's,
+    // a little at a time so that timers and packets stay interleaved.
+    let (clock_pause_guards, step): (Vec<ClockPauseGuard>, Duration) = if clock.is_virtual() {
+        let clock_pause_guards = scenario
+            .endpoints
+            .iter()
+            .map(|config| endpoints[config.name.as_str()].pause_clock())
+            .collect();
+        (clock_pause_guards, Duration::from_millis(10))
+    } else {
+        (Vec::new(), Duration::from_secs(0))
+    };
+    let paused_clocks: Vec<&ClockPauseGuard> = clock_pause_guards.iter().collect();
+
+    let started = clock.now();
+    let mut checks = Vec::new();
+    let mut next_call_id = 0xCA111D;
+    for entry in scenario.sorted_timeline() {
+        let until_entry = entry.at().checked_sub(clock.now() - started);
+        if let Some(until_entry) = until_entry {
+            clock.advance_with(until_entry, step, &paused_clocks);
+        }
+        info!("Scenario action at {:?}: {:?}", entry.at(), entry.action);
+        let endpoint = &endpoints[entry.action.endpoint()];
+        match &entry.action {
+            Action::Call { callee, video, .. } => {
+                let media_type = if *video {
+                    CallMediaType::Video
+                } else {
+                    CallMediaType::Audio
+                };
+                endpoint.create_outgoing_call(
+                    &PeerId::from(callee.as_str()),
+                    CallId::new(next_call_id),
+                    media_type,
+                    endpoint.device_id,
+                );
+                next_call_id += 1;
+            }
+            Action::Accept { .. } => endpoint.accept_latest_incoming_call(),
+            Action::Hangup { .. } => endpoint.hangup(),
+            Action::Mute { muted, .. } => endpoint.set_outgoing_audio_muted(*muted),
+            Action::SendVideo { .. } => {
+                endpoint.send_generated_video(640, 480, Duration::from_millis(33))
+            }
+            Action::SetBandwidthMode { mode, .. } => endpoint.update_bandwidth_mode(match mode {
+                BandwidthModeName::VeryLow => BandwidthMode::VeryLow,
+                BandwidthModeName::Low => BandwidthMode::Low,
+                BandwidthModeName::Normal => BandwidthMode::Normal,
+            }),
+            Action::SwitchNetwork { from, to, .. } => {
+                endpoint.set_network_interface_up(from, false);
+                endpoint.set_network_interface_up(to, true);
+            }
+            Action::SetLink {
+                endpoint: endpoint_name,
+                interface: interface_name,
+                link,
+            } => {
+                let interface = scenario
+                    .endpoint(endpoint_name)
+                    .and_then(|config| {
+                        config
+                            .interfaces
+                            .iter()
+                            .find(|interface| &interface.name == interface_name)
+                    })
+                    .expect("Find scenario interface");
+                match link {
+                    Some(link) => {
+                        let config = scenario.links[link].to_link_config();
+                        router
+                            .set_interface_configs(interface.ip, config.clone(), config)
+                            .expect("Set link configs");
+                    }
+                    None => {
+                        let outage =
+                            LinkSchedule::new(scenario.links[&interface.link].to_link_config())
+                                .then_outage(Duration::from_secs(0));
+                        router
+                            .set_interface_schedules(interface.ip, outage.clone(), outage)
+                            .expect("Set link schedules");
+                    }
+                }
+            }
+            Action::ExpectState {
+                endpoint: endpoint_name,
+                state,
+            } => {
+                checks.push(StateCheck {
+                    at:       entry.at(),
+                    endpoint: endpoint_name.clone(),
+                    expected: *state,
+                    actual:   endpoint.current_call_state(),
+                });
+            }
+        }
+    }
+    clock.advance_with(
+        scenario.duration().saturating_sub(clock.now() - started),
+        step,
+        &paused_clocks,
+    );
+    // Resume the CallManagers' clocks while their runtimes are still around.
+    drop(paused_clocks);
+    drop(clock_pause_guards);
+
+    let ips: Vec<String> = scenario
+        .endpoints
+        .iter()
+        .flat_map(|config| &config.interfaces)
+        .map(|interface| interface.ip.to_string())
+        .collect();
+    log_interface_stats(&router, &ips.iter().map(String::as_str).collect::<Vec<_>>());
+    router.stop_pcap();
     stopper.stop_all_and_join();
+
+    ScenarioReport {
+        name: scenario.name.clone(),
+        timelines: scenario
+            .endpoints
+            .iter()
+            .map(|config| {
+                let changes = endpoints[config.name.as_str()].call_state_changes(started);
+                (config.name.clone(), changes)
+            })
+            .collect(),
+        checks,
+    }
 }
 
 #[derive(Clone)]
 struct CallEndpoint {
     // We keep a copy of these outside of the actor state
     // so we can know them in any thread.
-    peer_id:   PeerId,
-    device_id: DeviceId,
+    peer_id:            PeerId,
+    device_id:          DeviceId,
     // There is probably a way to have a CallEndpoint without a thread,
     // but this is the easiest way to get around the nasty dependency cycle
     // of CallEndpoint -> CallManger -> NativePlatform -> CallEndpoint.
     // And it makes it pretty easy to schedule generation of video frames.
-    actor:     Actor<CallEndpointState>,
+    actor:              Actor<CallEndpointState>,
+    // When and to which state each call state change happened,
+    // kept outside of the actor so they can be read after it stops.
+    call_state_changes: Arc<Mutex<Vec<(Instant, CallStateKind, String)>>>,
+    // The clock of the router, used to time call state changes.
+    clock:              SimClock,
 }
 
 struct CallEndpointState {
@@ -219,8 +536,16 @@ struct CallEndpointState {
 
     // Keep a copy around to be able to schedule video frames
     actor:                 Actor<Self>,
+    // And schedule them according to the router's clock
+    clock:                 SimClock,
     // Keep a copy around to be able to push out video frames
     outgoing_video_source: VideoSource,
+    // Keep an unowned copy around to be able to mute
+    outgoing_audio_track:  AudioTrack,
+    // So the latest incoming call can be accepted without knowing its ID
+    incoming_call_id:      Option<CallId>,
+    // So interfaces can be brought down and back up by name
+    network_interfaces:    HashMap<String, (NetworkInterfaceType, IpAddr, u16)>,
 }
 
 impl CallEndpoint {
@@ -231,6 +556,7 @@ impl CallEndpoint {
         ice_server: &IceServer,
         signaling_server: &SignalingServer,
         router: &Router,
+        clock: &SimClock,
         stopper: &Stopper,
     ) -> Result<Self> {
         let peer_id = PeerId::from(peer_id);
@@ -239,17 +565,29 @@ impl CallEndpoint {
         let ice_server = ice_server.clone();
         let signaling_server: SignalingServer = signaling_server.clone();
         let router = router.clone();
+        let clock = clock.clone();
+        let clock_for_actor = clock.clone();
+        let call_state_changes = Arc::new(Mutex::new(Vec::new()));
+        let call_state_changes_for_actor = call_state_changes.clone();
 
         Ok(Self::from_actor(
             peer_id.clone(),
             device_id,
+            call_state_changes,
+            clock,
             Actor::start(stopper.clone(), move |actor| {
                 // Constructing this is a funny way of getting a clone of the CallEndpoint
                 // on the actor's thread so we can have it in the actor's state so we can
                 // pass it to the NativePlatform/CallManager.
                 // This is a little weird, but it seems nicer than doing some kind of
                 // Option<CallManager> thing that we have to set later.
-                let endpoint = Self::from_actor(peer_id.clone(), device_id, actor.clone());
+                let endpoint = Self::from_actor(
+                    peer_id.clone(),
+                    device_id,
+                    call_state_changes_for_actor,
+                    clock_for_actor.clone(),
+                    actor.clone(),
+                );
 
                 let pcf = PeerConnectionFactory::new(pcf::Config {
                     use_injectable_network: true, // Set up packet flow
@@ -268,9 +606,10 @@ impl CallEndpoint {
                 let router_as_sender = router.clone();
                 network.set_sender(Box::new(move |packet: injectable_network::Packet| {
                     router_as_sender.send_packet(router::Packet {
-                        source: packet.source,
-                        dest:   packet.dest,
-                        data:   packet.data,
+                        source:   packet.source,
+                        dest:     packet.dest,
+                        protocol: packet.protocol,
+                        data:     packet.data,
                     });
                 }));
 
@@ -299,6 +638,7 @@ impl CallEndpoint {
                 // And a CallContext.  We'll use the same context for each call.
                 let cert = Certificate::generate()?;
                 let outgoing_audio_track = pcf.create_outgoing_audio_track()?;
+                let unowned_outgoing_audio_track = AudioTrack::unowned(outgoing_audio_track.rffi());
                 let outgoing_video_source = pcf.create_outgoing_video_source()?;
                 let outgoing_video_track =
                     pcf.create_outgoing_video_track(&outgoing_video_source)?;
@@ -321,23 +661,35 @@ impl CallEndpoint {
                     call_context,
 
                     actor,
+                    clock: clock_for_actor,
                     outgoing_video_source,
+                    outgoing_audio_track: unowned_outgoing_audio_track,
+                    incoming_call_id: None,
+                    network_interfaces: HashMap::new(),
                 })
             })?,
         ))
     }
 
-    fn from_actor(peer_id: PeerId, device_id: DeviceId, actor: Actor<CallEndpointState>) -> Self {
+    fn from_actor(
+        peer_id: PeerId,
+        device_id: DeviceId,
+        call_state_changes: Arc<Mutex<Vec<(Instant, CallStateKind, String)>>>,
+        clock: SimClock,
+        actor: Actor<CallEndpointState>,
+    ) -> Self {
         Self {
             peer_id,
             device_id,
             actor,
+            call_state_changes,
+            clock,
         }
     }
 
     pub fn add_network_interface(
         &self,
-        name: &'static str,
+        name: &str,
         typ: NetworkInterfaceType,
         ip: &str,
         preference: u16,
@@ -347,13 +699,15 @@ impl CallEndpoint {
         let ip = ip.parse().expect("parse IP address");
 
         // To send across threads
+        let name = name.to_string();
         let send_config = send_config.clone();
         let receive_config = receive_config.clone();
 
         self.actor.send(move |state| {
             // Adding it to the network causes the PeerConnections to learn about it through
             // the NetworkMonitor.
-            state.network.add_interface(name, typ, ip, preference);
+            state.network.add_interface(&name, typ, ip, preference);
+            state.network_interfaces.insert(name, (typ, ip, preference));
             // Adding it to the router applies the config to the up and down links
             // and allow routing packets to and from other endpoints.alloc
             // Passing in network.get_receiver() causes packets from the PeerConnections
@@ -366,10 +720,11 @@ impl CallEndpoint {
                     send_config,
                     receive_config,
                     Box::new(move |packet: router::Packet| {
-                        network_as_receiver.receive_udp(injectable_network::Packet {
-                            source: packet.source,
-                            dest:   packet.dest,
-                            data:   packet.data,
+                        network_as_receiver.receive_packet(injectable_network::Packet {
+                            source:   packet.source,
+                            dest:     packet.dest,
+                            protocol: packet.protocol,
+                            data:     packet.data,
                         });
                     }),
                 )
@@ -382,6 +737,23 @@ impl CallEndpoint {
         });
     }
 
+    // Down interfaces stay in the router, but the PeerConnections
+    // learn that they are gone and stop using them.
+    pub fn set_network_interface_up(&self, name: &str, up: bool) {
+        // To send across threads
+        let name = name.to_string();
+
+        self.actor.send(
+            move |state| match (up, state.network_interfaces.get(&name)) {
+                (true, Some((typ, ip, preference))) => {
+                    state.network.add_interface(&name, *typ, *ip, *preference)
+                }
+                (false, Some(_)) => state.network.remove_interface(&name),
+                (_, None) => warn!("Can't change unknown network interface {:?}", name),
+            },
+        );
+    }
+
     fn create_outgoing_call(
         &self,
         callee_id: &PeerId,
@@ -409,6 +781,63 @@ impl CallEndpoint {
         });
     }
 
+    pub fn accept_latest_incoming_call(&self) {
+        self.actor.send(move |state| match state.incoming_call_id {
+            Some(call_id) => state
+                .call_manager
+                .accept_call(call_id)
+                .expect("accept incoming call"),
+            None => warn!("No incoming call to accept"),
+        });
+    }
+
+    pub fn set_outgoing_audio_muted(&self, muted: bool) {
+        self.actor.send(move |state| {
+            state.outgoing_audio_track.set_enabled(!muted);
+        });
+    }
+
+    pub fn update_bandwidth_mode(&self, bandwidth_mode: BandwidthMode) {
+        self.actor.send(move |state| {
+            let updated = state
+                .call_manager
+                .active_connection()
+                .and_then(|connection| connection.update_bandwidth_mode(bandwidth_mode));
+            if let Err(err) = updated {
+                warn!("Failed to update bandwidth mode: {}", err);
+            }
+        });
+    }
+
+    fn current_call_state(&self) -> CallStateKind {
+        let call_state_changes = self.call_state_changes.lock().expect("Lock call states");
+        call_state_changes
+            .last()
+            .map(|(_, kind, _)| *kind)
+            .unwrap_or(CallStateKind::Idle)
+    }
+
+    fn call_state_changes(&self, started: Instant) -> Vec<StateChange> {
+        let call_state_changes = self.call_state_changes.lock().expect("Lock call states");
+        call_state_changes
+            .iter()
+            .map(|(at, kind, description)| StateChange {
+                at:          at.saturating_duration_since(started),
+                kind:        *kind,
+                description: description.clone(),
+            })
+            .collect()
+    }
+
+    // Stops the CallManager's timers from firing until the returned guard is advanced.
+    fn pause_clock(&self) -> ClockPauseGuard {
+        let (sender, receiver) = mpsc::channel();
+        self.actor.send(move |state| {
+            let _ = sender.send(state.call_manager.pause_clock().expect("Pause clock"));
+        });
+        receiver.recv().expect("Receive paused clock")
+    }
+
     pub fn hangup(&self) {
         self.actor.send(move |state| {
             state.call_manager.hangup().expect("hangup");
@@ -500,9 +929,11 @@ impl CallEndpoint {
             state
                 .outgoing_video_source
                 .push_frame(VideoFrame::from_rgba(width, height, &rgba_data));
-            state.actor.send_delayed(duration, move |state| {
-                send_one_frame_and_schedule_another(state, width, height, duration);
-            });
+            state
+                .clock
+                .send_delayed(&state.actor, duration, move |state| {
+                    send_one_frame_and_schedule_another(state, width, height, duration);
+                });
         }
         self.actor.send(move |state| {
             send_one_frame_and_schedule_another(state, width, height, duration);
@@ -564,8 +995,24 @@ impl CallStateHandler for CallEndpoint {
             "State change in call from {}.{} to {}: now {:?}",
             self.peer_id, self.device_id, remote_peer_id, call_state
         );
+        let kind = match call_state {
+            CallState::Incoming(..) => CallStateKind::Incoming,
+            CallState::Outgoing(..) => CallStateKind::Outgoing,
+            CallState::Ringing => CallStateKind::Ringing,
+            CallState::Connecting => CallStateKind::Connecting,
+            CallState::Connected => CallStateKind::Connected,
+            CallState::Ended(_) => CallStateKind::Ended,
+            CallState::Concluded => CallStateKind::Concluded,
+        };
+        self.call_state_changes
+            .lock()
+            .expect("Lock call states")
+            .push((self.clock.now(), kind, call_state.to_string()));
 
         self.actor.send(move |state| {
+            if let CallState::Incoming(call_id, _call_media_type) = call_state {
+                state.incoming_call_id = Some(call_id);
+            }
             if let CallState::Incoming(call_id, _call_media_type)
             | CallState::Outgoing(call_id, _call_media_type) = call_state
             {
@@ -605,6 +1052,16 @@ impl CallStateHandler for CallEndpoint {
         );
         Ok(())
     }
+
+    fn handle_remote_hold(&self, remote_peer_id: &str, held: bool) -> Result<()> {
+        info!("Hold for {} => {}: {}", self.peer_id, remote_peer_id, held);
+        Ok(())
+    }
+
+    fn handle_remote_transferred(&self, remote_peer_id: &str) -> Result<()> {
+        info!("Transferred for {} => {}", self.peer_id, remote_peer_id);
+        Ok(())
+    }
 }
 
 impl GroupUpdateHandler for CallEndpoint {
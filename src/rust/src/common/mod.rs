@@ -92,6 +92,10 @@ pub enum CallState {
     /// After ConnectedAndAccepted, has gone disconnected temporarily and is trying to reconnect.
     ReconnectingAfterAccepted,
 
+    /// After ConnectedAndAccepted, the local user put the call on hold to take
+    /// another call, so no media flows either way until it is resumed.
+    OnHold,
+
     /// The call is in the process of terminating (hanging up).
     Terminating,
 
@@ -192,6 +196,19 @@ pub enum ApplicationEvent {
 
     /// Received an offer on a linked device from one that doesn't support multi-ring.
     IgnoreCallsFromNonMultiringCallers,
+
+    /// The remote side has put the call on hold.
+    RemoteHoldEnable,
+
+    /// The remote side has taken the call off hold.
+    RemoteHoldDisable,
+
+    /// The remote user moved the call to another of their devices, and the
+    /// call carries on with that device.
+    RemoteTransferred,
+
+    /// The call ended because another of the local user's devices took it over.
+    EndedTransferredToAnotherDevice,
 }
 
 impl fmt::Display for ApplicationEvent {
@@ -236,6 +253,9 @@ pub enum ConnectionState {
     /// ICE is disconnected/reconnecting after the call is accepted.
     ReconnectingAfterAccepted,
 
+    /// The call is on hold, so outgoing and incoming media are disabled.
+    OnHold,
+
     /// The connection is in the process of terminating
     Terminating,
 
@@ -40,22 +40,26 @@ use crate::webrtc::peer_connection_observer::NetworkRoute;
 /// Encapsulates the FSM and runtime upon which a Call runs.
 struct Context {
     /// Runtime upon which the CallStateMachine runs.
-    pub worker_runtime:  TaskQueueRuntime,
+    pub worker_runtime:           TaskQueueRuntime,
     /// Runtime that manages timing out a call.
-    pub timeout_runtime: Option<TaskQueueRuntime>,
+    pub timeout_runtime:          Option<TaskQueueRuntime>,
+    /// Runtime that manages timing out a transfer to another remote device.
+    pub transfer_timeout_runtime: Option<TaskQueueRuntime>,
 }
 
 impl Context {
     fn new() -> Result<Self> {
         Ok(Self {
-            worker_runtime:  TaskQueueRuntime::new("fsm-worker")?,
-            timeout_runtime: None,
+            worker_runtime:           TaskQueueRuntime::new("fsm-worker")?,
+            timeout_runtime:          None,
+            transfer_timeout_runtime: None,
         })
     }
 
     fn close(&mut self) {
         info!("stopping timeout runtime");
         self.timeout_runtime.take();
+        self.transfer_timeout_runtime.take();
         info!("stopping timeout runtime: complete");
     }
 }
@@ -127,6 +131,12 @@ where
     /// ICE candidates and signaling alive.
     /// And we also need to keep around that parent's offer that it created.
     forking:           Arc<CallMutex<Option<ForkingState<T>>>>,
+    /// Whether this outgoing call takes over an established call from another
+    /// of the local user's devices.
+    take_over:         Arc<AtomicBool>,
+    /// Another device of the remote user that is taking over the call, while
+    /// its connection is being set up.
+    transfer_device:   Arc<CallMutex<Option<DeviceId>>>,
 }
 
 impl<T> fmt::Display for Call<T>
@@ -205,6 +215,8 @@ where
             terminate_condvar: Arc::clone(&self.terminate_condvar),
             did_send_offer:    Arc::clone(&self.did_send_offer),
             forking:           Arc::clone(&self.forking),
+            take_over:         Arc::clone(&self.take_over),
+            transfer_device:   Arc::clone(&self.transfer_device),
         }
     }
 }
@@ -249,6 +261,8 @@ where
             terminate_condvar: Arc::new((Mutex::new(false), Condvar::new())),
             did_send_offer: Arc::new(AtomicBool::new(false)),
             forking: Arc::new(CallMutex::new(None, "forking")),
+            take_over: Arc::new(AtomicBool::new(false)),
+            transfer_device: Arc::new(CallMutex::new(None, "transfer_device")),
         };
 
         Ok(call)
@@ -278,6 +292,36 @@ where
         Ok(())
     }
 
+    /// Start a timer to drop a transfer to another remote device if its
+    /// connection takes too long to set up.  Replaces the timer of an
+    /// earlier transfer.
+    pub fn start_transfer_timeout_timer(
+        &self,
+        remote_device: DeviceId,
+        time_out_period: Duration,
+    ) -> Result<()> {
+        if !time_out_period.is_zero() {
+            if let Ok(mut fsm_context) = self.fsm_context.lock() {
+                let timeout_runtime = TaskQueueRuntime::new("fsm-transfer-timeout")?;
+
+                let mut call_clone = self.clone();
+                let when = Instant::now() + time_out_period;
+                let transfer_timeout_future = async move {
+                    let sleep = tokio::time::sleep_until(tokio::time::Instant::from_std(when));
+                    sleep.await;
+                    call_clone
+                        .inject_transfer_timeout(remote_device)
+                        .map_err(|e| error!("Inject transfer timeout failed: {:?}", e))
+                };
+
+                timeout_runtime.spawn(transfer_timeout_future);
+                fsm_context.transfer_timeout_runtime = Some(timeout_runtime);
+            }
+        }
+
+        Ok(())
+    }
+
     /// Return the Call identifier.
     pub fn call_id(&self) -> CallId {
         self.call_id
@@ -331,6 +375,48 @@ where
         }
     }
 
+    /// Mark this outgoing call as taking over an established call from
+    /// another of the local user's devices.
+    pub fn set_take_over(&self) {
+        self.take_over.store(true, Ordering::Release);
+    }
+
+    /// Return true if this call takes over an established call from another
+    /// of the local user's devices.
+    pub fn is_take_over(&self) -> bool {
+        self.take_over.load(Ordering::Acquire)
+    }
+
+    /// Return the remote device taking over the call, if a transfer is
+    /// under way.
+    pub fn transfer_device_id(&self) -> Result<Option<DeviceId>> {
+        Ok(*self.transfer_device.lock()?)
+    }
+
+    /// Make the connection to the device that took over the call the active
+    /// connection.
+    pub fn complete_transfer(&self, remote_device: DeviceId) -> Result<()> {
+        *self.active_device_id.lock()? = Some(remote_device);
+        *self.transfer_device.lock()? = None;
+        Ok(())
+    }
+
+    /// Drop the connection to a remote device that is taking over the call,
+    /// leaving the call as it was.  Returns false if the device isn't (or is
+    /// no longer) taking over the call.
+    pub fn abandon_transfer(&self, remote_device: DeviceId) -> Result<bool> {
+        let mut connection_map = self.connection_map.lock()?;
+        let mut transfer_device = self.transfer_device.lock()?;
+        if *transfer_device != Some(remote_device) {
+            return Ok(false);
+        }
+        *transfer_device = None;
+        if let Some(mut connection) = connection_map.remove(&remote_device) {
+            connection.terminate()?;
+        }
+        Ok(true)
+    }
+
     /// For an incoming call, create a PendingCall structure for
     /// holding the offer and ICE candidates sent by the remote side
     /// *before* the application has formally decided to accept the
@@ -561,6 +647,11 @@ where
                 )?;
                 let (local_secret, ice_gatherer, offer) =
                     parent_connection.start_outgoing_parent(self.media_type, bandwidth_mode)?;
+                let offer = if self.is_take_over() {
+                    offer.to_transfer()?
+                } else {
+                    offer
+                };
 
                 // Keep around so that it's not closed until all the connections are closed.
                 *(self.forking.lock()?) = Some(ForkingState {
@@ -578,6 +669,54 @@ where
         Ok(())
     }
 
+    /// Start a connection to another device of the remote user, which wants
+    /// to take over the call.  The active connection carries on until the
+    /// new one is ready.
+    pub fn received_transfer_offer(&self, received: signaling::ReceivedOffer) -> Result<()> {
+        let remote_device_id = received.sender_device_id;
+        info!(
+            "received_transfer_offer(): id: {}",
+            self.call_id().format(remote_device_id)
+        );
+
+        let mut call_manager = self.call_manager()?;
+        let bandwidth_mode = self.active_connection()?.local_bandwidth_mode()?;
+        let mut connection = call_manager.create_connection(
+            self,
+            remote_device_id,
+            ConnectionType::Incoming,
+            received.offer.latest_version(),
+            bandwidth_mode,
+        )?;
+        let answer = connection.start_incoming(received, Vec::new())?;
+        call_manager.send_answer(
+            self.clone(),
+            connection.clone(),
+            signaling::SendAnswer {
+                receiver_device_id: remote_device_id,
+                answer,
+            },
+        )?;
+
+        let mut connection_map = self.connection_map.lock()?;
+        let mut transfer_device = self.transfer_device.lock()?;
+        // Only one device can take over at a time, so drop an earlier attempt.
+        if let Some(previous_device_id) = transfer_device.take() {
+            if let Some(mut previous) = connection_map.remove(&previous_device_id) {
+                info!(
+                    "received_transfer_offer(): replacing transfer to device {}",
+                    previous_device_id
+                );
+                previous.terminate()?;
+            }
+        }
+        if let Some(mut replaced) = connection_map.insert(remote_device_id, connection) {
+            replaced.terminate()?;
+        }
+        *transfer_device = Some(remote_device_id);
+        Ok(())
+    }
+
     /// Handle the received answer.
     pub fn received_answer(&self, received: signaling::ReceivedAnswer) -> Result<()> {
         let sender_device_id = received.sender_device_id;
@@ -585,6 +724,7 @@ where
         if let hash_map::Entry::Vacant(e) = connection_map.entry(sender_device_id) {
             if self.state()? == CallState::ConnectedAndAccepted
                 || self.state()? == CallState::ReconnectingAfterAccepted
+                || self.state()? == CallState::OnHold
             {
                 info!(
                     "received_answer from device {} when already accepted, so ignore",
@@ -644,6 +784,7 @@ where
                 None => {
                     if self.state()? == CallState::ConnectedAndAccepted
                         || self.state()? == CallState::ReconnectingAfterAccepted
+                        || self.state()? == CallState::OnHold
                     {
                         // This can happen when call forking is enabled.
                         info!(
@@ -744,6 +885,12 @@ where
     pub fn handle_ice_failed(&mut self, remote_device: DeviceId) -> Result<()> {
         info!("ice_failed(): id: {}", self.call_id().format(remote_device));
 
+        if self.abandon_transfer(remote_device)? {
+            // The transfer failed, but the call carries on as before.
+            info!("ice_failed(): transfer connection");
+            return Ok(());
+        }
+
         if let Ok(active_device_id) = self.active_device_id() {
             // There is an active connection.
             if active_device_id == remote_device {
@@ -938,6 +1085,16 @@ where
         self.inject_event(CallEvent::AcceptCall)
     }
 
+    /// Inject a Hold event into the FSM.
+    pub fn inject_hold(&mut self) -> Result<()> {
+        self.inject_event(CallEvent::Hold)
+    }
+
+    /// Inject a Resume event into the FSM.
+    pub fn inject_resume(&mut self) -> Result<()> {
+        self.inject_event(CallEvent::Resume)
+    }
+
     /// Inject a local `SendHangupViaDataChannelToAll` event into the FSM.
     pub fn inject_send_hangup_via_data_channel_to_all(
         &mut self,
@@ -947,6 +1104,14 @@ where
         self.inject_event(CallEvent::SendHangupViaDataChannelToAll(hangup))
     }
 
+    /// Inject a `ReceivedTransferOffer` event into the FSM
+    pub fn inject_received_transfer_offer(
+        &mut self,
+        received: signaling::ReceivedOffer,
+    ) -> Result<()> {
+        self.inject_event(CallEvent::ReceivedTransferOffer(received))
+    }
+
     /// Inject a `ReceivedAnswer` event into the FSM
     pub fn inject_received_answer(&mut self, received: signaling::ReceivedAnswer) -> Result<()> {
         self.inject_event(CallEvent::ReceivedAnswer(received))
@@ -1001,6 +1166,15 @@ where
         self.inject_event(event)
     }
 
+    /// Inject a local `TransferTimeout` event into the FSM.
+    ///
+    /// `Called By:` Local timeout thread.
+    ///
+    pub fn inject_transfer_timeout(&mut self, remote_device: DeviceId) -> Result<()> {
+        let event = CallEvent::TransferTimeout(remote_device);
+        self.inject_event(event)
+    }
+
     #[allow(clippy::mutex_atomic)]
     /// Inject a synchronizing event into the FSM.
     ///
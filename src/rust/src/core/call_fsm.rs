@@ -14,6 +14,8 @@
 //!
 //! - StartOutgoingCall
 //! - Accept
+//! - Hold
+//! - Resume
 //! - LocalHangup
 //!
 //! ## Flow events from client application
@@ -29,18 +31,22 @@
 //! - RemoteVideoDisabled
 //! - RemoteSharingScreenEnabled
 //! - RemoteSharingScreenDisabled
+//! - RemoteHoldEnabled
+//! - RemoteHoldDisabled
 //! - RemoteHangup
 //! - IceFailed
 //! - Timeout
 //! - Reconnecting
 //!
 //! ## Signaling events from client application
+//! - ReceivedTransferOffer
 //! - ReceivedAnswer
 //! - ReceivedIce
 //!
 //! ## From Internal runtime
 //!
 //! - CallTimeout
+//! - TransferTimeout
 //! - InternalError
 
 use std::fmt;
@@ -81,6 +87,10 @@ pub enum CallEvent {
     StartCall,
     /// Accept incoming call (callee only).
     AcceptCall,
+    /// Put the accepted call on hold, to take another call.
+    Hold,
+    /// Take the call off hold.
+    Resume,
     /// Send Hangup
     SendHangupViaDataChannelToAll(signaling::Hangup),
 
@@ -89,6 +99,9 @@ pub enum CallEvent {
     Proceed(BandwidthMode),
 
     // Signaling events from client application
+    /// Received offer from another device of the remote user, which wants to
+    /// take over the call.
+    ReceivedTransferOffer(signaling::ReceivedOffer),
     /// Received answer from remote peer (caller only).
     ReceivedAnswer(signaling::ReceivedAnswer),
     /// Received ICE signaling from remote device.
@@ -106,6 +119,9 @@ pub enum CallEvent {
     InternalError(failure::Error),
     /// The call timed out while establishing a connection.
     CallTimeout,
+    /// A transfer to another remote device timed out while establishing a
+    /// connection.
+    TransferTimeout(DeviceId),
     /// Synchronize the FSM.
     Synchronize(Arc<(Mutex<bool>, Condvar)>),
     /// Terminate the call.
@@ -117,12 +133,17 @@ impl fmt::Display for CallEvent {
         let display = match self {
             CallEvent::StartCall => "StartCall".to_string(),
             CallEvent::AcceptCall => "AcceptCall".to_string(),
+            CallEvent::Hold => "Hold".to_string(),
+            CallEvent::Resume => "Resume".to_string(),
             CallEvent::SendHangupViaDataChannelToAll(hangup) => {
                 format!("SendHangupViaDataChannelToAll, hangup: {}", hangup)
             }
             CallEvent::Proceed(bandwidth_mode) => {
                 format!("Proceed, bandwidth_mode: {}", bandwidth_mode)
             }
+            CallEvent::ReceivedTransferOffer(received) => {
+                format!("ReceivedTransferOffer, device: {}", received.sender_device_id)
+            }
             CallEvent::ReceivedAnswer(received) => format!(
                 "ReceivedAnswer, device: {} feature_level: {}",
                 received.sender_device_id, received.sender_device_feature_level
@@ -142,6 +163,7 @@ impl fmt::Display for CallEvent {
             }
             CallEvent::InternalError(e) => format!("InternalError: {}", e),
             CallEvent::CallTimeout => "CallTimeout".to_string(),
+            CallEvent::TransferTimeout(d) => format!("TransferTimeout, device: {}", d),
             CallEvent::Synchronize(_) => "Synchronize".to_string(),
             CallEvent::Terminate => "Terminate".to_string(),
         };
@@ -326,6 +348,11 @@ where
             CallEvent::StartCall => self.handle_start_call(call, state),
             CallEvent::Proceed(bandwidth_mode) => self.handle_proceed(call, state, bandwidth_mode),
             CallEvent::AcceptCall => self.handle_accept_call(call, state),
+            CallEvent::Hold => self.handle_hold(call, state),
+            CallEvent::Resume => self.handle_resume(call, state),
+            CallEvent::ReceivedTransferOffer(received) => {
+                self.handle_received_transfer_offer(call, state, received)
+            }
             CallEvent::ReceivedAnswer(received) => {
                 self.handle_received_answer(call, state, received)
             }
@@ -341,6 +368,9 @@ where
             }
             CallEvent::InternalError(error) => self.handle_internal_error(call, error),
             CallEvent::CallTimeout => self.handle_call_timeout(call, state),
+            CallEvent::TransferTimeout(remote_device_id) => {
+                self.handle_transfer_timeout(call, remote_device_id)
+            }
             // Handled above
             CallEvent::SendHangupViaDataChannelToAll(_) => Ok(()),
             CallEvent::Synchronize(_) => Ok(()),
@@ -449,6 +479,35 @@ where
         Ok(())
     }
 
+    fn handle_received_transfer_offer(
+        &mut self,
+        call: Call<T>,
+        state: CallState,
+        received: signaling::ReceivedOffer,
+    ) -> Result<()> {
+        match state {
+            CallState::ConnectedAndAccepted
+            | CallState::ReconnectingAfterAccepted
+            | CallState::OnHold => {
+                let mut err_call = call.clone();
+                let received_offer_future = lazy(move |_| {
+                    if call.terminating()? {
+                        return Ok(());
+                    }
+                    call.received_transfer_offer(received)
+                })
+                .map_err(move |err| {
+                    err_call
+                        .inject_internal_error(err, "Handle Received Transfer Offer Future failed");
+                });
+
+                self.worker_spawn(received_offer_future);
+            }
+            _ => self.unexpected_state(state, "HandleReceivedTransferOffer"),
+        }
+        Ok(())
+    }
+
     fn handle_received_ice(
         &mut self,
         call: Call<T>,
@@ -460,7 +519,8 @@ where
             | CallState::ConnectingBeforeAccepted
             | CallState::ConnectedWithDataChannelBeforeAccepted
             | CallState::ConnectedAndAccepted
-            | CallState::ReconnectingAfterAccepted => {
+            | CallState::ReconnectingAfterAccepted
+            | CallState::OnHold => {
                 let mut err_call = call.clone();
                 let handle_received_ice_future = lazy(move |_| {
                     if call.terminating()? {
@@ -502,6 +562,17 @@ where
             return Ok(());
         }
 
+        // The hangup for a transfer goes to all of our devices, including the one
+        // that took the call over.
+        if hangup_type == signaling::HangupType::TransferredToAnotherDevice
+            && Some(call.local_device_id()) == hangup_device_id
+        {
+            info!(
+                "handle_received_hangup(): Ignoring hangup message for the transfer to this device"
+            );
+            return Ok(());
+        }
+
         // If already connected to device A, ignore hangup messages from device B.
         if let Ok(active_device_id) = call.active_device_id() {
             if sender_device_id != active_device_id {
@@ -554,6 +625,11 @@ where
                 app_event_without_propagation(ApplicationEvent::EndedRemoteHangupBusy)
             }
 
+            // Either side gets a transfer: another of our devices took the call over.
+            (signaling::HangupType::TransferredToAnotherDevice, _) => {
+                app_event_without_propagation(ApplicationEvent::EndedTransferredToAnotherDevice)
+            }
+
             // Everything else is unexpected: warn, and mostly treat like normal, no propagation.
             // TODO: Isn't NeedPermission for incoming normal because it's propagated above?
             // Should we make this no_app_event_and_no_propagation?
@@ -575,7 +651,8 @@ where
         | CallState::ConnectingBeforeAccepted
         | CallState::ConnectedWithDataChannelBeforeAccepted
         | CallState::ConnectedAndAccepted
-        | CallState::ReconnectingAfterAccepted = state
+        | CallState::ReconnectingAfterAccepted
+        | CallState::OnHold = state
         {
             call.set_state(CallState::Terminating)?;
         }
@@ -586,7 +663,7 @@ where
             // Hangup/Accepted has been sent to the other callees.
             // Don't propagate if we're already terminating/terminated because
             // we already sent out a Hangup to the other callees.
-            // Not for NotYetStarted | ConnectedAndAccepted | ReconnectingAfterAccepted | Terminating | Terminated states:
+            // Not for NotYetStarted | ConnectedAndAccepted | ReconnectingAfterAccepted | OnHold | Terminating | Terminated states:
             if let CallState::WaitingToProceed
             | CallState::ConnectingBeforeAccepted
             | CallState::ConnectedWithDataChannelBeforeAccepted = state
@@ -642,6 +719,61 @@ where
         Ok(())
     }
 
+    fn handle_hold(&mut self, call: Call<T>, state: CallState) -> Result<()> {
+        info!("handle_hold():");
+        match state {
+            CallState::ConnectedAndAccepted | CallState::ReconnectingAfterAccepted => {
+                call.set_state(CallState::OnHold)?;
+                let mut err_call = call.clone();
+                let hold_future = lazy(move |_| {
+                    if call.terminating()? {
+                        return Ok(());
+                    }
+                    let mut connection = call.active_connection()?;
+                    connection.inject_hold()
+                })
+                .map_err(move |err| {
+                    err_call.inject_internal_error(err, "Processing local hold request failed");
+                });
+
+                self.worker_spawn(hold_future);
+            }
+            _ => self.unexpected_state(state, "Hold"),
+        }
+        Ok(())
+    }
+
+    fn handle_resume(&mut self, call: Call<T>, state: CallState) -> Result<()> {
+        info!("handle_resume():");
+        match state {
+            CallState::OnHold => {
+                // If ICE dropped while on hold, the connection resumes into
+                // reconnecting, so the call does too.
+                if call.active_connection()?.ice_disconnected_on_hold()? {
+                    call.set_state(CallState::ReconnectingAfterAccepted)?;
+                    self.notify_application(call.clone(), ApplicationEvent::Reconnecting);
+                } else {
+                    call.set_state(CallState::ConnectedAndAccepted)?;
+                }
+                let mut err_call = call.clone();
+                let resume_future = lazy(move |_| {
+                    if call.terminating()? {
+                        return Ok(());
+                    }
+                    let mut connection = call.active_connection()?;
+                    connection.inject_resume()
+                })
+                .map_err(move |err| {
+                    err_call.inject_internal_error(err, "Processing local resume request failed");
+                });
+
+                self.worker_spawn(resume_future);
+            }
+            _ => self.unexpected_state(state, "Resume"),
+        }
+        Ok(())
+    }
+
     fn handle_send_hangup_via_data_channel_to_all(
         &mut self,
         call: Call<T>,
@@ -688,6 +820,7 @@ where
 
         match event {
             ConnectionObserverEvent::ConnectedWithDataChannelBeforeAccepted => {
+                let transferring = call.transfer_device_id()? == Some(remote_device_id);
                 match state {
                     CallState::ConnectingBeforeAccepted => {
                         // We use the fact that we are connected with a data channel
@@ -695,10 +828,22 @@ where
                         call.set_state(CallState::ConnectedWithDataChannelBeforeAccepted)?;
                         if let CallDirection::InComing = call.direction() {
                             self.notify_application(call, ApplicationEvent::LocalRinging)
+                        } else if call.is_take_over() {
+                            // Nothing rings, because the remote accepts right away.
+                            info!(
+                                "handle_connection_observer_event(): taking over from another device"
+                            );
                         } else {
                             self.notify_application(call, ApplicationEvent::RemoteRinging)
                         }
                     }
+                    CallState::ConnectedAndAccepted
+                    | CallState::ReconnectingAfterAccepted
+                    | CallState::OnHold
+                        if transferring =>
+                    {
+                        self.handle_transfer_connected(call, state, remote_device_id)?;
+                    }
                     _ => {
                         self.ignore_connection_observer_event(
                             call_id,
@@ -760,13 +905,16 @@ where
                                 };
 
                                 // Send the accepted indication via hangup signaling (it will be
-                                // replicated to all remote peers).
-                                let mut call_manager = call.call_manager()?;
-                                call_manager.send_hangup(
-                                    call.clone(),
-                                    call.call_id(),
-                                    signaling::SendHangup { hangup, use_legacy },
-                                )?;
+                                // replicated to all remote peers).  Not needed when taking over,
+                                // because no other device of the remote user answered.
+                                if !call.is_take_over() {
+                                    let mut call_manager = call.call_manager()?;
+                                    call_manager.send_hangup(
+                                        call.clone(),
+                                        call.call_id(),
+                                        signaling::SendHangup { hangup, use_legacy },
+                                    )?;
+                                }
 
                                 // Close all the other connections (this blocks).
                                 let mut call_clone = call.clone();
@@ -809,7 +957,7 @@ where
             ConnectionObserverEvent::ReceivedSenderStatusViaDataChannel(status) => {
                 if call.active_device_id()? == remote_device_id {
                     match state {
-                        CallState::ConnectedAndAccepted => {
+                        CallState::ConnectedAndAccepted | CallState::OnHold => {
                             if let Some(video_enabled) = status.video_enabled {
                                 if video_enabled {
                                     self.notify_application(
@@ -826,16 +974,29 @@ where
                             if let Some(sharing_screen) = status.sharing_screen {
                                 if sharing_screen {
                                     self.notify_application(
-                                        call,
+                                        call.clone(),
                                         ApplicationEvent::RemoteSharingScreenEnable,
                                     )
                                 } else {
                                     self.notify_application(
-                                        call,
+                                        call.clone(),
                                         ApplicationEvent::RemoteSharingScreenDisable,
                                     )
                                 }
                             }
+                            if let Some(held) = status.held {
+                                if held {
+                                    self.notify_application(
+                                        call,
+                                        ApplicationEvent::RemoteHoldEnable,
+                                    )
+                                } else {
+                                    self.notify_application(
+                                        call,
+                                        ApplicationEvent::RemoteHoldDisable,
+                                    )
+                                }
+                            }
                         }
                         _ => {
                             self.ignore_connection_observer_event(
@@ -938,6 +1099,69 @@ where
         }
     }
 
+    /// The remote user's other device that is taking over the call is connected,
+    /// so switch to it and drop the connection to the device it replaces.
+    fn handle_transfer_connected(
+        &mut self,
+        call: Call<T>,
+        state: CallState,
+        remote_device_id: DeviceId,
+    ) -> Result<()> {
+        info!(
+            "handle_transfer_connected(): call_id: {} remote_device_id: {}",
+            call.call_id(),
+            remote_device_id
+        );
+
+        if state == CallState::ReconnectingAfterAccepted {
+            call.set_state(CallState::ConnectedAndAccepted)?;
+            self.notify_application(call.clone(), ApplicationEvent::Reconnected);
+        }
+
+        let held = state == CallState::OnHold;
+        let mut err_call = call.clone();
+        let transfer_future = lazy(move |_| {
+            if call.terminating()? {
+                return Ok(());
+            }
+
+            call.complete_transfer(remote_device_id)?;
+            let mut connection = call.active_connection()?;
+            connection.inject_accept()?;
+            if held {
+                // The call stays on hold on the device taking over.
+                connection.inject_hold()?;
+            }
+            connection.connect_incoming_media()?;
+            connection.start_tick()?;
+
+            // Tell the replaced device, over the data channel if it's still up
+            // and over signaling in case it isn't.
+            let hangup = signaling::Hangup::TransferredToAnotherDevice(remote_device_id);
+            call.send_hangup_via_data_channel_to_all_except(hangup, remote_device_id)?;
+            call.call_manager()?.send_hangup(
+                call.clone(),
+                call.call_id(),
+                signaling::SendHangup {
+                    hangup,
+                    use_legacy: false,
+                },
+            )?;
+
+            call.notify_application(ApplicationEvent::RemoteTransferred)?;
+            call.notify_network_route_changed(connection.network_route()?)?;
+
+            // Close the connection to the replaced device (this blocks).
+            let mut call_clone = call.clone();
+            call_clone.terminate_connections_except_accepted(remote_device_id)
+        })
+        .map_err(move |err| {
+            err_call.inject_internal_error(err, "Processing transfer failed");
+        });
+        self.worker_spawn(transfer_future);
+        Ok(())
+    }
+
     fn handle_internal_error(&mut self, call: Call<T>, error: failure::Error) -> Result<()> {
         info!("handle_internal_error():");
 
@@ -971,7 +1195,9 @@ where
         info!("handle_call_timeout():");
 
         match state {
-            CallState::ConnectedAndAccepted | CallState::ReconnectingAfterAccepted => {} // Ok
+            CallState::ConnectedAndAccepted
+            | CallState::ReconnectingAfterAccepted
+            | CallState::OnHold => {} // Ok
             _ => {
                 let mut err_call = call.clone();
                 let timeout_future = lazy(move |_| {
@@ -988,6 +1214,27 @@ where
         Ok(())
     }
 
+    fn handle_transfer_timeout(&mut self, call: Call<T>, remote_device_id: DeviceId) -> Result<()> {
+        info!(
+            "handle_transfer_timeout(): remote_device_id: {}",
+            remote_device_id
+        );
+
+        let mut err_call = call.clone();
+        let timeout_future = lazy(move |_| {
+            if call.abandon_transfer(remote_device_id)? {
+                info!("handle_transfer_timeout(): transfer connection never connected");
+            }
+            Ok(())
+        })
+        .map_err(move |err| {
+            err_call.inject_internal_error(err, "Processing transfer timeout failed");
+        });
+
+        self.worker_spawn(timeout_future);
+        Ok(())
+    }
+
     fn handle_synchronize(&mut self, sync: Arc<(Mutex<bool>, Condvar)>) -> Result<()> {
         if let Some(worker_runtime) = &mut self.worker_runtime {
             CallStateMachine::<T>::sync_thread("worker", worker_runtime)?;
@@ -11,7 +11,7 @@ use std::fmt;
 use std::stringify;
 use std::sync::{Arc, Condvar, Mutex, MutexGuard};
 use std::thread;
-use std::time::{Duration, Instant, SystemTime};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use bytes::{Bytes, BytesMut};
 use futures::future::lazy;
@@ -36,12 +36,14 @@ use crate::core::bandwidth_mode::BandwidthMode;
 use crate::core::call::Call;
 use crate::core::call_mutex::CallMutex;
 use crate::core::connection::{Connection, ConnectionType};
+use crate::core::escalation::{Escalation, EscalationPhase, EscalationUpdate};
 use crate::core::group_call::Observer;
 use crate::core::http_client::HttpClient;
 use crate::core::platform::Platform;
+use crate::core::ring_store::{MemoryRingStore, OutstandingGroupRing, RingStore};
 use crate::core::sfu_client::SfuClient;
 use crate::core::util::{uuid_to_string, TaskQueueRuntime};
-use crate::core::{group_call, signaling};
+use crate::core::{group_call, resumption, signaling};
 use crate::error::RingRtcError;
 use crate::protobuf;
 use crate::webrtc::media::{AudioTrack, MediaStream, VideoTrack};
@@ -50,6 +52,9 @@ use crate::webrtc::peer_connection_factory::PeerConnectionFactory;
 
 const TIME_OUT_PERIOD: Duration = Duration::from_secs(60);
 pub const MAX_MESSAGE_AGE: Duration = Duration::from_secs(60);
+/// How long to remember ring IDs, so that rings replayed from the message queue
+/// (say, after a restart) don't ring again.
+const SEEN_RING_RETENTION: Duration = Duration::from_secs(60 * 60 * 24);
 
 /// Spawns a task on the worker runtime thread to handle an API
 /// request with error handling.
@@ -93,6 +98,43 @@ macro_rules! check_active_call {
     };
 }
 
+macro_rules! check_second_call {
+    (
+        $s:ident,
+        $f:expr
+    ) => {
+        match $s.second_call() {
+            Ok(v) => {
+                info!("{}(): second call_id: {}", $f, v.call_id());
+                v
+            }
+            _ => {
+                ringbenchx!(RingBench::Cm, RingBench::App, "no second call");
+                return Ok(());
+            }
+        }
+    };
+}
+
+macro_rules! check_current_call {
+    (
+        $s:ident,
+        $call_id:expr,
+        $f:expr
+    ) => {
+        match $s.current_call($call_id)? {
+            Some(v) => {
+                info!("{}(): current call_id: {}", $f, v.call_id());
+                v
+            }
+            None => {
+                ringbenchx!(RingBench::Cm, RingBench::App, "inactive call_id");
+                return Ok(());
+            }
+        }
+    };
+}
+
 /// Spawns a task on the worker runtime thread to handle an API
 /// request with no error handling.
 ///
@@ -178,16 +220,32 @@ struct HttpRequestTracker {
     next_request_id:    u32,
 }
 
-/// Information about a received group ring that hasn't yet been accepted or cancelled.
-#[derive(Debug)]
-struct OutstandingGroupRing {
-    ring_id:  group_call::RingId,
-    received: Instant,
+fn ring_status_from_response_type(
+    response_type: protobuf::signaling::call_message::ring_response::Type,
+) -> Option<group_call::RingStatus> {
+    use protobuf::signaling::call_message::ring_response::Type as ResponseType;
+    match response_type {
+        ResponseType::Ringing => None,
+        ResponseType::Accepted => Some(group_call::RingStatus::Accepted),
+        ResponseType::Declined => Some(group_call::RingStatus::Declined),
+        ResponseType::Busy => Some(group_call::RingStatus::Busy),
+    }
+}
+
+// The ring store keeps changes in memory even when it fails to save them, so a
+// failure only means that they may not survive a restart.  That's no reason to
+// stop handling calls.
+fn warn_if_ring_store_failed(result: Result<()>) {
+    if let Err(err) = result {
+        warn!("Failed to save to the ring store: {}", err);
+    }
 }
 
-impl OutstandingGroupRing {
-    fn has_expired(&self) -> bool {
-        self.received.elapsed() >= TIME_OUT_PERIOD
+fn remove_expired_group_rings(ring_store: &mut dyn RingStore) {
+    for (group_id, ring) in ring_store.rings() {
+        if ring.has_expired(TIME_OUT_PERIOD) {
+            warn_if_ring_store_failed(ring_store.remove_ring(&group_id));
+        }
     }
 }
 
@@ -203,12 +261,20 @@ where
     call_by_call_id:           Arc<CallMutex<HashMap<CallId, Call<T>>>>,
     /// CallId of the active call.
     active_call_id:            Arc<CallMutex<Option<CallId>>>,
+    /// CallId of the incoming call waiting to be answered, or of the call on
+    /// hold, alongside the active call.
+    second_call_id:            Arc<CallMutex<Option<CallId>>>,
+    /// Whether an offer received during an accepted call waits to be answered
+    /// instead of getting a busy response.
+    call_waiting_enabled:      Arc<CallMutex<bool>>,
     /// Map of all group calls.
     group_call_by_client_id:   Arc<CallMutex<HashMap<group_call::ClientId, group_call::Client>>>,
     /// Next value of the group call client id (sequential).
     next_group_call_client_id: Arc<CallMutex<u32>>,
-    /// Recent outstanding group rings, keyed by group ID.
-    outstanding_group_rings:   Arc<CallMutex<HashMap<group_call::GroupId, OutstandingGroupRing>>>,
+    /// Recent outstanding group rings, and the IDs of rings already seen.
+    ring_store:                Arc<CallMutex<Box<dyn RingStore>>>,
+    /// A direct call being moved to a group call, if any.
+    escalation:                Arc<CallMutex<Option<Escalation>>>,
     /// Busy indication if in either a direct or group call.
     busy:                      Arc<CallMutex<bool>>,
     /// Tokio runtime for back ground task execution.
@@ -274,9 +340,12 @@ where
             self_uuid:                 Arc::clone(&self.self_uuid),
             call_by_call_id:           Arc::clone(&self.call_by_call_id),
             active_call_id:            Arc::clone(&self.active_call_id),
+            second_call_id:            Arc::clone(&self.second_call_id),
+            call_waiting_enabled:      Arc::clone(&self.call_waiting_enabled),
             group_call_by_client_id:   Arc::clone(&self.group_call_by_client_id),
             next_group_call_client_id: Arc::clone(&self.next_group_call_client_id),
-            outstanding_group_rings:   Arc::clone(&self.outstanding_group_rings),
+            ring_store:                Arc::clone(&self.ring_store),
+            escalation:                Arc::clone(&self.escalation),
             busy:                      Arc::clone(&self.busy),
             worker_runtime:            Arc::clone(&self.worker_runtime),
             message_queue:             Arc::clone(&self.message_queue),
@@ -347,15 +416,18 @@ where
             self_uuid:                 Arc::new(CallMutex::new(None, "self_uuid")),
             call_by_call_id:           Arc::new(CallMutex::new(HashMap::new(), "call_by_call_id")),
             active_call_id:            Arc::new(CallMutex::new(None, "active_call_id")),
+            second_call_id:            Arc::new(CallMutex::new(None, "second_call_id")),
+            call_waiting_enabled:      Arc::new(CallMutex::new(false, "call_waiting_enabled")),
             group_call_by_client_id:   Arc::new(CallMutex::new(
                 HashMap::new(),
                 "group_call_by_client_id",
             )),
             next_group_call_client_id: Arc::new(CallMutex::new(0, "next_group_call_client_id")),
-            outstanding_group_rings:   Arc::new(CallMutex::new(
-                HashMap::new(),
-                "outstanding_group_rings",
+            ring_store:                Arc::new(CallMutex::new(
+                Box::new(MemoryRingStore::default()),
+                "ring_store",
             )),
+            escalation:                Arc::new(CallMutex::new(None, "escalation")),
             busy:                      Arc::new(CallMutex::new(false, "busy")),
             worker_runtime:            Arc::new(CallMutex::new(
                 Some(TaskQueueRuntime::new("call-manager-worker")?),
@@ -408,7 +480,13 @@ where
         let mut cm_error = self.clone();
         let remote_peer_error = remote_peer.clone();
         let future = lazy(move |_| {
-            call_manager.handle_call(remote_peer, call_id, call_media_type, local_device_id)
+            call_manager.handle_call(
+                remote_peer,
+                call_id,
+                call_media_type,
+                local_device_id,
+                false,
+            )
         })
         .map_err(move |err| {
             error!("Handle call failed: {}", err);
@@ -417,12 +495,49 @@ where
         self.worker_spawn(future)
     }
 
+    /// Take over a direct call that another of the local user's devices is
+    /// in, using the same CallId.
+    ///
+    /// The call starts like an outgoing call, but its offer is marked as a
+    /// transfer.  The remote device in the call answers it without ringing,
+    /// and once this device is connected it hangs up on the device that was
+    /// in the call.  The application gets RemoteAccepted when the call has
+    /// moved here, and the other device ends with
+    /// EndedTransferredToAnotherDevice.
+    pub fn take_over_call(
+        &mut self,
+        remote_peer: <T as Platform>::AppRemotePeer,
+        call_id: CallId,
+        call_media_type: CallMediaType,
+        local_device_id: DeviceId,
+    ) -> Result<()> {
+        info!("API:take_over_call({}):", call_id);
+
+        let mut call_manager = self.clone();
+        let mut cm_error = self.clone();
+        let remote_peer_error = remote_peer.clone();
+        let future = lazy(move |_| {
+            call_manager.handle_call(
+                remote_peer,
+                call_id,
+                call_media_type,
+                local_device_id,
+                true,
+            )
+        })
+        .map_err(move |err| {
+            error!("Handle take over call failed: {}", err);
+            cm_error.internal_create_api_error(&remote_peer_error, call_id, err);
+        });
+        self.worker_spawn(future)
+    }
+
     /// Accept an incoming call.
     pub fn accept_call(&mut self, call_id: CallId) -> Result<()> {
         handle_active_call_api!(self, CallManager::handle_accept_call, call_id)
     }
 
-    /// Drop the active call.
+    /// Drop the active call, or the call waiting or on hold.
     pub fn drop_call(&mut self, call_id: CallId) -> Result<()> {
         handle_active_call_api!(self, CallManager::handle_drop_call, call_id)
     }
@@ -454,22 +569,92 @@ where
     }
 
     /// Local hangup of the active call.
+    ///
+    /// If there's a call waiting or on hold, it becomes the active call.
     pub fn hangup(&mut self) -> Result<()> {
         handle_active_call_api!(self, CallManager::handle_hangup)
     }
 
+    /// Lets an offer received during an accepted direct call wait to be
+    /// answered, instead of getting a busy response.  Off by default.
+    ///
+    /// The waiting call starts like any other incoming call, but the active
+    /// call stays active; answer it with accept_waiting_call or decline it
+    /// with hangup_second_call.  Only one call can wait or be on hold at a time.
+    pub fn set_call_waiting_enabled(&mut self, enabled: bool) -> Result<()> {
+        info!("set_call_waiting_enabled(): {}", enabled);
+        *self.call_waiting_enabled.lock()? = enabled;
+        Ok(())
+    }
+
+    /// Put the active call on hold and accept the waiting call, which becomes
+    /// the active call.
+    pub fn accept_waiting_call(&mut self, call_id: CallId) -> Result<()> {
+        handle_active_call_api!(self, CallManager::handle_accept_waiting_call, call_id)
+    }
+
+    /// Put the active call on hold and resume the call on hold, which becomes
+    /// the active call.
+    pub fn swap_calls(&mut self) -> Result<()> {
+        handle_active_call_api!(self, CallManager::handle_swap_calls)
+    }
+
+    /// Put the active call on hold.
+    pub fn hold_call(&mut self, call_id: CallId) -> Result<()> {
+        handle_active_call_api!(self, CallManager::handle_hold_call, call_id)
+    }
+
+    /// Take the active call off hold, such as once the other call has ended.
+    pub fn resume_call(&mut self, call_id: CallId) -> Result<()> {
+        handle_active_call_api!(self, CallManager::handle_resume_call, call_id)
+    }
+
+    /// Local hangup of the call waiting (declining it) or on hold.
+    pub fn hangup_second_call(&mut self) -> Result<()> {
+        handle_active_call_api!(self, CallManager::handle_hangup_second_call)
+    }
+
+    /// Replace where outstanding group rings are kept, such as with a
+    /// [FileRingStore](crate::core::ring_store::FileRingStore) so that they
+    /// survive restarts.  Should be called before any call messages are received.
+    ///
+    /// Restored rings that haven't expired are reported to the application
+    /// again, and still expire on time.  Ones that expired in the meantime are
+    /// reported as expired.
+    pub fn set_ring_store(&mut self, ring_store: Box<dyn RingStore>) -> Result<()> {
+        handle_api!(self, CallManager::handle_set_ring_store, ring_store)
+    }
+
+    /// Returns false if the ring wasn't outstanding.
     fn remove_outstanding_group_ring(
         &mut self,
         group_id: group_call::GroupIdRef,
         ring_id: group_call::RingId,
-    ) -> Result<()> {
-        let mut outstanding_group_rings = self.outstanding_group_rings.lock()?;
-        if let Some(ring) = outstanding_group_rings.get(group_id) {
-            if ring.ring_id == ring_id {
-                outstanding_group_rings.remove(group_id);
+    ) -> Result<bool> {
+        let mut ring_store = self.ring_store.lock()?;
+        match ring_store.ring(group_id) {
+            Some(ring) if ring.ring_id == ring_id => {
+                warn_if_ring_store_failed(ring_store.remove_ring(group_id));
+                Ok(true)
             }
+            _ => Ok(false),
         }
-        Ok(())
+    }
+
+    /// Returns false if the ring had already been seen.
+    fn mark_group_ring_seen(&mut self, ring_id: group_call::RingId) -> Result<bool> {
+        let now = SystemTime::now();
+        let mut ring_store = self.ring_store.lock()?;
+        // Take this opportunity to forget old ring IDs.
+        warn_if_ring_store_failed(
+            ring_store
+                .forget_seen_before(now.checked_sub(SEEN_RING_RETENTION).unwrap_or(UNIX_EPOCH)),
+        );
+        if ring_store.has_seen(ring_id) {
+            return Ok(false);
+        }
+        warn_if_ring_store_failed(ring_store.mark_seen(ring_id, now));
+        Ok(true)
     }
 
     /// Cancel a group ring.
@@ -481,6 +666,24 @@ where
     ) -> Result<()> {
         info!("cancel_group_ring(): ring_id: {}", ring_id);
 
+        let ringer = self
+            .ring_store
+            .lock()?
+            .ring(&group_id)
+            .filter(|ring| ring.ring_id == ring_id)
+            .map(|ring| ring.sender_uuid);
+        self.cancel_group_ring_and_respond(group_id, ring_id, reason, ringer)
+    }
+
+    /// Cancel a group ring, and if there's a reason, let the other devices of the
+    /// current user and the ringer (if known) know.
+    fn cancel_group_ring_and_respond(
+        &mut self,
+        group_id: group_call::GroupId,
+        ring_id: group_call::RingId,
+        reason: Option<group_call::RingCancelReason>,
+        ringer: Option<group_call::UserId>,
+    ) -> Result<()> {
         self.remove_outstanding_group_ring(&group_id, ring_id)?;
 
         if let Some(reason) = reason {
@@ -504,6 +707,13 @@ where
                     }),
                     ..Default::default()
                 };
+                if let Some(ringer) = ringer {
+                    self.send_signaling_message(
+                        ringer,
+                        message.clone(),
+                        group_call::SignalingMessageUrgency::HandleImmediately,
+                    );
+                }
                 self.send_signaling_message(
                     self_uuid,
                     message,
@@ -656,6 +866,21 @@ where
         }
     }
 
+    /// Returns the call waiting or on hold alongside the active call.
+    pub fn second_call(&self) -> Result<Call<T>> {
+        let second_call_id = self.second_call_id.lock()?;
+        match *second_call_id {
+            Some(call_id) => {
+                let call_map = self.call_by_call_id.lock()?;
+                match call_map.get(&call_id) {
+                    Some(call) => Ok(call.clone()),
+                    None => Err(RingRtcError::CallIdNotFound(call_id).into()),
+                }
+            }
+            None => Err(RingRtcError::NoSecondCall.into()),
+        }
+    }
+
     /// Return active connection object.
     pub fn active_connection(&self) -> Result<Connection<T>> {
         info!("active_connection():");
@@ -677,6 +902,21 @@ where
         }
     }
 
+    /// Check if call_id refers to the call waiting or on hold.
+    pub fn call_is_second(&self, call_id: CallId) -> Result<bool> {
+        Ok(*self.second_call_id.lock()? == Some(call_id))
+    }
+
+    /// Returns the call with the given call_id if it's the active call or
+    /// the second call.
+    fn current_call(&self, call_id: CallId) -> Result<Option<Call<T>>> {
+        if self.call_is_active(call_id)? || self.call_is_second(call_id)? {
+            Ok(self.call_by_call_id.lock()?.get(&call_id).cloned())
+        } else {
+            Ok(None)
+        }
+    }
+
     /// Return the platform, under a locked mutex.
     pub fn platform(&self) -> Result<MutexGuard<'_, T>> {
         self.platform.lock()
@@ -740,7 +980,7 @@ where
         Ok(())
     }
 
-    #[cfg(feature = "sim")]
+    #[cfg(any(feature = "simnet", feature = "sim"))]
     pub fn pause_clock(&mut self) -> Result<crate::core::util::ClockPauseGuard> {
         let mut worker_runtime = self.worker_runtime.lock()?;
         if let Some(worker_runtime) = &mut *worker_runtime {
@@ -829,8 +1069,51 @@ where
         Ok(())
     }
 
-    /// Releases busy so another call can begin
+    /// Clears the active call_id, making the second call (if any) the
+    /// active call.  Busy is only released once no call is left.
+    fn end_active_call(&mut self) -> Result<()> {
+        let calls_left = {
+            let mut active_call_id = self.active_call_id.lock()?;
+            *active_call_id = self.second_call_id.lock()?.take();
+            active_call_id.is_some()
+        };
+        if !calls_left {
+            self.release_busy()?;
+        }
+        Ok(())
+    }
+
+    /// Clears the second call_id if it refers to the given call.
+    fn clear_second_call(&mut self, call_id: CallId) -> Result<()> {
+        let mut second_call_id = self.second_call_id.lock()?;
+        if *second_call_id == Some(call_id) {
+            *second_call_id = None;
+        }
+        Ok(())
+    }
+
+    /// Makes the second call the active call, and the active call the
+    /// second call.
+    fn swap_active_and_second_call(&mut self) -> Result<()> {
+        let mut active_call_id = self.active_call_id.lock()?;
+        let mut second_call_id = self.second_call_id.lock()?;
+        std::mem::swap(&mut *active_call_id, &mut *second_call_id);
+        Ok(())
+    }
+
+    /// Releases busy so another call can begin, unless the group call that
+    /// the direct call is moving to has taken it over.
     fn release_busy(&mut self) -> Result<()> {
+        if self
+            .escalation
+            .lock()?
+            .as_ref()
+            .map(Escalation::holds_busy)
+            .unwrap_or(false)
+        {
+            return Ok(());
+        }
+
         let mut busy = self.busy.lock()?;
         *busy = false;
 
@@ -902,6 +1185,8 @@ where
 
         info!("conclude_call(): call_id: {}", call_id);
 
+        self.clear_second_call(call_id)?;
+        self.end_escalation_of_ended_call(call_id)?;
         self.trim_messages(call_id)?;
 
         if let Some(event) = event {
@@ -957,8 +1242,22 @@ where
         }
 
         let call = self.active_call()?;
-        self.clear_active_call()?;
-        self.release_busy()?;
+        self.end_active_call()?;
+
+        let hangup = if send_hangup {
+            Some(signaling::Hangup::Normal)
+        } else {
+            None
+        };
+
+        self.terminate_call(call, hangup, Some(event))
+    }
+
+    /// Terminates the call waiting or on hold, leaving the active call as is.
+    fn terminate_second_call(&mut self, send_hangup: bool, event: ApplicationEvent) -> Result<()> {
+        info!("terminate_second_call():");
+
+        let call = self.second_call()?;
 
         let hangup = if send_hangup {
             Some(signaling::Hangup::Normal)
@@ -976,13 +1275,14 @@ where
         call_id: CallId,
         call_media_type: CallMediaType,
         local_device_id: DeviceId,
+        take_over: bool,
     ) -> Result<()> {
         ringbench!(
             RingBench::App,
             RingBench::Cm,
             format!(
-                "call()\t{}\t{}\t{}",
-                call_id, call_media_type, local_device_id
+                "call()\t{}\t{}\t{}\ttake_over={}",
+                call_id, call_media_type, local_device_id, take_over
             )
         );
 
@@ -1003,6 +1303,9 @@ where
                         local_device_id,
                         self.clone(),
                     )?;
+                    if take_over {
+                        call.set_take_over();
+                    }
 
                     // Whenever there is a new call, ensure that messages can flow.
                     self.reset_messages_in_flight()?;
@@ -1042,8 +1345,7 @@ where
         hangup: Option<signaling::Hangup>,
         event: ApplicationEvent,
     ) -> Result<()> {
-        self.clear_active_call()?;
-        self.release_busy()?;
+        self.end_active_call()?;
         self.terminate_call(active_call, hangup, Some(event))
     }
 
@@ -1055,6 +1357,10 @@ where
             format!("drop()\t{}", call_id)
         );
 
+        if self.call_is_second(call_id)? {
+            return self.terminate_second_call(false, ApplicationEvent::EndedAppDroppedCall);
+        }
+
         let active_call = check_active_call!(self, "handle_drop_call");
         if active_call.call_id() != call_id {
             ringbenchx!(RingBench::Cm, RingBench::App, "inactive call_id");
@@ -1077,14 +1383,9 @@ where
             format!("proceed()\t{}", call_id)
         );
 
-        let mut active_call = check_active_call!(self, "handle_proceed");
-        if active_call.call_id() != call_id {
-            ringbenchx!(RingBench::Cm, RingBench::App, "inactive call_id");
-            Ok(())
-        } else {
-            active_call.set_call_context(app_call_context)?;
-            active_call.inject_proceed(bandwidth_mode)
-        }
+        let mut call = check_current_call!(self, call_id, "handle_proceed");
+        call.set_call_context(app_call_context)?;
+        call.inject_proceed(bandwidth_mode)
     }
 
     /// Handle message_sent() API from application.
@@ -1104,24 +1405,23 @@ where
         let mut is_active_call = false;
         let mut should_handle = true;
 
-        if let Ok(active_call) = self.active_call() {
-            if active_call.call_id() == call_id {
-                is_active_call = true;
-                if let Ok(state) = active_call.state() {
-                    match state {
-                        CallState::ConnectedWithDataChannelBeforeAccepted
-                        | CallState::ConnectedAndAccepted
-                        | CallState::ReconnectingAfterAccepted => {
-                            // Get the last sent message type and see if it was for ICE.
-                            // Since we are in a connected state, don't handle it if so.
-                            if let Ok(message_queue) = self.message_queue.lock() {
-                                if message_queue.last_sent_message_type == Some(signaling::MessageType::Ice) {
-                                    should_handle = false
-                                }
+        if let Ok(Some(call)) = self.current_call(call_id) {
+            is_active_call = self.call_is_active(call_id)?;
+            if let Ok(state) = call.state() {
+                match state {
+                    CallState::ConnectedWithDataChannelBeforeAccepted
+                    | CallState::ConnectedAndAccepted
+                    | CallState::ReconnectingAfterAccepted
+                    | CallState::OnHold => {
+                        // Get the last sent message type and see if it was for ICE.
+                        // Since we are in a connected state, don't handle it if so.
+                        if let Ok(message_queue) = self.message_queue.lock() {
+                            if message_queue.last_sent_message_type == Some(signaling::MessageType::Ice) {
+                                should_handle = false
                             }
                         }
-                        _ => {}
                     }
+                    _ => {}
                 }
             }
         }
@@ -1190,6 +1490,111 @@ where
         )
     }
 
+    /// Handle accept_waiting_call() API from application.
+    fn handle_accept_waiting_call(&mut self, call_id: CallId) -> Result<()> {
+        ringbench!(
+            RingBench::App,
+            RingBench::Cm,
+            format!("accept_waiting()\t{}", call_id)
+        );
+
+        let mut waiting_call = check_second_call!(self, "handle_accept_waiting_call");
+        if waiting_call.call_id() != call_id {
+            ringbenchx!(RingBench::Cm, RingBench::App, "inactive call_id");
+            return Ok(());
+        }
+        // Don't hold the active call for a call that can't be accepted yet.
+        if waiting_call.state()? != CallState::ConnectedWithDataChannelBeforeAccepted {
+            ringbenchx!(RingBench::Cm, RingBench::App, "not ringing");
+            return Ok(());
+        }
+
+        let mut active_call = check_active_call!(self, "handle_accept_waiting_call");
+        active_call.inject_hold()?;
+        self.swap_active_and_second_call()?;
+        waiting_call.inject_accept_call()
+    }
+
+    /// Handle swap_calls() API from application.
+    fn handle_swap_calls(&mut self) -> Result<()> {
+        ringbench!(RingBench::App, RingBench::Cm, "swap_calls()");
+
+        let mut held_call = check_second_call!(self, "handle_swap_calls");
+        if held_call.state()? != CallState::OnHold {
+            ringbenchx!(RingBench::Cm, RingBench::App, "second call not on hold");
+            return Ok(());
+        }
+
+        let mut active_call = check_active_call!(self, "handle_swap_calls");
+        active_call.inject_hold()?;
+        self.swap_active_and_second_call()?;
+        held_call.inject_resume()
+    }
+
+    /// Handle hold_call() API from application.
+    fn handle_hold_call(&mut self, call_id: CallId) -> Result<()> {
+        ringbench!(
+            RingBench::App,
+            RingBench::Cm,
+            format!("hold()\t{}", call_id)
+        );
+
+        let mut active_call = check_active_call!(self, "handle_hold_call");
+        if active_call.call_id() != call_id {
+            ringbenchx!(RingBench::Cm, RingBench::App, "inactive call_id");
+            return Ok(());
+        }
+
+        active_call.inject_hold()
+    }
+
+    /// Handle resume_call() API from application.
+    fn handle_resume_call(&mut self, call_id: CallId) -> Result<()> {
+        ringbench!(
+            RingBench::App,
+            RingBench::Cm,
+            format!("resume()\t{}", call_id)
+        );
+
+        let mut active_call = check_active_call!(self, "handle_resume_call");
+        if active_call.call_id() != call_id {
+            ringbenchx!(RingBench::Cm, RingBench::App, "inactive call_id");
+            return Ok(());
+        }
+
+        active_call.inject_resume()
+    }
+
+    /// Handle hangup_second_call() API from application.
+    fn handle_hangup_second_call(&mut self) -> Result<()> {
+        ringbench!(RingBench::App, RingBench::Cm, "hangup_second_call()");
+
+        if self.second_call_id.lock()?.is_none() {
+            ringbenchx!(RingBench::Cm, RingBench::App, "no second call");
+            return Ok(());
+        }
+
+        self.terminate_second_call(true, ApplicationEvent::EndedLocalHangup)
+    }
+
+    /// Whether an incoming call can wait behind the active call: call waiting
+    /// is enabled, the active call has been accepted, and no other call is
+    /// waiting or on hold.
+    fn can_wait_behind(&self, active_call: &Call<T>) -> Result<bool> {
+        if !*self.call_waiting_enabled.lock()?
+            || self.second_call_id.lock()?.is_some()
+            || self.escalation.lock()?.is_some()
+        {
+            return Ok(false);
+        }
+        Ok(matches!(
+            active_call.state()?,
+            CallState::ConnectedAndAccepted
+                | CallState::ReconnectingAfterAccepted
+                | CallState::OnHold
+        ))
+    }
+
     /// Handle received_offer() API from application.
     fn handle_received_offer(
         &mut self,
@@ -1236,6 +1641,10 @@ where
             return Ok(());
         }
 
+        if received.offer.is_transfer() {
+            return self.handle_received_transfer_offer(remote_peer, incoming_call_id, received);
+        }
+
         let cm_clone = self.clone();
         let mut busy = cm_clone.busy.lock()?;
 
@@ -1329,10 +1738,20 @@ where
             Ignore(ApplicationEvent),
             RejectAsBusy(ApplicationEvent),
             Start,
+            Wait,
         }
 
+        let can_wait = match &active_call {
+            Some(active_call) => self.can_wait_behind(active_call)?,
+            None => false,
+        };
+
         let (active_call_action, incoming_call_action) = match collision {
             Collision::None => (ActiveCallAction::DontTerminate, IncomingCallAction::Start),
+            Collision::Busy if can_wait => {
+                info!("handle_received_offer(): call waiting");
+                (ActiveCallAction::DontTerminate, IncomingCallAction::Wait)
+            }
             Collision::Busy => (
                 ActiveCallAction::DontTerminate,
                 IncomingCallAction::RejectAsBusy(ApplicationEvent::ReceivedOfferWhileActive),
@@ -1355,7 +1774,7 @@ where
             ActiveCallAction::DontTerminate => {}
             ActiveCallAction::Terminate(app_event) => {
                 self.clear_active_call()?;
-                *busy = false;
+                *busy = self.second_call_id.lock()?.is_some();
                 self.terminate_call(
                     active_call.unwrap(),
                     Some(signaling::Hangup::Normal),
@@ -1390,10 +1809,52 @@ where
                 incoming_call.handle_received_offer(received)?;
                 incoming_call.inject_start_call()?
             }
+            IncomingCallAction::Wait => {
+                // The active call carries on, and the application decides
+                // whether to answer this one.
+                let mut second_call_id = self.second_call_id.lock()?;
+                let mut call_map = self.call_by_call_id.lock()?;
+                call_map.insert(incoming_call_id, incoming_call.clone());
+
+                *second_call_id = Some(incoming_call_id);
+                incoming_call.start_timeout_timer(TIME_OUT_PERIOD)?;
+                incoming_call.handle_received_offer(received)?;
+                incoming_call.inject_start_call()?
+            }
         }
         Ok(())
     }
 
+    /// Handle an offer from another device of the remote user of the active
+    /// call, which wants to take the call over.
+    ///
+    /// Transfer offers go to all of the local user's devices, so one for a
+    /// call that isn't active here is ignored rather than rung.
+    fn handle_received_transfer_offer(
+        &mut self,
+        remote_peer: <T as Platform>::AppRemotePeer,
+        call_id: CallId,
+        received: signaling::ReceivedOffer,
+    ) -> Result<()> {
+        if self.call_is_active(call_id)? {
+            let mut active_call = self.active_call()?;
+            if !self.remote_peer_equals_active(&active_call, &remote_peer) {
+                warn!("handle_received_transfer_offer(): remote peer doesn't match, ignoring");
+            } else if active_call.active_device_id().ok() == Some(received.sender_device_id) {
+                warn!("handle_received_transfer_offer(): offer from the device already in the call, ignoring");
+            } else {
+                let sender_device_id = received.sender_device_id;
+                active_call.inject_received_transfer_offer(received)?;
+                active_call.start_transfer_timeout_timer(sender_device_id, TIME_OUT_PERIOD)?;
+            }
+        } else {
+            ringbenchx!(RingBench::Cm, RingBench::App, "transfer of inactive call");
+        }
+
+        // The active call keeps its own remote peer, so we're done with this one.
+        self.notify_call_concluded(&remote_peer, call_id)
+    }
+
     /// Handle received_answer() API from application.
     fn handle_received_answer(
         &mut self,
@@ -1411,13 +1872,8 @@ where
             )
         );
 
-        let mut active_call = check_active_call!(self, "handle_received_answer");
-        if active_call.call_id() != call_id {
-            ringbenchx!(RingBench::Cm, RingBench::App, "inactive call_id");
-            return Ok(());
-        }
-
-        active_call.inject_received_answer(received)
+        let mut call = check_current_call!(self, call_id, "handle_received_answer");
+        call.inject_received_answer(received)
     }
 
     /// Handle received_ice() API from application.
@@ -1437,13 +1893,8 @@ where
             )
         );
 
-        let mut active_call = check_active_call!(self, "handle_received_ice");
-        if active_call.call_id() != call_id {
-            ringbenchx!(RingBench::Cm, RingBench::App, "inactive call_id");
-            return Ok(());
-        }
-
-        active_call.inject_received_ice(received)
+        let mut call = check_current_call!(self, call_id, "handle_received_ice");
+        call.inject_received_ice(received)
     }
 
     /// Handle received_hangup() API from application.
@@ -1461,13 +1912,8 @@ where
             )
         );
 
-        let mut active_call = check_active_call!(self, "handle_received_hangup");
-        if active_call.call_id() != call_id {
-            ringbenchx!(RingBench::Cm, RingBench::App, "inactive call_id");
-            return Ok(());
-        }
-
-        active_call.inject_received_hangup(received)
+        let mut call = check_current_call!(self, call_id, "handle_received_hangup");
+        call.inject_received_hangup(received)
     }
 
     /// Handle received_busy() API from application.
@@ -1557,36 +2003,45 @@ where
                     ring_intention.ring_id,
                 ) {
                     (Some(group_id), Some(ring_type), Some(ring_id)) => {
+                        let ring_id = group_call::RingId::from(ring_id);
+                        // A cancellation still counts as seeing the ring, in
+                        // case the ring itself arrives later.
+                        if !self.mark_group_ring_seen(ring_id)? && ring_type == IntentionType::Ring
+                        {
+                            info!("Ignoring already seen ring: ring_id: {}", ring_id);
+                            return Ok(());
+                        }
                         let ring_update = match ring_type {
                             IntentionType::Ring => {
                                 if message_age >= MAX_MESSAGE_AGE {
                                     group_call::RingUpdate::ExpiredRequest
                                 } else if *self.busy.lock()? {
-                                    // Let your other devices know.
-                                    self.cancel_group_ring(
+                                    // Let your other devices and the ringer know.
+                                    self.cancel_group_ring_and_respond(
                                         group_id.clone(),
-                                        ring_id.into(),
+                                        ring_id,
                                         Some(group_call::RingCancelReason::Busy),
+                                        Some(sender_uuid.clone()),
                                     )?;
                                     group_call::RingUpdate::BusyLocally
                                 } else {
                                     self.start_group_ring(
                                         group_id.clone(),
-                                        ring_id.into(),
+                                        ring_id,
                                         sender_uuid.clone(),
                                     )?;
                                     group_call::RingUpdate::Requested
                                 }
                             }
                             IntentionType::Cancelled => {
-                                self.remove_outstanding_group_ring(group_id, ring_id.into())?;
+                                self.remove_outstanding_group_ring(group_id, ring_id)?;
                                 group_call::RingUpdate::CancelledByRinger
                             }
                         };
 
                         self.platform.lock()?.group_call_ring_update(
                             std::mem::take(group_id),
-                            ring_id.into(),
+                            ring_id,
                             sender_uuid,
                             ring_update,
                         );
@@ -1600,9 +2055,32 @@ where
                 ring_response: Some(mut ring_response),
                 ..
             } => {
+                use protobuf::signaling::call_message::ring_response::Type as ResponseType;
+
                 {
                     let self_uuid = self.self_uuid.lock().expect("get self UUID");
                     if self_uuid.as_ref() != Some(&sender_uuid) {
+                        // It might be a response to a ring sent from one of our group calls.
+                        if let Some(status) = ring_response
+                            .r#type
+                            .and_then(ResponseType::from_i32)
+                            .and_then(ring_status_from_response_type)
+                        {
+                            let group_calls = self.group_call_by_client_id.lock()?;
+                            let group_call = group_calls
+                                .values()
+                                .find(|c| Some(&c.group_id) == ring_response.group_id.as_ref());
+                            if let (Some(group_call), Some(ring_id)) =
+                                (group_call, ring_response.ring_id)
+                            {
+                                group_call.on_ring_response_received(
+                                    sender_uuid,
+                                    ring_id.into(),
+                                    status,
+                                );
+                                return Ok(());
+                            }
+                        }
                         info!(
                             concat!(
                                 "Discarding ring response from another user {} for ring ID {}.",
@@ -1616,7 +2094,6 @@ where
                     }
                 }
 
-                use protobuf::signaling::call_message::ring_response::Type as ResponseType;
                 match (
                     &mut ring_response.group_id,
                     ring_response.r#type.and_then(ResponseType::from_i32),
@@ -1636,6 +2113,7 @@ where
                             }
                             ResponseType::Ringing => unreachable!("handled above"),
                         };
+                        self.mark_group_ring_seen(ring_id.into())?;
                         self.remove_outstanding_group_ring(group_id, ring_id.into())?;
                         self.platform.lock()?.group_call_ring_update(
                             std::mem::take(group_id),
@@ -1649,12 +2127,196 @@ where
                     }
                 }
             }
-            _ => {
-                warn!("Received unknown CallMessage - ignoring");
-            }
-        };
-        Ok(())
-    }
+            protobuf::signaling::CallMessage {
+                escalation: Some(mut escalation),
+                ..
+            } => {
+                use protobuf::signaling::call_message::escalation::Type as EscalationType;
+                match (
+                    &mut escalation.group_id,
+                    escalation.r#type.and_then(EscalationType::from_i32),
+                    escalation.call_id,
+                ) {
+                    (Some(group_id), Some(escalation_type), Some(call_id)) => {
+                        self.handle_received_escalation(
+                            sender_uuid,
+                            std::mem::take(group_id),
+                            CallId::new(call_id),
+                            escalation_type,
+                        )?;
+                    }
+                    _ => {
+                        warn!("Received malformed Escalation: {:?}", escalation);
+                    }
+                }
+            }
+            _ => {
+                warn!("Received unknown CallMessage - ignoring");
+            }
+        };
+        Ok(())
+    }
+
+    fn handle_received_escalation(
+        &mut self,
+        sender_uuid: group_call::UserId,
+        group_id: group_call::GroupId,
+        call_id: CallId,
+        escalation_type: protobuf::signaling::call_message::escalation::Type,
+    ) -> Result<()> {
+        use protobuf::signaling::call_message::escalation::Type as EscalationType;
+
+        info!(
+            "handle_received_escalation(): call_id: {} type: {:?}",
+            call_id, escalation_type
+        );
+        if !self.call_is_active(call_id)? {
+            info!("Ignoring escalation of inactive call");
+            return Ok(());
+        }
+
+        let (in_progress, is_for_this, phase) = match &*self.escalation.lock()? {
+            Some(escalation) => (
+                true,
+                escalation.is_for(call_id, &group_id, &sender_uuid),
+                Some(escalation.phase),
+            ),
+            None => (false, false, None),
+        };
+        match (escalation_type, phase) {
+            (EscalationType::Request, _) if !in_progress => {
+                *self.escalation.lock()? = Some(Escalation::new(
+                    call_id,
+                    group_id.clone(),
+                    sender_uuid,
+                    EscalationPhase::AwaitingLocalAnswer,
+                    None,
+                ));
+                self.notify_escalation_update(call_id, group_id, EscalationUpdate::Requested)
+            }
+            // If both users asked at once, the other request is as good as an acceptance.
+            (EscalationType::Request, Some(EscalationPhase::AwaitingRemoteAnswer))
+            | (EscalationType::Accept, Some(EscalationPhase::AwaitingRemoteAnswer))
+                if is_for_this =>
+            {
+                let client_id = match self.escalation.lock()?.as_mut() {
+                    Some(escalation) => {
+                        escalation.phase = EscalationPhase::Joining;
+                        escalation.client_id
+                    }
+                    None => None,
+                };
+                if let Some(client_id) = client_id {
+                    self.join_escalated_group_call(client_id);
+                }
+                self.notify_escalation_update(call_id, group_id, EscalationUpdate::Accepted)
+            }
+            (EscalationType::Request, _) if !is_for_this => {
+                info!("Declining escalation while another one is in progress");
+                self.send_escalation_message(
+                    sender_uuid,
+                    call_id,
+                    group_id,
+                    EscalationType::Decline,
+                );
+                Ok(())
+            }
+            // A decline from the requester withdraws the request.
+            (EscalationType::Decline, Some(EscalationPhase::AwaitingRemoteAnswer))
+            | (EscalationType::Decline, Some(EscalationPhase::AwaitingLocalAnswer))
+                if is_for_this =>
+            {
+                let _ = self.escalation.lock()?.take();
+                let update = if phase == Some(EscalationPhase::AwaitingRemoteAnswer) {
+                    EscalationUpdate::Declined
+                } else {
+                    EscalationUpdate::Failed
+                };
+                self.notify_escalation_update(call_id, group_id, update)
+            }
+            _ => {
+                info!("Ignoring unexpected escalation message");
+                Ok(())
+            }
+        }
+    }
+
+    // A direct call that ends before both users agreed to move it to a group
+    // call can't be moved any more.  Once they have agreed, the move goes on
+    // without it, since the other user may simply have finished first.
+    fn end_escalation_of_ended_call(&mut self, call_id: CallId) -> Result<()> {
+        let ended = {
+            let mut escalation = self.escalation.lock()?;
+            match escalation.take() {
+                Some(ended) if ended.call_id == call_id && !ended.holds_busy() => Some(ended),
+                other => {
+                    *escalation = other;
+                    None
+                }
+            }
+        };
+        match ended {
+            Some(ended) => {
+                self.notify_escalation_update(call_id, ended.group_id, EscalationUpdate::Failed)
+            }
+            None => Ok(()),
+        }
+    }
+
+    // Hangs up the direct call once the group call it's moving to carries the
+    // other user's media.
+    fn handle_complete_escalation(&mut self, client_id: group_call::ClientId) -> Result<()> {
+        let completed = {
+            let mut escalation = self.escalation.lock()?;
+            match escalation.take() {
+                Some(completed)
+                    if completed.client_id == Some(client_id) && completed.is_complete() =>
+                {
+                    completed
+                }
+                other => {
+                    *escalation = other;
+                    return Ok(());
+                }
+            }
+        };
+        info!(
+            "handle_complete_escalation(): call_id: {}",
+            completed.call_id
+        );
+
+        if self.call_is_active(completed.call_id)? {
+            // Busy isn't released, because the group call client holds it now.
+            let active_call = self.active_call()?;
+            self.clear_active_call()?;
+            self.terminate_call(
+                active_call,
+                Some(signaling::Hangup::Normal),
+                Some(ApplicationEvent::EndedLocalHangup),
+            )?;
+        }
+        self.notify_escalation_update(
+            completed.call_id,
+            completed.group_id,
+            EscalationUpdate::Completed,
+        )
+    }
+
+    fn notify_escalation_update(
+        &self,
+        call_id: CallId,
+        group_id: group_call::GroupId,
+        update: EscalationUpdate,
+    ) -> Result<()> {
+        info!(
+            "notify_escalation_update(): call_id: {} update: {:?}",
+            call_id, update
+        );
+        self.platform
+            .lock()?
+            .handle_escalation_update(call_id, group_id, update);
+        Ok(())
+    }
 
     fn start_group_ring(
         &mut self,
@@ -1663,30 +2325,47 @@ where
         sender_uuid: group_call::UserId,
     ) -> Result<()> {
         {
-            let mut outstanding_group_rings = self.outstanding_group_rings.lock()?;
+            let mut ring_store = self.ring_store.lock()?;
             // Take this opportunity to clear the outstanding rings table
             // (which should be small).
-            outstanding_group_rings.retain(|_group_id, ring| !ring.has_expired());
+            remove_expired_group_rings(ring_store.as_mut());
             // If there's an existing, non-expired ring, don't replace it.
-            outstanding_group_rings
-                .entry(group_id.clone())
-                .or_insert(OutstandingGroupRing {
-                    ring_id,
-                    received: Instant::now(),
-                });
+            if ring_store.ring(&group_id).is_none() {
+                warn_if_ring_store_failed(ring_store.insert_ring(
+                    group_id.clone(),
+                    OutstandingGroupRing {
+                        ring_id,
+                        sender_uuid: sender_uuid.clone(),
+                        received: SystemTime::now(),
+                    },
+                ));
+            }
         }
 
+        self.start_group_ring_timeout(group_id, ring_id, sender_uuid, TIME_OUT_PERIOD)
+    }
+
+    fn start_group_ring_timeout(
+        &mut self,
+        group_id: group_call::GroupId,
+        ring_id: group_call::RingId,
+        sender_uuid: group_call::UserId,
+        timeout: Duration,
+    ) -> Result<()> {
         let mut self_for_timeout = self.clone();
         self.worker_spawn(
             async move {
-                tokio::time::sleep(TIME_OUT_PERIOD).await;
-                self_for_timeout.remove_outstanding_group_ring(&group_id, ring_id)?;
-                self_for_timeout.platform.lock()?.group_call_ring_update(
-                    group_id,
-                    ring_id,
-                    sender_uuid,
-                    group_call::RingUpdate::ExpiredRequest,
-                );
+                tokio::time::sleep(timeout).await;
+                // The ring may have been accepted or cancelled since, or the ring store
+                // may have been replaced by one that doesn't have it.
+                if self_for_timeout.remove_outstanding_group_ring(&group_id, ring_id)? {
+                    self_for_timeout.platform.lock()?.group_call_ring_update(
+                        group_id,
+                        ring_id,
+                        sender_uuid,
+                        group_call::RingUpdate::ExpiredRequest,
+                    );
+                }
                 Ok(())
             }
             .map_err(|err: failure::Error| {
@@ -1697,10 +2376,46 @@ where
         Ok(())
     }
 
+    fn handle_set_ring_store(&mut self, ring_store: Box<dyn RingStore>) -> Result<()> {
+        let rings = ring_store.rings();
+        // Timers already started for the previous store's rings look the rings up in
+        // the new store when they go off, and do nothing if they aren't there.
+        *self.ring_store.lock()? = ring_store;
+
+        for (group_id, ring) in rings {
+            let time_left = ring.time_left(TIME_OUT_PERIOD);
+            let ring_update = if time_left == Duration::default() {
+                self.remove_outstanding_group_ring(&group_id, ring.ring_id)?;
+                group_call::RingUpdate::ExpiredRequest
+            } else {
+                self.start_group_ring_timeout(
+                    group_id.clone(),
+                    ring.ring_id,
+                    ring.sender_uuid.clone(),
+                    time_left,
+                )?;
+                group_call::RingUpdate::Requested
+            };
+            info!(
+                "Restored group ring: ring_id: {} update: {:?}",
+                ring.ring_id, ring_update
+            );
+            self.platform.lock()?.group_call_ring_update(
+                group_id,
+                ring.ring_id,
+                ring.sender_uuid,
+                ring_update,
+            );
+        }
+        Ok(())
+    }
+
     #[cfg(feature = "sim")]
     pub fn age_all_outstanding_group_rings(&mut self, age: Duration) {
-        for (_group_id, ring) in self.outstanding_group_rings.lock().unwrap().iter_mut() {
+        let mut ring_store = self.ring_store.lock().unwrap();
+        for (group_id, mut ring) in ring_store.rings() {
             ring.received -= age;
+            ring_store.insert_ring(group_id, ring).unwrap();
         }
     }
 
@@ -1750,6 +2465,8 @@ where
     fn handle_reset(&mut self) -> Result<()> {
         info!("handle_reset():");
 
+        let _ = self.escalation.lock()?.take();
+
         // gather all the calls from the call_map.
         let calls: Vec<Call<T>> = {
             let call_map = self.call_by_call_id.lock()?;
@@ -1762,6 +2479,7 @@ where
             let _ = self.terminate_call(call, Some(signaling::Hangup::Normal), None);
         }
 
+        let _ = self.second_call_id.lock()?.take();
         self.clear_active_call()?;
         self.release_busy()?;
 
@@ -2129,7 +2847,7 @@ where
         platform.disconnect_incoming_media(app_call_context)
     }
 
-    /// Received hangup from remote for the active call.
+    /// Received hangup from remote for the active or second call.
     pub(super) fn remote_hangup(
         &mut self,
         call_id: CallId,
@@ -2137,11 +2855,11 @@ where
     ) -> Result<()> {
         info!("remote_hangup(): call_id: {}", call_id);
 
+        let event = app_event_override.unwrap_or(ApplicationEvent::EndedRemoteHangup);
         if self.call_is_active(call_id)? {
-            match app_event_override {
-                Some(event) => self.terminate_active_call(false, event),
-                None => self.terminate_active_call(false, ApplicationEvent::EndedRemoteHangup),
-            }
+            self.terminate_active_call(false, event)
+        } else if self.call_is_second(call_id)? {
+            self.terminate_second_call(false, event)
         } else {
             info!("remote_hangup(): ignoring for inactive call");
             Ok(())
@@ -2164,39 +2882,45 @@ where
         platform.on_call_concluded(remote_peer)
     }
 
-    /// Local timeout of the active call.
+    /// Local timeout of the active or second call.
     pub(super) fn timeout(&mut self, call_id: CallId) -> Result<()> {
         info!("timeout(): call_id: {}", call_id);
 
         if self.call_is_active(call_id)? {
             self.terminate_active_call(true, ApplicationEvent::EndedTimeout)
+        } else if self.call_is_second(call_id)? {
+            self.terminate_second_call(true, ApplicationEvent::EndedTimeout)
         } else {
             info!("timeout(): ignoring for inactive call");
             Ok(())
         }
     }
 
-    /// Network failure occurred on the active call.
+    /// Network failure occurred on the active or second call.
     pub(super) fn connection_failure(&mut self, call_id: CallId) -> Result<()> {
         info!("call_failed(): call_id: {}", call_id);
 
         if self.call_is_active(call_id)? {
             self.terminate_active_call(true, ApplicationEvent::EndedConnectionFailure)
+        } else if self.call_is_second(call_id)? {
+            self.terminate_second_call(true, ApplicationEvent::EndedConnectionFailure)
         } else {
             info!("call_failed(): ignoring for inactive call");
             Ok(())
         }
     }
 
-    /// Internal error occurred on the active call.
+    /// Internal error occurred on the active or second call.
     ///
-    /// This shuts down the specified call if active and notifies the
-    /// application.
+    /// This shuts down the specified call if active or second and notifies
+    /// the application.
     pub(super) fn internal_error(&mut self, call_id: CallId, error: failure::Error) -> Result<()> {
         info!("internal_error(): call_id: {}, error: {}", call_id, error);
 
         if self.call_is_active(call_id)? {
             self.terminate_active_call(true, ApplicationEvent::EndedInternalFailure)
+        } else if self.call_is_second(call_id)? {
+            self.terminate_second_call(true, ApplicationEvent::EndedInternalFailure)
         } else {
             info!("internal_error(): ignoring for inactive call");
             Ok(())
@@ -2378,6 +3102,9 @@ where
         connection_state: group_call::ConnectionState,
     ) {
         info!("handle_connection_state_changed():");
+        self.update_escalation(client_id, |escalation| {
+            escalation.set_connection_state(connection_state)
+        });
         platform_handler!(
             self,
             handle_connection_state_changed,
@@ -2406,6 +3133,9 @@ where
         join_state: group_call::JoinState,
     ) {
         info!("handle_join_state_changed():");
+        self.update_escalation(client_id, |escalation| {
+            escalation.set_join_state(&join_state)
+        });
         platform_handler!(self, handle_join_state_changed, client_id, join_state);
     }
 
@@ -2416,6 +3146,9 @@ where
         reason: group_call::RemoteDevicesChangedReason,
     ) {
         info!("handle_remote_devices_changed(): {:?}", reason);
+        self.update_escalation(client_id, |escalation| {
+            escalation.set_remote_devices(remote_device_states)
+        });
         platform_handler!(
             self,
             handle_remote_devices_changed,
@@ -2425,6 +3158,74 @@ where
         );
     }
 
+    fn handle_reaction(&self, client_id: group_call::ClientId, reaction: group_call::Reaction) {
+        info!("handle_reaction():");
+        platform_handler!(self, handle_reaction, client_id, reaction);
+    }
+
+    fn handle_message_received(
+        &self,
+        client_id: group_call::ClientId,
+        message: group_call::ChatMessage,
+    ) {
+        info!("handle_message_received():");
+        platform_handler!(self, handle_message_received, client_id, message);
+    }
+
+    fn handle_control_request(
+        &self,
+        client_id: group_call::ClientId,
+        sender_demux_id: group_call::DemuxId,
+        request: group_call::ControlRequest,
+    ) {
+        info!("handle_control_request():");
+        platform_handler!(
+            self,
+            handle_control_request,
+            client_id,
+            sender_demux_id,
+            request
+        );
+    }
+
+    fn handle_audio_levels(
+        &self,
+        client_id: group_call::ClientId,
+        captured_level: group_call::AudioLevel,
+        received_levels: Vec<group_call::ReceivedAudioLevel>,
+    ) {
+        // Not logged because it's called so often.
+        platform_handler!(self, handle_audio_levels, client_id, captured_level, received_levels);
+    }
+
+    fn handle_resumption_snapshot(&self, client_id: group_call::ClientId, snapshot: Vec<u8>) {
+        info!("handle_resumption_snapshot():");
+        platform_handler!(self, handle_resumption_snapshot, client_id, snapshot);
+    }
+
+    fn handle_layout_changed(&self, client_id: group_call::ClientId, layout: group_call::Layout) {
+        info!("handle_layout_changed():");
+        platform_handler!(self, handle_layout_changed, client_id, layout);
+    }
+
+    fn handle_pinned_videos_not_forwarded(
+        &self,
+        client_id: group_call::ClientId,
+        demux_ids: Vec<group_call::DemuxId>,
+    ) {
+        info!("handle_pinned_videos_not_forwarded():");
+        platform_handler!(self, handle_pinned_videos_not_forwarded, client_id, demux_ids);
+    }
+
+    fn handle_ring_statuses_changed(
+        &self,
+        client_id: group_call::ClientId,
+        statuses: Vec<group_call::RecipientRingStatus>,
+    ) {
+        info!("handle_ring_statuses_changed():");
+        platform_handler!(self, handle_ring_statuses_changed, client_id, statuses);
+    }
+
     fn handle_incoming_video_track(
         &mut self,
         client_id: group_call::ClientId,
@@ -2463,9 +3264,21 @@ where
         );
     }
 
+    fn handle_pending_devices_changed(
+        &self,
+        client_id: group_call::ClientId,
+        pending_devices: &[group_call::PeekDeviceInfo],
+    ) {
+        info!("handle_pending_devices_changed():");
+        platform_handler!(self, handle_pending_devices_changed, client_id, pending_devices);
+    }
+
     fn handle_ended(&self, client_id: group_call::ClientId, reason: group_call::EndReason) {
         info!("handle_ended({:?}):", reason);
         platform_handler!(self, handle_ended, client_id, reason);
+        if let Err(error) = self.end_escalation_of_ended_group_call(client_id) {
+            error!("{}", error);
+        }
     }
 
     fn send_signaling_message(
@@ -2550,6 +3363,7 @@ where
                     era_id,
                     max_devices,
                     device_count,
+                    pending_devices: _,
                 } = peek_info.unwrap_or_default();
 
                 let members: HashSet<group_call::UserId> = devices
@@ -2594,12 +3408,10 @@ where
         let client_id = *next_group_call_client_id;
         *next_group_call_client_id += 1;
 
-        let mut outstanding_group_rings = self.outstanding_group_rings.lock()?;
+        let mut ring_store = self.ring_store.lock()?;
         // Take this opportunity to clear the outstanding rings table (which should be small).
-        outstanding_group_rings.retain(|_group_id, ring| !ring.has_expired());
-        let ring_id = outstanding_group_rings
-            .get(&group_id)
-            .map(|ring| ring.ring_id);
+        remove_expired_group_rings(ring_store.as_mut());
+        let ring_id = ring_store.ring(&group_id).map(|ring| ring.ring_id);
 
         let sfu_client = SfuClient::new(Box::new(self.clone()), sfu_url);
         let client = group_call::Client::start(
@@ -2634,6 +3446,10 @@ where
                 match group_call {
                     Some(_group_call) => {
                         // Let group_call drop.
+                        drop(group_call_map);
+                        if let Err(error) = self.end_escalation_of_ended_group_call(client_id) {
+                            error!("{}", error);
+                        }
                     }
                     None => {
                         warn!("Group Client not found for id: {}", client_id);
@@ -2688,6 +3504,11 @@ where
         group_call_api_handler!(self, client_id, join);
     }
 
+    pub fn resume(&mut self, client_id: group_call::ClientId, snapshot: Vec<u8>) {
+        info!("resume(): id: {}", client_id);
+        group_call_api_handler!(self, client_id, resume, snapshot);
+    }
+
     pub fn leave(&mut self, client_id: group_call::ClientId) {
         info!("leave(): id: {}", client_id);
         group_call_api_handler!(self, client_id, leave);
@@ -2727,6 +3548,123 @@ where
         group_call_api_handler!(self, client_id, set_sharing_screen, sharing_screen);
     }
 
+    pub fn set_hand_raised(&mut self, client_id: group_call::ClientId, raised: bool) {
+        info!("set_hand_raised(): id: {}", client_id);
+        group_call_api_handler!(self, client_id, set_hand_raised, raised);
+    }
+
+    pub fn send_reaction(&mut self, client_id: group_call::ClientId, value: String) {
+        info!("send_reaction(): id: {}", client_id);
+        group_call_api_handler!(self, client_id, send_reaction, value);
+    }
+
+    pub fn send_message(&mut self, client_id: group_call::ClientId, text: String) {
+        info!("send_message(): id: {}", client_id);
+        group_call_api_handler!(self, client_id, send_message, text);
+    }
+
+    pub fn send_control_request(
+        &mut self,
+        client_id: group_call::ClientId,
+        target_demux_id: group_call::DemuxId,
+        request: group_call::ControlRequest,
+    ) {
+        info!("send_control_request(): id: {}", client_id);
+        group_call_api_handler!(
+            self,
+            client_id,
+            send_control_request,
+            target_demux_id,
+            request
+        );
+    }
+
+    pub fn approve_pending_device(
+        &mut self,
+        client_id: group_call::ClientId,
+        demux_id: group_call::DemuxId,
+    ) {
+        info!("approve_pending_device(): id: {}", client_id);
+        group_call_api_handler!(self, client_id, approve_pending_device, demux_id);
+    }
+
+    pub fn deny_pending_device(
+        &mut self,
+        client_id: group_call::ClientId,
+        demux_id: group_call::DemuxId,
+    ) {
+        info!("deny_pending_device(): id: {}", client_id);
+        group_call_api_handler!(self, client_id, deny_pending_device, demux_id);
+    }
+
+    pub fn set_audio_levels_config(
+        &mut self,
+        client_id: group_call::ClientId,
+        config: Option<group_call::AudioLevelsConfig>,
+    ) {
+        info!("set_audio_levels_config(): id: {}", client_id);
+        group_call_api_handler!(self, client_id, set_audio_levels_config, config);
+    }
+
+    pub fn set_resumption_key(
+        &mut self,
+        client_id: group_call::ClientId,
+        key: Option<resumption::Key>,
+    ) {
+        info!("set_resumption_key(): id: {}", client_id);
+        group_call_api_handler!(self, client_id, set_resumption_key, key);
+    }
+
+    pub fn set_key_rotation_policy(
+        &mut self,
+        client_id: group_call::ClientId,
+        policy: group_call::KeyRotationPolicy,
+    ) {
+        info!("set_key_rotation_policy(): id: {}", client_id);
+        group_call_api_handler!(self, client_id, set_key_rotation_policy, policy);
+    }
+
+    pub fn set_layout_config(
+        &mut self,
+        client_id: group_call::ClientId,
+        config: Option<group_call::LayoutConfig>,
+    ) {
+        info!("set_layout_config(): id: {}", client_id);
+        group_call_api_handler!(self, client_id, set_layout_config, config);
+    }
+
+    pub fn set_pinned(
+        &mut self,
+        client_id: group_call::ClientId,
+        demux_id: group_call::DemuxId,
+        pin: Option<group_call::PinKind>,
+    ) {
+        info!("set_pinned(): id: {}", client_id);
+        group_call_api_handler!(self, client_id, set_pinned, demux_id, pin);
+    }
+
+    pub fn set_wait_for_slot_when_full(&mut self, client_id: group_call::ClientId, wait: bool) {
+        info!("set_wait_for_slot_when_full(): id: {}", client_id);
+        group_call_api_handler!(self, client_id, set_wait_for_slot_when_full, wait);
+    }
+
+    pub fn rotate_media_send_key(&mut self, client_id: group_call::ClientId) {
+        info!("rotate_media_send_key(): id: {}", client_id);
+        group_call_api_handler!(self, client_id, rotate_media_send_key);
+    }
+
+    // None if there is no such client.
+    pub fn key_rotation_metrics(
+        &mut self,
+        client_id: group_call::ClientId,
+    ) -> Result<Option<group_call::KeyRotationMetrics>> {
+        let group_call_map = self.group_call_by_client_id.lock()?;
+        match group_call_map.get(&client_id) {
+            Some(group_call) => Ok(Some(group_call.key_rotation_metrics()?)),
+            None => Ok(None),
+        }
+    }
+
     pub fn resend_media_keys(&mut self, client_id: group_call::ClientId) {
         info!("resend_media_keys(): id: {}", client_id);
         group_call_api_handler!(self, client_id, resend_media_keys);
@@ -2764,3 +3702,277 @@ where
         group_call_api_handler!(self, client_id, set_membership_proof, proof);
     }
 }
+
+impl<T> CallManager<T>
+where
+    T: Platform,
+{
+    /// Asks the other user of the active direct call to move it to a group
+    /// call, and creates the client for the group call.  Once the other user
+    /// accepts, the client joins, and the direct call is hung up as soon as
+    /// the other user's media arrives through the group call.  Progress is
+    /// passed to Platform::handle_escalation_update.
+    ///
+    /// The client starts out with the direct call's outgoing video and screen
+    /// sharing state.  The direct call's audio is muted at the track, which
+    /// CallManager can't see, so the caller says whether it is.
+    #[allow(clippy::too_many_arguments)]
+    pub fn request_escalation(
+        &mut self,
+        call_id: CallId,
+        remote_user_id: group_call::UserId,
+        group_id: group_call::GroupId,
+        sfu_url: String,
+        peer_connection_factory: Option<PeerConnectionFactory>,
+        outgoing_audio_track: AudioTrack,
+        outgoing_video_track: VideoTrack,
+        outgoing_audio_muted: bool,
+    ) -> Result<group_call::ClientId> {
+        use protobuf::signaling::call_message::escalation::Type as EscalationType;
+
+        info!("request_escalation(): call_id: {}", call_id);
+
+        let escalation = Arc::clone(&self.escalation);
+        let mut escalation = escalation.lock()?;
+        if escalation.is_some() {
+            return Err(RingRtcError::EscalationInProgress.into());
+        }
+        let client_id = self.create_escalated_group_call_client(
+            call_id,
+            group_id.clone(),
+            sfu_url,
+            peer_connection_factory,
+            outgoing_audio_track,
+            outgoing_video_track,
+            outgoing_audio_muted,
+        )?;
+        *escalation = Some(Escalation::new(
+            call_id,
+            group_id.clone(),
+            remote_user_id.clone(),
+            EscalationPhase::AwaitingRemoteAnswer,
+            Some(client_id),
+        ));
+        drop(escalation);
+
+        self.send_escalation_message(remote_user_id, call_id, group_id, EscalationType::Request);
+        Ok(client_id)
+    }
+
+    /// Accepts the other user's request to move the direct call to a group
+    /// call (see request_escalation), creating the client for the group call
+    /// and joining it.
+    pub fn accept_escalation(
+        &mut self,
+        call_id: CallId,
+        sfu_url: String,
+        peer_connection_factory: Option<PeerConnectionFactory>,
+        outgoing_audio_track: AudioTrack,
+        outgoing_video_track: VideoTrack,
+        outgoing_audio_muted: bool,
+    ) -> Result<group_call::ClientId> {
+        use protobuf::signaling::call_message::escalation::Type as EscalationType;
+
+        info!("accept_escalation(): call_id: {}", call_id);
+
+        let escalation = Arc::clone(&self.escalation);
+        let mut escalation = escalation.lock()?;
+        let requested = match escalation.as_mut() {
+            Some(requested)
+                if requested.call_id == call_id
+                    && requested.phase == EscalationPhase::AwaitingLocalAnswer =>
+            {
+                requested
+            }
+            _ => return Err(RingRtcError::NoEscalationRequest(call_id).into()),
+        };
+        let client_id = self.create_escalated_group_call_client(
+            call_id,
+            requested.group_id.clone(),
+            sfu_url,
+            peer_connection_factory,
+            outgoing_audio_track,
+            outgoing_video_track,
+            outgoing_audio_muted,
+        )?;
+        requested.phase = EscalationPhase::Joining;
+        requested.client_id = Some(client_id);
+        let group_id = requested.group_id.clone();
+        let remote_user_id = requested.remote_user_id.clone();
+        drop(escalation);
+
+        self.join_escalated_group_call(client_id);
+        self.send_escalation_message(remote_user_id, call_id, group_id, EscalationType::Accept);
+        Ok(client_id)
+    }
+
+    /// Declines the other user's request to move the direct call to a group
+    /// call, which goes on as before.
+    pub fn decline_escalation(&mut self, call_id: CallId) -> Result<()> {
+        use protobuf::signaling::call_message::escalation::Type as EscalationType;
+
+        info!("decline_escalation(): call_id: {}", call_id);
+
+        let declined = {
+            let mut escalation = self.escalation.lock()?;
+            match escalation.take() {
+                Some(declined)
+                    if declined.call_id == call_id
+                        && declined.phase == EscalationPhase::AwaitingLocalAnswer =>
+                {
+                    declined
+                }
+                other => {
+                    *escalation = other;
+                    return Err(RingRtcError::NoEscalationRequest(call_id).into());
+                }
+            }
+        };
+        self.send_escalation_message(
+            declined.remote_user_id,
+            call_id,
+            declined.group_id,
+            EscalationType::Decline,
+        );
+        Ok(())
+    }
+
+    // Creates the client for the group call a direct call is moving to, with
+    // the direct call's outgoing media state, and connects it.
+    #[allow(clippy::too_many_arguments)]
+    fn create_escalated_group_call_client(
+        &mut self,
+        call_id: CallId,
+        group_id: group_call::GroupId,
+        sfu_url: String,
+        peer_connection_factory: Option<PeerConnectionFactory>,
+        outgoing_audio_track: AudioTrack,
+        outgoing_video_track: VideoTrack,
+        outgoing_audio_muted: bool,
+    ) -> Result<group_call::ClientId> {
+        if !self.call_is_active(call_id)? {
+            return Err(RingRtcError::CallIdNotFound(call_id).into());
+        }
+        let sender_status = self.active_connection()?.last_sent_sender_status();
+        let video_enabled = sender_status
+            .as_ref()
+            .and_then(|status| status.video_enabled)
+            .unwrap_or(false);
+        let sharing_screen = sender_status
+            .and_then(|status| status.sharing_screen)
+            .unwrap_or(false);
+
+        let client_id = self.create_group_call_client(
+            group_id,
+            sfu_url,
+            peer_connection_factory,
+            outgoing_audio_track,
+            outgoing_video_track,
+        )?;
+        self.set_outgoing_audio_muted(client_id, outgoing_audio_muted);
+        self.set_outgoing_video_muted(client_id, !video_enabled);
+        // A new client isn't sharing its screen.
+        if sharing_screen {
+            self.set_sharing_screen(client_id, sharing_screen);
+        }
+        self.connect(client_id);
+        Ok(client_id)
+    }
+
+    // The direct call holds the busy state, so the client takes it over.
+    fn join_escalated_group_call(&mut self, client_id: group_call::ClientId) {
+        info!("join_escalated_group_call(): id: {}", client_id);
+        group_call_api_handler!(self, client_id, take_over_busy);
+        group_call_api_handler!(self, client_id, join);
+    }
+
+    fn send_escalation_message(
+        &mut self,
+        recipient: group_call::UserId,
+        call_id: CallId,
+        group_id: group_call::GroupId,
+        escalation_type: protobuf::signaling::call_message::escalation::Type,
+    ) {
+        let message = protobuf::signaling::CallMessage {
+            escalation: Some(protobuf::signaling::call_message::Escalation {
+                group_id: Some(group_id),
+                call_id:  Some(u64::from(call_id)),
+                r#type:   Some(escalation_type.into()),
+            }),
+            ..Default::default()
+        };
+        self.send_signaling_message(
+            recipient,
+            message,
+            group_call::SignalingMessageUrgency::HandleImmediately,
+        );
+    }
+
+    // Called as the group call a direct call is moving to progresses, to
+    // finish the move once it carries the other user's media.
+    fn update_escalation(
+        &self,
+        client_id: group_call::ClientId,
+        update: impl FnOnce(&mut Escalation),
+    ) {
+        let is_complete = match self.escalation.lock() {
+            Ok(mut escalation) => match escalation.as_mut() {
+                Some(escalation) if escalation.client_id == Some(client_id) => {
+                    update(escalation);
+                    escalation.is_complete()
+                }
+                _ => false,
+            },
+            Err(error) => {
+                error!("{}", error);
+                false
+            }
+        };
+        if is_complete {
+            let mut call_manager = self.clone();
+            let result = handle_api!(
+                call_manager,
+                CallManager::handle_complete_escalation,
+                client_id
+            );
+            if let Err(error) = result {
+                error!("{}", error);
+            }
+        }
+    }
+
+    // If the group call a direct call is moving to ends (or is deleted) first,
+    // the direct call goes on, with the busy state the client may have released.
+    fn end_escalation_of_ended_group_call(&self, client_id: group_call::ClientId) -> Result<()> {
+        use protobuf::signaling::call_message::escalation::Type as EscalationType;
+
+        let ended = {
+            let mut escalation = self.escalation.lock()?;
+            match escalation.take() {
+                Some(ended) if ended.client_id == Some(client_id) => ended,
+                other => {
+                    *escalation = other;
+                    return Ok(());
+                }
+            }
+        };
+        info!(
+            "end_escalation_of_ended_group_call(): call_id: {}",
+            ended.call_id
+        );
+
+        if self.call_is_active(ended.call_id)? {
+            *self.busy.lock()? = true;
+        }
+        if ended.phase == EscalationPhase::AwaitingRemoteAnswer {
+            // Withdraw the request.
+            self.clone().send_escalation_message(
+                ended.remote_user_id,
+                ended.call_id,
+                ended.group_id.clone(),
+                EscalationType::Decline,
+            );
+        }
+        self.notify_escalation_update(ended.call_id, ended.group_id, EscalationUpdate::Failed)
+    }
+}
@@ -0,0 +1,455 @@
+//
+// Copyright 2021 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Chat messages sent between devices in a group call over the
+//! encrypted RTP data path.
+//!
+//! RTP data can be lost, so messages are numbered per sender and
+//! receivers ask for what they missed: chunks of partially received
+//! messages and whole messages they noticed gaps for, either from
+//! later message IDs or from the latest message ID included with
+//! the sender's heartbeats.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use crate::{common::Result, error::RingRtcError};
+
+pub type MessageId = u64;
+pub type ChunkIndex = u32;
+
+// Leaves room for the protobuf fields, the encryption footer, and the RTP
+// and SRTP headers within a typical MTU.
+pub const MAX_CHUNK_LEN: usize = 1000;
+const MAX_CHUNK_COUNT: usize = 16;
+pub const MAX_MESSAGE_LEN: usize = MAX_CHUNK_COUNT * MAX_CHUNK_LEN;
+
+// How long sent messages are kept around to be retransmitted.
+const SENT_MESSAGE_RETENTION: Duration = Duration::from_secs(30);
+const MAX_SENT_MESSAGES_RETAINED: usize = 64;
+// Many receivers may ask for the same chunks at about the same time.
+const MIN_RETRANSMIT_INTERVAL: Duration = Duration::from_millis(250);
+
+// How long to wait without progress on an incomplete message before asking for it.
+const RETRANSMIT_REQUEST_DELAY: Duration = Duration::from_millis(500);
+const MAX_RETRANSMIT_REQUESTS: u32 = 5;
+// Gaps larger than this are given up on right away.
+const MAX_MISSING_MESSAGES: u64 = 32;
+const MAX_DELIVERED_MESSAGE_IDS_RETAINED: usize = 256;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Chunk {
+    pub message_id:  MessageId,
+    pub chunk_index: ChunkIndex,
+    pub chunk_count: u32,
+    pub data:        Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetransmitRequest {
+    pub message_id:    MessageId,
+    // Empty means all of the chunks.
+    pub chunk_indexes: Vec<ChunkIndex>,
+}
+
+struct SentMessage {
+    id:                 MessageId,
+    chunks:             Vec<Vec<u8>>,
+    sent:               Instant,
+    last_retransmitted: Option<Instant>,
+}
+
+#[derive(Default)]
+pub struct ChatSender {
+    latest_message_id: Option<MessageId>,
+    sent_messages:     VecDeque<SentMessage>,
+}
+
+impl ChatSender {
+    // Returns the chunks to send.
+    pub fn send(&mut self, message: &[u8], now: Instant) -> Result<Vec<Chunk>> {
+        if message.is_empty() || message.len() > MAX_MESSAGE_LEN {
+            return Err(RingRtcError::InvalidChatMessageLength(message.len()).into());
+        }
+        self.forget_old_messages(now);
+
+        let id = self.latest_message_id.map_or(1, |id| id + 1);
+        self.latest_message_id = Some(id);
+        let chunks: Vec<Vec<u8>> = message.chunks(MAX_CHUNK_LEN).map(<[u8]>::to_vec).collect();
+        let message = SentMessage {
+            id,
+            chunks,
+            sent: now,
+            last_retransmitted: None,
+        };
+        let all_chunks = message.chunks_to_send(&[]);
+        self.sent_messages.push_back(message);
+        Ok(all_chunks)
+    }
+
+    // Sent along with heartbeats so receivers notice when the latest messages were lost.
+    pub fn latest_message_id(&self) -> Option<MessageId> {
+        self.latest_message_id
+    }
+
+    // Returns the chunks to send again, if the message is still around
+    // and wasn't just retransmitted for someone else.
+    pub fn retransmit(&mut self, request: &RetransmitRequest, now: Instant) -> Vec<Chunk> {
+        self.forget_old_messages(now);
+        match self
+            .sent_messages
+            .iter_mut()
+            .find(|message| message.id == request.message_id)
+        {
+            Some(message) => {
+                if let Some(last_retransmitted) = message.last_retransmitted {
+                    if now.saturating_duration_since(last_retransmitted) < MIN_RETRANSMIT_INTERVAL {
+                        return vec![];
+                    }
+                }
+                message.last_retransmitted = Some(now);
+                message.chunks_to_send(&request.chunk_indexes)
+            }
+            None => vec![],
+        }
+    }
+
+    fn forget_old_messages(&mut self, now: Instant) {
+        while let Some(oldest) = self.sent_messages.front() {
+            if self.sent_messages.len() < MAX_SENT_MESSAGES_RETAINED
+                && now.saturating_duration_since(oldest.sent) < SENT_MESSAGE_RETENTION
+            {
+                break;
+            }
+            self.sent_messages.pop_front();
+        }
+    }
+}
+
+impl SentMessage {
+    fn chunks_to_send(&self, chunk_indexes: &[ChunkIndex]) -> Vec<Chunk> {
+        self.chunks
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| {
+                chunk_indexes.is_empty() || chunk_indexes.contains(&(*index as ChunkIndex))
+            })
+            .map(|(index, data)| Chunk {
+                message_id:  self.id,
+                chunk_index: index as ChunkIndex,
+                chunk_count: self.chunks.len() as u32,
+                data:        data.clone(),
+            })
+            .collect()
+    }
+}
+
+struct IncompleteMessage {
+    // Empty until a chunk of the message is received.
+    chunks:                   Vec<Option<Vec<u8>>>,
+    last_progress:            Instant,
+    retransmit_request_count: u32,
+}
+
+impl IncompleteMessage {
+    fn new(now: Instant) -> Self {
+        Self {
+            chunks:                   vec![],
+            last_progress:            now,
+            retransmit_request_count: 0,
+        }
+    }
+
+    fn missing_chunk_indexes(&self) -> Vec<ChunkIndex> {
+        self.chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk)| chunk.is_none())
+            .map(|(index, _)| index as ChunkIndex)
+            .collect()
+    }
+}
+
+// Reassembles the messages of one remote device.
+#[derive(Default)]
+pub struct ChatReceiver {
+    // Messages before the first one we hear about were sent before we
+    // could receive them, so we don't ask for them.
+    latest_message_id:     Option<MessageId>,
+    incomplete_messages:   HashMap<MessageId, IncompleteMessage>,
+    delivered_message_ids: VecDeque<MessageId>,
+}
+
+impl ChatReceiver {
+    // Returns the message if this chunk completed it.
+    pub fn receive_chunk(&mut self, chunk: Chunk, now: Instant) -> Option<(MessageId, Vec<u8>)> {
+        let chunk_count = chunk.chunk_count as usize;
+        if chunk_count == 0
+            || chunk_count > MAX_CHUNK_COUNT
+            || chunk.chunk_index as usize >= chunk_count
+            || chunk.data.len() > MAX_CHUNK_LEN
+            || self.delivered_message_ids.contains(&chunk.message_id)
+        {
+            return None;
+        }
+        self.notice_message_id(chunk.message_id, now);
+        let message = self.incomplete_messages.get_mut(&chunk.message_id)?;
+        if message.chunks.is_empty() {
+            message.chunks = vec![None; chunk_count];
+        } else if message.chunks.len() != chunk_count {
+            return None;
+        }
+        if message.chunks[chunk.chunk_index as usize].is_some() {
+            return None;
+        }
+        message.chunks[chunk.chunk_index as usize] = Some(chunk.data);
+        message.last_progress = now;
+        if message.chunks.iter().any(Option::is_none) {
+            return None;
+        }
+
+        let message = self.incomplete_messages.remove(&chunk.message_id)?;
+        self.delivered_message_ids.push_back(chunk.message_id);
+        if self.delivered_message_ids.len() > MAX_DELIVERED_MESSAGE_IDS_RETAINED {
+            self.delivered_message_ids.pop_front();
+        }
+        Some((
+            chunk.message_id,
+            message.chunks.into_iter().flatten().flatten().collect(),
+        ))
+    }
+
+    pub fn receive_latest_message_id(&mut self, latest_message_id: MessageId, now: Instant) {
+        if self.latest_message_id.is_none() {
+            // The latest message was sent before we could receive it.
+            self.latest_message_id = Some(latest_message_id);
+            return;
+        }
+        self.notice_message_id(latest_message_id, now);
+    }
+
+    // Returns what to ask the sender to send again.  Messages that
+    // have been asked for too many times are given up on.
+    pub fn retransmit_requests(&mut self, now: Instant) -> Vec<RetransmitRequest> {
+        let mut requests = vec![];
+        self.incomplete_messages.retain(|message_id, message| {
+            if now.saturating_duration_since(message.last_progress) < RETRANSMIT_REQUEST_DELAY {
+                return true;
+            }
+            if message.retransmit_request_count >= MAX_RETRANSMIT_REQUESTS {
+                warn!("Giving up on receiving chat message {}", message_id);
+                return false;
+            }
+            message.retransmit_request_count += 1;
+            message.last_progress = now;
+            requests.push(RetransmitRequest {
+                message_id:    *message_id,
+                chunk_indexes: message.missing_chunk_indexes(),
+            });
+            true
+        });
+        requests.sort_by_key(|request| request.message_id);
+        requests
+    }
+
+    fn notice_message_id(&mut self, message_id: MessageId, now: Instant) {
+        let first_missing = match self.latest_message_id {
+            None => message_id,
+            Some(latest) if message_id > latest => {
+                (latest + 1).max(message_id.saturating_sub(MAX_MISSING_MESSAGES))
+            }
+            Some(_) => {
+                return;
+            }
+        };
+        for missing in first_missing..=message_id {
+            self.incomplete_messages
+                .insert(missing, IncompleteMessage::new(now));
+        }
+        self.latest_message_id = Some(message_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deliver_all(
+        receiver: &mut ChatReceiver,
+        chunks: Vec<Chunk>,
+        now: Instant,
+    ) -> Vec<(MessageId, Vec<u8>)> {
+        chunks
+            .into_iter()
+            .filter_map(|chunk| receiver.receive_chunk(chunk, now))
+            .collect()
+    }
+
+    #[test]
+    fn chunking() {
+        let now = Instant::now();
+        let mut sender = ChatSender::default();
+        let mut receiver = ChatReceiver::default();
+
+        let short = b"hello".to_vec();
+        let long: Vec<u8> = (0..(MAX_CHUNK_LEN * 2 + 10)).map(|i| i as u8).collect();
+        let short_chunks = sender.send(&short, now).unwrap();
+        let long_chunks = sender.send(&long, now).unwrap();
+        assert_eq!(1, short_chunks.len());
+        assert_eq!(3, long_chunks.len());
+        assert_eq!(Some(2), sender.latest_message_id());
+
+        assert_eq!(
+            vec![(1, short)],
+            deliver_all(&mut receiver, short_chunks, now)
+        );
+        // Out of order and duplicated chunks
+        let mut chunks = long_chunks.clone();
+        chunks.reverse();
+        chunks.extend(long_chunks);
+        assert_eq!(vec![(2, long)], deliver_all(&mut receiver, chunks, now));
+        assert_eq!(
+            Vec::<RetransmitRequest>::new(),
+            receiver.retransmit_requests(now)
+        );
+
+        assert!(sender.send(&[], now).is_err());
+        assert!(sender.send(&vec![0; MAX_MESSAGE_LEN + 1], now).is_err());
+    }
+
+    #[test]
+    fn recover_lost_chunks() {
+        let now = Instant::now();
+        let later = now + RETRANSMIT_REQUEST_DELAY;
+        let mut sender = ChatSender::default();
+        let mut receiver = ChatReceiver::default();
+
+        let message = vec![7; MAX_CHUNK_LEN * 3];
+        let mut chunks = sender.send(&message, now).unwrap();
+        chunks.remove(1);
+        assert!(deliver_all(&mut receiver, chunks, now).is_empty());
+        assert!(receiver.retransmit_requests(now).is_empty());
+
+        let requests = receiver.retransmit_requests(later);
+        assert_eq!(
+            vec![RetransmitRequest {
+                message_id:    1,
+                chunk_indexes: vec![1],
+            }],
+            requests
+        );
+        let retransmitted = sender.retransmit(&requests[0], later);
+        assert_eq!(1, retransmitted.len());
+        // Someone else asking right away doesn't cause another retransmission.
+        assert!(sender.retransmit(&requests[0], later).is_empty());
+        assert_eq!(
+            vec![(1, message)],
+            deliver_all(&mut receiver, retransmitted, later)
+        );
+    }
+
+    #[test]
+    fn recover_lost_messages() {
+        let now = Instant::now();
+        let later = now + RETRANSMIT_REQUEST_DELAY;
+        let mut sender = ChatSender::default();
+        let mut receiver = ChatReceiver::default();
+
+        let first = sender.send(b"1", now).unwrap();
+        let _lost = sender.send(b"2", now).unwrap();
+        let third = sender.send(b"3", now).unwrap();
+        let _lost_at_the_end = sender.send(b"4", now).unwrap();
+        assert_eq!(1, deliver_all(&mut receiver, first, now).len());
+        assert_eq!(1, deliver_all(&mut receiver, third, now).len());
+        receiver.receive_latest_message_id(sender.latest_message_id().unwrap(), now);
+
+        let requests = receiver.retransmit_requests(later);
+        assert_eq!(
+            vec![
+                RetransmitRequest {
+                    message_id:    2,
+                    chunk_indexes: vec![],
+                },
+                RetransmitRequest {
+                    message_id:    4,
+                    chunk_indexes: vec![],
+                },
+            ],
+            requests
+        );
+        let retransmitted: Vec<Chunk> = requests
+            .iter()
+            .flat_map(|request| sender.retransmit(request, later))
+            .collect();
+        assert_eq!(
+            vec![(2, b"2".to_vec()), (4, b"4".to_vec())],
+            deliver_all(&mut receiver, retransmitted, later)
+        );
+    }
+
+    #[test]
+    fn give_up_on_lost_messages() {
+        let mut now = Instant::now();
+        let mut receiver = ChatReceiver::default();
+        receiver.receive_latest_message_id(1, now);
+        receiver.receive_latest_message_id(2, now);
+        for _ in 0..MAX_RETRANSMIT_REQUESTS {
+            now += RETRANSMIT_REQUEST_DELAY;
+            assert_eq!(1, receiver.retransmit_requests(now).len());
+        }
+        now += RETRANSMIT_REQUEST_DELAY;
+        assert!(receiver.retransmit_requests(now).is_empty());
+    }
+
+    #[test]
+    fn ignore_messages_sent_before_joining() {
+        let now = Instant::now();
+        let later = now + RETRANSMIT_REQUEST_DELAY;
+        let mut sender = ChatSender::default();
+        let mut receiver = ChatReceiver::default();
+
+        let _before_joining = sender.send(b"1", now).unwrap();
+        receiver.receive_latest_message_id(sender.latest_message_id().unwrap(), now);
+        assert!(receiver.retransmit_requests(later).is_empty());
+
+        let after_joining = sender.send(b"2", later).unwrap();
+        assert_eq!(
+            vec![(2, b"2".to_vec())],
+            deliver_all(&mut receiver, after_joining, later)
+        );
+        assert!(receiver
+            .retransmit_requests(later + RETRANSMIT_REQUEST_DELAY)
+            .is_empty());
+    }
+
+    #[test]
+    fn ignore_invalid_chunks() {
+        let now = Instant::now();
+        let mut receiver = ChatReceiver::default();
+        let chunk = |chunk_index, chunk_count| Chunk {
+            message_id: 1,
+            chunk_index,
+            chunk_count,
+            data: vec![1],
+        };
+        assert_eq!(None, receiver.receive_chunk(chunk(0, 0), now));
+        assert_eq!(None, receiver.receive_chunk(chunk(2, 2), now));
+        assert_eq!(
+            None,
+            receiver.receive_chunk(chunk(0, MAX_CHUNK_COUNT as u32 + 1), now)
+        );
+        assert_eq!(None, receiver.receive_chunk(chunk(0, 2), now));
+        // The chunk count can't change.
+        assert_eq!(None, receiver.receive_chunk(chunk(1, 3), now));
+        assert_eq!(
+            Some((1, vec![1, 1])),
+            receiver.receive_chunk(chunk(1, 2), now)
+        );
+        // Already delivered
+        assert_eq!(None, receiver.receive_chunk(chunk(0, 1), now));
+    }
+}
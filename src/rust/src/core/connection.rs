@@ -338,6 +338,8 @@ where
     state:                         Arc<CallMutex<ConnectionState>>,
     /// The current network route of the connection
     network_route:                 Arc<CallMutex<NetworkRoute>>,
+    /// Whether ICE is disconnected while the connection is on hold
+    ice_disconnected_on_hold:      Arc<CallMutex<bool>>,
     /// Execution context for the call connection FSM
     context:                       Arc<CallMutex<Context>>,
     /// Ancillary WebRTC data.
@@ -428,6 +430,7 @@ where
             direction:                     self.direction,
             state:                         Arc::clone(&self.state),
             network_route:                 Arc::clone(&self.network_route),
+            ice_disconnected_on_hold:      Arc::clone(&self.ice_disconnected_on_hold),
             context:                       Arc::clone(&self.context),
             webrtc:                        Arc::clone(&self.webrtc),
             bandwidth_modes:               Arc::clone(&self.bandwidth_modes),
@@ -482,6 +485,7 @@ where
             call: Arc::new(CallMutex::new(call, "call")),
             state: Arc::new(CallMutex::new(ConnectionState::NotYetStarted, "state")),
             network_route: Arc::new(CallMutex::new(NetworkRoute::default(), "network_route")),
+            ice_disconnected_on_hold: Arc::new(CallMutex::new(false, "ice_disconnected_on_hold")),
             context: Arc::new(CallMutex::new(context, "context")),
             webrtc: Arc::new(CallMutex::new(webrtc, "webrtc")),
             bandwidth_modes: Arc::new(CallMutex::new(
@@ -864,6 +868,12 @@ where
             let pc = webrtc.peer_connection()?;
             pc.set_outgoing_media_enabled(true);
             pc.set_incoming_media_enabled(true);
+        } else if new_state == ConnectionState::OnHold {
+            // Neither side hears or sees the other until the call is resumed.
+            let webrtc = self.webrtc.lock()?;
+            let pc = webrtc.peer_connection()?;
+            pc.set_outgoing_media_enabled(false);
+            pc.set_incoming_media_enabled(false);
         }
         Ok(())
     }
@@ -881,6 +891,19 @@ where
         Ok(())
     }
 
+    /// Return whether ICE is disconnected while on hold.
+    pub fn ice_disconnected_on_hold(&self) -> Result<bool> {
+        let ice_disconnected_on_hold = self.ice_disconnected_on_hold.lock()?;
+        Ok(*ice_disconnected_on_hold)
+    }
+
+    /// Update whether ICE is disconnected while on hold.
+    pub fn set_ice_disconnected_on_hold(&self, disconnected: bool) -> Result<()> {
+        let mut ice_disconnected_on_hold = self.ice_disconnected_on_hold.lock()?;
+        *ice_disconnected_on_hold = disconnected;
+        Ok(())
+    }
+
     /// Return the current feature level of the remote.
     pub fn remote_feature_level(&self) -> Result<FeatureLevel> {
         let remote_feature_level = self.remote_feature_level.lock()?;
@@ -1291,10 +1314,12 @@ where
                 previous.and_then(|sender_status| sender_status.video_enabled);
             let previous_sharing_screen =
                 previous.and_then(|sender_status| sender_status.sharing_screen);
+            let previous_held = previous.and_then(|sender_status| sender_status.held);
             data.sender_status = Some(protobuf::data_channel::SenderStatus {
                 id:             Some(u64::from(self.call_id)),
                 video_enabled:  updated.video_enabled.or(previous_video_enabled),
                 sharing_screen: updated.sharing_screen.or(previous_sharing_screen),
+                held:           updated.held.or(previous_held),
             });
         })
     }
@@ -1687,6 +1712,7 @@ where
                 signaling::SenderStatus {
                     video_enabled:  sender_status.video_enabled,
                     sharing_screen: sender_status.sharing_screen,
+                    held:           sender_status.held,
                 },
                 message.sequence_number,
             )
@@ -1785,6 +1811,20 @@ where
         self.inject_event(ConnectionEvent::Accept)
     }
 
+    /// Inject a local `Hold` event into the FSM.
+    ///
+    /// `Called By:` Local application.
+    pub fn inject_hold(&mut self) -> Result<()> {
+        self.inject_event(ConnectionEvent::Hold)
+    }
+
+    /// Inject a local `Resume` event into the FSM.
+    ///
+    /// `Called By:` Local application.
+    pub fn inject_resume(&mut self) -> Result<()> {
+        self.inject_event(ConnectionEvent::Resume)
+    }
+
     /// Inject a `UpdateSenderStatus` event into the FSM.
     ///
     /// `Called By:` Local application.
@@ -1899,7 +1939,7 @@ where
         self.inject_synchronize()
     }
 
-    #[cfg(feature = "sim")]
+    /// The video and screen sharing state last sent to the remote peer.
     pub fn last_sent_sender_status(&self) -> Option<protobuf::data_channel::SenderStatus> {
         self.accumulated_dcm_state
             .lock()
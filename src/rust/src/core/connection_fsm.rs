@@ -18,6 +18,8 @@
 //! - AcceptAnswer
 //! - AcceptOffer
 //! - AnswerCall
+//! - Hold
+//! - Resume
 //! - LocalHangup
 //! - UpdateSenderStatus
 //! - SendReceiverStatusViaDataChannel
@@ -85,6 +87,14 @@ pub enum ConnectionEvent {
     /// Source: app (user action)
     /// Action: got to "accepted" state and send accept message over the data channel
     Accept,
+    /// Put the call on hold.
+    /// Source: app (user action)
+    /// Action: disable media and send a held sender status over the data channel.
+    Hold,
+    /// Take the call off hold.
+    /// Source: app (user action)
+    /// Action: enable media again and send a sender status over the data channel.
+    Resume,
     /// Receive accepted message from remote peer.
     /// Source: data channel (PeerConnection)
     /// Action: bubble up to Call and transition states
@@ -151,6 +161,8 @@ impl fmt::Display for ConnectionEvent {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let display = match self {
             ConnectionEvent::Accept => "Accept".to_string(),
+            ConnectionEvent::Hold => "Hold".to_string(),
+            ConnectionEvent::Resume => "Resume".to_string(),
             ConnectionEvent::ReceivedHangup(call_id, hangup) => {
                 format!("RemoteHangup, call_id: {} hangup: {}", call_id, hangup)
             }
@@ -262,15 +274,15 @@ where
                     let state = cc.state()?;
                     match (state, &event) {
                         (
-                            ConnectionState::ConnectedAndAccepted,
+                            ConnectionState::ConnectedAndAccepted | ConnectionState::OnHold,
                             ConnectionEvent::ReceivedSenderStatusViaDataChannel(_, _, _),
                         )
                         | (
-                            ConnectionState::ConnectedAndAccepted,
+                            ConnectionState::ConnectedAndAccepted | ConnectionState::OnHold,
                             ConnectionEvent::ReceivedReceiverStatusViaDataChannel(_, _, _),
                         )
                         | (
-                            ConnectionState::ConnectedAndAccepted,
+                            ConnectionState::ConnectedAndAccepted | ConnectionState::OnHold,
                             ConnectionEvent::ReceivedAcceptedViaDataChannel(_),
                         ) => {
                             // Don't log periodic, ignored events at high verbosity
@@ -397,6 +409,8 @@ where
                 self.handle_received_hangup(connection, state, call_id, hangup)
             }
             ConnectionEvent::Accept => self.handle_accept(connection, state),
+            ConnectionEvent::Hold => self.handle_hold(connection, state),
+            ConnectionEvent::Resume => self.handle_resume(connection, state),
             ConnectionEvent::ReceivedAcceptedViaDataChannel(id) => {
                 self.handle_received_accepted_via_data_channel(connection, state, id)
             }
@@ -483,7 +497,8 @@ where
             ConnectionState::ConnectingBeforeAccepted
             | ConnectionState::ReconnectingAfterAccepted
             | ConnectionState::ConnectedBeforeAccepted
-            | ConnectionState::ConnectedAndAccepted => {
+            | ConnectionState::ConnectedAndAccepted
+            | ConnectionState::OnHold => {
                 self.notify_observer(connection, ConnectionObserverEvent::ReceivedHangup(hangup))
             }
             _ => self.unexpected_state(state, "RemoteHangup"),
@@ -511,7 +526,7 @@ where
                     ConnectionObserverEvent::ReceivedAcceptedViaDataChannel,
                 );
             }
-            ConnectionState::ConnectedAndAccepted => {
+            ConnectionState::ConnectedAndAccepted | ConnectionState::OnHold => {
                 // Ignore Accepted notifications in already-accepted state. These may arise
                 // because of expected data channel retransmissions.
             }
@@ -569,7 +584,8 @@ where
             ConnectionState::ConnectingBeforeAccepted
             | ConnectionState::ReconnectingAfterAccepted
             | ConnectionState::ConnectedBeforeAccepted
-            | ConnectionState::ConnectedAndAccepted => self.notify_observer(
+            | ConnectionState::ConnectedAndAccepted
+            | ConnectionState::OnHold => self.notify_observer(
                 connection,
                 ConnectionObserverEvent::ReceivedSenderStatusViaDataChannel(status),
             ),
@@ -629,7 +645,8 @@ where
             ConnectionState::ConnectingBeforeAccepted
             | ConnectionState::ReconnectingAfterAccepted
             | ConnectionState::ConnectedBeforeAccepted
-            | ConnectionState::ConnectedAndAccepted => {
+            | ConnectionState::ConnectedAndAccepted
+            | ConnectionState::OnHold => {
                 connection.set_remote_max_bitrate(max_bitrate)?
             }
             _ => self.unexpected_state(state, "ReceivedReceiverStatusViaDataChannel"),
@@ -652,7 +669,8 @@ where
             ConnectionState::ConnectingBeforeAccepted
             | ConnectionState::ReconnectingAfterAccepted
             | ConnectionState::ConnectedBeforeAccepted
-            | ConnectionState::ConnectedAndAccepted => {
+            | ConnectionState::ConnectedAndAccepted
+            | ConnectionState::OnHold => {
                 connection.handle_received_ice(ice)?;
             }
             _ => self.unexpected_state(state, "RemoteIceCandidate"),
@@ -666,13 +684,15 @@ where
             ConnectionState::ConnectingBeforeAccepted
             | ConnectionState::ReconnectingAfterAccepted
             | ConnectionState::ConnectedBeforeAccepted => {
+                // Enables media right away, so a Hold right behind this
+                // finds the call accepted.
+                connection.set_state(ConnectionState::ConnectedAndAccepted)?;
                 // notify the peer via a data channel message.
                 let mut err_connection = connection.clone();
                 let connected_future = lazy(move |_| {
                     if connection.terminating()? {
                         return Ok(());
                     }
-                    connection.set_state(ConnectionState::ConnectedAndAccepted)?;
                     connection.send_accepted_via_data_channel()
                 })
                 .map_err(move |err| {
@@ -686,6 +706,61 @@ where
         Ok(())
     }
 
+    fn handle_hold(&mut self, connection: Connection<T>, state: ConnectionState) -> Result<()> {
+        match state {
+            ConnectionState::ConnectedAndAccepted | ConnectionState::ReconnectingAfterAccepted => {
+                // Disables media right away, so a Resume right behind this
+                // finds the call on hold.
+                connection.set_ice_disconnected_on_hold(
+                    state == ConnectionState::ReconnectingAfterAccepted,
+                )?;
+                connection.set_state(ConnectionState::OnHold)?;
+                self.send_held_status(connection, true);
+            }
+            _ => self.unexpected_state(state, "Hold"),
+        }
+        Ok(())
+    }
+
+    fn handle_resume(&mut self, connection: Connection<T>, state: ConnectionState) -> Result<()> {
+        match state {
+            ConnectionState::OnHold => {
+                if connection.ice_disconnected_on_hold()? {
+                    // Media stays disabled until ICE reconnects.
+                    connection.set_state(ConnectionState::ReconnectingAfterAccepted)?;
+                    self.notify_observer(
+                        connection.clone(),
+                        ConnectionObserverEvent::ReconnectingAfterAccepted,
+                    );
+                } else {
+                    // Enables media again.
+                    connection.set_state(ConnectionState::ConnectedAndAccepted)?;
+                }
+                self.send_held_status(connection, false);
+            }
+            _ => self.unexpected_state(state, "Resume"),
+        }
+        Ok(())
+    }
+
+    fn send_held_status(&mut self, connection: Connection<T>, held: bool) {
+        let mut err_connection = connection.clone();
+        let send_held_status_future = lazy(move |_| {
+            if connection.terminating()? {
+                return Ok(());
+            }
+            connection.update_sender_status_from_fsm(signaling::SenderStatus {
+                held: Some(held),
+                ..Default::default()
+            })
+        })
+        .map_err(move |err| {
+            err_connection.inject_internal_error(err, "Sending held status failed");
+        });
+
+        self.worker_spawn(send_held_status_future);
+    }
+
     fn handle_send_hangup_via_data_channel(
         &mut self,
         connection: Connection<T>,
@@ -719,7 +794,8 @@ where
             ConnectionState::ConnectingBeforeAccepted
             | ConnectionState::ReconnectingAfterAccepted
             | ConnectionState::ConnectedBeforeAccepted
-            | ConnectionState::ConnectedAndAccepted => {
+            | ConnectionState::ConnectedAndAccepted
+            | ConnectionState::OnHold => {
                 // notify the peer via a data channel message.
                 let mut err_connection = connection.clone();
                 let send_sender_status_future = lazy(move |_| {
@@ -749,7 +825,8 @@ where
             ConnectionState::ConnectingBeforeAccepted
             | ConnectionState::ReconnectingAfterAccepted
             | ConnectionState::ConnectedBeforeAccepted
-            | ConnectionState::ConnectedAndAccepted => {
+            | ConnectionState::ConnectedAndAccepted
+            | ConnectionState::OnHold => {
                 let mut err_connection = connection.clone();
                 let update_bandwidth_mode_future = lazy(move |_| {
                     if connection.terminating()? {
@@ -843,6 +920,11 @@ where
                     ConnectionObserverEvent::ReconnectedAfterAccepted,
                 );
             }
+            ConnectionState::OnHold => {
+                // No media flows while on hold, so there's nothing to tell
+                // the call about a reconnection; just remember it for Resume.
+                connection.set_ice_disconnected_on_hold(false)?;
+            }
             _ => self.unexpected_state(state, "IceConnected"),
         }
         Ok(())
@@ -857,7 +939,8 @@ where
             ConnectionState::ConnectingBeforeAccepted
             | ConnectionState::ReconnectingAfterAccepted
             | ConnectionState::ConnectedBeforeAccepted
-            | ConnectionState::ConnectedAndAccepted => {
+            | ConnectionState::ConnectedAndAccepted
+            | ConnectionState::OnHold => {
                 connection.set_state(ConnectionState::IceFailed)?;
                 // For callee -- the call was disconnected while answering/local_ringing
                 // For caller -- the recipient was unreachable
@@ -884,6 +967,12 @@ where
                     ConnectionObserverEvent::ReconnectingAfterAccepted,
                 );
             }
+            ConnectionState::OnHold => {
+                // ICE keeps trying to reconnect on its own; if it gives up,
+                // IceFailed ends the call.  Remember it so Resume goes back
+                // to reconnecting rather than connected.
+                connection.set_ice_disconnected_on_hold(true)?;
+            }
             _ => self.unexpected_state(state, "IceDisconnected"),
         };
         Ok(())
@@ -928,7 +1017,8 @@ where
             ConnectionState::ConnectingBeforeAccepted
             | ConnectionState::ReconnectingAfterAccepted
             | ConnectionState::ConnectedBeforeAccepted
-            | ConnectionState::ConnectedAndAccepted => {
+            | ConnectionState::ConnectedAndAccepted
+            | ConnectionState::OnHold => {
                 let mut err_connection = connection.clone();
                 let add_stream_future = lazy(move |_| {
                     if connection.terminating()? {
@@ -22,6 +22,10 @@ pub enum Error {
 
 const RATCHET_INFO_STRING: &[u8; 15] = b"RingRTC Ratchet";
 const MAX_SENDER_STATES_TO_RETAIN: usize = 5;
+// A send state is saved after each time it changes, so the Context it was saved from
+// should be at most one step ahead of it.  The rest leaves room for saves that got lost
+// while staying far from the 256 steps after which receivers can no longer follow.
+const RESTORED_RATCHET_STEPS: RatchetCounter = 8;
 pub const MAC_SIZE_BYTES: usize = 16;
 
 // For some reason the linter doesn't detect this is required in the static assertions.
@@ -254,6 +258,21 @@ impl Context {
         )
     }
 
+    pub fn next_frame_counter(&self) -> FrameCounter {
+        self.next_frame_counter
+    }
+
+    /// Restores a send state saved from another Context, such as one from before a restart.
+    ///
+    /// The other Context may have encrypted frames with the same secret after the state was
+    /// saved, so the ratchet is advanced past anything it could have used and the frame counter
+    /// starts over.  Receivers that have the saved secret can advance their ratchets to match.
+    pub fn restore_send_state(&mut self, ratchet_counter: RatchetCounter, secret: Secret) {
+        self.sender_state = SenderState::new(ratchet_counter, secret)
+            .advance_ratchet(ratchet_counter.wrapping_add(RESTORED_RATCHET_STEPS));
+        self.next_frame_counter = 1;
+    }
+
     /// Ratchets our send state forward.
     ///
     /// This should be called when a new recipient joins the call. When an existing recipient leaves
@@ -285,6 +304,18 @@ impl Context {
         states.insert(0, SenderState::new(ratchet_counter, secret));
     }
 
+    /// The most recently added receive secret of each sender, ratcheted forward as far as
+    /// received frames have needed.
+    pub fn receive_secrets(&self) -> Vec<(SenderId, RatchetCounter, Secret)> {
+        self.remote_sender_states_by_id
+            .iter()
+            .filter_map(|(sender_id, states)| {
+                let state = states.first()?;
+                Some((*sender_id, state.ratchet_counter, state.current_secret))
+            })
+            .collect()
+    }
+
     fn get_mut_ref_sender_state_vec_by_id(&mut self, sender_id: SenderId) -> &mut Vec<SenderState> {
         self.remote_sender_states_by_id
             .entry(sender_id)
@@ -535,6 +566,59 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_restore_send_state() -> Result<(), Box<dyn std::error::Error>> {
+        let plaintext = b"Inspired hath in every holt and heeth";
+        let associated_data = Vec::from("Can't touch this");
+        let mut rng = StdRng::from_seed([0x58; 32]);
+        let mut ctx = Context::new(random_secret(&mut rng));
+        let sender_id: SenderId = 2718;
+        let (ratchet_counter, secret) = ctx.advance_send_ratchet();
+
+        let mut receiver = Context::new(random_secret(&mut rng));
+        receiver.add_receive_secret(sender_id, ratchet_counter, secret);
+        assert_eq!(
+            vec![(sender_id, ratchet_counter, secret)],
+            receiver.receive_secrets()
+        );
+
+        // Restoring many times, each time from the state saved by the last one,
+        // neither reuses the saved secret nor runs the frame counter up, and the
+        // receiver keeps up even once the ratchet counter wraps around.
+        let mut saved = (ratchet_counter, secret);
+        for _ in 0..100 {
+            let mut restored = Context::new(random_secret(&mut rng));
+            restored.restore_send_state(saved.0, saved.1);
+            let (restored_ratchet_counter, restored_secret) = restored.send_state();
+            assert_eq!(
+                saved.0.wrapping_add(RESTORED_RATCHET_STEPS),
+                restored_ratchet_counter
+            );
+            assert_ne!(saved.1, restored_secret);
+
+            for expected_frame_counter in 1..=3 {
+                let mut data = Vec::from(&plaintext[..]);
+                let mut mac = Mac::default();
+                let (ratchet_counter, frame_counter) =
+                    restored.encrypt(&mut data[..], &associated_data[..], &mut mac)?;
+                assert_eq!(restored_ratchet_counter, ratchet_counter);
+                assert_eq!(expected_frame_counter, frame_counter);
+                receiver.decrypt(
+                    sender_id,
+                    ratchet_counter,
+                    frame_counter,
+                    &mut data[..],
+                    &associated_data[..],
+                    &mac,
+                )?;
+                assert_eq!(&plaintext[..], &data[..]);
+            }
+            saved = restored.send_state();
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn test_advance_ratchet_equal_sender_states() {
         let mut rng = StdRng::from_seed([0x34; 32]);
@@ -0,0 +1,168 @@
+//
+// Copyright 2021 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Moving an active direct call to a group call without hanging up.
+//!
+//! Either user of the direct call can ask (CallManager::request_escalation).
+//! Once the other user accepts, both join the group call, and each of them
+//! hangs up the direct call only once the other user's media is arriving
+//! through the group call, so the conversation doesn't drop out in between.
+
+use crate::common::CallId;
+use crate::core::group_call::{
+    ClientId,
+    ConnectionState,
+    GroupId,
+    JoinState,
+    RemoteDeviceState,
+    UserId,
+};
+
+/// Passed to the application as an escalation progresses.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EscalationUpdate {
+    /// The other user asked to move the direct call to a group call.
+    /// Answer with CallManager::accept_escalation or decline_escalation.
+    Requested = 0,
+    /// The other user accepted, so the group call is being joined.
+    Accepted,
+    /// The other user declined, so the direct call goes on as before.
+    Declined,
+    /// The other user's media is arriving through the group call, so the
+    /// direct call was hung up.
+    Completed,
+    /// The direct call or the group call ended before the move was complete.
+    Failed,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EscalationPhase {
+    /// We asked, and are waiting for the other user to answer.
+    AwaitingRemoteAnswer,
+    /// The other user asked, and we're waiting for the application to answer.
+    AwaitingLocalAnswer,
+    /// Joining the group call.  The group call client now holds the busy state,
+    /// so ending the direct call doesn't release it.
+    Joining,
+}
+
+/// The CallManager's record of a direct call being moved to a group call.
+#[derive(Clone, Debug)]
+pub struct Escalation {
+    pub call_id:           CallId,
+    pub group_id:          GroupId,
+    pub remote_user_id:    UserId,
+    pub phase:             EscalationPhase,
+    /// The requester creates its group call client right away; the other
+    /// user creates one when accepting.
+    pub client_id:         Option<ClientId>,
+    joined:                bool,
+    connected:             bool,
+    remote_media_received: bool,
+}
+
+impl Escalation {
+    pub fn new(
+        call_id: CallId,
+        group_id: GroupId,
+        remote_user_id: UserId,
+        phase: EscalationPhase,
+        client_id: Option<ClientId>,
+    ) -> Self {
+        Self {
+            call_id,
+            group_id,
+            remote_user_id,
+            phase,
+            client_id,
+            joined: false,
+            connected: false,
+            remote_media_received: false,
+        }
+    }
+
+    pub fn is_for(&self, call_id: CallId, group_id: &[u8], remote_user_id: &[u8]) -> bool {
+        self.call_id == call_id
+            && self.group_id == group_id
+            && self.remote_user_id == remote_user_id
+    }
+
+    pub fn holds_busy(&self) -> bool {
+        self.phase == EscalationPhase::Joining
+    }
+
+    pub fn set_join_state(&mut self, join_state: &JoinState) {
+        self.joined = matches!(join_state, JoinState::Joined(_, _));
+    }
+
+    pub fn set_connection_state(&mut self, connection_state: ConnectionState) {
+        self.connected = connection_state == ConnectionState::Connected;
+    }
+
+    pub fn set_remote_devices(&mut self, remote_devices: &[RemoteDeviceState]) {
+        self.remote_media_received = remote_devices
+            .iter()
+            .any(|device| device.user_id == self.remote_user_id && device.media_keys_received);
+    }
+
+    /// True once the group call carries the other user's media, so the direct
+    /// call is no longer needed.
+    pub fn is_complete(&self) -> bool {
+        self.phase == EscalationPhase::Joining
+            && self.joined
+            && self.connected
+            && self.remote_media_received
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    fn remote_device(user_id: UserId, media_keys_received: bool) -> RemoteDeviceState {
+        let mut device =
+            RemoteDeviceState::new(1, user_id, 2, "device".to_string(), SystemTime::now());
+        device.media_keys_received = media_keys_received;
+        device
+    }
+
+    #[test]
+    fn complete_once_remote_media_arrives() {
+        let remote_user_id = vec![1, 2, 3];
+        let mut escalation = Escalation::new(
+            CallId::new(1),
+            vec![4],
+            remote_user_id.clone(),
+            EscalationPhase::AwaitingRemoteAnswer,
+            Some(5),
+        );
+        assert!(escalation.is_for(CallId::new(1), &[4], &remote_user_id));
+        assert!(!escalation.is_for(CallId::new(2), &[4], &remote_user_id));
+        assert!(!escalation.holds_busy());
+
+        // Connecting and joining early doesn't count until the other user accepts.
+        escalation.set_connection_state(ConnectionState::Connected);
+        escalation.set_join_state(&JoinState::Joined(0x10, "device".to_string()));
+        escalation.set_remote_devices(&[remote_device(remote_user_id.clone(), true)]);
+        assert!(!escalation.is_complete());
+
+        escalation.phase = EscalationPhase::Joining;
+        assert!(escalation.holds_busy());
+        assert!(escalation.is_complete());
+
+        // Someone else's media doesn't count.
+        escalation.set_remote_devices(&[
+            remote_device(remote_user_id.clone(), false),
+            remote_device(vec![9], true),
+        ]);
+        assert!(!escalation.is_complete());
+
+        escalation.set_remote_devices(&[remote_device(remote_user_id, true)]);
+        escalation.set_connection_state(ConnectionState::Reconnecting);
+        assert!(!escalation.is_complete());
+    }
+}
@@ -5,7 +5,7 @@
 
 use std::{
     collections::{HashMap, HashSet},
-    convert::TryInto,
+    convert::{TryFrom, TryInto},
     iter::FromIterator,
     mem::size_of,
     net::SocketAddr,
@@ -15,9 +15,14 @@ use std::{
 };
 
 use bytes::{Bytes, BytesMut};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac as _, NewMac};
 use num_enum::TryFromPrimitive;
 use prost::Message;
 use rand::Rng;
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+use x25519_dalek::{PublicKey, StaticSecret};
 
 use crate::core::util::uuid_to_string;
 use crate::{
@@ -26,7 +31,7 @@ use crate::{
         units::DataRate,
         Result,
     },
-    core::{call_mutex::CallMutex, crypto as frame_crypto, signaling, bandwidth_mode::BandwidthMode},
+    core::{call_mutex::CallMutex, chat, crypto as frame_crypto, resumption, signaling, bandwidth_mode::BandwidthMode},
     error::RingRtcError,
     protobuf,
     webrtc::{
@@ -42,7 +47,12 @@ use crate::{
         },
         rtp,
         sdp_observer::{create_ssd_observer, SessionDescription},
-        stats_observer::{create_stats_observer, StatsObserver},
+        stats_observer::{
+            create_audio_levels_stats_observer,
+            create_stats_observer,
+            AudioLevels,
+            StatsObserver,
+        },
     },
 };
 
@@ -74,7 +84,7 @@ pub type DemuxId = u32;
 //  auto fingerprint = rtc::SSLFingerprint::CreateUnique("sha-256", *identity);
 pub type DtlsFingerprint = [u8; 32];
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct RingId(i64);
 
 impl From<i64> for RingId {
@@ -124,6 +134,30 @@ pub enum RingCancelReason {
     Busy,
 }
 
+/// Where a ring sent with Client::ring stands for one of its recipients.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RingStatus {
+    /// Sent, with no response yet.
+    Ringing = 0,
+    /// The recipient joined the call.
+    Accepted,
+    /// The recipient declined the ring.
+    Declined,
+    /// The recipient was already in another call.
+    Busy,
+    /// The recipient didn't respond in time.
+    Expired,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecipientRingStatus {
+    pub user_id: UserId,
+    /// Recipients rung again on their own have a different ring ID than the group ring.
+    pub ring_id: RingId,
+    pub status:  RingStatus,
+}
+
 /// Indicates whether a signaling message should be marked for immediate processing
 /// even if the receiving app isn't running.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -186,6 +220,7 @@ pub enum RemoteDevicesChangedReason {
     SpeakerTimeChanged(DemuxId),
     HeartbeatStateChanged(DemuxId),
     ForwardeVideosChanged,
+    HandRaisedChanged(DemuxId),
 }
 
 // The callbacks from the Call to the Observer of the call.
@@ -233,6 +268,34 @@ pub trait Observer {
         reason: RemoteDevicesChangedReason,
     );
 
+    // Reactions are not part of the RemoteDeviceState because they don't last.
+    // Each is delivered once, in the order each remote device sent them.
+    fn handle_reaction(&self, client_id: ClientId, reaction: Reaction);
+
+    // Each chat message is delivered once, as soon as all of it has been received,
+    // so messages from the same device may be delivered out of order.
+    fn handle_message_received(&self, client_id: ClientId, message: ChatMessage);
+
+    // Control requests are only passed on when they come from a device of the call creator
+    // and carry a valid MAC from that device.
+    // The app decides whether to honor them, by calling set_outgoing_audio_muted(true),
+    // set_hand_raised(false), or leave().
+    fn handle_control_request(
+        &self,
+        client_id: ClientId,
+        sender_demux_id: DemuxId,
+        request: ControlRequest,
+    );
+
+    // Only called once enabled by Client::set_audio_levels_config.
+    // There is a received level for every remote device.
+    fn handle_audio_levels(
+        &self,
+        client_id: ClientId,
+        captured_level: AudioLevel,
+        received_levels: Vec<ReceivedAudioLevel>,
+    );
+
     // Notifies the observer of changes to the list of call participants.
     fn handle_peek_changed(
         &self,
@@ -244,6 +307,33 @@ pub trait Observer {
         device_count: u32,
     );
 
+    // Only called while joined, since only joined devices can approve or deny
+    // the devices waiting in the lobby (see Client::approve_pending_device).
+    fn handle_pending_devices_changed(
+        &self,
+        client_id: ClientId,
+        pending_devices: &[PeekDeviceInfo],
+    );
+
+    // Only called once enabled by Client::set_resumption_key, while joined, whenever
+    // the endpoint, the remote devices, or the media keys change.  The app should
+    // keep only the latest one, and delete it once the call is left or ended.
+    // Passing it to Client::resume after a restart rejoins as the same device.
+    fn handle_resumption_snapshot(&self, client_id: ClientId, snapshot: Vec<u8>);
+
+    // Only called once enabled by Client::set_layout_config, whenever the layout changes
+    // because of the config, the remote devices, or the latest speaker.
+    fn handle_layout_changed(&self, client_id: ClientId, layout: Layout);
+
+    // Called with the pinned devices (see Client::set_pinned) whose video was requested
+    // but that the SFU isn't forwarding, whenever that changes, including when it becomes empty.
+    fn handle_pinned_videos_not_forwarded(&self, client_id: ClientId, demux_ids: Vec<DemuxId>);
+
+    // Called with every recipient of the rings sent by Client::ring whenever any of
+    // their statuses change.  Only the group members we know of (see
+    // Client::set_group_members) or that responded are included.
+    fn handle_ring_statuses_changed(&self, client_id: ClientId, statuses: Vec<RecipientRingStatus>);
+
     // This is separate from handle_remote_devices_changed because everything else
     // is a pure state that can be copied, deleted, etc.
     // But the VideoTrack is a special handle which must be attached to.
@@ -312,11 +402,22 @@ pub enum ConnectionState {
 //      |            ^
 //      | join()     |
 //      V            |
+// WaitingForSlot -->|  leave()
+//      |            |
+//      | slot opens |
+//      V            |
 //   Joining      -->|  leave() or
 //      |            |  failed to join
 //      | joined     |
 //      V            |
+//   Pending      -->|  leave() or
+//      |            |  denied
+//      | approved   |
+//      V            |
 //   Joined       -->|
+//
+// WaitingForSlot is skipped unless the call is full and the client was told to wait.
+// Pending is skipped unless the SFU puts the device in the lobby.
 #[derive(Clone, Debug, PartialEq)]
 pub enum JoinState {
     /// Join() has not yet been called
@@ -330,9 +431,26 @@ pub enum JoinState {
     /// Join() has been called but a response from the SFU is pending.
     Joining,
 
+    /// Join() has been called and a DemuxId/RequestToken has been assigned,
+    /// but the SFU keeps the device in the lobby until a joined device approves it.
+    Pending(DemuxId, String),
+
     /// Join() has been called and a response from the SFU has been received.
     /// and a DemuxId/RequestToken has been assigned.
     Joined(DemuxId, String),
+
+    /// Join() has been called but the call is full, so the client waits
+    /// until a device leaves and then joins (see Client::set_wait_for_slot_when_full).
+    /// The ring ID is kept for when it does join.
+    WaitingForSlot(Option<RingId>),
+}
+
+// Whether the SFU let the device into the call when it joined
+// or put it in the lobby to wait for approval.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClientStatus {
+    Active,
+    Pending,
 }
 
 // The info about SFU needed in order to connect to it.
@@ -348,15 +466,28 @@ pub struct SfuInfo {
 #[derive(Clone, Debug, Default)]
 pub struct PeekInfo {
     /// Currently joined devices
-    pub devices:      Vec<PeekDeviceInfo>,
+    pub devices:         Vec<PeekDeviceInfo>,
+    /// Devices waiting in the lobby for a joined device to approve them
+    pub pending_devices: Vec<PeekDeviceInfo>,
     /// The user who created the call
-    pub creator:      Option<UserId>,
+    pub creator:         Option<UserId>,
     /// The "era" of this group call; changes every time the last partipant leaves and someone else joins again.
-    pub era_id:       Option<String>,
+    pub era_id:          Option<String>,
     /// The maximum number of devices that can join this group call.
-    pub max_devices:  Option<u32>,
+    pub max_devices:     Option<u32>,
     /// The number of devices currently joined (including local device/user).
-    pub device_count: u32,
+    pub device_count:    u32,
+}
+
+impl PeekInfo {
+    /// Whether another device joining would go over max_devices.
+    /// A call without a known maximum is never full.
+    pub fn is_full(&self) -> bool {
+        match self.max_devices {
+            Some(max_devices) => self.device_count >= max_devices,
+            None => false,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -391,6 +522,7 @@ pub enum EndReason {
     IceFailedAfterConnected,
     ServerChangedDemuxId,
     HasMaxDevices,
+    DeniedRequestToJoinCall,
 }
 
 pub type BoxedPeekInfoHandler = Box<dyn FnOnce(Result<PeekInfo>) + Send + 'static>;
@@ -398,11 +530,14 @@ pub type BoxedPeekInfoHandler = Box<dyn FnOnce(Result<PeekInfo>) + Send + 'stati
 // The callbacks from the Client to the "SFU client" for the group call.
 pub trait SfuClient {
     // This should call Client.on_sfu_client_joined when the SfuClient has joined.
+    // If resume_endpoint_id is set, the SFU gives that endpoint back if it still holds
+    // it for us (see Client::resume).
     fn join(
         &mut self,
         ice_ufrag: &str,
         ice_pwd: &str,
         dtls_fingerprint: &DtlsFingerprint,
+        resume_endpoint_id: Option<&str>,
         client: Client,
     );
     fn peek(&mut self, handle_remote_devices: BoxedPeekInfoHandler);
@@ -411,6 +546,13 @@ pub trait SfuClient {
     fn set_membership_proof(&mut self, proof: MembershipProof);
     fn set_group_members(&mut self, members: Vec<GroupMemberInfo>);
     fn leave(&mut self, long_device_id: String);
+    // Asks the SFU to remove another device from the call.
+    // The SFU only allows this for devices of the call creator.
+    fn remove_device(&mut self, long_device_id: String);
+    // Asks the SFU to let a device waiting in the lobby into the call, or to turn it away.
+    // The SFU only allows this for joined devices.
+    fn approve_device(&mut self, long_device_id: String);
+    fn deny_device(&mut self, long_device_id: String);
 }
 
 // Associates a group member's UUID with their UUID ciphertext
@@ -439,6 +581,183 @@ impl From<protobuf::group_call::device_to_device::Heartbeat> for HeartbeatState
     }
 }
 
+// A reaction (such as an emoji) sent by a remote device.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reaction {
+    pub demux_id: DemuxId,
+    pub value:    String,
+}
+
+// An in-call chat message sent by a remote device.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChatMessage {
+    pub demux_id:   DemuxId,
+    // Increments by one for each message a device sends.
+    pub message_id: u64,
+    pub text:       String,
+}
+
+/// How a remote device's video is prioritized, set with Client::set_pinned.
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, TryFromPrimitive)]
+pub enum PinKind {
+    /// Kept in view, so forwarded by the SFU before videos that aren't pinned.
+    Pinned = 0,
+    /// Shown bigger than everything else, so forwarded first of all.
+    /// Only one device is spotlighted at a time.
+    Spotlight,
+}
+
+impl PinKind {
+    fn video_priority(self) -> u32 {
+        match self {
+            PinKind::Pinned => 1,
+            PinKind::Spotlight => 2,
+        }
+    }
+}
+
+/// Something a device of the call creator can ask another device to do.
+///
+/// Requests carry a MAC keyed with the X25519 control keys of the sender and
+/// the target, which each device sends along with its media key.  Requests
+/// without a valid MAC from a device of the creator are ignored.
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, TryFromPrimitive)]
+pub enum ControlRequest {
+    /// Mute outgoing audio.
+    Mute = 0,
+    /// Lower a raised hand.
+    LowerHand,
+    /// Leave the call.
+    Remove,
+}
+
+impl From<ControlRequest> for protobuf::group_call::device_to_device::control_request::Type {
+    fn from(request: ControlRequest) -> Self {
+        match request {
+            ControlRequest::Mute => Self::Mute,
+            ControlRequest::LowerHand => Self::LowerHand,
+            ControlRequest::Remove => Self::Remove,
+        }
+    }
+}
+
+impl From<protobuf::group_call::device_to_device::control_request::Type> for ControlRequest {
+    fn from(proto: protobuf::group_call::device_to_device::control_request::Type) -> Self {
+        use protobuf::group_call::device_to_device::control_request::Type;
+        match proto {
+            Type::Mute => Self::Mute,
+            Type::LowerHand => Self::LowerHand,
+            Type::Remove => Self::Remove,
+        }
+    }
+}
+
+const CONTROL_REQUEST_KEY_INFO: &[u8] = b"RingRTC Control Request Key";
+const CONTROL_REQUEST_MAC_LEN: usize = 32;
+
+// The key is derived from the control keys of the sender and the target, so no
+// other device can compute a MAC that the target accepts.
+fn compute_control_request_mac(
+    local_secret: &StaticSecret,
+    remote_public_key: &PublicKey,
+    sender_demux_id: DemuxId,
+    request: &protobuf::group_call::device_to_device::ControlRequest,
+) -> [u8; CONTROL_REQUEST_MAC_LEN] {
+    let shared_secret = local_secret.diffie_hellman(remote_public_key);
+    let mut mac_key = [0u8; 32];
+    Hkdf::<Sha256>::new(None, shared_secret.as_bytes())
+        .expand(CONTROL_REQUEST_KEY_INFO, &mut mac_key)
+        .expect("HKDF should work with output of length 32");
+    let mut hmac = Hmac::<Sha256>::new_from_slice(&mac_key).expect("HMAC can take key of any size");
+    hmac.update(&sender_demux_id.to_be_bytes());
+    hmac.update(&request.target_demux_id.unwrap_or(0).to_be_bytes());
+    hmac.update(&request.r#type.unwrap_or(-1).to_be_bytes());
+    match &request.era_id {
+        Some(era_id) => {
+            hmac.update(&[1]);
+            hmac.update(era_id.as_bytes());
+        }
+        None => hmac.update(&[0]),
+    }
+    let mut mac = [0u8; CONTROL_REQUEST_MAC_LEN];
+    mac.copy_from_slice(&hmac.finalize().into_bytes());
+    mac
+}
+
+// Audio levels range from 0 (silence) to MAX_AUDIO_LEVEL (the loudest possible).
+pub type AudioLevel = u16;
+pub const MAX_AUDIO_LEVEL: AudioLevel = 32767;
+
+fn audio_level_from_fraction(fraction: f64) -> AudioLevel {
+    (fraction.clamp(0.0, 1.0) * (MAX_AUDIO_LEVEL as f64)).round() as AudioLevel
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReceivedAudioLevel {
+    pub demux_id: DemuxId,
+    pub level:    AudioLevel,
+}
+
+// How often audio levels are passed to Observer::handle_audio_levels
+// and how much they are smoothed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AudioLevelsConfig {
+    pub interval:  Duration,
+    // From 0.0 (not at all) up to, but not including, 1.0.
+    // Each level passed on is smoothing * the previous level + (1 - smoothing) * the latest level.
+    pub smoothing: f64,
+}
+
+impl Default for AudioLevelsConfig {
+    fn default() -> Self {
+        Self {
+            interval:  Duration::from_millis(200),
+            smoothing: 0.5,
+        }
+    }
+}
+
+// When the media send key is replaced with a new random one and sent to everyone.
+// That always happens when a user leaves, so they can't decrypt what comes after.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyRotationPolicy {
+    // The time between when a new key is sent out and when it is applied.
+    // See DEFAULT_MEDIA_SEND_KEY_ROTATION_DELAY.
+    pub apply_delay:        Duration,
+    // If set, the key is also rotated once it has been used this long.
+    pub max_key_age:        Option<Duration>,
+    // If set, the key is also rotated once this many frames have been sent with it.
+    pub max_frames_per_key: Option<u64>,
+}
+
+impl Default for KeyRotationPolicy {
+    fn default() -> Self {
+        Self {
+            apply_delay:        DEFAULT_MEDIA_SEND_KEY_ROTATION_DELAY,
+            max_key_age:        None,
+            max_frames_per_key: None,
+        }
+    }
+}
+
+// How well key rotation has kept media away from users who left.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct KeyRotationMetrics {
+    // Number of new keys applied.
+    pub rotations:            u64,
+    // The time between when a user left and when a key they don't have was applied,
+    // during which they could still decrypt our media, for the latest such rotation.
+    pub last_exposure_window: Option<Duration>,
+    // The largest such window so far in the call.
+    pub max_exposure_window:  Option<Duration>,
+}
+
+// Reactions are meant to be single emoji, which can take a few code points,
+// but anything longer is not sent and ignored when received.
+pub const MAX_REACTION_VALUE_LEN: usize = 64;
+
 // The info about remote devices received from the SFU
 #[derive(Clone, Debug, PartialEq)]
 pub struct RemoteDeviceState {
@@ -457,9 +776,29 @@ pub struct RemoteDeviceState {
     pub added_time:          SystemTime,
     // The most recent time at which this device became the primary speaker
     // Sorting using this value will give a history of who spoke.
-    pub speaker_time:        Option<SystemTime>,
-    pub leaving_received:    bool,
-    pub forwarding_video:     Option<bool>,
+    pub speaker_time:              Option<SystemTime>,
+    pub leaving_received:          bool,
+    pub forwarding_video:          Option<bool>,
+    pub hand_raised:               bool,
+    // The latest timestamp we received from an update to
+    // hand_raised.
+    raised_hand_rtp_timestamp:     Option<rtp::Timestamp>,
+    // The latest timestamp we received a reaction with.
+    // Reactions with the same or older timestamps are duplicates
+    // or arrived out of order and are ignored.
+    reaction_rtp_timestamp:        Option<rtp::Timestamp>,
+    // The latest timestamp we received a control request with,
+    // so that each request is only passed on once.
+    control_request_rtp_timestamp: Option<rtp::Timestamp>,
+}
+
+// Whether an RTP timestamp is newer than the last one received,
+// allowing for the timestamps wrapping around.
+fn is_newer_rtp_timestamp(timestamp: rtp::Timestamp, last: Option<rtp::Timestamp>) -> bool {
+    match last {
+        None => true,
+        Some(last) => (timestamp.wrapping_sub(last) as i32) > 0,
+    }
 }
 
 fn as_unix_millis(t: Option<SystemTime>) -> u64 {
@@ -475,7 +814,7 @@ fn as_unix_millis(t: Option<SystemTime>) -> u64 {
 }
 
 impl RemoteDeviceState {
-    fn new(
+    pub(crate) fn new(
         demux_id: DemuxId,
         user_id: UserId,
         short_device_id: u64,
@@ -495,6 +834,10 @@ impl RemoteDeviceState {
             speaker_time: None,
             leaving_received: false,
             forwarding_video: None,
+            hand_raised: false,
+            raised_hand_rtp_timestamp: None,
+            reaction_rtp_timestamp: None,
+            control_request_rtp_timestamp: None,
         }
     }
 
@@ -509,7 +852,7 @@ impl RemoteDeviceState {
 
 /// These can be sent to the SFU to request different resolutions of
 /// video for different remote dem
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct VideoRequest {
     pub demux_id:  DemuxId,
     pub width:     u16,
@@ -518,6 +861,171 @@ pub struct VideoRequest {
     pub framerate: Option<u16>,
 }
 
+// Passed to Client::set_layout_config to have the client lay out the remote devices
+// in a grid and request their video to match.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LayoutConfig {
+    pub viewport_width:   u16,
+    pub viewport_height:  u16,
+    // At least 1.
+    pub tiles_per_page:   usize,
+    pub page_index:       usize,
+    // These go first, in this order, if they are in the call,
+    // after any pinned with Client::set_pinned.
+    pub pinned_demux_ids: Vec<DemuxId>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Layout {
+    // Every remote device, pinned ones first.  The rest keep their places from one layout
+    // to the next, except that a speaker who isn't on the first page swaps places with
+    // the least recent speaker on it.
+    pub demux_ids:      Vec<DemuxId>,
+    // The config's page index, or the last page if there aren't that many.
+    pub page_index:     usize,
+    pub page_count:     usize,
+    pub tiles_per_page: usize,
+    pub columns:        u16,
+    pub rows:           u16,
+    pub tile_width:     u16,
+    pub tile_height:    u16,
+    // A request for every remote device: the tile size for those on the page
+    // and nothing for the rest.
+    pub video_requests: Vec<VideoRequest>,
+}
+
+impl Layout {
+    // The devices to show on the current page, in order from left to right and top to bottom.
+    pub fn page(&self) -> &[DemuxId] {
+        let start = std::cmp::min(self.page_index * self.tiles_per_page, self.demux_ids.len());
+        let end = std::cmp::min(start + self.tiles_per_page, self.demux_ids.len());
+        &self.demux_ids[start..end]
+    }
+}
+
+// Orders the devices starting from the previous order (for stability) and computes
+// the grid and the video requests for the requested page.
+fn compute_layout(
+    config: &LayoutConfig,
+    remote_devices: &[RemoteDeviceState],
+    previous_demux_ids: &[DemuxId],
+) -> Layout {
+    let tiles_per_page = std::cmp::max(config.tiles_per_page, 1);
+    let is_present = |demux_id: &DemuxId| {
+        remote_devices
+            .iter()
+            .any(|device| device.demux_id == *demux_id)
+    };
+    let is_pinned = |demux_id: &DemuxId| config.pinned_demux_ids.contains(demux_id);
+
+    let mut pinned: Vec<DemuxId> = Vec::new();
+    for demux_id in config.pinned_demux_ids.iter().copied() {
+        if is_present(&demux_id) && !pinned.contains(&demux_id) {
+            pinned.push(demux_id);
+        }
+    }
+    let mut unpinned: Vec<DemuxId> = previous_demux_ids
+        .iter()
+        .copied()
+        .filter(|demux_id| is_present(demux_id) && !is_pinned(demux_id))
+        .collect();
+    let mut added: Vec<&RemoteDeviceState> = remote_devices
+        .iter()
+        .filter(|device| {
+            !is_pinned(&device.demux_id) && !previous_demux_ids.contains(&device.demux_id)
+        })
+        .collect();
+    added.sort_by_key(|device| (device.added_time, device.demux_id));
+    unpinned.extend(added.into_iter().map(|device| device.demux_id));
+
+    // Keep the latest speaker on the first page.
+    let speaker_time = |demux_id: DemuxId| {
+        remote_devices
+            .iter()
+            .find(|device| device.demux_id == demux_id)
+            .and_then(|device| device.speaker_time)
+    };
+    let first_page_unpinned = tiles_per_page.saturating_sub(pinned.len());
+    let latest_speaker = unpinned
+        .iter()
+        .enumerate()
+        .filter_map(|(index, demux_id)| Some((speaker_time(*demux_id)?, index)))
+        .max();
+    if let Some((_, speaker_index)) = latest_speaker {
+        if speaker_index >= first_page_unpinned && first_page_unpinned > 0 {
+            // The least recent speaker, or the last one if there's a tie.
+            let swap_index = (0..first_page_unpinned)
+                .min_by_key(|index| (speaker_time(unpinned[*index]), std::cmp::Reverse(*index)))
+                .expect("first page has an unpinned device");
+            unpinned.swap(speaker_index, swap_index);
+        }
+    }
+
+    let mut demux_ids = pinned;
+    demux_ids.extend(unpinned);
+
+    let page_count = std::cmp::max(demux_ids.chunks(tiles_per_page).len(), 1);
+    let page_index = std::cmp::min(config.page_index, page_count - 1);
+    let page_start = page_index * tiles_per_page;
+    let page_end = std::cmp::min(page_start + tiles_per_page, demux_ids.len());
+    let page_len = page_end.saturating_sub(page_start);
+
+    let (columns, rows) = grid_dimensions(
+        std::cmp::min(demux_ids.len(), tiles_per_page),
+        config.viewport_width,
+        config.viewport_height,
+    );
+    let tile_width = config.viewport_width.checked_div(columns).unwrap_or(0);
+    let tile_height = config.viewport_height.checked_div(rows).unwrap_or(0);
+
+    let video_requests = demux_ids
+        .iter()
+        .enumerate()
+        .map(|(index, demux_id)| {
+            let on_page = index >= page_start && index < page_start + page_len;
+            VideoRequest {
+                demux_id:  *demux_id,
+                width:     if on_page { tile_width } else { 0 },
+                height:    if on_page { tile_height } else { 0 },
+                framerate: None,
+            }
+        })
+        .collect();
+
+    Layout {
+        demux_ids,
+        page_index,
+        page_count,
+        tiles_per_page,
+        columns,
+        rows,
+        tile_width,
+        tile_height,
+        video_requests,
+    }
+}
+
+// The (columns, rows) that make the shorter side of the tiles as long as possible.
+// Every page uses the same grid, sized for the first page, so tiles don't change size
+// when moving to a page that isn't full.
+fn grid_dimensions(tile_count: usize, viewport_width: u16, viewport_height: u16) -> (u16, u16) {
+    if tile_count == 0 {
+        return (0, 0);
+    }
+    let tile_count = std::cmp::min(tile_count, u16::MAX as usize) as u16;
+    (1..=tile_count)
+        .map(|columns| {
+            let rows = (tile_count - 1) / columns + 1;
+            (columns, rows)
+        })
+        .max_by_key(|(columns, rows)| {
+            let shorter_side = std::cmp::min(viewport_width / columns, viewport_height / rows);
+            // Prefer fewer empty tiles when the sizes are the same.
+            (shorter_side, std::cmp::Reverse((*columns as u32) * (*rows as u32)))
+        })
+        .expect("at least one column")
+}
+
 // This must stay in sync with the data PT in SfuClient.
 const RTP_DATA_PAYLOAD_TYPE: rtp::PayloadType = 101;
 // This must stay in sync with the data SSRC offset in SfuClient.
@@ -542,7 +1050,15 @@ const ALL_ALONE_MAX_SEND_RATE_KBPS: u64 = 1;
 // so a receiver may leave immediately after receiving a newly
 // generated key and it will be able to decrypt until after
 // a second rotation is applied.
-const MEDIA_SEND_KEY_ROTATION_DELAY_SECS: u64 = 3;
+const DEFAULT_MEDIA_SEND_KEY_ROTATION_DELAY: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum KeyRotationReason {
+    UserRemoved,
+    KeyAge,
+    FrameCount,
+    Requested,
+}
 
 enum KeyRotationState {
     // A key has been applied.  Nothing is pending.
@@ -553,6 +1069,8 @@ enum KeyRotationState {
         // Once it has been applied, another rotation needs to take place because
         // a user left the call while rotation was pending.
         needs_another_rotation: bool,
+        // When the earliest user that this key protects against left, if any.
+        departed_at:            Option<Instant>,
     },
 }
 
@@ -586,6 +1104,8 @@ pub struct Client {
     // because WebRTC calls back to the PeerConnectionObserver
     // synchronously.
     frame_crypto_context: Arc<CallMutex<frame_crypto::Context>>,
+    // Kept outside the actor state so it can be read synchronously.
+    key_rotation_metrics: Arc<CallMutex<KeyRotationMetrics>>,
     actor:                Actor<State>,
 }
 
@@ -652,10 +1172,25 @@ struct State {
     // Observer::handle_peek_changed only when it changes
     joined_members: HashSet<UserId>,
 
+    // The devices waiting in the lobby, as last passed to
+    // Observer::handle_pending_devices_changed.  Only kept while joined.
+    pending_devices: Vec<PeekDeviceInfo>,
+    // When the local device was put in the lobby, so that a peek requested since
+    // that doesn't list it means we were turned away rather than that the peek is stale.
+    pending_since:   Option<Instant>,
+
     // Things we send to other clients via heartbeats
     // These are unset until the app sets them.
     // But we err on the side of caution and don't send anything when they are unset.
     outgoing_heartbeat_state: HeartbeatState,
+    // Sent along with heartbeats so that devices that miss a change
+    // learn about it with the next heartbeat.
+    outgoing_hand_raised:     bool,
+
+    // Chat messages are sent through the SFU like heartbeats, but lost
+    // chunks are recovered by asking the sender to send them again.
+    chat_sender:    chat::ChatSender,
+    chat_receivers: HashMap<DemuxId, chat::ChatReceiver>,
 
     // Things for controlling the PeerConnection
     local_ice_ufrag:                  String,
@@ -672,6 +1207,14 @@ struct State {
     next_stats_time: Option<Instant>,
     stats_observer:  Box<StatsObserver>,
 
+    // Audio levels come from statistics as well, but they are gathered much more
+    // often and only when the observer wants them.
+    audio_levels_config:         Option<AudioLevelsConfig>,
+    audio_levels_polling:        bool,
+    audio_levels_stats_observer: Box<StatsObserver>,
+    captured_audio_level:        f64,
+    received_audio_levels:       HashMap<DemuxId, f64>,
+
     next_membership_proof_request_time: Option<Instant>,
 
     // We have to put this inside the actor state also because
@@ -686,6 +1229,12 @@ struct State {
         frame_crypto::RatchetCounter,
         frame_crypto::Secret,
     )>,
+    // Sent to other devices with our media key so that control requests between
+    // us and them can be authenticated (see ControlRequest).
+    control_secret:      StaticSecret,
+    // The control public keys that remote devices sent with their media keys.
+    // Keyed by user too so that one user can't replace another user's key.
+    control_public_keys: HashMap<(DemuxId, UserId), PublicKey>,
     // If we generate a new media send key when a user leaves the call,
     // during the time between when we generate it and apply it, we need
     // to make sure that user that joined in that window gets that key
@@ -696,6 +1245,32 @@ struct State {
     // be able do decrypt media after leaving if they leave immediately
     // after receiving a newly generated key.
     media_send_key_rotation_state: KeyRotationState,
+    key_rotation_policy:           KeyRotationPolicy,
+    // When the current media send key was applied and the frame counter at that time,
+    // so that it can be rotated once it gets too old or has been used too much.
+    media_send_key_applied_at:            Instant,
+    media_send_key_applied_frame_counter: frame_crypto::FrameCounter,
+    // When the earliest user that left since the last rotation started left.
+    earliest_unprotected_departure:       Option<Instant>,
+    key_rotation_metrics:                 Arc<CallMutex<KeyRotationMetrics>>,
+
+    // Set by the app to have encrypted snapshots passed to
+    // Observer::handle_resumption_snapshot.
+    resumption_key:          Option<resumption::Key>,
+    // Set by Client::resume until the SFU says whether we got our endpoint back.
+    resuming_from:           Option<resumption::Snapshot>,
+    // The remote devices from the snapshot we resumed from, until the first update
+    // of remote devices after resuming.  They already have our media send key.
+    resumed_remote_devices:  Vec<resumption::RemoteDevice>,
+
+    // Set by Client::set_wait_for_slot_when_full.  While JoinState::WaitingForSlot,
+    // this holds what join() needs to continue once a device leaves.
+    wait_for_slot_when_full: bool,
+    waiting_for_slot:        Option<(Client, Option<Vec<u8>>)>,
+
+    // Set by Client::take_over_busy.  The next join succeeds even though the
+    // CallManager is busy, because the busy state is being handed over to us.
+    taking_over_busy:        bool,
 
     // Things to control video requests.  We want to send them regularly on ticks,
     // but also limit how often they are sent "on demand".  So here's the rule:
@@ -705,6 +1280,15 @@ struct State {
     on_demand_video_request_sent_since_last_tick: bool,
     speaker_rtp_timestamp:                        Option<rtp::Timestamp>,
 
+    // Set by Client::set_layout_config.  When set, the video requests come from the layout.
+    layout_config: Option<LayoutConfig>,
+    layout:        Option<Layout>,
+
+    // Set by Client::set_pinned, with the spotlight (if any) first and the rest in the order
+    // they were pinned.
+    pinned:               Vec<(DemuxId, PinKind)>,
+    pinned_not_forwarded: Vec<DemuxId>,
+
     send_rates:               SendRates,
     max_receive_rate: Option<DataRate>,
     forwarding_video_demux_ids: HashSet<DemuxId>,
@@ -715,6 +1299,13 @@ struct State {
     /// to the whole group if the current client leaves before anyone else joins.
     cancellable_initial_ring: Option<RingId>,
 
+    /// Where each recipient of our rings stands, with when they were last rung,
+    /// in the order they were first rung.
+    outgoing_rings:    Vec<(RecipientRingStatus, Instant)>,
+    /// The latest ring sent to the whole group, so that responses from members we
+    /// didn't know about can still be tracked.
+    latest_group_ring: Option<RingId>,
+
     actor: Actor<State>,
 }
 
@@ -763,9 +1354,19 @@ impl RemoteDevices {
 // membership list from the SfuClient
 const TICK_INTERVAL: Duration = Duration::from_secs(1);
 
+// How long a recipient of our ring has to respond, which matches how long the
+// CallManager of the recipient rings for.
+const OUTGOING_RING_TIMEOUT: Duration = Duration::from_secs(60);
+
+// How often a device in the lobby asks the SfuClient whether it has been let in.
+const PENDING_PEEK_INTERVAL: Duration = Duration::from_secs(2);
+
 // The stats period, how often to get and log them.
 const STATS_INTERVAL: Duration = Duration::from_secs(10);
 
+// Gathering statistics any more often than this isn't worth it.
+const MIN_AUDIO_LEVELS_INTERVAL: Duration = Duration::from_millis(20);
+
 // How often to request an updated membership proof (24 hours).
 const MEMBERSHIP_PROOF_REQUEST_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
 
@@ -792,6 +1393,11 @@ impl Client {
             "Frame encryption context",
         ));
         let frame_crypto_context_for_outside_actor = frame_crypto_context.clone();
+        let key_rotation_metrics = Arc::new(CallMutex::new(
+            KeyRotationMetrics::default(),
+            "Key rotation metrics",
+        ));
+        let key_rotation_metrics_for_outside_actor = key_rotation_metrics.clone();
         let client = Self {
             client_id,
             group_id: group_id.clone(),
@@ -844,6 +1450,15 @@ impl Client {
                         observer.handle_ended(client_id, EndReason::FailedToCreatePeerConnection);
                         e
                     })?;
+                let media_send_key_applied_frame_counter =
+                    frame_crypto_context.lock()?.next_frame_counter();
+                let actor_for_audio_levels = actor.clone();
+                let audio_levels_stats_observer =
+                    create_audio_levels_stats_observer(Box::new(move |audio_levels| {
+                        actor_for_audio_levels.send(move |state| {
+                            Self::handle_audio_levels(state, audio_levels);
+                        });
+                    }));
                 Ok(State {
                     client_id,
                     group_id,
@@ -866,7 +1481,13 @@ impl Client {
 
                     joined_members: HashSet::new(),
 
+                    pending_devices: Vec::new(),
+                    pending_since: None,
+
                     outgoing_heartbeat_state: Default::default(),
+                    outgoing_hand_raised: false,
+                    chat_sender: Default::default(),
+                    chat_receivers: HashMap::new(),
 
                     local_dtls_fingerprint,
                     sfu_info: None,
@@ -878,26 +1499,57 @@ impl Client {
                     next_stats_time: None,
                     stats_observer: create_stats_observer(),
 
+                    audio_levels_config: None,
+                    audio_levels_polling: false,
+                    audio_levels_stats_observer,
+                    captured_audio_level: 0.0,
+                    received_audio_levels: HashMap::new(),
+
                     next_membership_proof_request_time: None,
 
                     frame_crypto_context,
                     pending_media_receive_keys: Vec::new(),
+                    control_secret: StaticSecret::new(&mut rand::rngs::OsRng),
+                    control_public_keys: HashMap::new(),
                     media_send_key_rotation_state: KeyRotationState::Applied,
+                    key_rotation_policy: KeyRotationPolicy::default(),
+                    media_send_key_applied_at: Instant::now(),
+                    media_send_key_applied_frame_counter,
+                    earliest_unprotected_departure: None,
+                    key_rotation_metrics,
+
+                    resumption_key: None,
+                    resuming_from: None,
+                    resumed_remote_devices: Vec::new(),
+
+                    wait_for_slot_when_full: false,
+                    waiting_for_slot: None,
+
+                    taking_over_busy: false,
 
                     video_requests: None,
                     on_demand_video_request_sent_since_last_tick: false,
                     speaker_rtp_timestamp: None,
 
+                    layout_config: None,
+                    layout: None,
+
+                    pinned: Vec::new(),
+                    pinned_not_forwarded: Vec::new(),
+
                     send_rates: SendRates::default(),
                     max_receive_rate: None,
                     forwarding_video_demux_ids: HashSet::default(),
 
                     cancellable_initial_ring: None,
+                    outgoing_rings: Vec::new(),
+                    latest_group_ring: None,
 
                     actor,
                 })
             })?,
             frame_crypto_context: frame_crypto_context_for_outside_actor,
+            key_rotation_metrics: key_rotation_metrics_for_outside_actor,
         };
 
         // After we have the actor, we can initialize the PeerConnectionObserverImpl
@@ -920,7 +1572,13 @@ impl Client {
             state.client_id
         );
 
-        Self::request_remote_devices_from_sfu_if_older_than(state, Duration::from_secs(10));
+        // A device in the lobby isn't reliably told that it's been approved or denied,
+        // so it has to keep asking, and so does a device waiting for a slot.
+        let max_peek_age = match state.join_state {
+            JoinState::Pending(..) | JoinState::WaitingForSlot(_) => PENDING_PEEK_INTERVAL,
+            _ => Duration::from_secs(10),
+        };
+        Self::request_remote_devices_from_sfu_if_older_than(state, max_peek_age);
 
         if let Err(err) = Self::send_heartbeat(state) {
             warn!("Failed to send regular heartbeat: {:?}", err);
@@ -945,6 +1603,12 @@ impl Client {
         Self::send_video_requests_to_sfu(state);
         state.on_demand_video_request_sent_since_last_tick = false;
 
+        Self::send_chat_retransmit_requests(state, now);
+
+        Self::rotate_media_send_key_if_due(state, now);
+
+        Self::expire_outgoing_rings(state, now);
+
         state
             .actor
             .send_delayed(TICK_INTERVAL, move |state| {
@@ -1080,11 +1744,12 @@ impl Client {
         let busy = state.busy.lock();
         match busy {
             Ok(mut busy) => {
-                if *busy {
+                if *busy && !state.taking_over_busy {
                     info!("Call Manager is busy with another call");
                     false
                 } else {
                     *busy = true;
+                    state.taking_over_busy = false;
                     true
                 }
             }
@@ -1112,50 +1777,169 @@ impl Client {
             "group_call::Client(outer)::join(client_id: {})",
             self.client_id
         );
+        self.join_or_resume(None);
+    }
+
+    // Like join(), but rejoins with the endpoint and media keys from a snapshot
+    // passed to Observer::handle_resumption_snapshot before a restart, so that
+    // other devices don't see us leave and join again.  The snapshot is decrypted
+    // with the key from Client::set_resumption_key, which must be set first.
+    // If the snapshot can't be used or the SFU no longer holds the endpoint,
+    // this joins as a new device.
+    pub fn resume(&self, snapshot: Vec<u8>) {
+        debug!(
+            "group_call::Client(outer)::resume(client_id: {})",
+            self.client_id
+        );
+        self.join_or_resume(Some(snapshot));
+    }
+
+    fn join_or_resume(&self, snapshot: Option<Vec<u8>>) {
         let callback = self.clone();
         self.actor.send(move |state| {
             debug!(
-                "group_call::Client(inner)::join(client_id: {})",
-                state.client_id
+                "group_call::Client(inner)::join(client_id: {}, resuming: {})",
+                state.client_id,
+                snapshot.is_some()
             );
-            match state.join_state {
-                JoinState::Joined(_, _) => {
-                    warn!("Can't join when already joined.");
-                }
-                JoinState::Joining => {
-                    warn!("Can't join when already joining.");
-                }
-                JoinState::NotJoined(ring_id) => {
-                    if let Some(PeekInfo{device_count, max_devices: Some(max_devices), ..}) = &state.last_peek_info {
-                        if device_count >= max_devices {
-                            info!("Ending group call client because there are {}/{} devices in the call.", device_count, max_devices);
-                            Self::end(state, EndReason::HasMaxDevices);
+            Self::join_inner(state, callback, snapshot);
+        });
+    }
+
+    // Pulled into a named private method because it's also called once a slot opens up.
+    fn join_inner(state: &mut State, callback: Client, snapshot: Option<Vec<u8>>) {
+        match state.join_state {
+            JoinState::Joined(_, _) => {
+                warn!("Can't join when already joined.");
+            }
+            JoinState::Joining => {
+                warn!("Can't join when already joining.");
+            }
+            JoinState::Pending(_, _) => {
+                warn!("Can't join when already waiting for approval.");
+            }
+            JoinState::NotJoined(ring_id) | JoinState::WaitingForSlot(ring_id) => {
+                if let Some(PeekInfo{device_count, max_devices: Some(max_devices), ..}) = &state.last_peek_info {
+                    if device_count >= max_devices {
+                        if state.wait_for_slot_when_full {
+                            info!("Waiting to join because there are {}/{} devices in the call.", device_count, max_devices);
+                            state.waiting_for_slot = Some((callback, snapshot));
+                            if state.join_state != JoinState::WaitingForSlot(ring_id) {
+                                Self::set_join_state_and_notify_observer(state, JoinState::WaitingForSlot(ring_id));
+                            }
                             return;
                         }
+                        info!("Ending group call client because there are {}/{} devices in the call.", device_count, max_devices);
+                        Self::end(state, EndReason::HasMaxDevices);
+                        return;
                     }
-                    if Self::take_busy(state) {
-                        Self::set_join_state_and_notify_observer(state, JoinState::Joining);
-                        Self::accept_ring_if_needed(state, ring_id);
-
-                        // Request group membership refresh before joining.
-                        // The Join request will then proceed once SfuClient has the token.
-                        state.observer.request_membership_proof(state.client_id);
-                        state.next_membership_proof_request_time = Some(Instant::now() + MEMBERSHIP_PROOF_REQUEST_INTERVAL);
-
-                        state.sfu_client.join(
-                            &state.local_ice_ufrag,
-                            &state.local_ice_pwd,
-                            &state.local_dtls_fingerprint,
-                            callback,
-                        );
-                    } else {
-                        Self::end(state, EndReason::CallManagerIsBusy);
-                    }
+                }
+                state.waiting_for_slot = None;
+                if Self::take_busy(state) {
+                    Self::set_join_state_and_notify_observer(state, JoinState::Joining);
+                    Self::accept_ring_if_needed(state, ring_id);
+
+                    // Request group membership refresh before joining.
+                    // The Join request will then proceed once SfuClient has the token.
+                    state.observer.request_membership_proof(state.client_id);
+                    state.next_membership_proof_request_time = Some(Instant::now() + MEMBERSHIP_PROOF_REQUEST_INTERVAL);
+
+                    state.resuming_from = snapshot
+                        .and_then(|snapshot| Self::decrypt_resumption_snapshot(state, &snapshot));
+                    state.sfu_client.join(
+                        &state.local_ice_ufrag,
+                        &state.local_ice_pwd,
+                        &state.local_dtls_fingerprint,
+                        state.resuming_from.as_ref().map(|snapshot| snapshot.long_device_id.as_str()),
+                        callback,
+                    );
+                } else {
+                    Self::end(state, EndReason::CallManagerIsBusy);
+                }
+            }
+        }
+    }
+
+    // When set, join() on a full call waits in JoinState::WaitingForSlot and joins as soon as
+    // peeking shows a device has left, instead of ending with EndReason::HasMaxDevices.
+    // Unsetting it while waiting ends the client that way.
+    pub fn set_wait_for_slot_when_full(&self, wait: bool) {
+        debug!(
+            "group_call::Client(outer)::set_wait_for_slot_when_full(client_id: {}, wait: {})",
+            self.client_id, wait
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::set_wait_for_slot_when_full(client_id: {}, wait: {})",
+                state.client_id, wait
+            );
+            state.wait_for_slot_when_full = wait;
+            if !wait {
+                if let JoinState::WaitingForSlot(_) = state.join_state {
+                    info!("Ending group call client because it stopped waiting for a slot.");
+                    Self::end(state, EndReason::HasMaxDevices);
                 }
             }
         });
     }
 
+    // Lets the next join() go ahead even if the CallManager is busy, for when
+    // the busy state belongs to a direct call that this client is replacing.
+    // Once joined, the client holds the busy state as if it had taken it.
+    pub fn take_over_busy(&self) {
+        debug!(
+            "group_call::Client(outer)::take_over_busy(client_id: {})",
+            self.client_id
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::take_over_busy(client_id: {})",
+                state.client_id
+            );
+            state.taking_over_busy = true;
+        });
+    }
+
+    fn join_if_slot_opened(state: &mut State) {
+        if let JoinState::WaitingForSlot(_) = state.join_state {
+            let is_full = state
+                .last_peek_info
+                .as_ref()
+                .map(PeekInfo::is_full)
+                .unwrap_or(true);
+            if !is_full {
+                if let Some((callback, snapshot)) = state.waiting_for_slot.take() {
+                    info!("A slot opened up in the call, so joining.");
+                    Self::join_inner(state, callback, snapshot);
+                }
+            }
+        }
+    }
+
+    fn decrypt_resumption_snapshot(state: &State, snapshot: &[u8]) -> Option<resumption::Snapshot> {
+        let key = match &state.resumption_key {
+            Some(key) => key,
+            None => {
+                warn!("Can't resume without a resumption key, so joining as a new device.");
+                return None;
+            }
+        };
+        match resumption::Snapshot::decrypt(key, snapshot) {
+            Ok(snapshot) if snapshot.group_id == state.group_id => {
+                info!("Asking the SFU for our previous endpoint: {:?}", snapshot);
+                Some(snapshot)
+            }
+            Ok(_) => {
+                warn!("Can't resume from a snapshot of another call, so joining as a new device.");
+                None
+            }
+            Err(err) => {
+                warn!("Can't resume from the snapshot ({}), so joining as a new device.", err);
+                None
+            }
+        }
+    }
+
     fn accept_ring_if_needed(state: &mut State, ring_id: Option<RingId>) {
         if let Some(ring_id) = ring_id {
             if let Some(self_uuid) = state.self_uuid.lock().expect("can read UUID").clone() {
@@ -1215,16 +1999,31 @@ impl Client {
             JoinState::NotJoined(_) => {
                 warn!("Can't leave when not joined.");
             }
-            JoinState::Joining | JoinState::Joined(_, _) => {
+            JoinState::WaitingForSlot(_) => {
+                // Nothing was sent to the SFU yet.
+                state.waiting_for_slot = None;
+                Self::set_join_state_and_notify_observer(state, JoinState::NotJoined(None));
+            }
+            JoinState::Joining | JoinState::Pending(_, _) | JoinState::Joined(_, _) => {
                 state.peer_connection.set_outgoing_media_enabled(false);
                 state.peer_connection.set_incoming_media_enabled(false);
                 Self::release_busy(state);
 
-                if let JoinState::Joined(local_demux_id, long_device_id) = state.join_state.clone()
-                {
-                    state.sfu_client.leave(long_device_id);
-                    Self::send_leaving_through_sfu_and_over_signaling(state, local_demux_id);
+                match state.join_state.clone() {
+                    JoinState::Joined(local_demux_id, long_device_id) => {
+                        state.sfu_client.leave(long_device_id);
+                        Self::send_leaving_through_sfu_and_over_signaling(state, local_demux_id);
+                    }
+                    JoinState::Pending(_, long_device_id) => {
+                        // Leave the lobby; no one else knows we're here.
+                        state.sfu_client.leave(long_device_id);
+                    }
+                    _ => {}
                 }
+                state.pending_devices.clear();
+                state.pending_since = None;
+                state.resuming_from = None;
+                state.resumed_remote_devices.clear();
                 Self::set_join_state_and_notify_observer(state, JoinState::NotJoined(None));
                 state.next_stats_time = None;
                 state.next_membership_proof_request_time = None;
@@ -1246,6 +2045,9 @@ impl Client {
         });
     }
 
+    /// Rings the whole group, or just one member of it, such as to ring again a member
+    /// who hasn't responded.  Members who accepted or declined aren't rung again on
+    /// their own.
     pub fn ring(&self, recipient: Option<UserId>) {
         debug!(
             "group_call::Client(outer)::ring(client_id: {}, recipient: {:?})",
@@ -1275,14 +2077,50 @@ impl Client {
                 ..Default::default()
             };
 
-            if let Some(_recipient) = recipient {
-                unimplemented!("cannot ring just one person yet");
+            let now = Instant::now();
+            if let Some(recipient) = recipient {
+                let status = state
+                    .outgoing_rings
+                    .iter()
+                    .find(|(ring, _)| ring.user_id == recipient)
+                    .map(|(ring, _)| ring.status);
+                if let Some(RingStatus::Accepted) | Some(RingStatus::Declined) = status {
+                    warn!("Not ringing a recipient again after {:?}", status);
+                    return;
+                }
+                state.observer.send_signaling_message(
+                    recipient.clone(),
+                    message,
+                    SignalingMessageUrgency::HandleImmediately,
+                );
+                Self::set_outgoing_ring(state, recipient, ring_id, now);
             } else {
                 state.observer.send_signaling_message_to_group(
                     state.group_id.clone(),
                     message,
                     SignalingMessageUrgency::HandleImmediately,
                 );
+                state.latest_group_ring = Some(ring_id);
+
+                // Those already in the call don't need to be rung.
+                let self_uuid = state.self_uuid.lock().expect("can read UUID").clone();
+                let joined_user_ids: HashSet<&UserId> = state
+                    .remote_devices
+                    .iter()
+                    .map(|device| &device.user_id)
+                    .collect();
+                let mut recipients: Vec<UserId> = state
+                    .known_members
+                    .iter()
+                    .filter(|user_id| {
+                        Some(*user_id) != self_uuid.as_ref() && !joined_user_ids.contains(user_id)
+                    })
+                    .cloned()
+                    .collect();
+                recipients.sort();
+                for recipient in recipients {
+                    Self::set_outgoing_ring(state, recipient, ring_id, now);
+                }
 
                 // If you're the only one in the call at the time of the ring,
                 // and then you leave before anyone joins, the ring is auto-cancelled.
@@ -1293,20 +2131,122 @@ impl Client {
                     state.cancellable_initial_ring = Some(ring_id)
                 }
             }
+            Self::notify_ring_statuses_changed(state);
         });
     }
 
-    pub fn set_outgoing_audio_muted(&self, muted: bool) {
+    fn set_outgoing_ring(state: &mut State, user_id: UserId, ring_id: RingId, now: Instant) {
+        let ring = RecipientRingStatus {
+            user_id,
+            ring_id,
+            status: RingStatus::Ringing,
+        };
+        match state
+            .outgoing_rings
+            .iter_mut()
+            .find(|(existing, _)| existing.user_id == ring.user_id)
+        {
+            Some(existing) => *existing = (ring, now),
+            None => state.outgoing_rings.push((ring, now)),
+        }
+    }
+
+    /// Called when a recipient of one of our rings responds.
+    pub fn on_ring_response_received(&self, sender: UserId, ring_id: RingId, status: RingStatus) {
         debug!(
-            "group_call::Client(outer)::set_audio_muted(client_id: {}, muted: {})",
-            self.client_id, muted
+            "group_call::Client(outer)::on_ring_response_received(client_id: {}, ring_id: {}, status: {:?})",
+            self.client_id, ring_id, status
         );
         self.actor.send(move |state| {
             debug!(
-                "group_call::Client(inner)::set_audio_muted(client_id: {}, muted: {})",
-                state.client_id, muted
+                "group_call::Client(inner)::on_ring_response_received(client_id: {}, ring_id: {}, status: {:?})",
+                state.client_id, ring_id, status
             );
-            // We don't modify the outgoing audio track.  We expect the app to handle that.
+
+            match state
+                .outgoing_rings
+                .iter_mut()
+                .find(|(ring, _)| ring.user_id == sender)
+            {
+                Some((ring, _)) if ring.ring_id == ring_id => {
+                    if ring.status == status || ring.status == RingStatus::Accepted {
+                        return;
+                    }
+                    ring.status = status;
+                }
+                Some(_) => {
+                    info!("Ignoring a response to a ring that was replaced: ring_id: {}", ring_id);
+                    return;
+                }
+                None if state.latest_group_ring == Some(ring_id) => {
+                    state.outgoing_rings.push((
+                        RecipientRingStatus {
+                            user_id: sender,
+                            ring_id,
+                            status,
+                        },
+                        Instant::now(),
+                    ));
+                }
+                None => {
+                    warn!("Ignoring a response to an unknown ring: ring_id: {}", ring_id);
+                    return;
+                }
+            }
+            Self::notify_ring_statuses_changed(state);
+        });
+    }
+
+    fn accept_outgoing_rings_of_joined_members(state: &mut State, joined_user_ids: &HashSet<UserId>) {
+        let mut changed = false;
+        for (ring, _) in &mut state.outgoing_rings {
+            if ring.status != RingStatus::Accepted && joined_user_ids.contains(&ring.user_id) {
+                ring.status = RingStatus::Accepted;
+                changed = true;
+            }
+        }
+        if changed {
+            Self::notify_ring_statuses_changed(state);
+        }
+    }
+
+    fn expire_outgoing_rings(state: &mut State, now: Instant) {
+        let mut changed = false;
+        for (ring, rung_at) in &mut state.outgoing_rings {
+            if ring.status == RingStatus::Ringing
+                && now.saturating_duration_since(*rung_at) >= OUTGOING_RING_TIMEOUT
+            {
+                ring.status = RingStatus::Expired;
+                changed = true;
+            }
+        }
+        if changed {
+            Self::notify_ring_statuses_changed(state);
+        }
+    }
+
+    fn notify_ring_statuses_changed(state: &mut State) {
+        let statuses = state
+            .outgoing_rings
+            .iter()
+            .map(|(ring, _)| ring.clone())
+            .collect();
+        state
+            .observer
+            .handle_ring_statuses_changed(state.client_id, statuses);
+    }
+
+    pub fn set_outgoing_audio_muted(&self, muted: bool) {
+        debug!(
+            "group_call::Client(outer)::set_audio_muted(client_id: {}, muted: {})",
+            self.client_id, muted
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::set_audio_muted(client_id: {}, muted: {})",
+                state.client_id, muted
+            );
+            // We don't modify the outgoing audio track.  We expect the app to handle that.
             state.outgoing_heartbeat_state.audio_muted = Some(muted);
             if let Err(err) = Self::send_heartbeat(state) {
                 warn!(
@@ -1380,6 +2320,454 @@ impl Client {
         });
     }
 
+    pub fn set_hand_raised(&self, raised: bool) {
+        debug!(
+            "group_call::Client(outer)::set_hand_raised(client_id: {}, raised: {})",
+            self.client_id, raised
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::set_hand_raised(client_id: {}, raised: {})",
+                state.client_id, raised
+            );
+            state.outgoing_hand_raised = raised;
+            if let Err(err) = Self::send_heartbeat(state) {
+                warn!(
+                    "Failed to send heartbeat after updating raised hand state: {:?}",
+                    err
+                );
+            }
+        });
+    }
+
+    pub fn send_reaction(&self, value: String) {
+        debug!(
+            "group_call::Client(outer)::send_reaction(client_id: {}, len: {})",
+            self.client_id,
+            value.len()
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::send_reaction(client_id: {}, len: {})",
+                state.client_id,
+                value.len()
+            );
+            if value.is_empty() || value.len() > MAX_REACTION_VALUE_LEN {
+                warn!("Not sending reaction of invalid length {}", value.len());
+                return;
+            }
+            if let Err(err) = Self::send_reaction_through_sfu(state, value) {
+                warn!("Failed to send reaction: {:?}", err);
+            }
+        });
+    }
+
+    pub fn send_message(&self, text: String) {
+        debug!(
+            "group_call::Client(outer)::send_message(client_id: {}, len: {})",
+            self.client_id,
+            text.len()
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::send_message(client_id: {}, len: {})",
+                state.client_id,
+                text.len()
+            );
+            if !matches!(state.join_state, JoinState::Joined(..)) {
+                warn!("Not sending chat message because we haven't joined");
+                return;
+            }
+            match state.chat_sender.send(text.as_bytes(), Instant::now()) {
+                Ok(chunks) => Self::send_chat_chunks(state, chunks),
+                Err(err) => warn!("Failed to send chat message: {:?}", err),
+            }
+        });
+    }
+
+    // None (the default) stops audio levels from being passed to the observer.
+    pub fn set_audio_levels_config(&self, config: Option<AudioLevelsConfig>) {
+        debug!(
+            "group_call::Client(outer)::set_audio_levels_config(client_id: {}, config: {:?})",
+            self.client_id, config
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::set_audio_levels_config(client_id: {}, config: {:?})",
+                state.client_id, config
+            );
+            match config {
+                Some(AudioLevelsConfig { smoothing, .. }) if !(0.0..1.0).contains(&smoothing) => {
+                    warn!("Ignoring audio levels config with invalid smoothing {}", smoothing);
+                }
+                Some(_) => {
+                    state.audio_levels_config = config;
+                    if !state.audio_levels_polling {
+                        state.audio_levels_polling = true;
+                        Self::poll_audio_levels(state);
+                    }
+                }
+                None => {
+                    // Polling stops the next time it's due.
+                    state.audio_levels_config = None;
+                    state.captured_audio_level = 0.0;
+                    state.received_audio_levels.clear();
+                }
+            }
+        });
+    }
+
+    fn poll_audio_levels(state: &mut State) {
+        let config = match state.audio_levels_config {
+            Some(config) => config,
+            None => {
+                state.audio_levels_polling = false;
+                return;
+            }
+        };
+        if let JoinState::Joined(..) = state.join_state {
+            if let Err(err) = state
+                .peer_connection
+                .get_stats(&state.audio_levels_stats_observer)
+            {
+                warn!("Failed to get statistics for audio levels: {:?}", err);
+            }
+        }
+        state.actor.send_delayed(
+            std::cmp::max(config.interval, MIN_AUDIO_LEVELS_INTERVAL),
+            Self::poll_audio_levels,
+        );
+    }
+
+    // Applies to rotations started after this.
+    pub fn set_key_rotation_policy(&self, policy: KeyRotationPolicy) {
+        debug!(
+            "group_call::Client(outer)::set_key_rotation_policy(client_id: {}, policy: {:?})",
+            self.client_id, policy
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::set_key_rotation_policy(client_id: {}, policy: {:?})",
+                state.client_id, policy
+            );
+            state.key_rotation_policy = policy;
+        });
+    }
+
+    // Replaces the media send key with a new random one, as if someone had left.
+    pub fn rotate_media_send_key(&self) {
+        debug!(
+            "group_call::Client(outer)::rotate_media_send_key(client_id: {})",
+            self.client_id
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::rotate_media_send_key(client_id: {})",
+                state.client_id
+            );
+            Self::rotate_media_send_key_and_send_to_users_not_removed(
+                state,
+                KeyRotationReason::Requested,
+            );
+        });
+    }
+
+    pub fn key_rotation_metrics(&self) -> Result<KeyRotationMetrics> {
+        let metrics = self.key_rotation_metrics.lock()?;
+        Ok(*metrics)
+    }
+
+    // None (the default) stops resumption snapshots from being passed to the observer.
+    pub fn set_resumption_key(&self, key: Option<resumption::Key>) {
+        debug!(
+            "group_call::Client(outer)::set_resumption_key(client_id: {}, enabled: {})",
+            self.client_id,
+            key.is_some()
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::set_resumption_key(client_id: {}, enabled: {})",
+                state.client_id,
+                key.is_some()
+            );
+            state.resumption_key = key;
+            Self::update_resumption_snapshot(state);
+        });
+    }
+
+    // Passes a new snapshot to the observer if it wants them and we're joined.
+    fn update_resumption_snapshot(state: &State) {
+        let key = match &state.resumption_key {
+            Some(key) => key,
+            None => {
+                return;
+            }
+        };
+        let (demux_id, long_device_id) = match &state.join_state {
+            JoinState::Joined(demux_id, long_device_id) => (*demux_id, long_device_id.clone()),
+            _ => {
+                return;
+            }
+        };
+        let (send_ratchet_counter, send_secret, receive_secrets) = {
+            let frame_crypto_context = state
+                .frame_crypto_context
+                .lock()
+                .expect("Get lock for frame encryption context to take a resumption snapshot");
+            let (send_ratchet_counter, send_secret) = frame_crypto_context.send_state();
+            (
+                send_ratchet_counter,
+                send_secret,
+                frame_crypto_context.receive_secrets(),
+            )
+        };
+        let remote_devices = state
+            .remote_devices
+            .iter()
+            .map(|device| resumption::RemoteDevice {
+                user_id:            device.user_id.clone(),
+                demux_id:           device.demux_id,
+                media_key:          receive_secrets
+                    .iter()
+                    .find(|(sender_id, _, _)| *sender_id == device.demux_id)
+                    .map(|(_, ratchet_counter, secret)| (*ratchet_counter, *secret)),
+                control_public_key: state
+                    .control_public_keys
+                    .get(&(device.demux_id, device.user_id.clone()))
+                    .map(|key| key.to_bytes()),
+            })
+            .collect();
+        let snapshot = resumption::Snapshot {
+            group_id: state.group_id.clone(),
+            demux_id,
+            long_device_id,
+            send_ratchet_counter,
+            send_secret,
+            remote_devices,
+            control_secret: state.control_secret.to_bytes(),
+        };
+        state.observer.handle_resumption_snapshot(
+            state.client_id,
+            snapshot.encrypt(key, &mut rand::rngs::OsRng),
+        );
+    }
+
+    fn restore_from_resumption_snapshot(state: &mut State, snapshot: resumption::Snapshot) {
+        info!(
+            "Resuming with our previous endpoint and media keys. client_id: {}",
+            state.client_id
+        );
+        {
+            let mut frame_crypto_context = state
+                .frame_crypto_context
+                .lock()
+                .expect("Get lock for frame encryption context to restore media send key");
+            frame_crypto_context
+                .restore_send_state(snapshot.send_ratchet_counter, snapshot.send_secret);
+            state.media_send_key_applied_frame_counter = frame_crypto_context.next_frame_counter();
+        }
+        // The remote devices still have our control public key.
+        state.control_secret = StaticSecret::from(snapshot.control_secret);
+        // These are added once the remote devices are known again.
+        for device in &snapshot.remote_devices {
+            if let Some(control_public_key) = device.control_public_key {
+                state.control_public_keys.insert(
+                    (device.demux_id, device.user_id.clone()),
+                    PublicKey::from(control_public_key),
+                );
+            }
+            if let Some((ratchet_counter, secret)) = device.media_key {
+                state.pending_media_receive_keys.push((
+                    device.user_id.clone(),
+                    device.demux_id,
+                    ratchet_counter,
+                    secret,
+                ));
+            }
+        }
+        state.resumed_remote_devices = snapshot.remote_devices;
+    }
+
+    fn handle_audio_levels(state: &mut State, audio_levels: AudioLevels) {
+        let smoothing = match (&state.audio_levels_config, &state.join_state) {
+            (Some(config), JoinState::Joined(..)) => config.smoothing,
+            _ => {
+                return;
+            }
+        };
+        let smooth = |previous: f64, latest: f64| smoothing * previous + (1.0 - smoothing) * latest;
+
+        state.captured_audio_level = smooth(state.captured_audio_level, audio_levels.sent);
+
+        // The audio SSRC of each remote device is the same as its demux ID.
+        let latest_received_levels: HashMap<DemuxId, f64> =
+            audio_levels.received.into_iter().collect();
+        let mut received_audio_levels = HashMap::new();
+        let mut received_levels = Vec::new();
+        for remote_device in state.remote_devices.iter() {
+            let demux_id = remote_device.demux_id;
+            let level = smooth(
+                state.received_audio_levels.get(&demux_id).copied().unwrap_or(0.0),
+                latest_received_levels.get(&demux_id).copied().unwrap_or(0.0),
+            );
+            received_audio_levels.insert(demux_id, level);
+            received_levels.push(ReceivedAudioLevel {
+                demux_id,
+                level: audio_level_from_fraction(level),
+            });
+        }
+        state.received_audio_levels = received_audio_levels;
+
+        state.observer.handle_audio_levels(
+            state.client_id,
+            audio_level_from_fraction(state.captured_audio_level),
+            received_levels,
+        );
+    }
+
+    pub fn send_control_request(&self, target_demux_id: DemuxId, request: ControlRequest) {
+        debug!(
+            "group_call::Client(outer)::send_control_request(client_id: {}, target_demux_id: {}, request: {:?})",
+            self.client_id, target_demux_id, request
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::send_control_request(client_id: {}, target_demux_id: {}, request: {:?})",
+                state.client_id, target_demux_id, request
+            );
+            let local_demux_id = match state.join_state {
+                JoinState::Joined(local_demux_id, _) => local_demux_id,
+                _ => {
+                    warn!("Not sending control request because we haven't joined");
+                    return;
+                }
+            };
+            let (creator, era_id) = match &state.last_peek_info {
+                Some(PeekInfo {
+                    creator: Some(creator),
+                    era_id,
+                    ..
+                }) => (creator.clone(), era_id.clone()),
+                _ => {
+                    warn!("Not sending control request because the call creator is unknown");
+                    return;
+                }
+            };
+            if state.self_uuid.lock().expect("can read UUID").as_ref() != Some(&creator) {
+                warn!("Not sending control request because we didn't create the call");
+                return;
+            }
+            let (long_device_id, target_public_key) =
+                match state.remote_devices.find_by_demux_id(target_demux_id) {
+                    Some(remote_device) => (
+                        remote_device.long_device_id.clone(),
+                        state
+                            .control_public_keys
+                            .get(&(target_demux_id, remote_device.user_id.clone()))
+                            .copied(),
+                    ),
+                    None => {
+                        warn!(
+                            "Not sending control request to unknown demux_id {}",
+                            target_demux_id
+                        );
+                        return;
+                    }
+                };
+
+            if let Some(target_public_key) = target_public_key {
+                let mut control_request = protobuf::group_call::device_to_device::ControlRequest {
+                    r#type:          Some(
+                        protobuf::group_call::device_to_device::control_request::Type::from(request).into(),
+                    ),
+                    target_demux_id: Some(target_demux_id),
+                    era_id,
+                    mac:             None,
+                };
+                control_request.mac = Some(
+                    compute_control_request_mac(
+                        &state.control_secret,
+                        &target_public_key,
+                        local_demux_id,
+                        &control_request,
+                    )
+                    .to_vec(),
+                );
+                let result = encode_proto(protobuf::group_call::DeviceToDevice {
+                    control_request: Some(control_request),
+                    ..Default::default()
+                })
+                .and_then(|request_msg| {
+                    Self::broadcast_data_through_sfu(state, "control request", &request_msg)
+                });
+                if let Err(err) = result {
+                    warn!("Failed to send control request: {:?}", err);
+                }
+            } else {
+                warn!(
+                    "Not sending control request to demux_id {} because we don't have its control key",
+                    target_demux_id
+                );
+            }
+
+            // The device might not honor the request, so we also ask the SFU to remove it.
+            if request == ControlRequest::Remove {
+                state.sfu_client.remove_device(long_device_id);
+            }
+        });
+    }
+
+    // Lets a device waiting in the lobby into the call.
+    pub fn approve_pending_device(&self, demux_id: DemuxId) {
+        debug!(
+            "group_call::Client(outer)::approve_pending_device(client_id: {}, demux_id: {})",
+            self.client_id, demux_id
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::approve_pending_device(client_id: {}, demux_id: {})",
+                state.client_id, demux_id
+            );
+            if let Some(long_device_id) = Self::find_pending_device(state, demux_id) {
+                state.sfu_client.approve_device(long_device_id);
+            }
+        });
+    }
+
+    // Turns away a device waiting in the lobby.
+    pub fn deny_pending_device(&self, demux_id: DemuxId) {
+        debug!(
+            "group_call::Client(outer)::deny_pending_device(client_id: {}, demux_id: {})",
+            self.client_id, demux_id
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::deny_pending_device(client_id: {}, demux_id: {})",
+                state.client_id, demux_id
+            );
+            if let Some(long_device_id) = Self::find_pending_device(state, demux_id) {
+                state.sfu_client.deny_device(long_device_id);
+            }
+        });
+    }
+
+    // Returns the long device ID the SFU needs to approve or deny a pending device.
+    fn find_pending_device(state: &State, demux_id: DemuxId) -> Option<String> {
+        if !matches!(state.join_state, JoinState::Joined(..)) {
+            warn!("Can't approve or deny pending devices when not joined.");
+            return None;
+        }
+        let long_device_id = state
+            .pending_devices
+            .iter()
+            .find(|device| device.demux_id == demux_id)
+            .map(|device| device.long_device_id.clone());
+        if long_device_id.is_none() {
+            warn!("Unknown pending device with demux_id {}", demux_id);
+        }
+        long_device_id
+    }
+
     pub fn resend_media_keys(&self) {
         debug!(
             "group_call::Client(outer)::resend_media_keys(client_id: {})",
@@ -1485,9 +2873,124 @@ impl Client {
                 Self::send_video_requests_to_sfu(state);
                 state.on_demand_video_request_sent_since_last_tick = true;
             }
+            Self::update_pinned_not_forwarded(state);
         });
     }
 
+    // None stops updating the layout, leaving the last video requests in place.
+    pub fn set_layout_config(&self, config: Option<LayoutConfig>) {
+        debug!(
+            "group_call::Client(outer)::set_layout_config(client_id: {}, config: {:?})",
+            self.client_id, config,
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::set_layout_config(client_id: {})",
+                state.client_id
+            );
+            if config.is_none() {
+                state.layout = None;
+            }
+            state.layout_config = config;
+            Self::update_layout(state);
+        });
+    }
+
+    fn update_layout(state: &mut State) {
+        let config = match &state.layout_config {
+            Some(config) => config,
+            None => {
+                return;
+            }
+        };
+        let mut config = config.clone();
+        config.pinned_demux_ids = state
+            .pinned
+            .iter()
+            .map(|(demux_id, _)| *demux_id)
+            .chain(config.pinned_demux_ids)
+            .collect();
+        let previous_demux_ids = state
+            .layout
+            .as_ref()
+            .map(|layout| &layout.demux_ids[..])
+            .unwrap_or(&[]);
+        let layout = compute_layout(&config, &state.remote_devices, previous_demux_ids);
+        if state.layout.as_ref() == Some(&layout) {
+            return;
+        }
+
+        state.video_requests = Some(layout.video_requests.clone());
+        if !state.on_demand_video_request_sent_since_last_tick {
+            Self::send_video_requests_to_sfu(state);
+            state.on_demand_video_request_sent_since_last_tick = true;
+        }
+        state.observer.handle_layout_changed(state.client_id, layout.clone());
+        state.layout = Some(layout);
+        Self::update_pinned_not_forwarded(state);
+    }
+
+    // A device that leaves is unpinned.  Passing None unpins it too.
+    pub fn set_pinned(&self, demux_id: DemuxId, pin: Option<PinKind>) {
+        debug!(
+            "group_call::Client(outer)::set_pinned(client_id: {}, demux_id: {}, pin: {:?})",
+            self.client_id, demux_id, pin,
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::set_pinned(client_id: {}, demux_id: {}, pin: {:?})",
+                state.client_id, demux_id, pin,
+            );
+            state.pinned.retain(|(pinned_demux_id, pinned_kind)| {
+                *pinned_demux_id != demux_id
+                    && !(pin == Some(PinKind::Spotlight) && *pinned_kind == PinKind::Spotlight)
+            });
+            match pin {
+                Some(PinKind::Spotlight) => state.pinned.insert(0, (demux_id, PinKind::Spotlight)),
+                Some(PinKind::Pinned) => state.pinned.push((demux_id, PinKind::Pinned)),
+                None => {}
+            }
+
+            Self::update_layout(state);
+            if !state.on_demand_video_request_sent_since_last_tick {
+                Self::send_video_requests_to_sfu(state);
+                state.on_demand_video_request_sent_since_last_tick = true;
+            }
+            Self::update_pinned_not_forwarded(state);
+        });
+    }
+
+    fn update_pinned_not_forwarded(state: &mut State) {
+        let video_requests = state.video_requests.as_deref().unwrap_or(&[]);
+        let pinned_not_forwarded: Vec<DemuxId> = state
+            .pinned
+            .iter()
+            .map(|(demux_id, _)| *demux_id)
+            .filter(|demux_id| {
+                let requested = video_requests
+                    .iter()
+                    .any(|request| request.demux_id == *demux_id && request.height > 0);
+                // Unknown until the SFU says what it is forwarding.
+                let not_forwarded = state
+                    .remote_devices
+                    .find_by_demux_id(*demux_id)
+                    .and_then(|device| device.forwarding_video)
+                    == Some(false);
+                requested && not_forwarded
+            })
+            .collect();
+        if pinned_not_forwarded != state.pinned_not_forwarded {
+            info!(
+                "Pinned videos not forwarded changed to {:?}. client_id: {}",
+                pinned_not_forwarded, state.client_id
+            );
+            state.pinned_not_forwarded = pinned_not_forwarded.clone();
+            state
+                .observer
+                .handle_pinned_videos_not_forwarded(state.client_id, pinned_not_forwarded);
+        }
+    }
+
     fn send_video_requests_to_sfu(state: &mut State) {
         use protobuf::group_call::{
             device_to_sfu::{
@@ -1514,6 +3017,11 @@ impl Client {
                                 // wire in landscape format with rotation metadata.
                                 // If it's not, we'll have a problem.
                                 height:          Some(min(request.height, request.width) as u32),
+                                priority:        state
+                                    .pinned
+                                    .iter()
+                                    .find(|(demux_id, _)| *demux_id == request.demux_id)
+                                    .map(|(_, pin)| pin.video_priority()),
                             }
                         })
                 })
@@ -1605,7 +3113,10 @@ impl Client {
         );
 
         let joining_or_joined = match state.join_state {
-            JoinState::Joined(_, _) | JoinState::Joining => true,
+            JoinState::Joined(_, _)
+            | JoinState::Pending(_, _)
+            | JoinState::Joining
+            | JoinState::WaitingForSlot(_) => true,
             JoinState::NotJoined(_) => false,
         };
         if joining_or_joined {
@@ -1631,7 +3142,10 @@ impl Client {
     }
 
     // This should be called by the SfuClient after it has joined.
-    pub fn on_sfu_client_joined(&self, result: Result<(SfuInfo, DemuxId, String)>) {
+    pub fn on_sfu_client_joined(
+        &self,
+        result: Result<(SfuInfo, DemuxId, String, ClientStatus)>,
+    ) {
         debug!(
             "group_call::Client(outer)::on_sfu_client_joined(client_id: {})",
             self.client_id
@@ -1642,7 +3156,8 @@ impl Client {
                 state.client_id
             );
 
-            if let Ok((sfu_info, local_demux_id, long_device_id)) = result {
+            let resuming_from = state.resuming_from.take();
+            if let Ok((sfu_info, local_demux_id, long_device_id, client_status)) = result {
                 match state.connection_state {
                     ConnectionState::NotConnected => {
                         warn!("The SFU completed joining before connect() was requested.");
@@ -1668,10 +3183,27 @@ impl Client {
                     }
                 };
                 match state.join_state {
-                    JoinState::NotJoined(_) => {
+                    JoinState::NotJoined(_) | JoinState::WaitingForSlot(_) => {
                         warn!("The SFU completed joining before join() was requested.");
                     }
+                    JoinState::Joining if client_status == ClientStatus::Pending => {
+                        info!("The SFU put us in the lobby until a joined device approves us.");
+                        state.pending_since = Some(Instant::now());
+                        Self::set_join_state_and_notify_observer(
+                            state,
+                            JoinState::Pending(local_demux_id, long_device_id),
+                        );
+                        // Find out as soon as possible whether we're already approved.
+                        Self::request_remote_devices_as_soon_as_possible(state);
+                    }
                     JoinState::Joining => {
+                        if let Some(snapshot) = resuming_from {
+                            if snapshot.demux_id == local_demux_id && snapshot.long_device_id == long_device_id {
+                                Self::restore_from_resumption_snapshot(state, snapshot);
+                            } else {
+                                info!("The SFU gave us a new endpoint, so we're joining as a new device.");
+                            }
+                        }
                         // The call to set_peek_info_inner needs the join state to be joined.
                         // But make sure to fire observer.handle_join_state_changed after
                         // set_peek_info_inner so that state.remote_devices are filled in.
@@ -1691,8 +3223,9 @@ impl Client {
                         Self::request_remote_devices_as_soon_as_possible(state);
                         state.next_stats_time =
                             Some(Instant::now() + STATS_INTERVAL);
+                        Self::update_resumption_snapshot(state);
                     }
-                    JoinState::Joined(_, _) => {
+                    JoinState::Pending(_, _) | JoinState::Joined(_, _) => {
                         warn!("The SFU completed joining more than once.");
                     }
                 };
@@ -1723,7 +3256,7 @@ impl Client {
                             demux_id: Some(sender_demux_id),
                             ratchet_counter: Some(ratchet_counter),
                             secret: Some(secret_vec),
-                            ..
+                            control_public_key,
                         }),
                     ..
                 } => {
@@ -1731,6 +3264,19 @@ impl Client {
                         warn!("on_signaling_message_received(): ignoring media receive key with wrong length");
                         return;
                     }
+                    // Signal authenticates the sender's user ID, so the key is theirs.
+                    match control_public_key.map(|key| resumption::ControlKey::try_from(&key[..])) {
+                        Some(Ok(control_public_key)) => {
+                            state.control_public_keys.insert(
+                                (sender_demux_id, sender_user_id.clone()),
+                                PublicKey::from(control_public_key),
+                            );
+                        }
+                        Some(Err(_)) => {
+                            warn!("on_signaling_message_received(): ignoring control public key with wrong length");
+                        }
+                        None => {}
+                    }
                     if let Ok(ratchet_counter) = ratchet_counter.try_into() {
                         let mut secret = frame_crypto::Secret::default();
                         secret.copy_from_slice(&secret_vec);
@@ -1741,6 +3287,7 @@ impl Client {
                             ratchet_counter,
                             secret,
                         );
+                        Self::update_resumption_snapshot(state);
                     } else {
                         warn!("on_signaling_message_received(): ignoring media receive key with ratchet counter that's too big");
                     }
@@ -1849,6 +3396,10 @@ impl Client {
                 ..
             }
         );
+        let requested_at = match state.remote_devices_request_state {
+            RemoteDevicesRequestState::Requested { at, .. } => Some(at),
+            _ => None,
+        };
         state.remote_devices_request_state =
             RemoteDevicesRequestState::Updated { at: Instant::now() };
 
@@ -1880,6 +3431,34 @@ impl Client {
             )
         }
 
+        // Once approved, a device in the lobby shows up with the joined devices.
+        // If a peek requested after it was put in the lobby lists it in neither,
+        // it was turned away.  (Peeks set by the app have no request time.)
+        let mut approved = false;
+        if let JoinState::Pending(local_demux_id, long_device_id) = state.join_state.clone() {
+            if peek_info
+                .devices
+                .iter()
+                .any(|device| device.demux_id == local_demux_id)
+            {
+                info!("A joined device approved us, so we're joining");
+                // Like on_sfu_client_joined, the join state must be joined for the processing below,
+                // but the observer isn't notified until state.remote_devices are filled in.
+                state.join_state = JoinState::Joined(local_demux_id, long_device_id);
+                state.pending_since = None;
+                approved = true;
+            } else if !peek_info
+                .pending_devices
+                .iter()
+                .any(|device| device.demux_id == local_demux_id)
+                && requested_at >= state.pending_since
+            {
+                info!("Ending group call client because a joined device denied us.");
+                Self::end(state, EndReason::DeniedRequestToJoinCall);
+                return;
+            }
+        }
+
         let peek_info_to_remember = peek_info.clone();
         if let JoinState::Joined(local_demux_id, _) = state.join_state {
             // We remember these before changing state.remote_devices so we can calculate changes after.
@@ -1962,9 +3541,16 @@ impl Client {
                 state
                     .observer
                     .handle_remote_devices_changed(state.client_id, &state.remote_devices, RemoteDevicesChangedReason::DemuxIdsChanged);
+                let remote_devices = &state.remote_devices;
+                state
+                    .pinned
+                    .retain(|(demux_id, _)| remote_devices.find_by_demux_id(*demux_id).is_some());
+                Self::update_layout(state);
+                Self::update_pinned_not_forwarded(state);
             }
 
             if new_user_ids != old_user_ids {
+                Self::accept_outgoing_rings_of_joined_members(state, &new_user_ids);
                 let joined_members: Vec<UserId> = new_user_ids.iter().cloned().collect();
                 state.observer.handle_peek_changed(
                     state.client_id,
@@ -1977,12 +3563,19 @@ impl Client {
             }
             // If someone was added, we must advance the send media key
             // and send it to everyone that was added.
+            // Devices we knew before resuming aren't new to us, even if we are new to them.
+            let resumed_remote_devices = std::mem::take(&mut state.resumed_remote_devices);
             let added_demux_ids: HashSet<DemuxId> =
                 new_demux_ids.difference(&old_demux_ids).copied().collect();
             let users_with_added_devices: Vec<UserId> = state
                 .remote_devices
                 .iter()
-                .filter(|device| added_demux_ids.contains(&device.demux_id))
+                .filter(|device| {
+                    added_demux_ids.contains(&device.demux_id)
+                        && !resumed_remote_devices.iter().any(|resumed| {
+                            resumed.demux_id == device.demux_id && resumed.user_id == device.user_id
+                        })
+                })
                 .map(|device| device.user_id.clone())
                 .collect();
             if !users_with_added_devices.is_empty() {
@@ -1997,8 +3590,16 @@ impl Client {
             }
 
             // If someone was removed, we must reset the send media key and send it to everyone not removed.
-            if old_user_ids.difference(&new_user_ids).next().is_some() {
-                Self::rotate_media_send_key_and_send_to_users_not_removed(state);
+            // That includes anyone who left while we were resuming, since they still have the key.
+            let removed_while_resuming = resumed_remote_devices
+                .iter()
+                .any(|resumed| !new_user_ids.contains(&resumed.user_id));
+            if old_user_ids.difference(&new_user_ids).next().is_some() || removed_while_resuming {
+                state.earliest_unprotected_departure.get_or_insert_with(Instant::now);
+                Self::rotate_media_send_key_and_send_to_users_not_removed(
+                    state,
+                    KeyRotationReason::UserRemoved,
+                );
             }
 
             // We can't gate this behind the demux IDs changing because a forged demux ID might
@@ -2030,8 +3631,35 @@ impl Client {
             if !new_demux_ids.is_empty() {
                 state.cancellable_initial_ring = None;
             }
+
+            let pending_devices: Vec<PeekDeviceInfo> = peek_info
+                .pending_devices
+                .into_iter()
+                .filter(|device| device.demux_id != local_demux_id)
+                .collect();
+            let old_pending_demux_ids: Vec<DemuxId> =
+                state.pending_devices.iter().map(|device| device.demux_id).collect();
+            let new_pending_demux_ids: Vec<DemuxId> =
+                pending_devices.iter().map(|device| device.demux_id).collect();
+            state.pending_devices = pending_devices;
+            if old_pending_demux_ids != new_pending_demux_ids {
+                state
+                    .observer
+                    .handle_pending_devices_changed(state.client_id, &state.pending_devices);
+            }
+
+            if approved {
+                state
+                    .observer
+                    .handle_join_state_changed(state.client_id, state.join_state.clone());
+                state.next_stats_time = Some(Instant::now() + STATS_INTERVAL);
+            }
+            if approved || demux_ids_changed {
+                Self::update_resumption_snapshot(state);
+            }
         }
         state.last_peek_info = Some(peek_info_to_remember);
+        Self::join_if_slot_opened(state);
 
         // Do this later so that we can use new_user_ids above without running into
         // referencing issues
@@ -2106,18 +3734,64 @@ impl Client {
         Ok(())
     }
 
-    fn rotate_media_send_key_and_send_to_users_not_removed(state: &mut State) {
+    fn rotate_media_send_key_if_due(state: &mut State, now: Instant) {
+        // There's no one to keep the key from, and no one to send a new one to.
+        if !matches!(state.join_state, JoinState::Joined(..)) || state.remote_devices.is_empty() {
+            return;
+        }
+        let policy = state.key_rotation_policy;
+        if let Some(max_key_age) = policy.max_key_age {
+            if now >= state.media_send_key_applied_at + max_key_age {
+                Self::rotate_media_send_key_and_send_to_users_not_removed(
+                    state,
+                    KeyRotationReason::KeyAge,
+                );
+                return;
+            }
+        }
+        if let Some(max_frames_per_key) = policy.max_frames_per_key {
+            let next_frame_counter = {
+                let frame_crypto_context = state
+                    .frame_crypto_context
+                    .lock()
+                    .expect("Get lock for frame encryption context to count frames");
+                frame_crypto_context.next_frame_counter()
+            };
+            let frames_sent = next_frame_counter
+                .saturating_sub(state.media_send_key_applied_frame_counter);
+            if frames_sent >= max_frames_per_key {
+                Self::rotate_media_send_key_and_send_to_users_not_removed(
+                    state,
+                    KeyRotationReason::FrameCount,
+                );
+            }
+        }
+    }
+
+    fn rotate_media_send_key_and_send_to_users_not_removed(
+        state: &mut State,
+        reason: KeyRotationReason,
+    ) {
         match state.media_send_key_rotation_state {
-            KeyRotationState::Pending { secret, .. } => {
-                info!("Waiting to generate a new media send key until after the pending one has been applied. client_id: {}", state.client_id);
+            KeyRotationState::Pending {
+                secret,
+                departed_at,
+                ..
+            } => {
+                if reason == KeyRotationReason::KeyAge || reason == KeyRotationReason::FrameCount {
+                    // The pending key takes care of it.
+                    return;
+                }
+                info!("Waiting to generate a new media send key until after the pending one has been applied. client_id: {} reason: {:?}", state.client_id, reason);
 
                 state.media_send_key_rotation_state = KeyRotationState::Pending {
                     secret,
                     needs_another_rotation: true,
+                    departed_at,
                 }
             }
             KeyRotationState::Applied => {
-                info!("Generating a new random media send key because a user has been removed. client_id: {}", state.client_id);
+                info!("Generating a new random media send key. client_id: {} reason: {:?}", state.client_id, reason);
 
                 // First generate a new key, then wait some time, and then apply it.
                 let ratchet_counter: frame_crypto::RatchetCounter = 0;
@@ -2144,32 +3818,69 @@ impl Client {
                     }
                 }
 
+                // Anyone who leaves from now on may have gotten this key, so they
+                // need another rotation.
                 state.media_send_key_rotation_state = KeyRotationState::Pending {
                     secret,
                     needs_another_rotation: false,
+                    departed_at: state.earliest_unprotected_departure.take(),
                 };
                 state
                     .actor
-                    .send_delayed(Duration::from_secs(MEDIA_SEND_KEY_ROTATION_DELAY_SECS), move |state| {
+                    .send_delayed(state.key_rotation_policy.apply_delay, move |state| {
                         info!("Applying the new send key. client_id: {}", state.client_id);
-                        {
+                        let next_frame_counter = {
                             let mut frame_crypto_context = state
                                 .frame_crypto_context
                                 .lock()
                                 .expect("Get lock for frame encryption context to reset media send key");
                             frame_crypto_context.reset_send_ratchet(secret);
-                        }
-
-                        let needs_another_rotation = matches!(state.media_send_key_rotation_state, KeyRotationState::Pending{needs_another_rotation: true, ..});
+                            frame_crypto_context.next_frame_counter()
+                        };
+                        let now = Instant::now();
+                        state.media_send_key_applied_at = now;
+                        state.media_send_key_applied_frame_counter = next_frame_counter;
+                        Self::update_resumption_snapshot(state);
+
+                        let (needs_another_rotation, departed_at) = match state.media_send_key_rotation_state {
+                            KeyRotationState::Pending { needs_another_rotation, departed_at, .. } => (needs_another_rotation, departed_at),
+                            KeyRotationState::Applied => (false, None),
+                        };
                         state.media_send_key_rotation_state = KeyRotationState::Applied;
+                        Self::record_key_rotation(state, departed_at.map(|departed_at| now.saturating_duration_since(departed_at)));
                         if needs_another_rotation {
-                            Self::rotate_media_send_key_and_send_to_users_not_removed(state);
+                            let reason = if state.earliest_unprotected_departure.is_some() {
+                                KeyRotationReason::UserRemoved
+                            } else {
+                                KeyRotationReason::Requested
+                            };
+                            Self::rotate_media_send_key_and_send_to_users_not_removed(state, reason);
                         }
                     })
             }
         }
     }
 
+    fn record_key_rotation(state: &State, exposure_window: Option<Duration>) {
+        let mut metrics = state
+            .key_rotation_metrics
+            .lock()
+            .expect("Get lock for key rotation metrics");
+        metrics.rotations += 1;
+        if let Some(exposure_window) = exposure_window {
+            metrics.last_exposure_window = Some(exposure_window);
+            metrics.max_exposure_window = Some(
+                metrics
+                    .max_exposure_window
+                    .map_or(exposure_window, |max| max.max(exposure_window)),
+            );
+        }
+        info!(
+            "Key rotation metrics. client_id: {} metrics: {:?}",
+            state.client_id, *metrics
+        );
+    }
+
     fn advance_media_send_key_and_send_to_users_with_added_devices(
         state: &mut State,
         users_with_added_devices: &[UserId],
@@ -2256,9 +3967,10 @@ impl Client {
         debug!("  recipient_id: {}", uuid_to_string(&recipient_id));
 
         let media_key = protobuf::group_call::device_to_device::MediaKey {
-            demux_id:        Some(local_demux_id),
-            ratchet_counter: Some(ratchet_counter as u32),
-            secret:          Some(secret.to_vec()),
+            demux_id:           Some(local_demux_id),
+            ratchet_counter:    Some(ratchet_counter as u32),
+            secret:             Some(secret.to_vec()),
+            control_public_key: Some(PublicKey::from(&state.control_secret).as_bytes().to_vec()),
         };
         let message = protobuf::group_call::DeviceToDevice {
             group_id: Some(state.group_id.clone()),
@@ -2491,10 +4203,87 @@ impl Client {
                         sharing_screen: state.outgoing_heartbeat_state.sharing_screen,
                     })
                 },
+                raised_hand: Some(protobuf::group_call::device_to_device::RaisedHand {
+                    raised: Some(state.outgoing_hand_raised),
+                }),
+                chat_state: state
+                    .chat_sender
+                    .latest_message_id()
+                    .map(
+                        |latest_message_id| protobuf::group_call::device_to_device::ChatState {
+                            latest_message_id: Some(latest_message_id),
+                        },
+                    ),
                 ..Default::default()
             }
         })?;
-        Self::broadcast_data_through_sfu(state, &heartbeat_msg)
+        Self::broadcast_data_through_sfu(state, "heartbeat", &heartbeat_msg)
+    }
+
+    fn send_reaction_through_sfu(state: &mut State, value: String) -> Result<()> {
+        let reaction_msg = encode_proto(protobuf::group_call::DeviceToDevice {
+            reaction: Some(protobuf::group_call::device_to_device::Reaction { value: Some(value) }),
+            ..Default::default()
+        })?;
+        Self::broadcast_data_through_sfu(state, "reaction", &reaction_msg)
+    }
+
+    fn send_chat_chunks(state: &mut State, chunks: Vec<chat::Chunk>) {
+        for chunk in chunks {
+            let result = encode_proto(protobuf::group_call::DeviceToDevice {
+                chat_message: Some(protobuf::group_call::device_to_device::ChatMessage {
+                    message_id:  Some(chunk.message_id),
+                    chunk_index: Some(chunk.chunk_index),
+                    chunk_count: Some(chunk.chunk_count),
+                    chunk:       Some(chunk.data),
+                }),
+                ..Default::default()
+            })
+            .and_then(|chunk_msg| {
+                Self::broadcast_data_through_sfu(state, "chat message", &chunk_msg)
+            });
+            if let Err(err) = result {
+                warn!("Failed to send chat message chunk: {:?}", err);
+            }
+        }
+    }
+
+    fn send_chat_retransmit_requests(state: &mut State, now: Instant) {
+        // Forget about devices that have left.
+        let remote_demux_ids: HashSet<DemuxId> =
+            state.remote_devices.iter().map(|rd| rd.demux_id).collect();
+        state
+            .chat_receivers
+            .retain(|demux_id, _| remote_demux_ids.contains(demux_id));
+
+        let mut requests = vec![];
+        for (demux_id, receiver) in state.chat_receivers.iter_mut() {
+            for request in receiver.retransmit_requests(now) {
+                requests.push(
+                    protobuf::group_call::device_to_device::ChatRetransmitRequest {
+                        demux_id:      Some(*demux_id),
+                        message_id:    Some(request.message_id),
+                        chunk_indexes: request.chunk_indexes,
+                    },
+                );
+            }
+        }
+        for request in requests {
+            debug!(
+                "Asking demux_id {:?} to retransmit chat message {:?}",
+                request.demux_id, request.message_id
+            );
+            let result = encode_proto(protobuf::group_call::DeviceToDevice {
+                chat_retransmit_request: Some(request),
+                ..Default::default()
+            })
+            .and_then(|request_msg| {
+                Self::broadcast_data_through_sfu(state, "chat retransmit request", &request_msg)
+            });
+            if let Err(err) = result {
+                warn!("Failed to send chat retransmit request: {:?}", err);
+            }
+        }
     }
 
     fn send_leaving_through_sfu_and_over_signaling(state: &mut State, local_demux_id: DemuxId) {
@@ -2510,7 +4299,7 @@ impl Client {
             ..DeviceToDevice::default()
         };
         if let Ok(encoded_msg) = encode_proto(msg) {
-            if Self::broadcast_data_through_sfu(state, &encoded_msg).is_err() {
+            if Self::broadcast_data_through_sfu(state, "leaving", &encoded_msg).is_err() {
                 warn!("Could not send leaving message through the SFU");
             } else {
                 debug!("Send leaving message over RTP through SFU.");
@@ -2568,10 +4357,13 @@ impl Client {
         }
     }
 
-    fn broadcast_data_through_sfu(state: &mut State, message: &[u8]) -> Result<()> {
+    // The message is sent end-to-end encrypted, so only its kind and size are logged.
+    fn broadcast_data_through_sfu(state: &mut State, kind: &str, message: &[u8]) -> Result<()> {
         debug!(
-            "group_call::Client(inner)::broadcast_data_through_sfu(client_id: {}, message: {:?})",
-            state.client_id, message,
+            "group_call::Client(inner)::broadcast_data_through_sfu(client_id: {}, kind: {}, len: {})",
+            state.client_id,
+            kind,
+            message.len(),
         );
         if let JoinState::Joined(local_demux_id, _) = state.join_state {
             let message = Self::encrypt_data(state, message)?;
@@ -2656,6 +4448,32 @@ impl Client {
                         if let Some(heartbeat) = msg.heartbeat {
                             self.handle_heartbeat_received(demux_id, header.timestamp, heartbeat);
                         }
+                        if let Some(raised_hand) = msg.raised_hand {
+                            self.handle_raised_hand_received(
+                                demux_id,
+                                header.timestamp,
+                                raised_hand,
+                            );
+                        }
+                        if let Some(reaction) = msg.reaction {
+                            self.handle_reaction_received(demux_id, header.timestamp, reaction);
+                        }
+                        if let Some(chat_message) = msg.chat_message {
+                            self.handle_chat_message_received(demux_id, chat_message);
+                        }
+                        if let Some(chat_state) = msg.chat_state {
+                            self.handle_chat_state_received(demux_id, chat_state);
+                        }
+                        if let Some(chat_retransmit_request) = msg.chat_retransmit_request {
+                            self.handle_chat_retransmit_request_received(chat_retransmit_request);
+                        }
+                        if let Some(control_request) = msg.control_request {
+                            self.handle_control_request_received(
+                                demux_id,
+                                header.timestamp,
+                                control_request,
+                            );
+                        }
                         if let Some(_leaving) = msg.leaving {
                             self.actor.send(move |state| {
                                 Self::handle_leaving_received(state, demux_id);
@@ -2729,6 +4547,7 @@ impl Client {
                 state
                     .observer
                     .handle_remote_devices_changed(state.client_id, &state.remote_devices, RemoteDevicesChangedReason::SpeakerTimeChanged(demux_id));
+                Self::update_layout(state);
             } else {
                 debug!(
                     "Ignoring speaker change because it isn't a known remote devices: {}",
@@ -2755,7 +4574,8 @@ impl Client {
                     remote_device.forwarding_video = Some(forwarding_video_demux_ids.contains(&remote_device.demux_id));
                 }
                 state.forwarding_video_demux_ids = forwarding_video_demux_ids;
-                state.observer.handle_remote_devices_changed(state.client_id, &state.remote_devices, RemoteDevicesChangedReason::ForwardeVideosChanged)
+                state.observer.handle_remote_devices_changed(state.client_id, &state.remote_devices, RemoteDevicesChangedReason::ForwardeVideosChanged);
+                Self::update_pinned_not_forwarded(state);
             }
         })
     }
@@ -2792,50 +4612,318 @@ impl Client {
         });
     }
 
-    fn handle_leaving_received(state: &mut State, demux_id: DemuxId) {
-        // It's likely we haven't received an update from the SFU about this demux_id leaving.
-        debug!(
-            "Request devices because we just received a leaving message from demux_id = {}",
-            demux_id
-        );
-        if let Some(device) = state
-            .remote_devices
-            .find_by_demux_id_mut(demux_id)
-        {
-            if !device.leaving_received {
-                device.leaving_received = true;
-                Self::request_remote_devices_as_soon_as_possible(state);
+    fn handle_raised_hand_received(
+        &self,
+        demux_id: DemuxId,
+        timestamp: rtp::Timestamp,
+        raised_hand: protobuf::group_call::device_to_device::RaisedHand,
+    ) {
+        self.actor.send(move |state| {
+            if let Some(remote_device) = state.remote_devices.find_by_demux_id_mut(demux_id) {
+                if is_newer_rtp_timestamp(timestamp, remote_device.raised_hand_rtp_timestamp) {
+                    remote_device.raised_hand_rtp_timestamp = Some(timestamp);
+                    let hand_raised = raised_hand.raised.unwrap_or(false);
+                    if remote_device.hand_raised != hand_raised {
+                        remote_device.hand_raised = hand_raised;
+                        state.observer.handle_remote_devices_changed(
+                            state.client_id,
+                            &state.remote_devices,
+                            RemoteDevicesChangedReason::HandRaisedChanged(demux_id),
+                        );
+                    }
+                }
+            } else {
+                warn!(
+                    "Ignoring received raised hand for unknown demux_id {}",
+                    demux_id
+                );
+            }
+        });
+    }
 
-                // It's also possible we have learned this before the SFU has, in which case the SFU may have stale data.
-                // So let's wait a little while and ask again.
+    fn handle_reaction_received(
+        &self,
+        demux_id: DemuxId,
+        timestamp: rtp::Timestamp,
+        reaction: protobuf::group_call::device_to_device::Reaction,
+    ) {
+        let value = match reaction.value {
+            Some(value) if !value.is_empty() && value.len() <= MAX_REACTION_VALUE_LEN => value,
+            _ => {
+                warn!(
+                    "Ignoring received reaction with invalid value from demux_id {}",
+                    demux_id
+                );
+                return;
+            }
+        };
+        self.actor.send(move |state| {
+            if let Some(remote_device) = state
+                .remote_devices
+                .find_by_demux_id_mut(demux_id)
+            {
+                if !is_newer_rtp_timestamp(timestamp, remote_device.reaction_rtp_timestamp) {
+                    debug!(
+                        "Ignoring duplicate or out of order reaction from demux_id {} with timestamp {}",
+                        demux_id, timestamp
+                    );
+                    return;
+                }
+                remote_device.reaction_rtp_timestamp = Some(timestamp);
                 state
-                    .actor
-                    .send_delayed(Duration::from_secs(2), move |state| {
-                        info!("Request devices because we received a leaving message from demux_id = {} a while ago", demux_id);
-                        Self::request_remote_devices_as_soon_as_possible(state);
-                    });
+                    .observer
+                    .handle_reaction(state.client_id, Reaction { demux_id, value });
+            } else {
+                warn!(
+                    "Ignoring received reaction for unknown demux_id {}",
+                    demux_id
+                );
             }
-        }
+        });
     }
 
-    #[cfg(feature = "sim")]
-    pub fn synchronize(&self) {
-        let barrier = Arc::new(std::sync::Barrier::new(2));
-        let barrier_for_task = barrier.clone();
-
-        self.actor.send(move |_| {
-            barrier_for_task.wait();
+    fn handle_chat_message_received(
+        &self,
+        demux_id: DemuxId,
+        chat_message: protobuf::group_call::device_to_device::ChatMessage,
+    ) {
+        let chunk = match chat_message {
+            protobuf::group_call::device_to_device::ChatMessage {
+                message_id: Some(message_id),
+                chunk_index: Some(chunk_index),
+                chunk_count: Some(chunk_count),
+                chunk: Some(data),
+            } => chat::Chunk {
+                message_id,
+                chunk_index,
+                chunk_count,
+                data,
+            },
+            _ => {
+                warn!(
+                    "Ignoring incomplete chat message chunk from demux_id {}",
+                    demux_id
+                );
+                return;
+            }
+        };
+        self.actor.send(move |state| {
+            if state.remote_devices.find_by_demux_id(demux_id).is_none() {
+                warn!(
+                    "Ignoring received chat message for unknown demux_id {}",
+                    demux_id
+                );
+                return;
+            }
+            let receiver = state.chat_receivers.entry(demux_id).or_default();
+            if let Some((message_id, message)) = receiver.receive_chunk(chunk, Instant::now()) {
+                match String::from_utf8(message) {
+                    Ok(text) => state.observer.handle_message_received(
+                        state.client_id,
+                        ChatMessage {
+                            demux_id,
+                            message_id,
+                            text,
+                        },
+                    ),
+                    Err(_) => warn!(
+                        "Ignoring chat message {} from demux_id {} that isn't UTF-8",
+                        message_id, demux_id
+                    ),
+                }
+            }
         });
-
-        barrier.wait();
     }
-}
 
-fn encode_proto(msg: impl prost::Message) -> Result<BytesMut> {
-    let mut bytes = BytesMut::with_capacity(msg.encoded_len());
-    msg.encode(&mut bytes)?;
-    Ok(bytes)
-}
+    fn handle_chat_state_received(
+        &self,
+        demux_id: DemuxId,
+        chat_state: protobuf::group_call::device_to_device::ChatState,
+    ) {
+        if let Some(latest_message_id) = chat_state.latest_message_id {
+            self.actor.send(move |state| {
+                if state.remote_devices.find_by_demux_id(demux_id).is_some() {
+                    state
+                        .chat_receivers
+                        .entry(demux_id)
+                        .or_default()
+                        .receive_latest_message_id(latest_message_id, Instant::now());
+                }
+            });
+        }
+    }
+
+    fn handle_chat_retransmit_request_received(
+        &self,
+        request: protobuf::group_call::device_to_device::ChatRetransmitRequest,
+    ) {
+        self.actor.send(move |state| {
+            if let (JoinState::Joined(local_demux_id, _), Some(demux_id), Some(message_id)) =
+                (&state.join_state, request.demux_id, request.message_id)
+            {
+                // Everyone receives the requests, but only the sender answers.
+                if *local_demux_id != demux_id {
+                    return;
+                }
+                let chunks = state.chat_sender.retransmit(
+                    &chat::RetransmitRequest {
+                        message_id,
+                        chunk_indexes: request.chunk_indexes,
+                    },
+                    Instant::now(),
+                );
+                Self::send_chat_chunks(state, chunks);
+            }
+        });
+    }
+
+    fn handle_control_request_received(
+        &self,
+        demux_id: DemuxId,
+        timestamp: rtp::Timestamp,
+        control_request: protobuf::group_call::device_to_device::ControlRequest,
+    ) {
+        let request = match control_request
+            .r#type
+            .and_then(protobuf::group_call::device_to_device::control_request::Type::from_i32)
+        {
+            Some(request_type) => ControlRequest::from(request_type),
+            None => {
+                warn!(
+                    "Ignoring control request of unknown type from demux_id {}",
+                    demux_id
+                );
+                return;
+            }
+        };
+        self.actor.send(move |state| {
+            // Everyone receives the requests, but only the target is asked.
+            match state.join_state {
+                JoinState::Joined(local_demux_id, _)
+                    if control_request.target_demux_id == Some(local_demux_id) => {}
+                _ => {
+                    return;
+                }
+            }
+            let (creator, era_id) = match &state.last_peek_info {
+                Some(PeekInfo {
+                    creator: Some(creator),
+                    era_id,
+                    ..
+                }) => (creator, era_id),
+                _ => {
+                    warn!("Ignoring control request because the call creator is unknown");
+                    return;
+                }
+            };
+            if control_request.era_id != *era_id {
+                warn!(
+                    "Ignoring control request from demux_id {} for era {:?}",
+                    demux_id, control_request.era_id
+                );
+                return;
+            }
+            if let Some(remote_device) = state
+                .remote_devices
+                .find_by_demux_id_mut(demux_id)
+            {
+                if remote_device.user_id != *creator {
+                    warn!(
+                        "Ignoring control request from demux_id {} because it isn't from the call creator",
+                        demux_id
+                    );
+                    return;
+                }
+                // The demux ID comes from the SSRC the SFU forwarded the request with,
+                // so check that the creator's device really sent it.
+                let authentic = match (
+                    state
+                        .control_public_keys
+                        .get(&(demux_id, remote_device.user_id.clone())),
+                    &control_request.mac,
+                ) {
+                    (Some(sender_public_key), Some(mac)) => bool::from(
+                        compute_control_request_mac(
+                            &state.control_secret,
+                            sender_public_key,
+                            demux_id,
+                            &control_request,
+                        )[..]
+                            .ct_eq(&mac[..]),
+                    ),
+                    _ => false,
+                };
+                if !authentic {
+                    warn!(
+                        "Ignoring control request from demux_id {} without a valid MAC",
+                        demux_id
+                    );
+                    return;
+                }
+                if !is_newer_rtp_timestamp(timestamp, remote_device.control_request_rtp_timestamp) {
+                    debug!(
+                        "Ignoring duplicate or out of order control request from demux_id {} with timestamp {}",
+                        demux_id, timestamp
+                    );
+                    return;
+                }
+                remote_device.control_request_rtp_timestamp = Some(timestamp);
+                state
+                    .observer
+                    .handle_control_request(state.client_id, demux_id, request);
+            } else {
+                warn!(
+                    "Ignoring received control request for unknown demux_id {}",
+                    demux_id
+                );
+            }
+        });
+    }
+
+    fn handle_leaving_received(state: &mut State, demux_id: DemuxId) {
+        // It's likely we haven't received an update from the SFU about this demux_id leaving.
+        debug!(
+            "Request devices because we just received a leaving message from demux_id = {}",
+            demux_id
+        );
+        if let Some(device) = state
+            .remote_devices
+            .find_by_demux_id_mut(demux_id)
+        {
+            if !device.leaving_received {
+                device.leaving_received = true;
+                Self::request_remote_devices_as_soon_as_possible(state);
+
+                // It's also possible we have learned this before the SFU has, in which case the SFU may have stale data.
+                // So let's wait a little while and ask again.
+                state
+                    .actor
+                    .send_delayed(Duration::from_secs(2), move |state| {
+                        info!("Request devices because we received a leaving message from demux_id = {} a while ago", demux_id);
+                        Self::request_remote_devices_as_soon_as_possible(state);
+                    });
+            }
+        }
+    }
+
+    #[cfg(feature = "sim")]
+    pub fn synchronize(&self) {
+        let barrier = Arc::new(std::sync::Barrier::new(2));
+        let barrier_for_task = barrier.clone();
+
+        self.actor.send(move |_| {
+            barrier_for_task.wait();
+        });
+
+        barrier.wait();
+    }
+}
+
+fn encode_proto(msg: impl prost::Message) -> Result<BytesMut> {
+    let mut bytes = BytesMut::with_capacity(msg.encoded_len());
+    msg.encode(&mut bytes)?;
+    Ok(bytes)
+}
 
 // We need to wrap a Call to implement PeerConnectionObserverTrait
 // because we need to pass an impl into PeerConnectionObserver::new
@@ -3167,6 +5255,7 @@ mod tests {
         Mutex,
     };
 
+    use crate::simnet::sfu::{MockSfu, MockSfuConfig};
     use crate::webrtc::sim::media::FAKE_AUDIO_TRACK;
 
     use super::*;
@@ -3174,9 +5263,10 @@ mod tests {
 
     #[derive(Clone)]
     struct FakeSfuClient {
-        sfu_info:       SfuInfo,
-        local_demux_id: DemuxId,
-        request_count:  Arc<AtomicU64>,
+        sfu_info:        SfuInfo,
+        local_demux_id:  DemuxId,
+        request_count:   Arc<AtomicU64>,
+        removed_devices: Arc<CallMutex<Vec<String>>>,
     }
 
     impl FakeSfuClient {
@@ -3185,6 +5275,10 @@ mod tests {
                 sfu_info,
                 local_demux_id,
                 request_count: Arc::new(AtomicU64::new(0)),
+                removed_devices: Arc::new(CallMutex::new(
+                    Vec::new(),
+                    "FakeSfuClient removed devices",
+                )),
             }
         }
     }
@@ -3193,6 +5287,14 @@ mod tests {
         pub fn request_count(&self) -> u64 {
             self.request_count.load(atomic::Ordering::SeqCst)
         }
+
+        fn removed_devices(&self) -> Vec<String> {
+            let removed_devices = self
+                .removed_devices
+                .lock()
+                .expect("Lock removed devices to read them");
+            removed_devices.clone()
+        }
     }
 
     impl SfuClient for FakeSfuClient {
@@ -3201,12 +5303,14 @@ mod tests {
             _ice_ufrag: &str,
             _ice_pwd: &str,
             _dtls_fingerprint: &DtlsFingerprint,
+            _resume_endpoint_id: Option<&str>,
             client: Client,
         ) {
             client.on_sfu_client_joined(Ok((
                 self.sfu_info.clone(),
                 self.local_demux_id,
                 "token".to_string(),
+                ClientStatus::Active,
             )));
         }
         fn peek(&mut self, _handle_remote_devices: BoxedPeekInfoHandler) {
@@ -3215,6 +5319,15 @@ mod tests {
         fn set_group_members(&mut self, _members: Vec<GroupMemberInfo>) {}
         fn set_membership_proof(&mut self, _proof: MembershipProof) {}
         fn leave(&mut self, _long_device_id: String) {}
+        fn remove_device(&mut self, long_device_id: String) {
+            let mut removed_devices = self
+                .removed_devices
+                .lock()
+                .expect("Lock removed devices to add one");
+            removed_devices.push(long_device_id);
+        }
+        fn approve_device(&mut self, _long_device_id: String) {}
+        fn deny_device(&mut self, _long_device_id: String) {}
     }
 
     // TODO: Put this in common util area?
@@ -3266,13 +5379,17 @@ mod tests {
 
     #[derive(Clone, Default)]
     struct FakeObserverPeekState {
-        joined_members: Vec<UserId>,
-        creator:        Option<UserId>,
-        era_id:         Option<String>,
-        max_devices:    Option<u32>,
-        device_count:   u32,
+        joined_members:    Vec<UserId>,
+        creator:           Option<UserId>,
+        era_id:            Option<String>,
+        max_devices:       Option<u32>,
+        device_count:      u32,
+        // From handle_pending_devices_changed rather than handle_peek_changed
+        pending_demux_ids: Vec<DemuxId>,
     }
 
+    type AudioLevelsUpdate = (AudioLevel, Vec<ReceivedAudioLevel>);
+
     #[derive(Clone)]
     struct FakeObserver {
         // For sending messages
@@ -3280,12 +5397,23 @@ mod tests {
         recipients:                    Arc<CallMutex<Vec<TestClient>>>,
         outgoing_signaling_blocked:    Arc<CallMutex<bool>>,
         sent_group_signaling_messages: Arc<CallMutex<Vec<protobuf::signaling::CallMessage>>>,
+        sent_ring_intentions:          Arc<CallMutex<Vec<(UserId, protobuf::signaling::CallMessage)>>>,
 
+        pending:                     Event,
+        waiting_for_slot:            Event,
         joined:                      Event,
         remote_devices:              Arc<CallMutex<Vec<RemoteDeviceState>>>,
         remote_devices_at_join_time: Arc<CallMutex<Vec<RemoteDeviceState>>>,
         peek_state:                  Arc<CallMutex<FakeObserverPeekState>>,
         send_rates:                  Arc<CallMutex<Option<SendRates>>>,
+        reactions:                   Arc<CallMutex<Vec<Reaction>>>,
+        chat_messages:               Arc<CallMutex<Vec<ChatMessage>>>,
+        control_requests:            Arc<CallMutex<Vec<(DemuxId, ControlRequest)>>>,
+        audio_levels:                Arc<CallMutex<Vec<AudioLevelsUpdate>>>,
+        resumption_snapshot:         Arc<CallMutex<Option<Vec<u8>>>>,
+        layouts:                     Arc<CallMutex<Vec<Layout>>>,
+        pinned_videos_not_forwarded: Arc<CallMutex<Vec<Vec<DemuxId>>>>,
+        ring_statuses:               Arc<CallMutex<Vec<RecipientRingStatus>>>,
         ended:                       Waitable<EndReason>,
         era_id:                      Option<String>,
 
@@ -3306,6 +5434,12 @@ mod tests {
                     Vec::new(),
                     "FakeObserver sent group messages",
                 )),
+                sent_ring_intentions: Arc::new(CallMutex::new(
+                    Vec::new(),
+                    "FakeObserver sent ring intentions",
+                )),
+                pending: Event::default(),
+                waiting_for_slot: Event::default(),
                 joined: Event::default(),
                 remote_devices: Arc::new(CallMutex::new(Vec::new(), "FakeObserver remote devices")),
                 remote_devices_at_join_time: Arc::new(CallMutex::new(
@@ -3317,6 +5451,20 @@ mod tests {
                     "FakeObserver peek state",
                 )),
                 send_rates: Arc::new(CallMutex::new(None, "FakeObserver send rates")),
+                reactions: Arc::new(CallMutex::new(Vec::new(), "FakeObserver reactions")),
+                chat_messages: Arc::new(CallMutex::new(Vec::new(), "FakeObserver chat messages")),
+                control_requests: Arc::new(CallMutex::new(
+                    Vec::new(),
+                    "FakeObserver control requests",
+                )),
+                audio_levels: Arc::new(CallMutex::new(Vec::new(), "FakeObserver audio levels")),
+                resumption_snapshot: Arc::new(CallMutex::new(None, "FakeObserver resumption snapshot")),
+                layouts: Arc::new(CallMutex::new(Vec::new(), "FakeObserver layouts")),
+                pinned_videos_not_forwarded: Arc::new(CallMutex::new(
+                    Vec::new(),
+                    "FakeObserver pinned videos not forwarded",
+                )),
+                ring_statuses: Arc::new(CallMutex::new(Vec::new(), "FakeObserver ring statuses")),
                 ended: Waitable::default(),
                 era_id: None,
                 request_membership_proof_invocation_count: Default::default(),
@@ -3379,6 +5527,69 @@ mod tests {
             send_rates.clone()
         }
 
+        fn reactions(&self) -> Vec<Reaction> {
+            let reactions = self.reactions.lock().expect("Lock reactions to read them");
+            reactions.clone()
+        }
+
+        fn chat_messages(&self) -> Vec<ChatMessage> {
+            let chat_messages = self
+                .chat_messages
+                .lock()
+                .expect("Lock chat messages to read them");
+            chat_messages.clone()
+        }
+
+        fn control_requests(&self) -> Vec<(DemuxId, ControlRequest)> {
+            let control_requests = self
+                .control_requests
+                .lock()
+                .expect("Lock control requests to read them");
+            control_requests.clone()
+        }
+
+        fn audio_levels(&self) -> Vec<AudioLevelsUpdate> {
+            let audio_levels = self
+                .audio_levels
+                .lock()
+                .expect("Lock audio levels to read them");
+            audio_levels.clone()
+        }
+
+        fn resumption_snapshot(&self) -> Option<Vec<u8>> {
+            let resumption_snapshot = self
+                .resumption_snapshot
+                .lock()
+                .expect("Lock resumption snapshot to read it");
+            resumption_snapshot.clone()
+        }
+
+        fn layouts(&self) -> Vec<Layout> {
+            let layouts = self.layouts.lock().expect("Lock layouts to read them");
+            layouts.clone()
+        }
+
+        fn pinned_videos_not_forwarded(&self) -> Vec<Vec<DemuxId>> {
+            let pinned_videos_not_forwarded = self
+                .pinned_videos_not_forwarded
+                .lock()
+                .expect("Lock pinned videos not forwarded to read them");
+            pinned_videos_not_forwarded.clone()
+        }
+
+        fn ring_statuses(&self) -> Vec<RecipientRingStatus> {
+            let ring_statuses = self.ring_statuses.lock().expect("Lock ring statuses to read them");
+            ring_statuses.clone()
+        }
+
+        fn sent_ring_intentions(&self) -> Vec<(UserId, protobuf::signaling::CallMessage)> {
+            let mut sent_ring_intentions = self
+                .sent_ring_intentions
+                .lock()
+                .expect("Lock sent ring intentions to take them");
+            std::mem::take(&mut *sent_ring_intentions)
+        }
+
         /// Gets the number of `request_membership_proof` since last checked.
         fn request_membership_proof_invocation_count(&self) -> u64 {
             self.request_membership_proof_invocation_count.swap(0, Ordering::Relaxed)
@@ -3404,6 +5615,12 @@ mod tests {
         }
 
         fn handle_join_state_changed(&self, _client_id: ClientId, join_state: JoinState) {
+            if let JoinState::Pending(_, _) = join_state {
+                self.pending.set();
+            }
+            if let JoinState::WaitingForSlot(_) = join_state {
+                self.waiting_for_slot.set();
+            }
             if let JoinState::Joined(_, _) = join_state {
                 let mut owned_remote_devices_at_join_time = self
                     .remote_devices_at_join_time
@@ -3431,6 +5648,48 @@ mod tests {
             self.handle_remote_devices_changed_invocation_count.fetch_add(1, Ordering::Relaxed);
         }
 
+        fn handle_reaction(&self, _client_id: ClientId, reaction: Reaction) {
+            let mut reactions = self
+                .reactions
+                .lock()
+                .expect("Lock reactions to handle reaction");
+            reactions.push(reaction);
+        }
+
+        fn handle_message_received(&self, _client_id: ClientId, message: ChatMessage) {
+            let mut chat_messages = self
+                .chat_messages
+                .lock()
+                .expect("Lock chat messages to handle message");
+            chat_messages.push(message);
+        }
+
+        fn handle_control_request(
+            &self,
+            _client_id: ClientId,
+            sender_demux_id: DemuxId,
+            request: ControlRequest,
+        ) {
+            let mut control_requests = self
+                .control_requests
+                .lock()
+                .expect("Lock control requests to handle request");
+            control_requests.push((sender_demux_id, request));
+        }
+
+        fn handle_audio_levels(
+            &self,
+            _client_id: ClientId,
+            captured_level: AudioLevel,
+            received_levels: Vec<ReceivedAudioLevel>,
+        ) {
+            let mut audio_levels = self
+                .audio_levels
+                .lock()
+                .expect("Lock audio levels to handle levels");
+            audio_levels.push((captured_level, received_levels));
+        }
+
         fn handle_peek_changed(
             &self,
             _client_id: ClientId,
@@ -3451,6 +5710,21 @@ mod tests {
             owned_state.device_count = device_count;
         }
 
+        fn handle_pending_devices_changed(
+            &self,
+            _client_id: ClientId,
+            pending_devices: &[PeekDeviceInfo],
+        ) {
+            let mut owned_state = self
+                .peek_state
+                .lock()
+                .expect("Lock peek state to handle update");
+            owned_state.pending_demux_ids = pending_devices
+                .iter()
+                .map(|device| device.demux_id)
+                .collect();
+        }
+
         fn handle_send_rates_changed(&self, _client_id: ClientId, send_rates: SendRates) {
             let mut self_send_rates = self
                 .send_rates
@@ -3472,6 +5746,13 @@ mod tests {
                 );
                 return;
             }
+            if call_message.ring_intention.is_some() {
+                self.sent_ring_intentions
+                    .lock()
+                    .expect("Lock sent ring intentions to add one")
+                    .push((recipient_id, call_message));
+                return;
+            }
             let recipients = self
                 .recipients
                 .lock()
@@ -3515,6 +5796,31 @@ mod tests {
             self.sent_group_signaling_messages.lock().expect("adding message").push(call_message);
             info!("Recorded group-wide call message from {:?}", self.user_id);
         }
+        fn handle_resumption_snapshot(&self, _client_id: ClientId, snapshot: Vec<u8>) {
+            let mut resumption_snapshot = self
+                .resumption_snapshot
+                .lock()
+                .expect("Lock resumption snapshot to replace it");
+            *resumption_snapshot = Some(snapshot);
+        }
+        fn handle_layout_changed(&self, _client_id: ClientId, layout: Layout) {
+            let mut layouts = self.layouts.lock().expect("Lock layouts to add one");
+            layouts.push(layout);
+        }
+        fn handle_pinned_videos_not_forwarded(&self, _client_id: ClientId, demux_ids: Vec<DemuxId>) {
+            let mut pinned_videos_not_forwarded = self
+                .pinned_videos_not_forwarded
+                .lock()
+                .expect("Lock pinned videos not forwarded to add them");
+            pinned_videos_not_forwarded.push(demux_ids);
+        }
+        fn handle_ring_statuses_changed(&self, _client_id: ClientId, statuses: Vec<RecipientRingStatus>) {
+            let mut ring_statuses = self
+                .ring_statuses
+                .lock()
+                .expect("Lock ring statuses to replace them");
+            *ring_statuses = statuses;
+        }
         fn handle_incoming_video_track(
             &mut self,
             _client_id: ClientId,
@@ -3536,6 +5842,7 @@ mod tests {
         client:                Client,
         sfu_rtp_packet_sender: Option<mpsc::Sender<(rtp::Header, Vec<u8>)>>,
         default_peek_info:     PeekInfo,
+        busy:                  Arc<CallMutex<bool>>,
     }
 
     // Just so it's something different
@@ -3568,7 +5875,7 @@ mod tests {
                 demux_id,
                 Box::new(sfu_client.clone()),
                 Box::new(observer.clone()),
-                fake_busy,
+                fake_busy.clone(),
                 fake_self_uuid,
                 None,
                 fake_audio_track,
@@ -3584,6 +5891,7 @@ mod tests {
                 client,
                 sfu_rtp_packet_sender: None,
                 default_peek_info: PeekInfo::default(),
+                busy: fake_busy,
             }
         }
 
@@ -3639,6 +5947,16 @@ mod tests {
             self.wait_for_client_to_process();
         }
 
+        fn join_state(&self) -> JoinState {
+            let (sender, receiver) = mpsc::channel();
+            self.client.actor.send(move |state| {
+                sender
+                    .send(state.join_state.clone())
+                    .expect("Send join state to test");
+            });
+            receiver.recv().expect("Receive join state")
+        }
+
         fn wait_for_client_to_process(&self) {
             let event = Event::default();
             let cloned = event.clone();
@@ -3983,6 +6301,100 @@ mod tests {
         client5.disconnect_and_wait_until_ended();
     }
 
+    #[test]
+    fn frame_encryption_rotation_policy() {
+        let mut client1 = TestClient::new(vec![1], 1, None);
+        client1.connect_join_and_wait_until_joined();
+
+        let mut client2 = TestClient::new(vec![2], 2, None);
+        client2.connect_join_and_wait_until_joined();
+
+        let mut client3 = TestClient::new(vec![3], 3, None);
+        client3.connect_join_and_wait_until_joined();
+
+        let apply_delay = Duration::from_millis(100);
+        client1.client.set_key_rotation_policy(KeyRotationPolicy {
+            apply_delay,
+            ..KeyRotationPolicy::default()
+        });
+        set_group_and_wait_until_applied(&[&client1, &client2, &client3]);
+        assert_eq!(
+            KeyRotationMetrics::default(),
+            client1.client.key_rotation_metrics().unwrap()
+        );
+
+        // A forced rotation doesn't protect against anyone in particular.
+        client1.client.rotate_media_send_key();
+        std::thread::sleep(Duration::from_millis(500));
+        let metrics = client1.client.key_rotation_metrics().unwrap();
+        assert_eq!(1, metrics.rotations);
+        assert_eq!(None, metrics.last_exposure_window);
+
+        let is_audio = true;
+        let plaintext = &b"Fake Audio"[..];
+        let ciphertext = client1.encrypt_media(is_audio, plaintext).unwrap();
+        assert_eq!(
+            plaintext,
+            client2
+                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
+                .unwrap()
+        );
+        assert_eq!(
+            plaintext,
+            client3
+                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
+                .unwrap()
+        );
+
+        // Removing client3 rotates after the configured delay, which is how long
+        // they could still decrypt.
+        set_group_and_wait_until_applied(&[&client1, &client2]);
+        std::thread::sleep(Duration::from_millis(500));
+        let metrics = client1.client.key_rotation_metrics().unwrap();
+        assert_eq!(2, metrics.rotations);
+        let exposure_window = metrics.last_exposure_window.unwrap();
+        assert!(exposure_window >= apply_delay);
+        assert!(exposure_window < Duration::from_millis(500));
+        assert_eq!(Some(exposure_window), metrics.max_exposure_window);
+
+        let ciphertext = client1.encrypt_media(is_audio, plaintext).unwrap();
+        assert_eq!(
+            plaintext,
+            client2
+                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
+                .unwrap()
+        );
+        assert!(client3
+            .decrypt_media(client1.demux_id, is_audio, &ciphertext)
+            .is_err());
+
+        // Sending enough frames rotates on the next tick.
+        client1.client.set_key_rotation_policy(KeyRotationPolicy {
+            apply_delay,
+            max_frames_per_key: Some(3),
+            ..KeyRotationPolicy::default()
+        });
+        for _ in 0..2 {
+            client1.encrypt_media(is_audio, plaintext).unwrap();
+        }
+        std::thread::sleep(Duration::from_millis(1500));
+        let metrics = client1.client.key_rotation_metrics().unwrap();
+        assert_eq!(3, metrics.rotations);
+        assert_eq!(Some(exposure_window), metrics.last_exposure_window);
+
+        let ciphertext = client1.encrypt_media(is_audio, plaintext).unwrap();
+        assert_eq!(
+            plaintext,
+            client2
+                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
+                .unwrap()
+        );
+
+        client1.disconnect_and_wait_until_ended();
+        client2.disconnect_and_wait_until_ended();
+        client3.disconnect_and_wait_until_ended();
+    }
+
     #[test]
     fn frame_encryption_resend_keys() {
         let mut client1 = TestClient::new(vec![1], 1, None);
@@ -4057,42 +6469,482 @@ mod tests {
     }
 
     #[test]
-    fn frame_encryption_someone_forging_demux_id() {
+    fn frame_encryption_someone_forging_demux_id() {
+        let mut client1 = TestClient::new(vec![1], 1, None);
+        client1.connect_join_and_wait_until_joined();
+
+        let mut client2 = TestClient::new(vec![2], 2, None);
+        client2.connect_join_and_wait_until_joined();
+
+        // Client3 is pretending to have demux ID 1 when sending media keys
+        let mut client3 = TestClient::new(vec![3], 3, Some(1));
+        client3.connect_join_and_wait_until_joined();
+
+        set_group_and_wait_until_applied(&[&client1, &client2, &client3]);
+
+        let is_audio = true;
+        let plaintext = &b"Fake Audio"[..];
+        let ciphertext1 = client1.encrypt_media(is_audio, plaintext).unwrap();
+        let ciphertext3 = client3.encrypt_media(is_audio, plaintext).unwrap();
+        // The forger doesn't mess anything up for the others
+        assert_eq!(
+            plaintext,
+            client2
+                .decrypt_media(client1.demux_id, is_audio, &ciphertext1)
+                .unwrap()
+        );
+        // And you can't decrypt from the forger.
+        assert!(client2
+            .decrypt_media(client3.demux_id, is_audio, &ciphertext3)
+            .is_err());
+
+        client1.disconnect_and_wait_until_ended();
+        client2.disconnect_and_wait_until_ended();
+        client3.disconnect_and_wait_until_ended();
+    }
+
+    #[test]
+    fn frame_encryption_resume() {
+        let resumption_key = [0x16; 32];
+        let client1 = TestClient::new(vec![1], 1, None);
+        client1.client.set_resumption_key(Some(resumption_key));
+        client1.connect_join_and_wait_until_joined();
+        let mut client2 = TestClient::new(vec![2], 2, None);
+        client2.connect_join_and_wait_until_joined();
+        set_group_and_wait_until_applied(&[&client1, &client2]);
+        let snapshot = client1
+            .observer
+            .resumption_snapshot()
+            .expect("Snapshot after joining");
+
+        // client1's process "restarts" without leaving.  Neither side sends keys
+        // again, and media still gets through both ways.
+        let mut client1 = TestClient::new(vec![1], 1, None);
+        client1.client.set_resumption_key(Some(resumption_key));
+        client1.client.connect();
+        client1.client.resume(snapshot);
+        client1.observer.joined.wait();
+        client1.observer.set_outgoing_signaling_blocked(true);
+        client2.observer.set_outgoing_signaling_blocked(true);
+        set_group_and_wait_until_applied(&[&client1, &client2]);
+
+        let plaintext = &b"Roses are red"[..];
+        let ciphertext = client1.encrypt_media(true, plaintext).unwrap();
+        assert_eq!(plaintext, client2.decrypt_media(1, true, &ciphertext).unwrap());
+        let ciphertext = client2.encrypt_media(true, plaintext).unwrap();
+        assert_eq!(plaintext, client1.decrypt_media(2, true, &ciphertext).unwrap());
+
+        // Snapshots can't be used with another key.
+        let client1_again = TestClient::new(vec![1], 1, None);
+        client1_again.client.set_resumption_key(Some([0x61; 32]));
+        client1_again.client.connect();
+        client1_again.client.resume(client1.observer.resumption_snapshot().unwrap());
+        client1_again.observer.joined.wait();
+        let restored_keys: Waitable<bool> = Waitable::default();
+        let restored_keys_to_set = restored_keys.clone();
+        client1_again.client.actor.send(move |state| {
+            restored_keys_to_set.set(!state.pending_media_receive_keys.is_empty());
+        });
+        assert!(!restored_keys.wait());
+
+        client1.disconnect_and_wait_until_ended();
+        client1_again.disconnect_and_wait_until_ended();
+        client2.disconnect_and_wait_until_ended();
+    }
+
+    #[test]
+    fn remote_heartbeat_state() {
+        let client1 = TestClient::new(vec![1], 1, None);
+        client1.connect_join_and_wait_until_joined();
+
+        let client2 = TestClient::new(vec![2], 2, None);
+        client2.connect_join_and_wait_until_joined();
+
+        set_group_and_wait_until_applied(&[&client1, &client2]);
+
+        let remote_devices2 = client2.observer.remote_devices();
+        assert_eq!(1, remote_devices2.len());
+        assert_eq!(client1.demux_id, remote_devices2[0].demux_id);
+        assert_eq!(None, remote_devices2[0].heartbeat_state.audio_muted);
+        assert_eq!(None, remote_devices2[0].heartbeat_state.video_muted);
+        assert_eq!(None, remote_devices2[0].heartbeat_state.presenting);
+        assert_eq!(None, remote_devices2[0].heartbeat_state.sharing_screen);
+
+        client1.client.set_outgoing_audio_muted(true);
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
+
+        let remote_devices2 = client2.observer.remote_devices();
+        assert_eq!(1, remote_devices2.len());
+        assert_eq!(client1.demux_id, remote_devices2[0].demux_id);
+        assert_eq!(Some(true), remote_devices2[0].heartbeat_state.audio_muted);
+        assert_eq!(None, remote_devices2[0].heartbeat_state.video_muted);
+        assert_eq!(None, remote_devices2[0].heartbeat_state.presenting);
+        assert_eq!(None, remote_devices2[0].heartbeat_state.sharing_screen);
+
+        client1.client.set_outgoing_video_muted(false);
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
+
+        let remote_devices2 = client2.observer.remote_devices();
+        assert_eq!(1, remote_devices2.len());
+        assert_eq!(client1.demux_id, remote_devices2[0].demux_id);
+        assert_eq!(Some(true), remote_devices2[0].heartbeat_state.audio_muted);
+        assert_eq!(Some(false), remote_devices2[0].heartbeat_state.video_muted);
+        assert_eq!(None, remote_devices2[0].heartbeat_state.presenting);
+        assert_eq!(None, remote_devices2[0].heartbeat_state.sharing_screen);
+
+        client1.client.set_presenting(true);
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
+
+        let remote_devices2 = client2.observer.remote_devices();
+        assert_eq!(1, remote_devices2.len());
+        assert_eq!(client1.demux_id, remote_devices2[0].demux_id);
+        assert_eq!(Some(true), remote_devices2[0].heartbeat_state.audio_muted);
+        assert_eq!(Some(false), remote_devices2[0].heartbeat_state.video_muted);
+        assert_eq!(Some(true), remote_devices2[0].heartbeat_state.presenting);
+        assert_eq!(None, remote_devices2[0].heartbeat_state.sharing_screen);
+
+        client1.client.set_sharing_screen(true);
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
+
+        let remote_devices2 = client2.observer.remote_devices();
+        assert_eq!(1, remote_devices2.len());
+        assert_eq!(client1.demux_id, remote_devices2[0].demux_id);
+        assert_eq!(Some(true), remote_devices2[0].heartbeat_state.audio_muted);
+        assert_eq!(Some(false), remote_devices2[0].heartbeat_state.video_muted);
+        assert_eq!(Some(true), remote_devices2[0].heartbeat_state.presenting);
+        assert_eq!(
+            Some(true),
+            remote_devices2[0].heartbeat_state.sharing_screen
+        );
+    }
+
+    #[test]
+    fn remote_hand_raised() {
+        let client1 = TestClient::new(vec![1], 1, None);
+        client1.connect_join_and_wait_until_joined();
+
+        let client2 = TestClient::new(vec![2], 2, None);
+        client2.connect_join_and_wait_until_joined();
+
+        set_group_and_wait_until_applied(&[&client1, &client2]);
+
+        let remote_devices2 = client2.observer.remote_devices();
+        assert_eq!(1, remote_devices2.len());
+        assert!(!remote_devices2[0].hand_raised);
+
+        client1.client.set_hand_raised(true);
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
+
+        let remote_devices2 = client2.observer.remote_devices();
+        assert_eq!(client1.demux_id, remote_devices2[0].demux_id);
+        assert!(remote_devices2[0].hand_raised);
+
+        // An old update arriving late doesn't lower the hand.
+        client2.client.handle_raised_hand_received(
+            client1.demux_id,
+            0,
+            protobuf::group_call::device_to_device::RaisedHand {
+                raised: Some(false),
+            },
+        );
+        client2.wait_for_client_to_process();
+        assert!(client2.observer.remote_devices()[0].hand_raised);
+
+        client1.client.set_hand_raised(false);
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
+        assert!(!client2.observer.remote_devices()[0].hand_raised);
+
+        client1.disconnect_and_wait_until_ended();
+        client2.disconnect_and_wait_until_ended();
+    }
+
+    #[test]
+    fn reactions() {
+        let client1 = TestClient::new(vec![1], 1, None);
+        client1.connect_join_and_wait_until_joined();
+
+        let client2 = TestClient::new(vec![2], 2, None);
+        client2.connect_join_and_wait_until_joined();
+
+        set_group_and_wait_until_applied(&[&client1, &client2]);
+
+        client1.client.send_reaction("👍".to_string());
+        client1.client.send_reaction("🎉".to_string());
+        // Too long to send
+        client1
+            .client
+            .send_reaction("👍".repeat(MAX_REACTION_VALUE_LEN));
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
+
+        let reaction = |value: &str| Reaction {
+            demux_id: client1.demux_id,
+            value:    value.to_string(),
+        };
+        assert_eq!(
+            vec![reaction("👍"), reaction("🎉")],
+            client2.observer.reactions()
+        );
+
+        // Duplicates and reactions that arrive out of order are ignored.
+        let reaction_proto = |value: &str| protobuf::group_call::device_to_device::Reaction {
+            value: Some(value.to_string()),
+        };
+        client2
+            .client
+            .handle_reaction_received(client1.demux_id, 1_000, reaction_proto("😂"));
+        client2
+            .client
+            .handle_reaction_received(client1.demux_id, 1_000, reaction_proto("😂"));
+        client2
+            .client
+            .handle_reaction_received(client1.demux_id, 999, reaction_proto("😮"));
+        client2
+            .client
+            .handle_reaction_received(client1.demux_id, 1_001, reaction_proto(""));
+        client2
+            .client
+            .handle_reaction_received(client1.demux_id, 1_002, reaction_proto("😮"));
+        client2.wait_for_client_to_process();
+        assert_eq!(
+            vec![
+                reaction("👍"),
+                reaction("🎉"),
+                reaction("😂"),
+                reaction("😮")
+            ],
+            client2.observer.reactions()
+        );
+        // Reactions aren't sent back to the sender.
+        assert_eq!(Vec::<Reaction>::new(), client1.observer.reactions());
+
+        client1.disconnect_and_wait_until_ended();
+        client2.disconnect_and_wait_until_ended();
+    }
+
+    #[test]
+    fn rtp_timestamps_wrap_around() {
+        assert!(is_newer_rtp_timestamp(5, None));
+        assert!(is_newer_rtp_timestamp(6, Some(5)));
+        assert!(!is_newer_rtp_timestamp(5, Some(5)));
+        assert!(!is_newer_rtp_timestamp(4, Some(5)));
+        assert!(is_newer_rtp_timestamp(0, Some(u32::MAX)));
+        assert!(is_newer_rtp_timestamp(10, Some(u32::MAX - 10)));
+        assert!(!is_newer_rtp_timestamp(u32::MAX, Some(0)));
+    }
+
+    #[test]
+    fn chat_messages() {
+        let client1 = TestClient::new(vec![1], 1, None);
+        client1.connect_join_and_wait_until_joined();
+
+        let client2 = TestClient::new(vec![2], 2, None);
+        client2.connect_join_and_wait_until_joined();
+
+        set_group_and_wait_until_applied(&[&client1, &client2]);
+
+        let long_text = "🦀".repeat(chat::MAX_CHUNK_LEN);
+        client1.client.send_message("hello".to_string());
+        client1.client.send_message(long_text.clone());
+        // Too long to send
+        client1
+            .client
+            .send_message("x".repeat(chat::MAX_MESSAGE_LEN + 1));
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
+
+        let message = |message_id: u64, text: &str| ChatMessage {
+            demux_id: client1.demux_id,
+            message_id,
+            text: text.to_string(),
+        };
+        assert_eq!(
+            vec![message(1, "hello"), message(2, &long_text)],
+            client2.observer.chat_messages()
+        );
+
+        // Retransmissions that weren't needed aren't delivered again.
+        client1.client.handle_chat_retransmit_request_received(
+            protobuf::group_call::device_to_device::ChatRetransmitRequest {
+                demux_id:      Some(client1.demux_id),
+                message_id:    Some(2),
+                chunk_indexes: vec![],
+            },
+        );
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
+        assert_eq!(2, client2.observer.chat_messages().len());
+        assert!(client1.observer.chat_messages().is_empty());
+
+        client1.disconnect_and_wait_until_ended();
+        client2.disconnect_and_wait_until_ended();
+    }
+
+    // Makes the request the sender would send to the target, MAC included.
+    fn control_request_with_mac(
+        sender: &TestClient,
+        target: &TestClient,
+        request_type: protobuf::group_call::device_to_device::control_request::Type,
+        era_id: &str,
+    ) -> protobuf::group_call::device_to_device::ControlRequest {
+        let sender_demux_id = sender.demux_id;
+        let target_id = (target.demux_id, target.user_id.clone());
+        let mut request = protobuf::group_call::device_to_device::ControlRequest {
+            r#type:          Some(request_type.into()),
+            target_demux_id: Some(target.demux_id),
+            era_id:          Some(era_id.to_string()),
+            mac:             None,
+        };
+        let (request_sender, request_receiver) = mpsc::channel();
+        sender.client.actor.send(move |state| {
+            let target_public_key = state.control_public_keys[&target_id];
+            request.mac = Some(
+                compute_control_request_mac(
+                    &state.control_secret,
+                    &target_public_key,
+                    sender_demux_id,
+                    &request,
+                )
+                .to_vec(),
+            );
+            request_sender
+                .send(request)
+                .expect("Send control request with MAC");
+        });
+        request_receiver
+            .recv()
+            .expect("Receive control request with MAC")
+    }
+
+    #[test]
+    fn control_requests() {
         let mut client1 = TestClient::new(vec![1], 1, None);
         client1.connect_join_and_wait_until_joined();
 
         let mut client2 = TestClient::new(vec![2], 2, None);
         client2.connect_join_and_wait_until_joined();
 
-        // Client3 is pretending to have demux ID 1 when sending media keys
-        let mut client3 = TestClient::new(vec![3], 3, Some(1));
-        client3.connect_join_and_wait_until_joined();
-
-        set_group_and_wait_until_applied(&[&client1, &client2, &client3]);
+        let peek_info = PeekInfo {
+            creator: Some(client1.user_id.clone()),
+            era_id: Some("era".to_string()),
+            ..PeekInfo::default()
+        };
+        client1.default_peek_info = peek_info.clone();
+        client2.default_peek_info = peek_info;
+        set_group_and_wait_until_applied(&[&client1, &client2]);
 
-        let is_audio = true;
-        let plaintext = &b"Fake Audio"[..];
-        let ciphertext1 = client1.encrypt_media(is_audio, plaintext).unwrap();
-        let ciphertext3 = client3.encrypt_media(is_audio, plaintext).unwrap();
-        // The forger doesn't mess anything up for the others
+        client1
+            .client
+            .send_control_request(client2.demux_id, ControlRequest::Mute);
+        client1
+            .client
+            .send_control_request(client2.demux_id, ControlRequest::LowerHand);
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
         assert_eq!(
-            plaintext,
+            vec![
+                (client1.demux_id, ControlRequest::Mute),
+                (client1.demux_id, ControlRequest::LowerHand)
+            ],
+            client2.observer.control_requests()
+        );
+
+        // Only the creator can send requests.
+        client2
+            .client
+            .send_control_request(client1.demux_id, ControlRequest::Mute);
+        client2.wait_for_client_to_process();
+        client1.wait_for_client_to_process();
+        assert!(client1.observer.control_requests().is_empty());
+
+        // And received requests from others are ignored too.
+        client1.client.handle_control_request_received(
+            client2.demux_id,
+            1000,
+            protobuf::group_call::device_to_device::ControlRequest {
+                r#type:          Some(
+                    protobuf::group_call::device_to_device::control_request::Type::Remove.into(),
+                ),
+                target_demux_id: Some(client1.demux_id),
+                era_id:          Some("era".to_string()),
+                mac:             None,
+            },
+        );
+        client1.wait_for_client_to_process();
+        assert!(client1.observer.control_requests().is_empty());
+
+        // Requests without a valid MAC from the creator's device are ignored, even though
+        // they arrive with the creator's demux ID.
+        let unsigned = protobuf::group_call::device_to_device::ControlRequest {
+            r#type:          Some(
+                protobuf::group_call::device_to_device::control_request::Type::Remove.into(),
+            ),
+            target_demux_id: Some(client2.demux_id),
+            era_id:          Some("era".to_string()),
+            mac:             None,
+        };
+        let mut tampered = control_request_with_mac(
+            &client1,
+            &client2,
+            protobuf::group_call::device_to_device::control_request::Type::Mute,
+            "era",
+        );
+        tampered.r#type =
+            Some(protobuf::group_call::device_to_device::control_request::Type::Remove.into());
+        let mut wrong_length = tampered.clone();
+        wrong_length.mac.as_mut().unwrap().pop();
+        for request in vec![unsigned, tampered, wrong_length] {
             client2
-                .decrypt_media(client1.demux_id, is_audio, &ciphertext1)
-                .unwrap()
+                .client
+                .handle_control_request_received(client1.demux_id, 1000, request);
+        }
+        client2.wait_for_client_to_process();
+        assert_eq!(2, client2.observer.control_requests().len());
+
+        // Requests from an earlier era or that were already received are ignored.
+        for (timestamp, era_id) in &[(1000, "old era"), (0, "era")] {
+            client2.client.handle_control_request_received(
+                client1.demux_id,
+                *timestamp,
+                control_request_with_mac(
+                    &client1,
+                    &client2,
+                    protobuf::group_call::device_to_device::control_request::Type::Remove,
+                    era_id,
+                ),
+            );
+        }
+        client2.wait_for_client_to_process();
+        assert_eq!(2, client2.observer.control_requests().len());
+
+        // Removal is also requested from the SFU in case the device doesn't honor it.
+        client1
+            .client
+            .send_control_request(client2.demux_id, ControlRequest::Remove);
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
+        assert_eq!(
+            Some(&(client1.demux_id, ControlRequest::Remove)),
+            client2.observer.control_requests().last()
         );
-        // And you can't decrypt from the forger.
-        assert!(client2
-            .decrypt_media(client3.demux_id, is_audio, &ciphertext3)
-            .is_err());
+        assert_eq!(
+            vec![demux_id_to_long_device_id(client2.demux_id)],
+            client1.sfu_client.removed_devices()
+        );
+        assert!(client2.sfu_client.removed_devices().is_empty());
 
         client1.disconnect_and_wait_until_ended();
         client2.disconnect_and_wait_until_ended();
-        client3.disconnect_and_wait_until_ended();
     }
 
     #[test]
-    fn remote_heartbeat_state() {
+    fn audio_levels() {
         let client1 = TestClient::new(vec![1], 1, None);
         client1.connect_join_and_wait_until_joined();
 
@@ -4101,64 +6953,65 @@ mod tests {
 
         set_group_and_wait_until_applied(&[&client1, &client2]);
 
-        let remote_devices2 = client2.observer.remote_devices();
-        assert_eq!(1, remote_devices2.len());
-        assert_eq!(client1.demux_id, remote_devices2[0].demux_id);
-        assert_eq!(None, remote_devices2[0].heartbeat_state.audio_muted);
-        assert_eq!(None, remote_devices2[0].heartbeat_state.video_muted);
-        assert_eq!(None, remote_devices2[0].heartbeat_state.presenting);
-        assert_eq!(None, remote_devices2[0].heartbeat_state.sharing_screen);
-
-        client1.client.set_outgoing_audio_muted(true);
-        client1.wait_for_client_to_process();
-        client2.wait_for_client_to_process();
-
-        let remote_devices2 = client2.observer.remote_devices();
-        assert_eq!(1, remote_devices2.len());
-        assert_eq!(client1.demux_id, remote_devices2[0].demux_id);
-        assert_eq!(Some(true), remote_devices2[0].heartbeat_state.audio_muted);
-        assert_eq!(None, remote_devices2[0].heartbeat_state.video_muted);
-        assert_eq!(None, remote_devices2[0].heartbeat_state.presenting);
-        assert_eq!(None, remote_devices2[0].heartbeat_state.sharing_screen);
-
-        client1.client.set_outgoing_video_muted(false);
-        client1.wait_for_client_to_process();
-        client2.wait_for_client_to_process();
-
-        let remote_devices2 = client2.observer.remote_devices();
-        assert_eq!(1, remote_devices2.len());
-        assert_eq!(client1.demux_id, remote_devices2[0].demux_id);
-        assert_eq!(Some(true), remote_devices2[0].heartbeat_state.audio_muted);
-        assert_eq!(Some(false), remote_devices2[0].heartbeat_state.video_muted);
-        assert_eq!(None, remote_devices2[0].heartbeat_state.presenting);
-        assert_eq!(None, remote_devices2[0].heartbeat_state.sharing_screen);
-
-        client1.client.set_presenting(true);
-        client1.wait_for_client_to_process();
-        client2.wait_for_client_to_process();
+        let receive_audio_levels = |sent: f64, received: f64| {
+            client1.client.actor.send(move |state| {
+                Client::handle_audio_levels(
+                    state,
+                    AudioLevels {
+                        sent,
+                        // The second one isn't a remote device.
+                        received: vec![(2, received), (3, 1.0)],
+                    },
+                );
+            });
+            client1.wait_for_client_to_process();
+        };
+        let received_level = |level: AudioLevel| {
+            vec![ReceivedAudioLevel {
+                demux_id: 2,
+                level,
+            }]
+        };
 
-        let remote_devices2 = client2.observer.remote_devices();
-        assert_eq!(1, remote_devices2.len());
-        assert_eq!(client1.demux_id, remote_devices2[0].demux_id);
-        assert_eq!(Some(true), remote_devices2[0].heartbeat_state.audio_muted);
-        assert_eq!(Some(false), remote_devices2[0].heartbeat_state.video_muted);
-        assert_eq!(Some(true), remote_devices2[0].heartbeat_state.presenting);
-        assert_eq!(None, remote_devices2[0].heartbeat_state.sharing_screen);
+        // Nothing is passed on until enabled.
+        receive_audio_levels(1.0, 0.5);
+        assert!(client1.observer.audio_levels().is_empty());
 
-        client1.client.set_sharing_screen(true);
-        client1.wait_for_client_to_process();
-        client2.wait_for_client_to_process();
+        client1.client.set_audio_levels_config(Some(AudioLevelsConfig {
+            interval:  Duration::from_secs(60),
+            smoothing: 0.5,
+        }));
+        receive_audio_levels(1.0, 0.5);
+        receive_audio_levels(1.0, 0.5);
+        assert_eq!(
+            vec![
+                (16384, received_level(8192)),
+                (24575, received_level(12288))
+            ],
+            client1.observer.audio_levels()
+        );
 
-        let remote_devices2 = client2.observer.remote_devices();
-        assert_eq!(1, remote_devices2.len());
-        assert_eq!(client1.demux_id, remote_devices2[0].demux_id);
-        assert_eq!(Some(true), remote_devices2[0].heartbeat_state.audio_muted);
-        assert_eq!(Some(false), remote_devices2[0].heartbeat_state.video_muted);
-        assert_eq!(Some(true), remote_devices2[0].heartbeat_state.presenting);
+        // Invalid smoothing is ignored.
+        client1.client.set_audio_levels_config(Some(AudioLevelsConfig {
+            interval:  Duration::from_secs(60),
+            smoothing: 1.0,
+        }));
+        client1.client.set_audio_levels_config(Some(AudioLevelsConfig {
+            interval:  Duration::from_secs(60),
+            smoothing: 0.0,
+        }));
+        receive_audio_levels(0.0, 1.0);
         assert_eq!(
-            Some(true),
-            remote_devices2[0].heartbeat_state.sharing_screen
+            Some(&(0, received_level(MAX_AUDIO_LEVEL))),
+            client1.observer.audio_levels().last()
         );
+
+        client1.client.set_audio_levels_config(None);
+        receive_audio_levels(1.0, 1.0);
+        assert_eq!(3, client1.observer.audio_levels().len());
+
+        client1.disconnect_and_wait_until_ended();
+        client2.disconnect_and_wait_until_ended();
     }
 
     fn hash_set<T: std::hash::Hash + Eq + Clone>(vals: impl IntoIterator<Item = T>) -> HashSet<T> {
@@ -4173,7 +7026,7 @@ mod tests {
         assert!(client.observer.remote_devices().is_empty());
 
         let peek_info = PeekInfo {
-            devices:      vec![
+            devices:         vec![
                 PeekDeviceInfo {
                     demux_id:        2,
                     user_id:         Some(b"2".to_vec()),
@@ -4187,10 +7040,11 @@ mod tests {
                     long_device_id:  demux_id_to_long_device_id(3),
                 },
             ],
-            creator:      None,
-            era_id:       None,
-            max_devices:  None,
-            device_count: 3,
+            creator:         None,
+            era_id:          None,
+            max_devices:     None,
+            pending_devices: vec![],
+            device_count:    3,
         };
         client.client.set_peek_info(Ok(peek_info));
         client.wait_for_client_to_process();
@@ -4432,14 +7286,17 @@ mod tests {
                         VideoRequestProto {
                             short_device_id: Some(demux_id_to_short_device_id(2)),
                             height:          Some(1080),
+                            priority:        None,
                         },
                         VideoRequestProto {
                             short_device_id: Some(demux_id_to_short_device_id(3)),
                             height:          Some(80),
+                            priority:        None,
                         },
                         VideoRequestProto {
                             short_device_id: Some(demux_id_to_short_device_id(4)),
                             height:          Some(0),
+                            priority:        None,
                         },
                     ],
                     max_kbps:  None,
@@ -4493,14 +7350,17 @@ mod tests {
                         VideoRequestProto {
                             short_device_id: Some(demux_id_to_short_device_id(2)),
                             height:          Some(1080),
+                            priority:        None,
                         },
                         VideoRequestProto {
                             short_device_id: Some(demux_id_to_short_device_id(3)),
                             height:          Some(80),
+                            priority:        None,
                         },
                         VideoRequestProto {
                             short_device_id: Some(demux_id_to_short_device_id(4)),
                             height:          Some(0),
+                            priority:        None,
                         },
                     ],
                     max_kbps:  Some(1),
@@ -4522,14 +7382,17 @@ mod tests {
                         VideoRequestProto {
                             short_device_id: Some(demux_id_to_short_device_id(2)),
                             height:          Some(1080),
+                            priority:        None,
                         },
                         VideoRequestProto {
                             short_device_id: Some(demux_id_to_short_device_id(3)),
                             height:          Some(80),
+                            priority:        None,
                         },
                         VideoRequestProto {
                             short_device_id: Some(demux_id_to_short_device_id(4)),
                             height:          Some(0),
+                            priority:        None,
                         },
                     ],
                     max_kbps:  Some(500),
@@ -4551,14 +7414,17 @@ mod tests {
                         VideoRequestProto {
                             short_device_id: Some(demux_id_to_short_device_id(2)),
                             height:          Some(1080),
+                            priority:        None,
                         },
                         VideoRequestProto {
                             short_device_id: Some(demux_id_to_short_device_id(3)),
                             height:          Some(80),
+                            priority:        None,
                         },
                         VideoRequestProto {
                             short_device_id: Some(demux_id_to_short_device_id(4)),
                             height:          Some(0),
+                            priority:        None,
                         },
                     ],
                     max_kbps:  Some(20_000_000),
@@ -4670,52 +7536,134 @@ mod tests {
         let client1 = TestClient::new(vec![1], 1, None);
         client1.client.connect();
         client1.client.set_peek_info(Ok(PeekInfo {
-            devices:      vec![PeekDeviceInfo {
+            devices:         vec![PeekDeviceInfo {
                 demux_id:        2,
                 user_id:         None,
                 short_device_id: demux_id_to_short_device_id(2),
                 long_device_id:  demux_id_to_long_device_id(2),
             }],
-            device_count: 1,
-            max_devices:  Some(1),
-            creator:      None,
-            era_id:       None,
+            pending_devices: vec![],
+            device_count:    1,
+            max_devices:     Some(1),
+            creator:         None,
+            era_id:          None,
         }));
         client1.client.join();
         assert_eq!(EndReason::HasMaxDevices, client1.observer.ended.wait());
 
         let client1 = TestClient::new(vec![1], 1, None);
         client1.client.set_peek_info(Ok(PeekInfo {
-            devices:      vec![PeekDeviceInfo {
+            devices:         vec![PeekDeviceInfo {
                 demux_id:        2,
                 user_id:         None,
                 short_device_id: demux_id_to_short_device_id(2),
                 long_device_id:  demux_id_to_long_device_id(2),
             }],
-            device_count: 1,
-            max_devices:  Some(2),
-            creator:      None,
-            era_id:       None,
+            pending_devices: vec![],
+            device_count:    1,
+            max_devices:     Some(2),
+            creator:         None,
+            era_id:          None,
         }));
         client1.connect_join_and_wait_until_joined();
         client1.disconnect_and_wait_until_ended();
     }
 
+    #[test]
+    fn full_call_wait_for_slot() {
+        let full_peek_info = PeekInfo {
+            devices:         vec![PeekDeviceInfo {
+                demux_id:        2,
+                user_id:         None,
+                short_device_id: demux_id_to_short_device_id(2),
+                long_device_id:  demux_id_to_long_device_id(2),
+            }],
+            pending_devices: vec![],
+            device_count:    1,
+            max_devices:     Some(1),
+            creator:         None,
+            era_id:          None,
+        };
+        assert!(full_peek_info.is_full());
+        let open_peek_info = PeekInfo {
+            devices: vec![],
+            device_count: 0,
+            ..full_peek_info.clone()
+        };
+        assert!(!open_peek_info.is_full());
+
+        let client1 = TestClient::new(vec![1], 1, None);
+        client1.client.set_wait_for_slot_when_full(true);
+        client1.client.connect();
+        client1.client.set_peek_info(Ok(full_peek_info.clone()));
+        client1.client.join();
+        client1.observer.waiting_for_slot.wait();
+
+        client1.client.set_peek_info(Ok(full_peek_info.clone()));
+        assert_eq!(JoinState::WaitingForSlot(None), client1.join_state());
+
+        // Joins once the other device leaves.
+        client1.client.set_peek_info(Ok(open_peek_info));
+        client1.observer.joined.wait();
+        client1.disconnect_and_wait_until_ended();
+
+        // Leaving stops waiting.
+        let client1 = TestClient::new(vec![1], 1, None);
+        client1.client.set_wait_for_slot_when_full(true);
+        client1.client.connect();
+        client1.client.set_peek_info(Ok(full_peek_info.clone()));
+        client1.client.join();
+        client1.observer.waiting_for_slot.wait();
+        client1.client.leave();
+        assert_eq!(JoinState::NotJoined(None), client1.join_state());
+        client1.disconnect_and_wait_until_ended();
+
+        // So does no longer wanting to wait, which ends the client.
+        let client1 = TestClient::new(vec![1], 1, None);
+        client1.client.set_wait_for_slot_when_full(true);
+        client1.client.connect();
+        client1.client.set_peek_info(Ok(full_peek_info));
+        client1.client.join();
+        client1.observer.waiting_for_slot.wait();
+        client1.client.set_wait_for_slot_when_full(false);
+        assert_eq!(EndReason::HasMaxDevices, client1.observer.ended.wait());
+    }
+
+    #[test]
+    fn take_over_busy() {
+        let client1 = TestClient::new(vec![1], 1, None);
+        *client1.busy.lock().unwrap() = true;
+        client1.client.connect();
+        client1.client.take_over_busy();
+        client1.client.join();
+        client1.observer.joined.wait();
+        assert!(*client1.busy.lock().unwrap());
+        client1.client.leave();
+        assert_eq!(JoinState::NotJoined(None), client1.join_state());
+        assert!(!*client1.busy.lock().unwrap());
+
+        // Only the next join takes over.
+        *client1.busy.lock().unwrap() = true;
+        client1.client.join();
+        assert_eq!(EndReason::CallManagerIsBusy, client1.observer.ended.wait());
+    }
+
     #[test]
     #[ignore] // Because it's too slow
     fn membership_proof_requests() {
         let client1 = TestClient::new(vec![1], 1, None);
         client1.client.set_peek_info(Ok(PeekInfo {
-            devices:      vec![PeekDeviceInfo {
+            devices:         vec![PeekDeviceInfo {
                 demux_id:        2,
                 user_id:         None,
                 short_device_id: demux_id_to_short_device_id(2),
                 long_device_id:  demux_id_to_long_device_id(2),
             }],
-            device_count: 1,
-            max_devices:  Some(2),
-            creator:      None,
-            era_id:       None,
+            pending_devices: vec![],
+            device_count:    1,
+            max_devices:     Some(2),
+            creator:         None,
+            era_id:          None,
         }));
         assert_eq!(0, client1.observer.request_membership_proof_invocation_count());
 
@@ -4760,53 +7708,238 @@ mod tests {
         assert_eq!(vec![4, 3, 2], client1.speakers());
         assert_eq!(0, client1.observer.handle_remote_devices_changed_invocation_count());
 
-        // Changed back
-        std::thread::sleep(std::time::Duration::from_millis(1));
-        client1.receive_speaker(3, 3);
-        assert_eq!(vec![3, 4, 2], client1.speakers());
-        assert_eq!(1, client1.observer.handle_remote_devices_changed_invocation_count());
+        // Changed back
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        client1.receive_speaker(3, 3);
+        assert_eq!(vec![3, 4, 2], client1.speakers());
+        assert_eq!(1, client1.observer.handle_remote_devices_changed_invocation_count());
+
+        // Ignore unknown demux ID
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        client1.receive_speaker(4, 5);
+        assert_eq!(vec![3, 4, 2], client1.speakers());
+        assert_eq!(0, client1.observer.handle_remote_devices_changed_invocation_count());
+
+        // Didn't change
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        client1.receive_speaker(6, 3);
+        assert_eq!(vec![3, 4, 2], client1.speakers());
+        assert_eq!(0, client1.observer.handle_remote_devices_changed_invocation_count());
+
+        // Ignore old messages
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        client1.receive_speaker(5, 4);
+        assert_eq!(vec![3, 4, 2], client1.speakers());
+        assert_eq!(0, client1.observer.handle_remote_devices_changed_invocation_count());
+
+        // Ignore when the local device is the current speaker
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        client1.receive_speaker(7, 1);
+        assert_eq!(vec![3, 4, 2], client1.speakers());
+        assert_eq!(0, client1.observer.handle_remote_devices_changed_invocation_count());
+
+        // Finally give 2 a chance
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        client1.receive_speaker(8, 2);
+        assert_eq!(vec![2, 3, 4], client1.speakers());
+        assert_eq!(1, client1.observer.handle_remote_devices_changed_invocation_count());
+
+        // Swap only the top two; leave the third alone
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        client1.receive_speaker(9, 3);
+        assert_eq!(vec![3, 2, 4], client1.speakers());
+        assert_eq!(1, client1.observer.handle_remote_devices_changed_invocation_count());
+
+        // Unchanged
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        client1.receive_speaker(10, 3);
+        assert_eq!(vec![3, 2, 4], client1.speakers());
+        assert_eq!(0, client1.observer.handle_remote_devices_changed_invocation_count());
+
+        client1.disconnect_and_wait_until_ended();
+    }
+
+    #[test]
+    fn pinned_video_requests() {
+        use protobuf::group_call::{
+            device_to_sfu::{
+                video_request_message::VideoRequest as VideoRequestProto,
+                VideoRequestMessage,
+            },
+            DeviceToSfu,
+        };
+
+        let mut client1 = TestClient::new(vec![1], 1, None);
+        let client2 = TestClient::new(vec![2], 2, None);
+        let client3 = TestClient::new(vec![3], 3, None);
+        let client4 = TestClient::new(vec![4], 4, None);
+
+        let (sender, receiver) = mpsc::channel();
+        client1.sfu_rtp_packet_sender = Some(sender);
+        client1.connect_join_and_wait_until_joined();
+        client1.set_remotes_and_wait_until_applied(&[&client2, &client3, &client4]);
+
+        let requests = [2, 3, 4]
+            .iter()
+            .map(|demux_id| VideoRequest {
+                demux_id:  *demux_id,
+                width:     640,
+                height:    360,
+                framerate: None,
+            })
+            .collect();
+        client1.client.request_video(requests);
+
+        // Requests are only sent on demand once per tick, so wait for the one with the pins.
+        let wait_for_priorities = |priorities: [Option<u32>; 3]| {
+            let expected = DeviceToSfu {
+                video_request: Some(VideoRequestMessage {
+                    requests: [2, 3, 4]
+                        .iter()
+                        .zip(priorities.iter())
+                        .map(|(demux_id, priority)| VideoRequestProto {
+                            short_device_id: Some(demux_id_to_short_device_id(*demux_id)),
+                            height:          Some(360),
+                            priority:        *priority,
+                        })
+                        .collect(),
+                    max_kbps: None,
+                }),
+                ..DeviceToSfu::default()
+            };
+            let deadline = Instant::now() + Duration::from_secs(3);
+            loop {
+                let (_header, payload) = receiver
+                    .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                    .expect("Get RTP packet to SFU");
+                if DeviceToSfu::decode(&payload[..]).unwrap() == expected {
+                    break;
+                }
+            }
+        };
+
+        wait_for_priorities([None, None, None]);
+        client1.client.set_pinned(3, Some(PinKind::Pinned));
+        client1.client.set_pinned(4, Some(PinKind::Spotlight));
+        wait_for_priorities([None, Some(1), Some(2)]);
+
+        // There's only one spotlight.
+        client1.client.set_pinned(3, Some(PinKind::Spotlight));
+        wait_for_priorities([None, Some(2), None]);
+
+        // The UI is told when a pinned video isn't being forwarded, and when it is again.
+        client1
+            .client
+            .handle_forwarding_video_received(vec![2, 4].into_iter().collect());
+        client1.wait_for_client_to_process();
+        assert_eq!(vec![vec![3]], client1.observer.pinned_videos_not_forwarded());
+        client1
+            .client
+            .handle_forwarding_video_received(vec![2, 3].into_iter().collect());
+        client1.wait_for_client_to_process();
+        assert_eq!(
+            vec![vec![3], vec![]],
+            client1.observer.pinned_videos_not_forwarded()
+        );
+
+        // Devices that leave are unpinned.
+        client1.set_remotes_and_wait_until_applied(&[&client2, &client4]);
+        client1.client.set_pinned(4, Some(PinKind::Pinned));
+        client1.wait_for_client_to_process();
+        client1.set_remotes_and_wait_until_applied(&[&client2, &client3, &client4]);
+        wait_for_priorities([None, None, Some(1)]);
 
-        // Ignore unknown demux ID
-        std::thread::sleep(std::time::Duration::from_millis(1));
-        client1.receive_speaker(4, 5);
-        assert_eq!(vec![3, 4, 2], client1.speakers());
-        assert_eq!(0, client1.observer.handle_remote_devices_changed_invocation_count());
+        client1.disconnect_and_wait_until_ended();
+    }
 
-        // Didn't change
-        std::thread::sleep(std::time::Duration::from_millis(1));
-        client1.receive_speaker(6, 3);
-        assert_eq!(vec![3, 4, 2], client1.speakers());
-        assert_eq!(0, client1.observer.handle_remote_devices_changed_invocation_count());
+    #[test]
+    fn layout() {
+        let client1 = TestClient::new(vec![1], 1, None);
+        let client2 = TestClient::new(vec![2], 2, None);
+        let client3 = TestClient::new(vec![3], 3, None);
+        let client4 = TestClient::new(vec![4], 4, None);
+        client1.connect_join_and_wait_until_joined();
 
-        // Ignore old messages
-        std::thread::sleep(std::time::Duration::from_millis(1));
-        client1.receive_speaker(5, 4);
-        assert_eq!(vec![3, 4, 2], client1.speakers());
-        assert_eq!(0, client1.observer.handle_remote_devices_changed_invocation_count());
+        let config = LayoutConfig {
+            viewport_width:   640,
+            viewport_height:  360,
+            tiles_per_page:   2,
+            page_index:       0,
+            pinned_demux_ids: vec![],
+        };
+        client1.client.set_layout_config(Some(config.clone()));
+        client1.set_remotes_and_wait_until_applied(&[&client3, &client4]);
+        let layout = client1.observer.layouts().pop().unwrap();
+        assert_eq!(vec![3, 4], layout.demux_ids);
+        assert_eq!(&[3, 4], layout.page());
+        assert_eq!((2, 1), (layout.columns, layout.rows));
+        assert_eq!((320, 360), (layout.tile_width, layout.tile_height));
 
-        // Ignore when the local device is the current speaker
+        // New people go at the end, off the first page.
         std::thread::sleep(std::time::Duration::from_millis(1));
-        client1.receive_speaker(7, 1);
-        assert_eq!(vec![3, 4, 2], client1.speakers());
-        assert_eq!(0, client1.observer.handle_remote_devices_changed_invocation_count());
+        client1.set_remotes_and_wait_until_applied(&[&client2, &client4, &client3]);
+        let layout = client1.observer.layouts().pop().unwrap();
+        assert_eq!(vec![3, 4, 2], layout.demux_ids);
+        assert_eq!(2, layout.page_count);
+        assert_eq!(
+            vec![
+                VideoRequest {
+                    demux_id:  3,
+                    width:     320,
+                    height:    360,
+                    framerate: None,
+                },
+                VideoRequest {
+                    demux_id:  4,
+                    width:     320,
+                    height:    360,
+                    framerate: None,
+                },
+                VideoRequest {
+                    demux_id:  2,
+                    width:     0,
+                    height:    0,
+                    framerate: None,
+                },
+            ],
+            layout.video_requests
+        );
 
-        // Finally give 2 a chance
+        // A speaker off the first page swaps with the least recent speaker on it.
         std::thread::sleep(std::time::Duration::from_millis(1));
-        client1.receive_speaker(8, 2);
-        assert_eq!(vec![2, 3, 4], client1.speakers());
-        assert_eq!(1, client1.observer.handle_remote_devices_changed_invocation_count());
-
-        // Swap only the top two; leave the third alone
+        client1.receive_speaker(1, 2);
+        assert_eq!(vec![3, 2, 4], client1.observer.layouts().pop().unwrap().demux_ids);
         std::thread::sleep(std::time::Duration::from_millis(1));
-        client1.receive_speaker(9, 3);
-        assert_eq!(vec![3, 2, 4], client1.speakers());
-        assert_eq!(1, client1.observer.handle_remote_devices_changed_invocation_count());
+        client1.receive_speaker(2, 4);
+        assert_eq!(vec![4, 2, 3], client1.observer.layouts().pop().unwrap().demux_ids);
 
-        // Unchanged
+        // Speakers already on the first page don't move anyone.
+        let layout_count = client1.observer.layouts().len();
         std::thread::sleep(std::time::Duration::from_millis(1));
-        client1.receive_speaker(10, 3);
-        assert_eq!(vec![3, 2, 4], client1.speakers());
-        assert_eq!(0, client1.observer.handle_remote_devices_changed_invocation_count());
+        client1.receive_speaker(3, 2);
+        assert_eq!(layout_count, client1.observer.layouts().len());
+
+        // Pinned devices go first, and the video requests follow the page.
+        client1.client.set_layout_config(Some(LayoutConfig {
+            page_index: 1,
+            pinned_demux_ids: vec![3],
+            ..config
+        }));
+        client1.wait_for_client_to_process();
+        let layout = client1.observer.layouts().pop().unwrap();
+        assert_eq!(vec![3, 2, 4], layout.demux_ids);
+        assert_eq!(&[4], layout.page());
+
+        let (requests_sender, requests_receiver) = std::sync::mpsc::channel();
+        client1.client.actor.send(move |state| {
+            requests_sender
+                .send(state.video_requests.clone())
+                .expect("Send video requests to test");
+        });
+        assert_eq!(
+            Some(layout.video_requests),
+            requests_receiver.recv().unwrap()
+        );
 
         client1.disconnect_and_wait_until_ended();
     }
@@ -4865,11 +7998,12 @@ mod tests {
             })
             .collect();
         client1.client.set_peek_info(Ok(PeekInfo {
-            devices:      vec![],
-            device_count: 0,
-            max_devices:  None,
-            creator:      None,
-            era_id:       None,
+            devices:         vec![],
+            pending_devices: vec![],
+            device_count:    0,
+            max_devices:     None,
+            creator:         None,
+            era_id:          None,
         }));
         client1.wait_for_client_to_process();
         assert_eq!(
@@ -4882,11 +8016,12 @@ mod tests {
         );
 
         client1.client.set_peek_info(Ok(PeekInfo {
-            devices:      (&devices[..1]).to_vec(),
-            device_count: 1,
-            max_devices:  None,
-            creator:      None,
-            era_id:       None,
+            devices:         devices[..1].to_vec(),
+            pending_devices: vec![],
+            device_count:    1,
+            max_devices:     None,
+            creator:         None,
+            era_id:          None,
         }));
         client1.wait_for_client_to_process();
         assert_eq!(
@@ -4899,11 +8034,12 @@ mod tests {
         );
 
         client1.client.set_peek_info(Ok(PeekInfo {
-            devices:      (&devices[..2]).to_vec(),
-            device_count: 1,
-            max_devices:  None,
-            creator:      None,
-            era_id:       None,
+            devices:         devices[..2].to_vec(),
+            pending_devices: vec![],
+            device_count:    1,
+            max_devices:     None,
+            creator:         None,
+            era_id:          None,
         }));
         client1.wait_for_client_to_process();
         assert_eq!(
@@ -4916,11 +8052,12 @@ mod tests {
         );
 
         client1.client.set_peek_info(Ok(PeekInfo {
-            devices:      (&devices[..5]).to_vec(),
-            device_count: 5,
-            max_devices:  None,
-            creator:      None,
-            era_id:       None,
+            devices:         devices[..5].to_vec(),
+            pending_devices: vec![],
+            device_count:    5,
+            max_devices:     None,
+            creator:         None,
+            era_id:          None,
         }));
         client1.wait_for_client_to_process();
         assert_eq!(
@@ -4933,11 +8070,12 @@ mod tests {
         );
 
         client1.client.set_peek_info(Ok(PeekInfo {
-            devices:      (&devices[..20]).to_vec(),
-            device_count: 20,
-            max_devices:  None,
-            creator:      None,
-            era_id:       None,
+            devices:         devices[..20].to_vec(),
+            pending_devices: vec![],
+            device_count:    20,
+            max_devices:     None,
+            creator:         None,
+            era_id:          None,
         }));
         client1.wait_for_client_to_process();
         assert_eq!(
@@ -4972,11 +8110,12 @@ mod tests {
         );
 
         client1.client.set_peek_info(Ok(PeekInfo {
-            devices:      (&devices[..0]).to_vec(),
-            device_count: 0,
-            max_devices:  None,
-            creator:      None,
-            era_id:       None,
+            devices:         devices[..0].to_vec(),
+            pending_devices: vec![],
+            device_count:    0,
+            max_devices:     None,
+            creator:         None,
+            era_id:          None,
         }));
         client1.wait_for_client_to_process();
         assert_eq!(
@@ -5000,11 +8139,12 @@ mod tests {
         );
 
         client1.client.set_peek_info(Ok(PeekInfo {
-            devices:      (&devices[..20]).to_vec(),
-            device_count: 20,
-            max_devices:  None,
-            creator:      None,
-            era_id:       None,
+            devices:         devices[..20].to_vec(),
+            pending_devices: vec![],
+            device_count:    20,
+            max_devices:     None,
+            creator:         None,
+            era_id:          None,
         }));
         client1.wait_for_client_to_process();
         assert_eq!(
@@ -5094,6 +8234,133 @@ mod tests {
         }
     }
 
+    #[test]
+    fn ring_statuses() {
+        let client1 = TestClient::new(vec![1], 1, None);
+        let client2 = TestClient::new(vec![2], 2, None);
+        let members: Vec<GroupMemberInfo> = (1..=4)
+            .map(|i| GroupMemberInfo {
+                user_id:            vec![i],
+                user_id_ciphertext: vec![i],
+            })
+            .collect();
+        client1.client.set_group_members(members);
+        client1.connect_join_and_wait_until_joined();
+
+        client1.client.ring(None);
+        client1.wait_for_client_to_process();
+        let sent_messages = std::mem::take(
+            &mut *client1
+                .observer
+                .sent_group_signaling_messages
+                .lock()
+                .expect("finished processing"),
+        );
+        let group_ring_id = match &sent_messages[..] {
+            [protobuf::signaling::CallMessage {
+                ring_intention:
+                    Some(protobuf::signaling::call_message::RingIntention {
+                        ring_id: Some(ring_id),
+                        ..
+                    }),
+                ..
+            }] => RingId::from(*ring_id),
+            _ => panic!("group messages not as expected; here's what we got: {:?}", sent_messages),
+        };
+        let status = |user_id: u8, ring_id: RingId, status: RingStatus| RecipientRingStatus {
+            user_id: vec![user_id],
+            ring_id,
+            status,
+        };
+        // We don't ring ourselves.
+        assert_eq!(
+            vec![
+                status(2, group_ring_id, RingStatus::Ringing),
+                status(3, group_ring_id, RingStatus::Ringing),
+                status(4, group_ring_id, RingStatus::Ringing),
+            ],
+            client1.observer.ring_statuses()
+        );
+
+        // Responses from members we didn't know about are tracked too,
+        // but responses to other rings aren't.
+        client1
+            .client
+            .on_ring_response_received(vec![3], group_ring_id, RingStatus::Declined);
+        client1
+            .client
+            .on_ring_response_received(vec![5], group_ring_id, RingStatus::Busy);
+        client1
+            .client
+            .on_ring_response_received(vec![4], RingId::from(1234), RingStatus::Declined);
+        client1.set_remotes_and_wait_until_applied(&[&client2]);
+        assert_eq!(
+            vec![
+                status(2, group_ring_id, RingStatus::Accepted),
+                status(3, group_ring_id, RingStatus::Declined),
+                status(4, group_ring_id, RingStatus::Ringing),
+                status(5, group_ring_id, RingStatus::Busy),
+            ],
+            client1.observer.ring_statuses()
+        );
+
+        client1.client.actor.send(|state| {
+            for (_, rung_at) in &mut state.outgoing_rings {
+                *rung_at -= OUTGOING_RING_TIMEOUT;
+            }
+            Client::expire_outgoing_rings(state, Instant::now());
+        });
+        client1.wait_for_client_to_process();
+        assert_eq!(
+            status(4, group_ring_id, RingStatus::Expired),
+            client1.observer.ring_statuses()[2]
+        );
+
+        // Someone who declined isn't rung again, but someone who didn't respond is,
+        // with a new ring ID.
+        client1.client.ring(Some(vec![3]));
+        client1.client.ring(Some(vec![4]));
+        client1.wait_for_client_to_process();
+        let sent_ring_intentions = client1.observer.sent_ring_intentions();
+        let direct_ring_id = match &sent_ring_intentions[..] {
+            [(
+                recipient,
+                protobuf::signaling::CallMessage {
+                    ring_intention:
+                        Some(protobuf::signaling::call_message::RingIntention {
+                            ring_id: Some(ring_id),
+                            ..
+                        }),
+                    ..
+                },
+            )] => {
+                assert_eq!(&vec![4], recipient);
+                RingId::from(*ring_id)
+            }
+            _ => panic!("ring intentions not as expected: {:?}", sent_ring_intentions),
+        };
+        assert_ne!(group_ring_id, direct_ring_id);
+        assert_eq!(
+            status(4, direct_ring_id, RingStatus::Ringing),
+            client1.observer.ring_statuses()[2]
+        );
+
+        // A late response to the group ring doesn't count for the new ring.
+        client1
+            .client
+            .on_ring_response_received(vec![4], group_ring_id, RingStatus::Declined);
+        client1
+            .client
+            .on_ring_response_received(vec![4], direct_ring_id, RingStatus::Busy);
+        client1.wait_for_client_to_process();
+        assert_eq!(
+            status(4, direct_ring_id, RingStatus::Busy),
+            client1.observer.ring_statuses()[2]
+        );
+
+        client1.disconnect_and_wait_until_ended();
+    }
+
     #[test]
     fn group_ring_no_cancel_if_someone_joins() {
         let client1 = TestClient::new(vec![1], 1, None);
@@ -5245,6 +8512,312 @@ mod tests {
             }
         }
     }
+
+    // A Client that talks to a MockSfu through the real SfuClient.
+    struct MockSfuTestClient {
+        user_id:  UserId,
+        observer: FakeObserver,
+        client:   Client,
+    }
+
+    impl MockSfuTestClient {
+        fn new(sfu: &MockSfu, user_id: &[u8], client_id: ClientId) -> Self {
+            let sfu_client =
+                crate::core::sfu_client::SfuClient::new(Box::new(sfu.clone()), sfu.url());
+            let observer = FakeObserver::new(user_id.to_vec());
+            let client = Client::start(
+                b"fake group ID".to_vec(),
+                client_id,
+                Box::new(sfu_client),
+                Box::new(observer.clone()),
+                Arc::new(CallMutex::new(false, "fake_busy")),
+                Arc::new(CallMutex::new(Some(user_id.to_vec()), "fake_self_uuid")),
+                None,
+                AudioTrack::owned(FAKE_AUDIO_TRACK as *const u32),
+                None,
+                None,
+            )
+            .expect("Start Client");
+            Self {
+                user_id: user_id.to_vec(),
+                observer,
+                client,
+            }
+        }
+
+        fn connect_join_and_wait_until_joined(&self, sfu: &MockSfu, members: &[&[u8]]) -> DemuxId {
+            self.connect_and_join(members, None);
+            self.observer.joined.wait();
+            self.attach_to_sfu(sfu)
+        }
+
+        // For when the mock SFU puts the device in the lobby.
+        fn connect_join_and_wait_until_pending(&self, sfu: &MockSfu, members: &[&[u8]]) -> DemuxId {
+            self.connect_and_join(members, None);
+            self.observer.pending.wait();
+            self.attach_to_sfu(sfu)
+        }
+
+        fn connect_resume_and_wait_until_joined(
+            &self,
+            sfu: &MockSfu,
+            members: &[&[u8]],
+            snapshot: Vec<u8>,
+        ) -> DemuxId {
+            self.connect_and_join(members, Some(snapshot));
+            self.observer.joined.wait();
+            self.attach_to_sfu(sfu)
+        }
+
+        // The mock SFU treats the user part of the proof as the user ID ciphertext,
+        // so we use the plain user ID for both.
+        fn connect_and_join(&self, members: &[&[u8]], resume_from: Option<Vec<u8>>) {
+            self.client.connect();
+            let mut proof = self.user_id.clone();
+            proof.extend_from_slice(b":proof");
+            self.client.set_membership_proof(proof);
+            self.client.set_group_members(
+                members
+                    .iter()
+                    .map(|member| GroupMemberInfo {
+                        user_id:            member.to_vec(),
+                        user_id_ciphertext: member.to_vec(),
+                    })
+                    .collect(),
+            );
+            match resume_from {
+                Some(snapshot) => self.client.resume(snapshot),
+                None => self.client.join(),
+            }
+        }
+
+        // Passes RTP between the Client and the mock SFU once it has a demux ID.
+        fn attach_to_sfu(&self, sfu: &MockSfu) -> DemuxId {
+            let local_demux_id: Waitable<DemuxId> = Waitable::default();
+            let local_demux_id_to_set = local_demux_id.clone();
+            self.client.actor.send(move |state| {
+                if let JoinState::Pending(demux_id, _) | JoinState::Joined(demux_id, _) =
+                    state.join_state
+                {
+                    local_demux_id_to_set.set(demux_id);
+                }
+            });
+            let local_demux_id = local_demux_id.wait();
+
+            let sfu_for_sink = sfu.clone();
+            self.client.actor.send(move |state| {
+                state
+                    .peer_connection
+                    .set_rtp_packet_sink(Box::new(move |header, payload| {
+                        sfu_for_sink.receive_rtp(local_demux_id, header, payload.to_vec());
+                    }));
+            });
+            let client = self.client.clone();
+            sfu.set_rtp_receiver(
+                local_demux_id,
+                Box::new(move |header, payload| client.handle_rtp_received(header, payload)),
+            );
+            local_demux_id
+        }
+
+        fn wait_until_remote_demux_ids_are(&self, expected: &[DemuxId]) {
+            let expected: HashSet<DemuxId> = expected.iter().copied().collect();
+            for _ in 0..100 {
+                let actual: HashSet<DemuxId> = self
+                    .observer
+                    .remote_devices()
+                    .iter()
+                    .map(|device| device.demux_id)
+                    .collect();
+                if actual == expected {
+                    return;
+                }
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            panic!("Remote devices never became {:?}", expected);
+        }
+
+        fn wait_until_pending_demux_ids_are(&self, expected: &[DemuxId]) {
+            for _ in 0..100 {
+                if self.observer.peek_state().pending_demux_ids == expected {
+                    return;
+                }
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            panic!("Pending devices never became {:?}", expected);
+        }
+    }
+
+    #[test]
+    fn mock_sfu_full_call() {
+        let stopper = Stopper::new();
+        let sfu = MockSfu::start(
+            &stopper,
+            MockSfuConfig::new("10.0.0.1:10000".parse().unwrap()),
+        )
+        .expect("Start MockSfu");
+        let members: &[&[u8]] = &[b"alice", b"bob"];
+
+        let alice = MockSfuTestClient::new(&sfu, b"alice", 1);
+        let alice_demux_id = alice.connect_join_and_wait_until_joined(&sfu, members);
+        alice.wait_until_remote_demux_ids_are(&[]);
+
+        let bob = MockSfuTestClient::new(&sfu, b"bob", 2);
+        let bob_demux_id = bob.connect_join_and_wait_until_joined(&sfu, members);
+        assert_ne!(alice_demux_id, bob_demux_id);
+
+        // Alice learns about Bob from the SFU telling her someone joined.
+        alice.wait_until_remote_demux_ids_are(&[bob_demux_id]);
+        bob.wait_until_remote_demux_ids_are(&[alice_demux_id]);
+        assert_eq!(Some(b"alice".to_vec()), bob.observer.peek_state().creator);
+        assert_eq!(2, bob.observer.peek_state().device_count);
+
+        bob.client.leave();
+        alice.wait_until_remote_demux_ids_are(&[]);
+        assert_eq!(
+            vec![alice_demux_id],
+            sfu.participants()
+                .iter()
+                .map(|p| p.demux_id)
+                .collect::<Vec<_>>()
+        );
+
+        alice.client.disconnect();
+        alice.observer.ended.wait();
+        bob.client.disconnect();
+        bob.observer.ended.wait();
+        stopper.stop_all_and_join();
+    }
+
+    #[test]
+    fn mock_sfu_creator_removes_device() {
+        let stopper = Stopper::new();
+        let sfu = MockSfu::start(
+            &stopper,
+            MockSfuConfig::new("10.0.0.1:10000".parse().unwrap()),
+        )
+        .expect("Start MockSfu");
+        let members: &[&[u8]] = &[b"alice", b"bob"];
+
+        let alice = MockSfuTestClient::new(&sfu, b"alice", 1);
+        let alice_demux_id = alice.connect_join_and_wait_until_joined(&sfu, members);
+        let bob = MockSfuTestClient::new(&sfu, b"bob", 2);
+        let bob_demux_id = bob.connect_join_and_wait_until_joined(&sfu, members);
+        alice.wait_until_remote_demux_ids_are(&[bob_demux_id]);
+        bob.wait_until_remote_demux_ids_are(&[alice_demux_id]);
+
+        // Bob didn't create the call, so Bob can't remove Alice.
+        bob.client
+            .send_control_request(alice_demux_id, ControlRequest::Remove);
+        alice
+            .client
+            .send_control_request(bob_demux_id, ControlRequest::Remove);
+        alice.wait_until_remote_demux_ids_are(&[]);
+        assert_eq!(
+            vec![alice_demux_id],
+            sfu.participants()
+                .iter()
+                .map(|p| p.demux_id)
+                .collect::<Vec<_>>()
+        );
+
+        alice.client.disconnect();
+        alice.observer.ended.wait();
+        bob.client.disconnect();
+        bob.observer.ended.wait();
+        stopper.stop_all_and_join();
+    }
+
+    #[test]
+    fn mock_sfu_lobby() {
+        let stopper = Stopper::new();
+        let mut config = MockSfuConfig::new("10.0.0.1:10000".parse().unwrap());
+        config.approval_required = true;
+        let sfu = MockSfu::start(&stopper, config).expect("Start MockSfu");
+        let members: &[&[u8]] = &[b"alice", b"bob", b"carol"];
+
+        // The first device doesn't need anyone's approval.
+        let alice = MockSfuTestClient::new(&sfu, b"alice", 1);
+        let alice_demux_id = alice.connect_join_and_wait_until_joined(&sfu, members);
+
+        let bob = MockSfuTestClient::new(&sfu, b"bob", 2);
+        let bob_demux_id = bob.connect_join_and_wait_until_pending(&sfu, members);
+        alice.wait_until_pending_demux_ids_are(&[bob_demux_id]);
+        // Bob can't let Bob in.
+        bob.client.approve_pending_device(bob_demux_id);
+        alice.client.approve_pending_device(bob_demux_id);
+        bob.observer.joined.wait();
+        alice.wait_until_pending_demux_ids_are(&[]);
+        alice.wait_until_remote_demux_ids_are(&[bob_demux_id]);
+        bob.wait_until_remote_demux_ids_are(&[alice_demux_id]);
+
+        // Any joined device can turn someone away.
+        let carol = MockSfuTestClient::new(&sfu, b"carol", 3);
+        let carol_demux_id = carol.connect_join_and_wait_until_pending(&sfu, members);
+        bob.wait_until_pending_demux_ids_are(&[carol_demux_id]);
+        bob.client.deny_pending_device(carol_demux_id);
+        assert_eq!(
+            EndReason::DeniedRequestToJoinCall,
+            carol.observer.ended.wait()
+        );
+        alice.wait_until_pending_demux_ids_are(&[]);
+        assert!(sfu.pending_participants().is_empty());
+        assert_eq!(2, sfu.participants().len());
+
+        alice.client.disconnect();
+        alice.observer.ended.wait();
+        bob.client.disconnect();
+        bob.observer.ended.wait();
+        stopper.stop_all_and_join();
+    }
+
+    #[test]
+    fn mock_sfu_resume() {
+        let stopper = Stopper::new();
+        let sfu = MockSfu::start(
+            &stopper,
+            MockSfuConfig::new("10.0.0.1:10000".parse().unwrap()),
+        )
+        .expect("Start MockSfu");
+        let members: &[&[u8]] = &[b"alice", b"bob"];
+        let resumption_key = [0x16; 32];
+
+        let alice = MockSfuTestClient::new(&sfu, b"alice", 1);
+        alice.client.set_resumption_key(Some(resumption_key));
+        let alice_demux_id = alice.connect_join_and_wait_until_joined(&sfu, members);
+        let bob = MockSfuTestClient::new(&sfu, b"bob", 2);
+        let bob_demux_id = bob.connect_join_and_wait_until_joined(&sfu, members);
+        alice.wait_until_remote_demux_ids_are(&[bob_demux_id]);
+        let snapshot = alice
+            .observer
+            .resumption_snapshot()
+            .expect("Snapshot after joining");
+
+        // Alice's app restarts and gets the same endpoint back.
+        let alice_again = MockSfuTestClient::new(&sfu, b"alice", 3);
+        alice_again.client.set_resumption_key(Some(resumption_key));
+        assert_eq!(
+            alice_demux_id,
+            alice_again.connect_resume_and_wait_until_joined(&sfu, members, snapshot.clone())
+        );
+        alice_again.wait_until_remote_demux_ids_are(&[bob_demux_id]);
+        bob.wait_until_remote_demux_ids_are(&[alice_demux_id]);
+        assert_eq!(2, sfu.participants().len());
+
+        // Bob can't use Alice's snapshot (or key) to take over Alice's endpoint.
+        let bob_again = MockSfuTestClient::new(&sfu, b"bob", 4);
+        bob_again.client.set_resumption_key(Some(resumption_key));
+        let bob_again_demux_id =
+            bob_again.connect_resume_and_wait_until_joined(&sfu, members, snapshot);
+        assert_ne!(alice_demux_id, bob_again_demux_id);
+        assert_eq!(3, sfu.participants().len());
+
+        for client in &[alice_again, bob, bob_again, alice] {
+            client.client.disconnect();
+            client.observer.ended.wait();
+        }
+        stopper.stop_all_and_join();
+    }
 }
 
 #[cfg(test)]
@@ -5352,6 +8925,78 @@ mod remote_devices_tests {
         assert_eq!(vec![1, 2, 3].into_iter().collect::<HashSet<_>>(), remote_devices.demux_id_set());
     }
 
+    #[test]
+    fn layout_order() {
+        let devices: Vec<RemoteDeviceState> =
+            (1..=5).map(|id| remote_device_state(id, None)).collect();
+        let config = LayoutConfig {
+            viewport_width:   1000,
+            viewport_height:  1000,
+            tiles_per_page:   3,
+            page_index:       0,
+            pinned_demux_ids: vec![4, 9],
+        };
+
+        let layout = compute_layout(&config, &devices, &[]);
+        assert_eq!(vec![4, 1, 2, 3, 5], layout.demux_ids);
+        assert_eq!(2, layout.page_count);
+        assert_eq!(&[4, 1, 2], layout.page());
+
+        // The previous order is kept, with new devices at the end.
+        let config = LayoutConfig {
+            pinned_demux_ids: vec![],
+            ..config
+        };
+        let layout = compute_layout(&config, &devices, &[3, 1, 2, 5]);
+        assert_eq!(vec![3, 1, 2, 5, 4], layout.demux_ids);
+
+        // A speaker off the first page replaces the least recent speaker on it.
+        let mut devices = devices;
+        devices[0].speaker_time = Some(time(2)); // demux ID 1
+        devices[2].speaker_time = Some(time(3)); // demux ID 3
+        devices[4].speaker_time = Some(time(4)); // demux ID 5
+        let layout = compute_layout(&config, &devices, &[3, 1, 2, 5, 4]);
+        assert_eq!(vec![3, 1, 5, 2, 4], layout.demux_ids);
+    }
+
+    #[test]
+    fn layout_pages() {
+        let devices: Vec<RemoteDeviceState> =
+            (1..=3).map(|id| remote_device_state(id, None)).collect();
+        let config = LayoutConfig {
+            viewport_width:   640,
+            viewport_height:  360,
+            tiles_per_page:   2,
+            page_index:       5,
+            pinned_demux_ids: vec![],
+        };
+        let layout = compute_layout(&config, &devices, &[]);
+        assert_eq!(1, layout.page_index);
+        assert_eq!(2, layout.page_count);
+        assert_eq!(&[3], layout.page());
+        let requested: Vec<(DemuxId, u16, u16)> = layout
+            .video_requests
+            .iter()
+            .map(|request| (request.demux_id, request.width, request.height))
+            .collect();
+        assert_eq!(vec![(1, 0, 0), (2, 0, 0), (3, 320, 360)], requested);
+
+        let layout = compute_layout(&config, &[], &[]);
+        assert_eq!((0, 1), (layout.page_index, layout.page_count));
+        assert!(layout.page().is_empty());
+        assert!(layout.video_requests.is_empty());
+    }
+
+    #[test]
+    fn layout_grid_dimensions() {
+        assert_eq!((0, 0), grid_dimensions(0, 640, 360));
+        assert_eq!((1, 1), grid_dimensions(1, 640, 360));
+        assert_eq!((2, 1), grid_dimensions(2, 640, 360));
+        assert_eq!((2, 2), grid_dimensions(4, 640, 360));
+        assert_eq!((3, 2), grid_dimensions(5, 640, 360));
+        assert_eq!((1, 3), grid_dimensions(3, 100, 1000));
+    }
+
     fn time(timestamp: u64) -> SystemTime {
         SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp)
     }
@@ -20,6 +20,7 @@ use crate::common::{
 use crate::core::bandwidth_mode::BandwidthMode;
 use crate::core::call::Call;
 use crate::core::connection::{Connection, ConnectionType};
+use crate::core::escalation::EscalationUpdate;
 use crate::core::{group_call, signaling};
 use crate::webrtc::media::{MediaStream, VideoTrack};
 use crate::webrtc::peer_connection_observer::NetworkRoute;
@@ -185,6 +186,15 @@ pub trait Platform: fmt::Debug + fmt::Display + Send + Sized + 'static {
         update: group_call::RingUpdate,
     );
 
+    /// Notify the application of progress moving a direct call to a group
+    /// call (see CallManager::request_escalation).
+    fn handle_escalation_update(
+        &self,
+        call_id: CallId,
+        group_id: group_call::GroupId,
+        update: EscalationUpdate,
+    );
+
     fn handle_peek_response(
         &self,
         request_id: u32,
@@ -225,6 +235,44 @@ pub trait Platform: fmt::Debug + fmt::Display + Send + Sized + 'static {
         _reason: group_call::RemoteDevicesChangedReason,
     );
 
+    fn handle_reaction(&self, client_id: group_call::ClientId, reaction: group_call::Reaction);
+
+    fn handle_message_received(
+        &self,
+        client_id: group_call::ClientId,
+        message: group_call::ChatMessage,
+    );
+
+    fn handle_control_request(
+        &self,
+        client_id: group_call::ClientId,
+        sender_demux_id: group_call::DemuxId,
+        request: group_call::ControlRequest,
+    );
+
+    fn handle_audio_levels(
+        &self,
+        client_id: group_call::ClientId,
+        captured_level: group_call::AudioLevel,
+        received_levels: Vec<group_call::ReceivedAudioLevel>,
+    );
+
+    fn handle_resumption_snapshot(&self, client_id: group_call::ClientId, snapshot: Vec<u8>);
+
+    fn handle_layout_changed(&self, client_id: group_call::ClientId, layout: group_call::Layout);
+
+    fn handle_pinned_videos_not_forwarded(
+        &self,
+        client_id: group_call::ClientId,
+        demux_ids: Vec<group_call::DemuxId>,
+    );
+
+    fn handle_ring_statuses_changed(
+        &self,
+        client_id: group_call::ClientId,
+        statuses: Vec<group_call::RecipientRingStatus>,
+    );
+
     fn handle_incoming_video_track(
         &self,
         client_id: group_call::ClientId,
@@ -242,5 +290,11 @@ pub trait Platform: fmt::Debug + fmt::Display + Send + Sized + 'static {
         device_count: u32,
     );
 
+    fn handle_pending_devices_changed(
+        &self,
+        client_id: group_call::ClientId,
+        pending_devices: &[group_call::PeekDeviceInfo],
+    );
+
     fn handle_ended(&self, client_id: group_call::ClientId, reason: group_call::EndReason);
 }
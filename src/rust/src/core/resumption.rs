@@ -0,0 +1,285 @@
+//
+// Copyright 2021 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Snapshots of what a group call client needs to rejoin a call as the same
+//! device after its process restarts: its endpoint, the media keys and the
+//! control keys.
+//!
+//! Snapshots are encrypted with a key from the app, so the app can store them
+//! wherever it likes.  Whether the previous endpoint can still be used is up to
+//! the SFU; if it can't, the client joins as a new device.
+
+use std::{convert::TryInto, fmt, mem::size_of};
+
+use aes_ctr::cipher::{NewStreamCipher, SyncStreamCipher};
+use aes_ctr::Aes256Ctr;
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac as _, NewMac};
+use prost::Message as _;
+use rand::{CryptoRng, Rng};
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+
+use crate::{
+    common::Result,
+    core::{
+        crypto as frame_crypto,
+        group_call::{DemuxId, GroupId, UserId},
+    },
+    error::RingRtcError,
+    protobuf,
+};
+
+/// Provided by the app, which should keep it somewhere safer than the snapshots.
+pub type Key = [u8; 32];
+
+const VERSION: u8 = 1;
+const SALT_LEN: usize = 32;
+const MAC_LEN: usize = 32;
+const KEYS_INFO_STRING: &[u8] = b"RingRTC Resumption Snapshot Keys";
+
+type HmacSha256 = Hmac<Sha256>;
+type AesKey = [u8; 32];
+type HmacKey = [u8; 32];
+
+/// The bytes of an X25519 key used to authenticate control requests.
+pub type ControlKey = [u8; 32];
+
+/// A remote device as we knew it when the snapshot was taken.
+#[derive(Clone, PartialEq, Eq)]
+pub struct RemoteDevice {
+    pub user_id:            UserId,
+    pub demux_id:           DemuxId,
+    // Unset if the device hadn't sent its media key yet.
+    pub media_key:          Option<(frame_crypto::RatchetCounter, frame_crypto::Secret)>,
+    // Unset if the device hadn't sent its control public key yet.
+    pub control_public_key: Option<ControlKey>,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub group_id:             GroupId,
+    pub demux_id:             DemuxId,
+    pub long_device_id:       String,
+    pub send_ratchet_counter: frame_crypto::RatchetCounter,
+    pub send_secret:          frame_crypto::Secret,
+    pub remote_devices:       Vec<RemoteDevice>,
+    pub control_secret:       ControlKey,
+}
+
+// Leaves out the keys so that snapshots can be logged.
+impl fmt::Debug for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Snapshot")
+            .field("demux_id", &self.demux_id)
+            .field("long_device_id", &self.long_device_id)
+            .field("remote_devices", &self.remote_devices.len())
+            .finish()
+    }
+}
+
+impl Snapshot {
+    /// Encrypts the snapshot as VERSION || salt || ciphertext || HMAC, with keys
+    /// derived from the app's key and a random salt.
+    pub fn encrypt<R: Rng + CryptoRng + ?Sized>(&self, key: &Key, rng: &mut R) -> Vec<u8> {
+        let mut plaintext = Vec::new();
+        self.to_proto()
+            .encode(&mut plaintext)
+            .expect("can encode a snapshot into a Vec");
+
+        let mut salt = [0u8; SALT_LEN];
+        rng.fill(&mut salt[..]);
+        let (aes_key, hmac_key) = derive_keys(key, &salt);
+
+        let mut encrypted = Vec::with_capacity(1 + SALT_LEN + plaintext.len() + MAC_LEN);
+        encrypted.push(VERSION);
+        encrypted.extend_from_slice(&salt);
+        let ciphertext_start = encrypted.len();
+        encrypted.extend_from_slice(&plaintext);
+        apply_keystream(&aes_key, &mut encrypted[ciphertext_start..]);
+        let mac = compute_mac(&hmac_key, &encrypted);
+        encrypted.extend_from_slice(&mac);
+        encrypted
+    }
+
+    pub fn decrypt(key: &Key, encrypted: &[u8]) -> Result<Self> {
+        if encrypted.len() < 1 + SALT_LEN + MAC_LEN || encrypted[0] != VERSION {
+            return Err(RingRtcError::InvalidResumptionSnapshot.into());
+        }
+        let (authenticated, mac) = encrypted.split_at(encrypted.len() - MAC_LEN);
+        let (aes_key, hmac_key) = derive_keys(key, &authenticated[1..1 + SALT_LEN]);
+        if !bool::from(compute_mac(&hmac_key, authenticated).ct_eq(mac)) {
+            return Err(RingRtcError::InvalidResumptionSnapshot.into());
+        }
+
+        let mut plaintext = authenticated[1 + SALT_LEN..].to_vec();
+        apply_keystream(&aes_key, &mut plaintext);
+        let proto = protobuf::group_call::ResumptionSnapshot::decode(&plaintext[..])
+            .map_err(|_| RingRtcError::InvalidResumptionSnapshot)?;
+        Self::from_proto(proto).ok_or_else(|| RingRtcError::InvalidResumptionSnapshot.into())
+    }
+
+    fn to_proto(&self) -> protobuf::group_call::ResumptionSnapshot {
+        protobuf::group_call::ResumptionSnapshot {
+            group_id:             Some(self.group_id.clone()),
+            demux_id:             Some(self.demux_id),
+            long_device_id:       Some(self.long_device_id.clone()),
+            send_ratchet_counter: Some(self.send_ratchet_counter.into()),
+            send_secret:          Some(self.send_secret.to_vec()),
+            remote_devices:       self
+                .remote_devices
+                .iter()
+                .map(
+                    |device| protobuf::group_call::resumption_snapshot::RemoteDevice {
+                        user_id:            Some(device.user_id.clone()),
+                        demux_id:           Some(device.demux_id),
+                        ratchet_counter:    device
+                            .media_key
+                            .map(|(ratchet_counter, _)| ratchet_counter.into()),
+                        secret:             device.media_key.map(|(_, secret)| secret.to_vec()),
+                        control_public_key: device.control_public_key.map(|key| key.to_vec()),
+                    },
+                )
+                .collect(),
+            control_secret:       Some(self.control_secret.to_vec()),
+        }
+    }
+
+    fn from_proto(proto: protobuf::group_call::ResumptionSnapshot) -> Option<Self> {
+        let remote_devices = proto
+            .remote_devices
+            .into_iter()
+            .map(|device| {
+                let media_key = match (device.ratchet_counter, device.secret) {
+                    (Some(ratchet_counter), Some(secret)) => {
+                        Some((ratchet_counter.try_into().ok()?, to_secret(&secret)?))
+                    }
+                    _ => None,
+                };
+                let control_public_key = match device.control_public_key {
+                    Some(key) => Some(to_control_key(&key)?),
+                    None => None,
+                };
+                Some(RemoteDevice {
+                    user_id: device.user_id?,
+                    demux_id: device.demux_id?,
+                    media_key,
+                    control_public_key,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self {
+            group_id: proto.group_id?,
+            demux_id: proto.demux_id?,
+            long_device_id: proto.long_device_id?,
+            send_ratchet_counter: proto.send_ratchet_counter?.try_into().ok()?,
+            send_secret: to_secret(&proto.send_secret?)?,
+            remote_devices,
+            control_secret: to_control_key(&proto.control_secret?)?,
+        })
+    }
+}
+
+fn to_secret(bytes: &[u8]) -> Option<frame_crypto::Secret> {
+    if bytes.len() != size_of::<frame_crypto::Secret>() {
+        return None;
+    }
+    let mut secret = frame_crypto::Secret::default();
+    secret.copy_from_slice(bytes);
+    Some(secret)
+}
+
+fn to_control_key(bytes: &[u8]) -> Option<ControlKey> {
+    bytes.try_into().ok()
+}
+
+fn derive_keys(key: &Key, salt: &[u8]) -> (AesKey, HmacKey) {
+    let mut okm = [0u8; size_of::<AesKey>() + size_of::<HmacKey>()];
+    Hkdf::<Sha256>::new(Some(salt), &key[..])
+        .expand(KEYS_INFO_STRING, &mut okm[..])
+        .unwrap_or_else(|_| panic!("HKDF should work with output of length {}", okm.len()));
+    let mut aes_key = AesKey::default();
+    let mut hmac_key = HmacKey::default();
+    aes_key.copy_from_slice(&okm[..size_of::<AesKey>()]);
+    hmac_key.copy_from_slice(&okm[size_of::<AesKey>()..]);
+    (aes_key, hmac_key)
+}
+
+// Each snapshot gets its own keys, so a zero IV is fine.
+fn apply_keystream(aes_key: &AesKey, data: &mut [u8]) {
+    let mut cipher = Aes256Ctr::new(&(*aes_key).into(), &[0u8; 16].into());
+    cipher.apply_keystream(data);
+}
+
+fn compute_mac(hmac_key: &HmacKey, data: &[u8]) -> [u8; MAC_LEN] {
+    let mut hmac =
+        HmacSha256::new_from_slice(&hmac_key[..]).expect("HMAC can take key of any size");
+    hmac.update(data);
+    let mut mac = [0u8; MAC_LEN];
+    mac.copy_from_slice(&hmac.finalize().into_bytes());
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn snapshot(rng: &mut StdRng) -> Snapshot {
+        Snapshot {
+            group_id:             vec![1, 2, 3],
+            demux_id:             0x10,
+            long_device_id:       "abcdef".to_string(),
+            send_ratchet_counter: 3,
+            send_secret:          frame_crypto::random_secret(rng),
+            remote_devices:       vec![
+                RemoteDevice {
+                    user_id:            vec![4, 5],
+                    demux_id:           0x20,
+                    media_key:          Some((7, frame_crypto::random_secret(rng))),
+                    control_public_key: Some(rng.gen()),
+                },
+                RemoteDevice {
+                    user_id:            vec![6],
+                    demux_id:           0x30,
+                    media_key:          None,
+                    control_public_key: None,
+                },
+            ],
+            control_secret:       rng.gen(),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut rng = StdRng::from_seed([0x16; 32]);
+        let key: Key = rng.gen();
+        let snapshot = snapshot(&mut rng);
+
+        let encrypted = snapshot.encrypt(&key, &mut rng);
+        assert_eq!(snapshot, Snapshot::decrypt(&key, &encrypted).unwrap());
+
+        // The same snapshot doesn't encrypt the same way twice.
+        assert_ne!(encrypted, snapshot.encrypt(&key, &mut rng));
+    }
+
+    #[test]
+    fn wrong_key_or_tampering() {
+        let mut rng = StdRng::from_seed([0x61; 32]);
+        let key: Key = rng.gen();
+        let encrypted = snapshot(&mut rng).encrypt(&key, &mut rng);
+
+        let wrong_key: Key = rng.gen();
+        assert!(Snapshot::decrypt(&wrong_key, &encrypted).is_err());
+
+        for i in 0..encrypted.len() {
+            let mut tampered = encrypted.clone();
+            tampered[i] ^= 1;
+            assert!(Snapshot::decrypt(&key, &tampered).is_err());
+        }
+        assert!(Snapshot::decrypt(&key, &encrypted[..encrypted.len() - 1]).is_err());
+        assert!(Snapshot::decrypt(&key, &[]).is_err());
+    }
+}
@@ -0,0 +1,341 @@
+//
+// Copyright 2021 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Storage for received group rings, so that a ring received just before the
+//! app restarts isn't lost, and so that rings replayed from the message queue
+//! after a restart don't ring again.
+//!
+//! Times are wall-clock times rather than Instants so that they still mean
+//! something after a restart.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use prost::Message as _;
+
+use crate::common::Result;
+use crate::core::group_call::{GroupId, GroupIdRef, RingId, UserId};
+use crate::error::RingRtcError;
+use crate::protobuf;
+
+/// Information about a received group ring that hasn't yet been accepted or cancelled.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutstandingGroupRing {
+    pub ring_id:     RingId,
+    pub sender_uuid: UserId,
+    pub received:    SystemTime,
+}
+
+impl OutstandingGroupRing {
+    /// How much longer the ring lasts, or zero if it has expired.
+    /// If the clock has gone backwards, the ring is treated as just received.
+    pub fn time_left(&self, timeout: Duration) -> Duration {
+        let elapsed = self.received.elapsed().unwrap_or_default();
+        timeout.checked_sub(elapsed).unwrap_or_default()
+    }
+
+    pub fn has_expired(&self, timeout: Duration) -> bool {
+        self.time_left(timeout) == Duration::default()
+    }
+}
+
+/// Where the CallManager keeps outstanding group rings (at most one per group)
+/// and the IDs of rings it has already seen.
+///
+/// Changes should be kept even if they can't be saved: the CallManager logs
+/// the error and carries on as if the change was made.
+pub trait RingStore: Send {
+    /// All stored rings, expired or not.
+    fn rings(&self) -> Vec<(GroupId, OutstandingGroupRing)>;
+    fn ring(&self, group_id: GroupIdRef) -> Option<OutstandingGroupRing>;
+    /// Replaces any ring already stored for the group.
+    fn insert_ring(&mut self, group_id: GroupId, ring: OutstandingGroupRing) -> Result<()>;
+    fn remove_ring(&mut self, group_id: GroupIdRef) -> Result<()>;
+
+    fn has_seen(&self, ring_id: RingId) -> bool;
+    fn mark_seen(&mut self, ring_id: RingId, seen: SystemTime) -> Result<()>;
+    /// Forgets ring IDs seen before the given time, so the store doesn't grow forever.
+    fn forget_seen_before(&mut self, time: SystemTime) -> Result<()>;
+}
+
+/// Forgets everything when the process exits.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryRingStore {
+    rings: HashMap<GroupId, OutstandingGroupRing>,
+    seen:  HashMap<RingId, SystemTime>,
+}
+
+impl RingStore for MemoryRingStore {
+    fn rings(&self) -> Vec<(GroupId, OutstandingGroupRing)> {
+        self.rings
+            .iter()
+            .map(|(group_id, ring)| (group_id.clone(), ring.clone()))
+            .collect()
+    }
+
+    fn ring(&self, group_id: GroupIdRef) -> Option<OutstandingGroupRing> {
+        self.rings.get(group_id).cloned()
+    }
+
+    fn insert_ring(&mut self, group_id: GroupId, ring: OutstandingGroupRing) -> Result<()> {
+        self.rings.insert(group_id, ring);
+        Ok(())
+    }
+
+    fn remove_ring(&mut self, group_id: GroupIdRef) -> Result<()> {
+        self.rings.remove(group_id);
+        Ok(())
+    }
+
+    fn has_seen(&self, ring_id: RingId) -> bool {
+        self.seen.contains_key(&ring_id)
+    }
+
+    fn mark_seen(&mut self, ring_id: RingId, seen: SystemTime) -> Result<()> {
+        self.seen.insert(ring_id, seen);
+        Ok(())
+    }
+
+    fn forget_seen_before(&mut self, time: SystemTime) -> Result<()> {
+        self.seen.retain(|_ring_id, seen| *seen >= time);
+        Ok(())
+    }
+}
+
+/// Keeps everything in memory, and rewrites the whole file whenever anything
+/// changes.  There are only ever a handful of entries, so that's cheap.
+#[derive(Debug)]
+pub struct FileRingStore {
+    path:   PathBuf,
+    memory: MemoryRingStore,
+}
+
+impl FileRingStore {
+    /// Loads the store from the file at the given path, or starts empty if
+    /// there's no file there yet.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let memory = match fs::read(&path) {
+            Ok(contents) => Self::decode(&contents)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => MemoryRingStore::default(),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Self { path, memory })
+    }
+
+    fn decode(contents: &[u8]) -> Result<MemoryRingStore> {
+        let proto = protobuf::group_call::RingStoreContents::decode(contents)
+            .map_err(|_| RingRtcError::InvalidRingStore)?;
+        let rings = proto
+            .rings
+            .into_iter()
+            .map(|ring| {
+                Some((
+                    ring.group_id?,
+                    OutstandingGroupRing {
+                        ring_id:     ring.ring_id?.into(),
+                        sender_uuid: ring.sender_uuid?,
+                        received:    from_millis(ring.received_millis?),
+                    },
+                ))
+            })
+            .collect::<Option<_>>();
+        let seen = proto
+            .seen_rings
+            .into_iter()
+            .map(|seen| Some((seen.ring_id?.into(), from_millis(seen.seen_millis?))))
+            .collect::<Option<_>>();
+        match (rings, seen) {
+            (Some(rings), Some(seen)) => Ok(MemoryRingStore { rings, seen }),
+            _ => Err(RingRtcError::InvalidRingStore.into()),
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let proto = protobuf::group_call::RingStoreContents {
+            rings:      self
+                .memory
+                .rings
+                .iter()
+                .map(
+                    |(group_id, ring)| protobuf::group_call::ring_store_contents::Ring {
+                        group_id:        Some(group_id.clone()),
+                        ring_id:         Some(ring.ring_id.into()),
+                        sender_uuid:     Some(ring.sender_uuid.clone()),
+                        received_millis: Some(to_millis(ring.received)),
+                    },
+                )
+                .collect(),
+            seen_rings: self
+                .memory
+                .seen
+                .iter()
+                .map(
+                    |(ring_id, seen)| protobuf::group_call::ring_store_contents::SeenRing {
+                        ring_id:     Some((*ring_id).into()),
+                        seen_millis: Some(to_millis(*seen)),
+                    },
+                )
+                .collect(),
+        };
+        let mut encoded = Vec::new();
+        proto
+            .encode(&mut encoded)
+            .expect("can encode a ring store into a Vec");
+        encoded
+    }
+
+    // Writes to a temporary file first so that a crash mid-write can't leave
+    // a truncated store behind.
+    fn save(&self) -> Result<()> {
+        let mut temp_path = self.path.clone().into_os_string();
+        temp_path.push(".tmp");
+        fs::write(&temp_path, self.encode())?;
+        fs::rename(&temp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+impl RingStore for FileRingStore {
+    fn rings(&self) -> Vec<(GroupId, OutstandingGroupRing)> {
+        self.memory.rings()
+    }
+
+    fn ring(&self, group_id: GroupIdRef) -> Option<OutstandingGroupRing> {
+        self.memory.ring(group_id)
+    }
+
+    fn insert_ring(&mut self, group_id: GroupId, ring: OutstandingGroupRing) -> Result<()> {
+        self.memory.insert_ring(group_id, ring)?;
+        self.save()
+    }
+
+    fn remove_ring(&mut self, group_id: GroupIdRef) -> Result<()> {
+        if self.memory.rings.contains_key(group_id) {
+            self.memory.remove_ring(group_id)?;
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    fn has_seen(&self, ring_id: RingId) -> bool {
+        self.memory.has_seen(ring_id)
+    }
+
+    fn mark_seen(&mut self, ring_id: RingId, seen: SystemTime) -> Result<()> {
+        self.memory.mark_seen(ring_id, seen)?;
+        self.save()
+    }
+
+    fn forget_seen_before(&mut self, time: SystemTime) -> Result<()> {
+        let seen_count = self.memory.seen.len();
+        self.memory.forget_seen_before(time)?;
+        if self.memory.seen.len() != seen_count {
+            self.save()?;
+        }
+        Ok(())
+    }
+}
+
+fn to_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+fn from_millis(millis: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(millis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ring(ring_id: i64, received: SystemTime) -> OutstandingGroupRing {
+        OutstandingGroupRing {
+            ring_id: ring_id.into(),
+            sender_uuid: vec![1, 2, 3],
+            received,
+        }
+    }
+
+    // Avoids sub-millisecond times, which don't survive a round trip through a file.
+    fn now() -> SystemTime {
+        from_millis(to_millis(SystemTime::now()))
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "ringrtc-ring-store-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn expiration() {
+        let timeout = Duration::from_secs(60);
+        let fresh = ring(1, SystemTime::now());
+        assert!(!fresh.has_expired(timeout));
+        assert!(fresh.time_left(timeout) > Duration::from_secs(50));
+
+        let old = ring(1, SystemTime::now() - Duration::from_secs(61));
+        assert!(old.has_expired(timeout));
+        assert_eq!(Duration::default(), old.time_left(timeout));
+
+        let from_the_future = ring(1, SystemTime::now() + Duration::from_secs(600));
+        assert!(!from_the_future.has_expired(timeout));
+    }
+
+    #[test]
+    fn seen_rings() {
+        let mut store = MemoryRingStore::default();
+        let now = now();
+        store
+            .mark_seen(1.into(), now - Duration::from_secs(10))
+            .unwrap();
+        store.mark_seen(2.into(), now).unwrap();
+        assert!(store.has_seen(1.into()));
+        assert!(store.has_seen(2.into()));
+        assert!(!store.has_seen(3.into()));
+
+        store
+            .forget_seen_before(now - Duration::from_secs(5))
+            .unwrap();
+        assert!(!store.has_seen(1.into()));
+        assert!(store.has_seen(2.into()));
+    }
+
+    #[test]
+    fn file_round_trip() {
+        let path = temp_path("round-trip");
+        let now = now();
+        {
+            let mut store = FileRingStore::open(&path).unwrap();
+            assert!(store.rings().is_empty());
+            store.insert_ring(vec![1], ring(10, now)).unwrap();
+            store.insert_ring(vec![2], ring(20, now)).unwrap();
+            store.remove_ring(&[2]).unwrap();
+            store.mark_seen(10.into(), now).unwrap();
+            store.mark_seen(20.into(), now).unwrap();
+        }
+
+        let store = FileRingStore::open(&path).unwrap();
+        assert_eq!(vec![(vec![1], ring(10, now))], store.rings());
+        assert_eq!(Some(ring(10, now)), store.ring(&[1]));
+        assert_eq!(None, store.ring(&[2]));
+        assert!(store.has_seen(10.into()));
+        assert!(store.has_seen(20.into()));
+        assert!(!store.has_seen(30.into()));
+
+        fs::write(&path, b"not a ring store").unwrap();
+        assert!(FileRingStore::open(&path).is_err());
+        let _ = fs::remove_file(&path);
+    }
+}
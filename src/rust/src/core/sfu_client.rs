@@ -14,6 +14,7 @@ use serde_json::json;
 use crate::common::{HttpMethod, HttpResponse, Result};
 use crate::core::group_call::{
     BoxedPeekInfoHandler,
+    ClientStatus,
     DemuxId,
     GroupMemberInfo,
     MembershipProof,
@@ -28,10 +29,13 @@ use crate::error::RingRtcError;
 #[derive(Deserialize, Debug)]
 struct JoinResponse {
     #[serde(rename = "endpointId")]
-    endpoint_id: String,
+    endpoint_id:   String,
     #[serde(rename = "ssrcPrefix")]
-    ssrc_prefix: u32,
-    transport:   SfuTransport,
+    ssrc_prefix:   u32,
+    transport:     SfuTransport,
+    // "ACTIVE" or "PENDING" (waiting in the lobby); absent if the SFU doesn't have a lobby.
+    #[serde(rename = "clientStatus", default)]
+    client_status: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -67,6 +71,9 @@ struct ParticipantsResponse {
 
     participants: Vec<SfuParticipant>,
 
+    #[serde(rename = "pendingClients", default)]
+    pending_clients: Vec<SfuParticipant>,
+
     creator: Option<String>,
 }
 
@@ -94,6 +101,7 @@ pub struct SfuClient {
         String,
         String,
         group_call::DtlsFingerprint,
+        Option<String>,
         group_call::Client,
     )>,
 }
@@ -113,7 +121,9 @@ impl SfuClient {
         }
     }
 
-    fn process_join_response(response: Option<HttpResponse>) -> Result<(SfuInfo, DemuxId, String)> {
+    fn process_join_response(
+        response: Option<HttpResponse>,
+    ) -> Result<(SfuInfo, DemuxId, String, ClientStatus)> {
         let body = match response {
             Some(r) if r.status_code >= 200 && r.status_code <= 300 => r.body,
             Some(r) if r.status_code == RESPONSE_CODE_MAX_PARTICIPANTS_REACHED => {
@@ -174,6 +184,10 @@ impl SfuClient {
         let ice_ufrag = deserialized.transport.ufrag;
         let ice_pwd = deserialized.transport.pwd;
         let endpoint_id = deserialized.endpoint_id;
+        let client_status = match deserialized.client_status.as_deref() {
+            Some("PENDING") => ClientStatus::Pending,
+            _ => ClientStatus::Active,
+        };
 
         let info = group_call::SfuInfo {
             udp_addresses,
@@ -183,10 +197,10 @@ impl SfuClient {
         };
         let demux_id = deserialized.ssrc_prefix;
         debug!(
-            "SfuClient: successful join, info: {:?}, demux_id: {}, endpoint_id: {}",
-            info, demux_id, endpoint_id
+            "SfuClient: successful join, info: {:?}, demux_id: {}, endpoint_id: {}, client_status: {:?}",
+            info, demux_id, endpoint_id, client_status
         );
-        Ok((info, demux_id, endpoint_id))
+        Ok((info, demux_id, endpoint_id, client_status))
     }
 
     fn join_with_header(
@@ -195,11 +209,12 @@ impl SfuClient {
         ice_ufrag: &str,
         ice_pwd: &str,
         dtls_fingerprint: &group_call::DtlsFingerprint,
+        resume_endpoint_id: Option<&str>,
         client: group_call::Client,
     ) {
         info!("SfuClient join_with_header:");
 
-        let join_json = json!({
+        let mut join_json = json!({
             // The payload types, header extensions, fingerprint hash, payload formats,
             // and SSRCs need to match those configured in peer_connection.cc
             // (CreateSessionDescriptionForGroupCall) and group_call.rs
@@ -323,6 +338,11 @@ impl SfuClient {
                 },
             ],
         });
+        // If the SFU still holds this endpoint for us, it gives it back
+        // instead of assigning a new one.
+        if let Some(resume_endpoint_id) = resume_endpoint_id {
+            join_json["resumeEndpointId"] = json!(resume_endpoint_id);
+        }
         debug!("Sending join request: {}", join_json.to_string());
         let participants_url = format!("{}/v1/conference/participants", self.url);
         let mut headers = HashMap::new();
@@ -350,11 +370,12 @@ impl SfuClient {
             Some(r) if r.status_code == RESPONSE_CODE_NO_CONFERENCE => {
                 info!("SfuClient: no participants joined");
                 return Ok(PeekInfo {
-                    devices:      vec![],
-                    creator:      None,
-                    era_id:       None,
-                    max_devices:  None,
-                    device_count: 0,
+                    devices:         vec![],
+                    pending_devices: vec![],
+                    creator:         None,
+                    era_id:          None,
+                    max_devices:     None,
+                    device_count:    0,
                 });
             }
             Some(r) => {
@@ -387,29 +408,16 @@ impl SfuClient {
         let devices: Vec<group_call::PeekDeviceInfo> = deserialized
             .participants
             .into_iter()
-            .filter_map(|p| {
-                let demux_id = p.ssrc_prefix;
-                let user_id = Self::lookup_uuid_by_endpoint_id(&member_prefixes, &p.endpoint_id);
-                if let Ok(short_device_id) =
-                    p.endpoint_id.split('-').nth(1).unwrap_or_default().parse()
-                {
-                    Some(group_call::PeekDeviceInfo {
-                        demux_id,
-                        user_id,
-                        short_device_id,
-                        long_device_id: p.endpoint_id,
-                    })
-                } else {
-                    warn!(
-                        "Ignoring device with unparsable endpoint ID: {}",
-                        p.endpoint_id
-                    );
-                    None
-                }
-            })
+            .filter_map(|p| Self::peek_device_info(&member_prefixes, p))
+            .collect();
+        let pending_devices: Vec<group_call::PeekDeviceInfo> = deserialized
+            .pending_clients
+            .into_iter()
+            .filter_map(|p| Self::peek_device_info(&member_prefixes, p))
             .collect();
         Ok(PeekInfo {
             devices,
+            pending_devices,
             creator,
             era_id,
             max_devices,
@@ -417,6 +425,34 @@ impl SfuClient {
         })
     }
 
+    fn peek_device_info(
+        member_prefixes: &[UuidEndpointPrefix],
+        participant: SfuParticipant,
+    ) -> Option<group_call::PeekDeviceInfo> {
+        let demux_id = participant.ssrc_prefix;
+        let user_id = Self::lookup_uuid_by_endpoint_id(member_prefixes, &participant.endpoint_id);
+        if let Ok(short_device_id) = participant
+            .endpoint_id
+            .split('-')
+            .nth(1)
+            .unwrap_or_default()
+            .parse()
+        {
+            Some(group_call::PeekDeviceInfo {
+                demux_id,
+                user_id,
+                short_device_id,
+                long_device_id: participant.endpoint_id,
+            })
+        } else {
+            warn!(
+                "Ignoring device with unparsable endpoint ID: {}",
+                participant.endpoint_id
+            );
+            None
+        }
+    }
+
     fn request_remote_devices_with_header(
         &self,
         auth_header: &str,
@@ -464,6 +500,42 @@ impl SfuClient {
             self.request_remote_devices_with_header(header, handle_response);
         }
     }
+
+    // Used to leave, to remove other devices (which the SFU only allows for the call creator),
+    // and to approve or deny devices waiting in the lobby.
+    fn update_participant(&self, method: HttpMethod, endpoint_path: String, action: &'static str) {
+        let auth_header = match self.auth_header.as_ref() {
+            Some(h) => h,
+            None => {
+                // We shouldn't have been able to join without an auth header. In theory, we could
+                // request a new auth token and use it, but it will likely take longer and be less
+                // reliable than just letting it time out.
+                warn!("SfuClient: {} without an auth header; ignoring", action);
+                return;
+            }
+        };
+
+        let endpoint_url = format!("{}/v1/conference/participants/{}", self.url, endpoint_path);
+        let mut headers = HashMap::new();
+        headers.insert("Authorization".to_string(), auth_header.to_string());
+        self.http_client.make_request(
+            endpoint_url,
+            method,
+            headers,
+            None,
+            Box::new(move |resp| match resp {
+                Some(r) if r.status_code >= 200 && r.status_code <= 300 => {
+                    debug!("SfuClient: {} successful", action);
+                }
+                Some(r) => {
+                    warn!("SfuClient: HTTP error while {} ({})", action, r.status_code);
+                }
+                _ => {
+                    warn!("SfuClient: HTTP error while {} (no response)", action);
+                }
+            }),
+        );
+    }
 }
 
 impl group_call::SfuClient for SfuClient {
@@ -490,9 +562,18 @@ impl group_call::SfuClient for SfuClient {
         self.auth_header = Some(header.clone());
 
         // Release any tasks that were blocked on getting the token.
-        if let Some((ice_ufrag, ice_pwd, dtls_fingerprint, client)) = self.deferred_join.take() {
+        if let Some((ice_ufrag, ice_pwd, dtls_fingerprint, resume_endpoint_id, client)) =
+            self.deferred_join.take()
+        {
             info!("membership token received, proceeding with deferred join");
-            self.join_with_header(&header, &ice_ufrag, &ice_pwd, &dtls_fingerprint, client);
+            self.join_with_header(
+                &header,
+                &ice_ufrag,
+                &ice_pwd,
+                &dtls_fingerprint,
+                resume_endpoint_id.as_deref(),
+                client,
+            );
         }
     }
 
@@ -501,16 +582,31 @@ impl group_call::SfuClient for SfuClient {
         ice_ufrag: &str,
         ice_pwd: &str,
         dtls_fingerprint: &group_call::DtlsFingerprint,
+        resume_endpoint_id: Option<&str>,
         client: group_call::Client,
     ) {
         match self.auth_header.as_ref() {
-            Some(h) => self.join_with_header(h, ice_ufrag, ice_pwd, dtls_fingerprint, client),
+            Some(h) => self.join_with_header(
+                h,
+                ice_ufrag,
+                ice_pwd,
+                dtls_fingerprint,
+                resume_endpoint_id,
+                client,
+            ),
             None => {
                 info!("join requested without membership token - deferring");
                 let ice_ufrag = ice_ufrag.to_string();
                 let ice_pwd = ice_pwd.to_string();
                 let dtls_fingerprint = *dtls_fingerprint;
-                self.deferred_join = Some((ice_ufrag, ice_pwd, dtls_fingerprint, client));
+                let resume_endpoint_id = resume_endpoint_id.map(str::to_string);
+                self.deferred_join = Some((
+                    ice_ufrag,
+                    ice_pwd,
+                    dtls_fingerprint,
+                    resume_endpoint_id,
+                    client,
+                ));
             }
         }
     }
@@ -547,37 +643,29 @@ impl group_call::SfuClient for SfuClient {
 
     fn leave(&mut self, endpoint_id: String) {
         info!("SfuClient leave");
+        self.update_participant(HttpMethod::Delete, endpoint_id, "leaving");
+    }
 
-        let auth_header = match self.auth_header.as_ref() {
-            Some(h) => h,
-            None => {
-                // We shouldn't have been able to join without an auth header. In theory, we could
-                // request a new auth token and use it, but it will likely take longer and be less
-                // reliable than just letting it time out.
-                warn!("Requesting to leave a conference without an auth header; ignoring");
-                return;
-            }
-        };
+    fn remove_device(&mut self, endpoint_id: String) {
+        info!("SfuClient remove_device");
+        self.update_participant(HttpMethod::Delete, endpoint_id, "removing a device");
+    }
 
-        let endpoint_url = format!("{}/v1/conference/participants/{}", self.url, endpoint_id);
-        let mut headers = HashMap::new();
-        headers.insert("Authorization".to_string(), auth_header.to_string());
-        self.http_client.make_request(
-            endpoint_url,
-            HttpMethod::Delete,
-            headers,
-            None,
-            Box::new(move |resp| match resp {
-                Some(r) if r.status_code >= 200 && r.status_code <= 300 => {
-                    debug!("SfuClient: leave successful");
-                }
-                Some(r) => {
-                    warn!("SfuClient: HTTP error while leaving ({})", r.status_code);
-                }
-                _ => {
-                    warn!("SfuClient: HTTP error while leaving (no response)");
-                }
-            }),
+    fn approve_device(&mut self, endpoint_id: String) {
+        info!("SfuClient approve_device");
+        self.update_participant(
+            HttpMethod::Post,
+            format!("{}/approve", endpoint_id),
+            "approving a device",
+        );
+    }
+
+    fn deny_device(&mut self, endpoint_id: String) {
+        info!("SfuClient deny_device");
+        self.update_participant(
+            HttpMethod::Post,
+            format!("{}/deny", endpoint_id),
+            "denying a device",
         );
     }
 }
@@ -131,7 +131,8 @@ impl Offer {
         v4: protobuf::signaling::ConnectionParametersV4,
     ) -> Result<Self> {
         let proto = protobuf::signaling::Offer {
-            v4: Some(v4),
+            v4:       Some(v4),
+            transfer: None,
         };
 
         let mut opaque = BytesMut::with_capacity(proto.encoded_len());
@@ -150,12 +151,31 @@ impl Offer {
         }
     }
 
+    /// The same offer, marked as taking over an established call from
+    /// another of the sender's devices.
+    pub fn to_transfer(&self) -> Result<Self> {
+        let proto = protobuf::signaling::Offer {
+            transfer: Some(true),
+            ..self.proto.clone()
+        };
+
+        let mut opaque = BytesMut::with_capacity(proto.encoded_len());
+        proto.encode(&mut opaque)?;
+
+        Self::new(self.call_media_type, opaque.to_vec())
+    }
+
+    pub fn is_transfer(&self) -> bool {
+        self.proto.transfer.unwrap_or(false)
+    }
+
     pub fn to_info_string(&self) -> String {
         format!(
-            "opaque.len={}\tproto.version={}\ttype={}",
+            "opaque.len={}\tproto.version={}\ttype={}\ttransfer={}",
             self.opaque.len(),
             self.latest_version(),
-            self.call_media_type
+            self.call_media_type,
+            self.is_transfer()
         )
     }
 }
@@ -358,6 +378,8 @@ pub enum Hangup {
     // If you want to express that you NeedPermission on your device,
     // You can either fill it in or with your own device_id.
     NeedPermission(Option<DeviceId>),
+    // The call moved from the device receiving this to the given device of the same user.
+    TransferredToAnotherDevice(DeviceId),
 }
 
 impl Hangup {
@@ -374,6 +396,10 @@ impl Hangup {
                 (HangupType::BusyOnAnotherDevice, Some(*other_device_id))
             }
             Self::NeedPermission(other_device_id) => (HangupType::NeedPermission, *other_device_id),
+            Self::TransferredToAnotherDevice(other_device_id) => (
+                HangupType::TransferredToAnotherDevice,
+                Some(*other_device_id),
+            ),
         }
     }
 
@@ -389,6 +415,7 @@ impl Hangup {
             HangupType::DeclinedOnAnotherDevice => Self::DeclinedOnAnotherDevice(device_id),
             HangupType::BusyOnAnotherDevice => Self::BusyOnAnotherDevice(device_id),
             HangupType::NeedPermission => Self::NeedPermission(Some(device_id)),
+            HangupType::TransferredToAnotherDevice => Self::TransferredToAnotherDevice(device_id),
         }
     }
 }
@@ -410,12 +437,14 @@ impl fmt::Display for Hangup {
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum HangupType {
     // On this device
-    Normal                  = 0,
-    AcceptedOnAnotherDevice = 1,
-    DeclinedOnAnotherDevice = 2,
-    BusyOnAnotherDevice     = 3,
+    Normal                     = 0,
+    AcceptedOnAnotherDevice    = 1,
+    DeclinedOnAnotherDevice    = 2,
+    BusyOnAnotherDevice        = 3,
     // On either another device or this device
-    NeedPermission          = 4,
+    NeedPermission             = 4,
+    // Sent by the remote to the device that the call moved away from
+    TransferredToAnotherDevice = 5,
 }
 
 impl HangupType {
@@ -426,6 +455,7 @@ impl HangupType {
             2 => Some(HangupType::DeclinedOnAnotherDevice),
             3 => Some(HangupType::BusyOnAnotherDevice),
             4 => Some(HangupType::NeedPermission),
+            5 => Some(HangupType::TransferredToAnotherDevice),
             _ => None,
         }
     }
@@ -501,4 +531,6 @@ pub struct ReceivedBusy {
 pub struct SenderStatus {
     pub video_enabled:  Option<bool>,
     pub sharing_screen: Option<bool>,
+    /// Whether the sender has put the call on hold.
+    pub held:           Option<bool>,
 }
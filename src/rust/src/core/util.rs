@@ -381,7 +381,7 @@ impl TaskQueueRuntime {
         self.rt.as_ref().unwrap().spawn(future);
     }
 
-    #[cfg(feature = "sim")]
+    #[cfg(any(feature = "simnet", feature = "sim"))]
     pub fn pause_clock(&self) -> ClockPauseGuard {
         let _guard = self.rt.as_ref().unwrap().enter();
         tokio::time::pause();
@@ -391,12 +391,26 @@ impl TaskQueueRuntime {
     }
 }
 
-#[cfg(feature = "sim")]
+#[cfg(any(feature = "simnet", feature = "sim"))]
 pub struct ClockPauseGuard {
     handle: runtime::Handle,
 }
 
-#[cfg(feature = "sim")]
+#[cfg(any(feature = "simnet", feature = "sim"))]
+impl ClockPauseGuard {
+    /// Moves the paused clock forward, firing any timers that are due,
+    /// and waits for that to happen.
+    pub fn advance(&self, duration: std::time::Duration) {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.handle.spawn(async move {
+            tokio::time::advance(duration).await;
+            let _ = sender.send(());
+        });
+        let _ = receiver.recv();
+    }
+}
+
+#[cfg(any(feature = "simnet", feature = "sim"))]
 impl Drop for ClockPauseGuard {
     fn drop(&mut self) {
         let _guard = self.handle.enter();
@@ -6,7 +6,7 @@
 use lazy_static::lazy_static;
 use std::cell::RefCell;
 use std::collections::{HashMap, VecDeque};
-use std::convert::TryFrom;
+use std::convert::{TryFrom, TryInto};
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
@@ -24,6 +24,7 @@ use crate::core::bandwidth_mode::BandwidthMode;
 use crate::core::call_manager::CallManager;
 use crate::core::group_call;
 use crate::core::group_call::{GroupId, SignalingMessageUrgency, UserId};
+use crate::core::ring_store::FileRingStore;
 use crate::core::signaling;
 use crate::native::{
     CallState,
@@ -122,6 +123,12 @@ pub enum Event {
     // Whether the remote is sharing its screen or not
     // Like call state, we ID the call by PeerId and assume there is only one.
     RemoteSharingScreen(PeerId, bool),
+    // Whether the remote has put the call on hold or not
+    // Like call state, we ID the call by PeerId and assume there is only one.
+    RemoteHold(PeerId, bool),
+    // The remote moved the call to another of its devices.
+    // Like call state, we ID the call by PeerId and assume there is only one.
+    RemoteTransferred(PeerId),
     // The group call has an update.
     GroupUpdate(GroupUpdate),
     // JavaScript should initiate an HTTP request.
@@ -212,6 +219,16 @@ impl CallStateHandler for Sender<Event> {
         ))?;
         Ok(())
     }
+
+    fn handle_remote_hold(&self, remote_peer_id: &str, held: bool) -> Result<()> {
+        self.send(Event::RemoteHold(remote_peer_id.to_string(), held))?;
+        Ok(())
+    }
+
+    fn handle_remote_transferred(&self, remote_peer_id: &str) -> Result<()> {
+        self.send(Event::RemoteTransferred(remote_peer_id.to_string()))?;
+        Ok(())
+    }
 }
 
 impl HttpClient for Sender<Event> {
@@ -472,6 +489,21 @@ fn setSelfUuid(mut cx: FunctionContext) -> JsResult<JsValue> {
     Ok(cx.undefined().upcast())
 }
 
+#[allow(non_snake_case)]
+fn setRingStorePath(mut cx: FunctionContext) -> JsResult<JsValue> {
+    debug!("JsCallManager.setRingStorePath()");
+
+    let path = cx.argument::<JsString>(0)?.value(&mut cx);
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        let ring_store = FileRingStore::open(path)?;
+        endpoint.call_manager.set_ring_store(Box::new(ring_store))?;
+        Ok(())
+    })
+    .or_else(|err: failure::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
 #[allow(non_snake_case)]
 fn createOutgoingCall(mut cx: FunctionContext) -> JsResult<JsValue> {
     let peer_id = cx.argument::<JsString>(0)?.value(&mut cx) as PeerId;
@@ -503,6 +535,34 @@ fn createOutgoingCall(mut cx: FunctionContext) -> JsResult<JsValue> {
     Ok(create_id_arg(&mut cx, call_id.as_u64()))
 }
 
+#[allow(non_snake_case)]
+fn takeOverCall(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let peer_id = cx.argument::<JsString>(0)?.value(&mut cx) as PeerId;
+    let call_id = CallId::new(get_id_arg(&mut cx, 1));
+    let video_enabled = cx.argument::<JsBoolean>(2)?.value(&mut cx);
+    let local_device_id = cx.argument::<JsNumber>(3)?.value(&mut cx) as DeviceId;
+
+    let media_type = if video_enabled {
+        CallMediaType::Video
+    } else {
+        CallMediaType::Audio
+    };
+
+    debug!(
+        "JsCallManager.takeOverCall({}, {}, {}, {})",
+        peer_id, call_id, media_type, local_device_id
+    );
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint
+            .call_manager
+            .take_over_call(peer_id, call_id, media_type, local_device_id)?;
+        Ok(())
+    })
+    .or_else(|err: failure::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
 #[allow(non_snake_case)]
 fn cancelGroupRing(mut cx: FunctionContext) -> JsResult<JsValue> {
     debug!("JsCallManager.cancelGroupRing()");
@@ -1088,6 +1148,77 @@ fn deleteGroupCallClient(mut cx: FunctionContext) -> JsResult<JsValue> {
     Ok(cx.undefined().upcast())
 }
 
+#[allow(non_snake_case)]
+fn requestEscalation(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let call_id = CallId::new(get_id_arg(&mut cx, 0));
+    let remote_user_id = cx.argument::<JsBuffer>(1)?;
+    let remote_user_id = cx.borrow(&remote_user_id, |handle| handle.as_slice().to_vec());
+    let group_id = cx.argument::<JsBuffer>(2)?;
+    let group_id = cx.borrow(&group_id, |handle| handle.as_slice().to_vec());
+    let sfu_url = cx.argument::<JsString>(3)?.value(&mut cx);
+    let audio_muted = cx.argument::<JsBoolean>(4)?.value(&mut cx);
+    debug!("JsCallManager.requestEscalation({})", call_id);
+
+    let mut client_id = group_call::INVALID_CLIENT_ID;
+    with_call_endpoint(&mut cx, |endpoint| {
+        let peer_connection_factory = endpoint.peer_connection_factory.clone();
+        let outgoing_audio_track = endpoint.outgoing_audio_track.clone();
+        let outgoing_video_track = endpoint.outgoing_video_track.clone();
+        client_id = endpoint.call_manager.request_escalation(
+            call_id,
+            remote_user_id,
+            group_id,
+            sfu_url,
+            Some(peer_connection_factory),
+            outgoing_audio_track,
+            outgoing_video_track,
+            audio_muted,
+        )?;
+        Ok(())
+    })
+    .or_else(|err: failure::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.number(client_id).upcast())
+}
+
+#[allow(non_snake_case)]
+fn acceptEscalation(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let call_id = CallId::new(get_id_arg(&mut cx, 0));
+    let sfu_url = cx.argument::<JsString>(1)?.value(&mut cx);
+    let audio_muted = cx.argument::<JsBoolean>(2)?.value(&mut cx);
+    debug!("JsCallManager.acceptEscalation({})", call_id);
+
+    let mut client_id = group_call::INVALID_CLIENT_ID;
+    with_call_endpoint(&mut cx, |endpoint| {
+        let peer_connection_factory = endpoint.peer_connection_factory.clone();
+        let outgoing_audio_track = endpoint.outgoing_audio_track.clone();
+        let outgoing_video_track = endpoint.outgoing_video_track.clone();
+        client_id = endpoint.call_manager.accept_escalation(
+            call_id,
+            sfu_url,
+            Some(peer_connection_factory),
+            outgoing_audio_track,
+            outgoing_video_track,
+            audio_muted,
+        )?;
+        Ok(())
+    })
+    .or_else(|err: failure::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.number(client_id).upcast())
+}
+
+#[allow(non_snake_case)]
+fn declineEscalation(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let call_id = CallId::new(get_id_arg(&mut cx, 0));
+    debug!("JsCallManager.declineEscalation({})", call_id);
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint.call_manager.decline_escalation(call_id)?;
+        Ok(())
+    })
+    .or_else(|err: failure::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
 #[allow(non_snake_case)]
 fn connect(mut cx: FunctionContext) -> JsResult<JsValue> {
     let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
@@ -1113,6 +1244,21 @@ fn join(mut cx: FunctionContext) -> JsResult<JsValue> {
     Ok(cx.undefined().upcast())
 }
 
+#[allow(non_snake_case)]
+fn resume(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
+    let snapshot = cx.argument::<JsBuffer>(1)?;
+    let snapshot = cx.borrow(&snapshot, |handle| handle.as_slice().to_vec());
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint.outgoing_video_track.set_content_hint(false);
+        endpoint.call_manager.resume(client_id, snapshot);
+        Ok(())
+    })
+    .or_else(|err: failure::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
 #[allow(non_snake_case)]
 fn leave(mut cx: FunctionContext) -> JsResult<JsValue> {
     let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
@@ -1208,6 +1354,315 @@ fn setOutgoingGroupCallVideoIsScreenShare(mut cx: FunctionContext) -> JsResult<J
     Ok(cx.undefined().upcast())
 }
 
+#[allow(non_snake_case)]
+fn setHandRaised(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
+    let raised = cx.argument::<JsBoolean>(1)?.value(&mut cx);
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint.call_manager.set_hand_raised(client_id, raised);
+        Ok(())
+    })
+    .or_else(|err: failure::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
+#[allow(non_snake_case)]
+fn sendReaction(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
+    let value = cx.argument::<JsString>(1)?.value(&mut cx);
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint.call_manager.send_reaction(client_id, value);
+        Ok(())
+    })
+    .or_else(|err: failure::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
+#[allow(non_snake_case)]
+fn sendMessage(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
+    let text = cx.argument::<JsString>(1)?.value(&mut cx);
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint.call_manager.send_message(client_id, text);
+        Ok(())
+    })
+    .or_else(|err: failure::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
+#[allow(non_snake_case)]
+fn sendControlRequest(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
+    let target_demux_id = cx.argument::<JsNumber>(1)?.value(&mut cx) as group_call::DemuxId;
+    let request = cx.argument::<JsNumber>(2)?.value(&mut cx);
+    let request = group_call::ControlRequest::try_from(request as i32)
+        .or_else(|err| cx.throw_error(err.to_string()))?;
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint
+            .call_manager
+            .send_control_request(client_id, target_demux_id, request);
+        Ok(())
+    })
+    .or_else(|err: failure::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
+#[allow(non_snake_case)]
+fn approvePendingDevice(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
+    let demux_id = cx.argument::<JsNumber>(1)?.value(&mut cx) as group_call::DemuxId;
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint
+            .call_manager
+            .approve_pending_device(client_id, demux_id);
+        Ok(())
+    })
+    .or_else(|err: failure::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
+#[allow(non_snake_case)]
+fn denyPendingDevice(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
+    let demux_id = cx.argument::<JsNumber>(1)?.value(&mut cx) as group_call::DemuxId;
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint
+            .call_manager
+            .deny_pending_device(client_id, demux_id);
+        Ok(())
+    })
+    .or_else(|err: failure::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
+#[allow(non_snake_case)]
+fn setResumptionKey(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
+    let key_or_undef = cx.argument::<JsValue>(1)?;
+    let key = match key_or_undef.downcast::<JsUndefined, _>(&mut cx) {
+        Ok(_) => None,
+        Err(_) => {
+            let key = key_or_undef.downcast_or_throw::<JsBuffer, _>(&mut cx)?;
+            let key = cx.borrow(&key, |handle| handle.as_slice().to_vec());
+            match key.as_slice().try_into() {
+                Ok(key) => Some(key),
+                Err(_) => {
+                    return cx.throw_error(format!(
+                        "A resumption key must be 32 bytes, not {}",
+                        key.len()
+                    ));
+                }
+            }
+        }
+    };
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint.call_manager.set_resumption_key(client_id, key);
+        Ok(())
+    })
+    .or_else(|err: failure::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
+#[allow(non_snake_case)]
+fn setLayoutConfig(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
+    let viewport_width_or_undef = cx.argument::<JsValue>(1)?;
+    let config = match viewport_width_or_undef.downcast::<JsUndefined, _>(&mut cx) {
+        Ok(_) => None,
+        Err(_) => {
+            // By checking 'undefined' first, we get an error message that mentions 'number'.
+            let viewport_width = viewport_width_or_undef
+                .downcast_or_throw::<JsNumber, _>(&mut cx)?
+                .value(&mut cx);
+            let viewport_height = cx.argument::<JsNumber>(2)?.value(&mut cx);
+            let tiles_per_page = cx.argument::<JsNumber>(3)?.value(&mut cx);
+            let page_index = cx.argument::<JsNumber>(4)?.value(&mut cx);
+            let js_pinned_demux_ids = cx.argument::<JsArray>(5)?;
+            let mut pinned_demux_ids = Vec::new();
+            for i in 0..js_pinned_demux_ids.len(&mut cx) {
+                let demux_id = js_pinned_demux_ids
+                    .get(&mut cx, i)?
+                    .downcast_or_throw::<JsNumber, _>(&mut cx)?
+                    .value(&mut cx);
+                pinned_demux_ids.push(demux_id as group_call::DemuxId);
+            }
+            Some(group_call::LayoutConfig {
+                viewport_width: viewport_width as u16,
+                viewport_height: viewport_height as u16,
+                tiles_per_page: tiles_per_page as usize,
+                page_index: page_index as usize,
+                pinned_demux_ids,
+            })
+        }
+    };
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint.call_manager.set_layout_config(client_id, config);
+        Ok(())
+    })
+    .or_else(|err: failure::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
+#[allow(non_snake_case)]
+fn setPinned(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
+    let demux_id = cx.argument::<JsNumber>(1)?.value(&mut cx) as group_call::DemuxId;
+    let pin_or_undef = cx.argument::<JsValue>(2)?;
+    let pin = match pin_or_undef.downcast::<JsUndefined, _>(&mut cx) {
+        Ok(_) => None,
+        Err(_) => {
+            // By checking 'undefined' first, we get an error message that mentions 'number'.
+            let pin = pin_or_undef
+                .downcast_or_throw::<JsNumber, _>(&mut cx)?
+                .value(&mut cx);
+            Some(
+                group_call::PinKind::try_from(pin as i32)
+                    .or_else(|err| cx.throw_error(err.to_string()))?,
+            )
+        }
+    };
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint.call_manager.set_pinned(client_id, demux_id, pin);
+        Ok(())
+    })
+    .or_else(|err: failure::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
+#[allow(non_snake_case)]
+fn setWaitForSlotWhenFull(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
+    let wait = cx.argument::<JsBoolean>(1)?.value(&mut cx);
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint
+            .call_manager
+            .set_wait_for_slot_when_full(client_id, wait);
+        Ok(())
+    })
+    .or_else(|err: failure::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
+#[allow(non_snake_case)]
+fn setKeyRotationPolicy(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
+    let apply_delay_millis = cx.argument::<JsNumber>(1)?.value(&mut cx);
+    let max_key_age_millis_or_undef = cx.argument::<JsValue>(2)?;
+    let max_key_age = match max_key_age_millis_or_undef.downcast::<JsUndefined, _>(&mut cx) {
+        Ok(_) => None,
+        Err(_) => {
+            // By checking 'undefined' first, we get an error message that mentions 'number'.
+            let max_key_age_millis = max_key_age_millis_or_undef
+                .downcast_or_throw::<JsNumber, _>(&mut cx)?
+                .value(&mut cx);
+            Some(Duration::from_millis(max_key_age_millis as u64))
+        }
+    };
+    let max_frames_per_key_or_undef = cx.argument::<JsValue>(3)?;
+    let max_frames_per_key = match max_frames_per_key_or_undef.downcast::<JsUndefined, _>(&mut cx) {
+        Ok(_) => None,
+        Err(_) => Some(
+            max_frames_per_key_or_undef
+                .downcast_or_throw::<JsNumber, _>(&mut cx)?
+                .value(&mut cx) as u64,
+        ),
+    };
+    let policy = group_call::KeyRotationPolicy {
+        apply_delay: Duration::from_millis(apply_delay_millis as u64),
+        max_key_age,
+        max_frames_per_key,
+    };
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint
+            .call_manager
+            .set_key_rotation_policy(client_id, policy);
+        Ok(())
+    })
+    .or_else(|err: failure::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
+#[allow(non_snake_case)]
+fn rotateMediaSendKey(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint.call_manager.rotate_media_send_key(client_id);
+        Ok(())
+    })
+    .or_else(|err: failure::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
+#[allow(non_snake_case)]
+fn getKeyRotationMetrics(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
+
+    let metrics = with_call_endpoint(&mut cx, |endpoint| {
+        endpoint.call_manager.key_rotation_metrics(client_id)
+    })
+    .or_else(|err: failure::Error| cx.throw_error(format!("{}", err)))?;
+    let metrics = match metrics {
+        Some(metrics) => metrics,
+        None => {
+            return Ok(cx.undefined().upcast());
+        }
+    };
+
+    let js_metrics = JsObject::new(&mut cx);
+    let rotations = cx.number(metrics.rotations as f64);
+    js_metrics.set(&mut cx, "rotations", rotations)?;
+    if let Some(window) = metrics.last_exposure_window {
+        let window = cx.number(window.as_millis() as f64);
+        js_metrics.set(&mut cx, "lastExposureWindowMillis", window)?;
+    }
+    if let Some(window) = metrics.max_exposure_window {
+        let window = cx.number(window.as_millis() as f64);
+        js_metrics.set(&mut cx, "maxExposureWindowMillis", window)?;
+    }
+    Ok(js_metrics.upcast())
+}
+
+#[allow(non_snake_case)]
+fn setAudioLevelsConfig(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
+    let interval_millis_or_undef = cx.argument::<JsValue>(1)?;
+    let config = match interval_millis_or_undef.downcast::<JsUndefined, _>(&mut cx) {
+        Ok(_) => None,
+        Err(_) => {
+            // By checking 'undefined' first, we get an error message that mentions 'number'.
+            let interval_millis = interval_millis_or_undef
+                .downcast_or_throw::<JsNumber, _>(&mut cx)?
+                .value(&mut cx);
+            let smoothing = cx.argument::<JsNumber>(2)?.value(&mut cx);
+            Some(group_call::AudioLevelsConfig {
+                interval: Duration::from_millis(interval_millis as u64),
+                smoothing,
+            })
+        }
+    };
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint
+            .call_manager
+            .set_audio_levels_config(client_id, config);
+        Ok(())
+    })
+    .or_else(|err: failure::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
 #[allow(non_snake_case)]
 fn groupRing(mut cx: FunctionContext) -> JsResult<JsValue> {
     let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
@@ -1766,6 +2221,27 @@ fn poll(mut cx: FunctionContext) -> JsResult<JsValue> {
                 method.call(&mut cx, observer, args)?;
             }
 
+            Event::RemoteHold(peer_id, held) => {
+                let method_name = "onRemoteHold";
+                let args: Vec<Handle<JsValue>> =
+                    vec![cx.string(peer_id).upcast(), cx.boolean(held).upcast()];
+                let method = *observer
+                    .get(&mut cx, method_name)?
+                    .downcast::<JsFunction, _>(&mut cx)
+                    .expect("onRemoteHold is a function");
+                method.call(&mut cx, observer, args)?;
+            }
+
+            Event::RemoteTransferred(peer_id) => {
+                let method_name = "onRemoteTransferred";
+                let args: Vec<Handle<JsValue>> = vec![cx.string(peer_id).upcast()];
+                let method = *observer
+                    .get(&mut cx, method_name)?
+                    .downcast::<JsFunction, _>(&mut cx)
+                    .expect("onRemoteTransferred is a function");
+                method.call(&mut cx, observer, args)?;
+            }
+
             Event::SendHttpRequest {
                 request_id,
                 url,
@@ -1908,6 +2384,8 @@ fn poll(mut cx: FunctionContext) -> JsResult<JsValue> {
                         group_call::JoinState::NotJoined(_) => 0,
                         group_call::JoinState::Joining => 1,
                         group_call::JoinState::Joined(_, _) => 2,
+                        group_call::JoinState::Pending(_, _) => 3,
+                        group_call::JoinState::WaitingForSlot(_) => 4,
                     })
                     .upcast(),
                 ];
@@ -1967,6 +2445,7 @@ fn poll(mut cx: FunctionContext) -> JsResult<JsValue> {
                             None => cx.undefined().upcast(),
                             Some(forwarding_video) => cx.boolean(forwarding_video).upcast(),
                         };
+                    let hand_raised = cx.boolean(remote_device_state.hand_raised);
 
                     let js_remote_device_state = cx.empty_object();
                     js_remote_device_state.set(&mut cx, "demuxId", demux_id)?;
@@ -1983,6 +2462,7 @@ fn poll(mut cx: FunctionContext) -> JsResult<JsValue> {
                     js_remote_device_state.set(&mut cx, "addedTime", added_time)?;
                     js_remote_device_state.set(&mut cx, "speakerTime", speaker_time)?;
                     js_remote_device_state.set(&mut cx, "forwardingVideo", forwarding_video)?;
+                    js_remote_device_state.set(&mut cx, "handRaised", hand_raised)?;
 
                     js_remote_device_states.set(&mut cx, i as u32, js_remote_device_state)?;
                 }
@@ -1999,6 +2479,161 @@ fn poll(mut cx: FunctionContext) -> JsResult<JsValue> {
                 method.call(&mut cx, observer, args)?;
             }
 
+            Event::GroupUpdate(GroupUpdate::Reaction(client_id, reaction)) => {
+                let method_name = "handleReaction";
+
+                let args: Vec<Handle<JsValue>> = vec![
+                    cx.number(client_id).upcast(),
+                    cx.number(reaction.demux_id).upcast(),
+                    cx.string(reaction.value).upcast(),
+                ];
+                let error_message = format!("{} is a function", method_name);
+                let method = *observer
+                    .get(&mut cx, method_name)?
+                    .downcast::<JsFunction, _>(&mut cx)
+                    .expect(&error_message);
+                method.call(&mut cx, observer, args)?;
+            }
+
+            Event::GroupUpdate(GroupUpdate::MessageReceived(client_id, message)) => {
+                let method_name = "handleMessageReceived";
+
+                // The message ID is a string because we can't safely convert a u64 to a JavaScript-compatible number.
+                let args: Vec<Handle<JsValue>> = vec![
+                    cx.number(client_id).upcast(),
+                    cx.number(message.demux_id).upcast(),
+                    cx.string(message.message_id.to_string()).upcast(),
+                    cx.string(message.text).upcast(),
+                ];
+                let error_message = format!("{} is a function", method_name);
+                let method = *observer
+                    .get(&mut cx, method_name)?
+                    .downcast::<JsFunction, _>(&mut cx)
+                    .expect(&error_message);
+                method.call(&mut cx, observer, args)?;
+            }
+
+            Event::GroupUpdate(GroupUpdate::AudioLevels(
+                client_id,
+                captured_level,
+                received_levels,
+            )) => {
+                let method_name = "handleAudioLevels";
+
+                let js_received_levels = JsArray::new(&mut cx, received_levels.len() as u32);
+                for (i, received_level) in received_levels.iter().enumerate() {
+                    let demux_id = cx.number(received_level.demux_id);
+                    let level = cx.number(received_level.level);
+
+                    let js_received_level = cx.empty_object();
+                    js_received_level.set(&mut cx, "demuxId", demux_id)?;
+                    js_received_level.set(&mut cx, "level", level)?;
+
+                    js_received_levels.set(&mut cx, i as u32, js_received_level)?;
+                }
+
+                let args: Vec<Handle<JsValue>> = vec![
+                    cx.number(client_id).upcast(),
+                    cx.number(captured_level).upcast(),
+                    js_received_levels.upcast(),
+                ];
+                let error_message = format!("{} is a function", method_name);
+                let method = *observer
+                    .get(&mut cx, method_name)?
+                    .downcast::<JsFunction, _>(&mut cx)
+                    .expect(&error_message);
+                method.call(&mut cx, observer, args)?;
+            }
+
+            Event::GroupUpdate(GroupUpdate::ResumptionSnapshot(client_id, snapshot)) => {
+                let method_name = "handleResumptionSnapshot";
+                let args: Vec<Handle<JsValue>> = vec![
+                    cx.number(client_id).upcast(),
+                    to_js_buffer(&mut cx, &snapshot),
+                ];
+                let error_message = format!("{} is a function", method_name);
+                let method = *observer
+                    .get(&mut cx, method_name)?
+                    .downcast::<JsFunction, _>(&mut cx)
+                    .expect(&error_message);
+                method.call(&mut cx, observer, args)?;
+            }
+
+            Event::GroupUpdate(GroupUpdate::LayoutChanged(client_id, layout)) => {
+                let method_name = "handleLayoutChanged";
+
+                let js_demux_ids = JsArray::new(&mut cx, layout.demux_ids.len() as u32);
+                for (i, demux_id) in layout.demux_ids.iter().enumerate() {
+                    let demux_id = cx.number(*demux_id);
+                    js_demux_ids.set(&mut cx, i as u32, demux_id)?;
+                }
+
+                let js_layout = cx.empty_object();
+                js_layout.set(&mut cx, "demuxIds", js_demux_ids)?;
+                let page_index = cx.number(layout.page_index as f64);
+                js_layout.set(&mut cx, "pageIndex", page_index)?;
+                let page_count = cx.number(layout.page_count as f64);
+                js_layout.set(&mut cx, "pageCount", page_count)?;
+                let tiles_per_page = cx.number(layout.tiles_per_page as f64);
+                js_layout.set(&mut cx, "tilesPerPage", tiles_per_page)?;
+                let columns = cx.number(layout.columns);
+                js_layout.set(&mut cx, "columns", columns)?;
+                let rows = cx.number(layout.rows);
+                js_layout.set(&mut cx, "rows", rows)?;
+                let tile_width = cx.number(layout.tile_width);
+                js_layout.set(&mut cx, "tileWidth", tile_width)?;
+                let tile_height = cx.number(layout.tile_height);
+                js_layout.set(&mut cx, "tileHeight", tile_height)?;
+
+                let args: Vec<Handle<JsValue>> =
+                    vec![cx.number(client_id).upcast(), js_layout.upcast()];
+                let error_message = format!("{} is a function", method_name);
+                let method = *observer
+                    .get(&mut cx, method_name)?
+                    .downcast::<JsFunction, _>(&mut cx)
+                    .expect(&error_message);
+                method.call(&mut cx, observer, args)?;
+            }
+
+            Event::GroupUpdate(GroupUpdate::PinnedVideosNotForwarded(client_id, demux_ids)) => {
+                let method_name = "handlePinnedVideosNotForwarded";
+
+                let js_demux_ids = JsArray::new(&mut cx, demux_ids.len() as u32);
+                for (i, demux_id) in demux_ids.iter().enumerate() {
+                    let demux_id = cx.number(*demux_id);
+                    js_demux_ids.set(&mut cx, i as u32, demux_id)?;
+                }
+
+                let args: Vec<Handle<JsValue>> =
+                    vec![cx.number(client_id).upcast(), js_demux_ids.upcast()];
+                let error_message = format!("{} is a function", method_name);
+                let method = *observer
+                    .get(&mut cx, method_name)?
+                    .downcast::<JsFunction, _>(&mut cx)
+                    .expect(&error_message);
+                method.call(&mut cx, observer, args)?;
+            }
+
+            Event::GroupUpdate(GroupUpdate::ControlRequest(
+                client_id,
+                sender_demux_id,
+                request,
+            )) => {
+                let method_name = "handleControlRequest";
+
+                let args: Vec<Handle<JsValue>> = vec![
+                    cx.number(client_id).upcast(),
+                    cx.number(sender_demux_id).upcast(),
+                    cx.number(request as i32).upcast(),
+                ];
+                let error_message = format!("{} is a function", method_name);
+                let method = *observer
+                    .get(&mut cx, method_name)?
+                    .downcast::<JsFunction, _>(&mut cx)
+                    .expect(&error_message);
+                method.call(&mut cx, observer, args)?;
+            }
+
             Event::GroupUpdate(GroupUpdate::IncomingVideoTrack(
                 client_id,
                 remote_demux_id,
@@ -2020,6 +2655,61 @@ fn poll(mut cx: FunctionContext) -> JsResult<JsValue> {
                 .or_else(|err: failure::Error| cx.throw_error(format!("{}", err)))?;
             }
 
+            Event::GroupUpdate(GroupUpdate::RingStatusesChanged(client_id, statuses)) => {
+                let method_name = "handleRingStatusesChanged";
+
+                let js_statuses = JsArray::new(&mut cx, statuses.len() as u32);
+                for (i, status) in statuses.iter().enumerate() {
+                    let user_id = to_js_buffer(&mut cx, &status.user_id);
+                    let ring_id = cx.string(status.ring_id.to_string());
+                    let ring_status = cx.number(status.status as i32);
+
+                    let js_status = cx.empty_object();
+                    js_status.set(&mut cx, "userId", user_id)?;
+                    js_status.set(&mut cx, "ringId", ring_id)?;
+                    js_status.set(&mut cx, "status", ring_status)?;
+
+                    js_statuses.set(&mut cx, i as u32, js_status)?;
+                }
+
+                let args: Vec<Handle<JsValue>> =
+                    vec![cx.number(client_id).upcast(), js_statuses.upcast()];
+                let error_message = format!("{} is a function", method_name);
+                let method = *observer
+                    .get(&mut cx, method_name)?
+                    .downcast::<JsFunction, _>(&mut cx)
+                    .expect(&error_message);
+                method.call(&mut cx, observer, args)?;
+            }
+
+            Event::GroupUpdate(GroupUpdate::PendingDevicesChanged(client_id, pending_devices)) => {
+                let method_name = "handlePendingDevicesChanged";
+
+                let js_pending_devices = JsArray::new(&mut cx, pending_devices.len() as u32);
+                for (i, pending_device) in pending_devices.iter().enumerate() {
+                    let demux_id = cx.number(pending_device.demux_id);
+                    let user_id: neon::handle::Handle<JsValue> = match &pending_device.user_id {
+                        Some(user_id) => to_js_buffer(&mut cx, user_id).upcast(),
+                        None => cx.undefined().upcast(),
+                    };
+
+                    let js_pending_device = cx.empty_object();
+                    js_pending_device.set(&mut cx, "demuxId", demux_id)?;
+                    js_pending_device.set(&mut cx, "userId", user_id)?;
+
+                    js_pending_devices.set(&mut cx, i as u32, js_pending_device)?;
+                }
+
+                let args: Vec<Handle<JsValue>> =
+                    vec![cx.number(client_id).upcast(), js_pending_devices.upcast()];
+                let error_message = format!("{} is a function", method_name);
+                let method = *observer
+                    .get(&mut cx, method_name)?
+                    .downcast::<JsFunction, _>(&mut cx)
+                    .expect(&error_message);
+                method.call(&mut cx, observer, args)?;
+            }
+
             Event::GroupUpdate(GroupUpdate::PeekChanged {
                 client_id,
                 members,
@@ -2166,6 +2856,26 @@ fn poll(mut cx: FunctionContext) -> JsResult<JsValue> {
                     .expect(&error_message);
                 method.call(&mut cx, observer, args)?;
             }
+
+            Event::GroupUpdate(GroupUpdate::Escalation {
+                call_id,
+                group_id,
+                update,
+            }) => {
+                let method_name = "escalationUpdate";
+
+                let args = [
+                    create_id_arg(&mut cx, call_id.as_u64()),
+                    to_js_buffer(&mut cx, &group_id).upcast(),
+                    cx.number(update as i32).upcast(),
+                ];
+                let error_message = format!("{} is a function", method_name);
+                let method = *observer
+                    .get(&mut cx, method_name)?
+                    .downcast::<JsFunction, _>(&mut cx)
+                    .expect(&error_message);
+                method.call(&mut cx, observer, args)?;
+            }
         }
     }
     Ok(cx.undefined().upcast())
@@ -2177,7 +2887,9 @@ register_module!(mut cx, {
     cx.export_value("callEndpointPropertyKey", js_property_key)?;
 
     cx.export_function("cm_setSelfUuid", setSelfUuid)?;
+    cx.export_function("cm_setRingStorePath", setRingStorePath)?;
     cx.export_function("cm_createOutgoingCall", createOutgoingCall)?;
+    cx.export_function("cm_takeOverCall", takeOverCall)?;
     cx.export_function("cm_cancelGroupRing", cancelGroupRing)?;
     cx.export_function("cm_proceed", proceed)?;
     cx.export_function("cm_accept", accept)?;
@@ -2205,8 +2917,12 @@ register_module!(mut cx, {
     cx.export_function("cm_receiveGroupCallVideoFrame", receiveGroupCallVideoFrame)?;
     cx.export_function("cm_createGroupCallClient", createGroupCallClient)?;
     cx.export_function("cm_deleteGroupCallClient", deleteGroupCallClient)?;
+    cx.export_function("cm_requestEscalation", requestEscalation)?;
+    cx.export_function("cm_acceptEscalation", acceptEscalation)?;
+    cx.export_function("cm_declineEscalation", declineEscalation)?;
     cx.export_function("cm_connect", connect)?;
     cx.export_function("cm_join", join)?;
+    cx.export_function("cm_resume", resume)?;
     cx.export_function("cm_leave", leave)?;
     cx.export_function("cm_disconnect", disconnect)?;
     cx.export_function("cm_setOutgoingAudioMuted", setOutgoingAudioMuted)?;
@@ -2216,6 +2932,20 @@ register_module!(mut cx, {
         "cm_setOutgoingGroupCallVideoIsScreenShare",
         setOutgoingGroupCallVideoIsScreenShare,
     )?;
+    cx.export_function("cm_setHandRaised", setHandRaised)?;
+    cx.export_function("cm_sendReaction", sendReaction)?;
+    cx.export_function("cm_sendMessage", sendMessage)?;
+    cx.export_function("cm_sendControlRequest", sendControlRequest)?;
+    cx.export_function("cm_approvePendingDevice", approvePendingDevice)?;
+    cx.export_function("cm_denyPendingDevice", denyPendingDevice)?;
+    cx.export_function("cm_setAudioLevelsConfig", setAudioLevelsConfig)?;
+    cx.export_function("cm_setResumptionKey", setResumptionKey)?;
+    cx.export_function("cm_setKeyRotationPolicy", setKeyRotationPolicy)?;
+    cx.export_function("cm_setLayoutConfig", setLayoutConfig)?;
+    cx.export_function("cm_setPinned", setPinned)?;
+    cx.export_function("cm_setWaitForSlotWhenFull", setWaitForSlotWhenFull)?;
+    cx.export_function("cm_rotateMediaSendKey", rotateMediaSendKey)?;
+    cx.export_function("cm_getKeyRotationMetrics", getKeyRotationMetrics)?;
     cx.export_function("cm_groupRing", groupRing)?;
     cx.export_function("cm_resendMediaKeys", resendMediaKeys)?;
     cx.export_function("cm_setBandwidthMode", setBandwidthMode)?;
@@ -27,6 +27,8 @@ pub enum RingRtcError {
     CallManagerIsBusy,
     #[fail(display = "No active call found")]
     NoActiveCall,
+    #[fail(display = "No call waiting or on hold")]
+    NoSecondCall,
     #[fail(display = "CallID not found in call_map: {}", _0)]
     CallIdNotFound(CallId),
     #[fail(display = "Connection not found in connection_map: {}", _0)]
@@ -144,6 +146,34 @@ pub enum RingRtcError {
     #[fail(display = "Failed to decrypt")]
     FailedToDecrypt,
 
+    // Group call chat error codes
+    #[fail(display = "Chat message length of {} bytes is not allowed", _0)]
+    InvalidChatMessageLength(usize),
+
+    // Group call resumption error codes
+    #[fail(display = "Resumption snapshot couldn't be decrypted or is malformed")]
+    InvalidResumptionSnapshot,
+
+    // Group ring store error codes
+    #[fail(display = "Ring store is malformed")]
+    InvalidRingStore,
+
+    // Escalation to group call error codes
+    #[fail(display = "Already moving a direct call to a group call")]
+    EscalationInProgress,
+    #[fail(display = "No request to move call {} to a group call", _0)]
+    NoEscalationRequest(CallId),
+
+    // Simnet error codes
+    #[fail(display = "Link schedule has no step with a link config")]
+    SimnetLinkScheduleWithoutConfig,
+    #[fail(display = "Invalid link config: {}", _0)]
+    SimnetInvalidLinkConfig(String),
+    #[fail(display = "Invalid link trace at line {}: {}", _0, _1)]
+    SimnetInvalidTrace(usize, String),
+    #[fail(display = "Invalid scenario: {}", _0)]
+    SimnetInvalidScenario(String),
+
     // Misc error codes
     #[fail(display = "Failed to negotiate SRTP keys")]
     SrtpKeyNegotiationFailure,
@@ -41,12 +41,16 @@ pub mod core {
     pub mod call_fsm;
     pub mod call_manager;
     pub mod call_mutex;
+    pub mod chat;
     pub mod connection;
     pub mod connection_fsm;
     pub mod crypto;
+    pub mod escalation;
     pub mod group_call;
     pub mod http_client;
     pub mod platform;
+    pub mod resumption;
+    pub mod ring_store;
     pub mod sfu_client;
     pub mod signaling;
     pub mod util;
@@ -146,7 +150,16 @@ pub mod sim {
     pub mod sim_platform;
 }
 
-#[cfg(feature = "simnet")]
+#[cfg(any(feature = "simnet", feature = "sim"))]
 pub mod simnet {
+    pub mod clock;
+    pub mod nat;
+    pub mod pcap;
     pub mod router;
+    pub mod scenario;
+    pub mod sfu;
+    pub mod stats;
+    pub mod stun;
+    pub mod trace;
+    pub mod turn;
 }
@@ -18,6 +18,7 @@ use crate::common::{
 use crate::core::bandwidth_mode::BandwidthMode;
 use crate::core::call::Call;
 use crate::core::connection::{Connection, ConnectionType};
+use crate::core::escalation::EscalationUpdate;
 use crate::core::platform::{Platform, PlatformItem};
 use crate::core::{
     group_call::{self, GroupId, SignalingMessageUrgency, UserId},
@@ -115,6 +116,8 @@ pub trait CallStateHandler {
     fn handle_call_state(&self, remote_peer_id: &str, state: CallState) -> Result<()>;
     fn handle_remote_video_state(&self, remote_peer_id: &str, enabled: bool) -> Result<()>;
     fn handle_remote_sharing_screen(&self, remote_peer_id: &str, enabled: bool) -> Result<()>;
+    fn handle_remote_hold(&self, remote_peer_id: &str, held: bool) -> Result<()>;
+    fn handle_remote_transferred(&self, remote_peer_id: &str) -> Result<()>;
     fn handle_network_route(&self, remote_peer_id: &str, network_route: NetworkRoute)
         -> Result<()>;
 }
@@ -191,6 +194,7 @@ pub enum EndReason {
     DeclinedOnAnotherDevice,
     BusyOnAnotherDevice,
     CallerIsNotMultiring,
+    TransferredToAnotherDevice,
 }
 
 impl fmt::Display for EndReason {
@@ -213,6 +217,7 @@ impl fmt::Display for EndReason {
             EndReason::DeclinedOnAnotherDevice => "DeclinedOnAnotherDevice",
             EndReason::BusyOnAnotherDevice => "BusyOnAnotherDevice",
             EndReason::CallerIsNotMultiring => "CallerIsNotMultiring",
+            EndReason::TransferredToAnotherDevice => "TransferredToAnotherDevice",
         };
         write!(f, "({})", display)
     }
@@ -236,7 +241,24 @@ pub enum GroupUpdate {
     ConnectionStateChanged(group_call::ClientId, group_call::ConnectionState),
     JoinStateChanged(group_call::ClientId, group_call::JoinState),
     RemoteDeviceStatesChanged(group_call::ClientId, Vec<group_call::RemoteDeviceState>),
+    Reaction(group_call::ClientId, group_call::Reaction),
+    MessageReceived(group_call::ClientId, group_call::ChatMessage),
+    ControlRequest(
+        group_call::ClientId,
+        group_call::DemuxId,
+        group_call::ControlRequest,
+    ),
+    AudioLevels(
+        group_call::ClientId,
+        group_call::AudioLevel,
+        Vec<group_call::ReceivedAudioLevel>,
+    ),
+    ResumptionSnapshot(group_call::ClientId, Vec<u8>),
+    LayoutChanged(group_call::ClientId, group_call::Layout),
+    PinnedVideosNotForwarded(group_call::ClientId, Vec<group_call::DemuxId>),
+    RingStatusesChanged(group_call::ClientId, Vec<group_call::RecipientRingStatus>),
     IncomingVideoTrack(group_call::ClientId, group_call::DemuxId, VideoTrack),
+    PendingDevicesChanged(group_call::ClientId, Vec<group_call::PeekDeviceInfo>),
     PeekChanged {
         client_id:    group_call::ClientId,
         members:      Vec<group_call::UserId>,
@@ -260,6 +282,11 @@ pub enum GroupUpdate {
         sender:   group_call::UserId,
         update:   group_call::RingUpdate,
     },
+    Escalation {
+        call_id:  CallId,
+        group_id: group_call::GroupId,
+        update:   EscalationUpdate,
+    },
     NetworkRouteChanged(group_call::ClientId, NetworkRoute),
 }
 
@@ -271,11 +298,21 @@ impl fmt::Display for GroupUpdate {
             GroupUpdate::ConnectionStateChanged(_, _) => "ConnectionStateChanged".to_string(),
             GroupUpdate::JoinStateChanged(_, _) => "JoinStateChanged".to_string(),
             GroupUpdate::RemoteDeviceStatesChanged(_, _) => "RemoteDeviceStatesChanged".to_string(),
+            GroupUpdate::Reaction(_, _) => "Reaction".to_string(),
+            GroupUpdate::MessageReceived(_, _) => "MessageReceived".to_string(),
+            GroupUpdate::ControlRequest(_, _, _) => "ControlRequest".to_string(),
+            GroupUpdate::AudioLevels(_, _, _) => "AudioLevels".to_string(),
+            GroupUpdate::ResumptionSnapshot(_, _) => "ResumptionSnapshot".to_string(),
+            GroupUpdate::LayoutChanged(_, _) => "LayoutChanged".to_string(),
+            GroupUpdate::PinnedVideosNotForwarded(_, _) => "PinnedVideosNotForwarded".to_string(),
+            GroupUpdate::RingStatusesChanged(_, _) => "RingStatusesChanged".to_string(),
             GroupUpdate::IncomingVideoTrack(_, _, _) => "IncomingVideoTrack".to_string(),
+            GroupUpdate::PendingDevicesChanged(_, _) => "PendingDevicesChanged".to_string(),
             GroupUpdate::PeekChanged { .. } => "PeekChanged".to_string(),
             GroupUpdate::PeekResponse { .. } => "PeekResponse".to_string(),
             GroupUpdate::Ended(_, reason) => format!("Ended({:?})", reason),
             GroupUpdate::Ring { update, .. } => format!("Ring({:?})", update),
+            GroupUpdate::Escalation { update, .. } => format!("Escalation({:?})", update),
             GroupUpdate::NetworkRouteChanged(_, network_route) => {
                 format!("NetworkRouteChanged({:?})", network_route)
             }
@@ -353,6 +390,14 @@ impl NativePlatform {
             .handle_remote_sharing_screen(peer_id, enabled)
     }
 
+    fn send_remote_hold(&self, peer_id: &str, held: bool) -> Result<()> {
+        self.state_handler.handle_remote_hold(peer_id, held)
+    }
+
+    fn send_remote_transferred(&self, peer_id: &str) -> Result<()> {
+        self.state_handler.handle_remote_transferred(peer_id)
+    }
+
     fn send_signaling(
         &self,
         recipient_id: &str,
@@ -573,6 +618,13 @@ impl Platform for NativePlatform {
             ApplicationEvent::RemoteSharingScreenDisable => {
                 self.send_remote_sharing_screen(remote_peer, false)
             }
+            ApplicationEvent::RemoteHoldEnable => self.send_remote_hold(remote_peer, true),
+            ApplicationEvent::RemoteHoldDisable => self.send_remote_hold(remote_peer, false),
+            ApplicationEvent::RemoteTransferred => self.send_remote_transferred(remote_peer),
+            ApplicationEvent::EndedTransferredToAnotherDevice => self.send_state(
+                remote_peer,
+                CallState::Ended(EndReason::TransferredToAnotherDevice),
+            ),
         }?;
         Ok(())
     }
@@ -826,6 +878,123 @@ impl Platform for NativePlatform {
         }
     }
 
+    fn handle_reaction(&self, client_id: group_call::ClientId, reaction: group_call::Reaction) {
+        info!("NativePlatform::handle_reaction(): id: {}", client_id);
+
+        let result = self.send_group_update(GroupUpdate::Reaction(client_id, reaction));
+        if result.is_err() {
+            error!("{:?}", result.err());
+        }
+    }
+
+    fn handle_message_received(
+        &self,
+        client_id: group_call::ClientId,
+        message: group_call::ChatMessage,
+    ) {
+        info!(
+            "NativePlatform::handle_message_received(): id: {}",
+            client_id
+        );
+
+        let result = self.send_group_update(GroupUpdate::MessageReceived(client_id, message));
+        if result.is_err() {
+            error!("{:?}", result.err());
+        }
+    }
+
+    fn handle_control_request(
+        &self,
+        client_id: group_call::ClientId,
+        sender_demux_id: group_call::DemuxId,
+        request: group_call::ControlRequest,
+    ) {
+        info!(
+            "NativePlatform::handle_control_request(): id: {}",
+            client_id
+        );
+
+        let result = self.send_group_update(GroupUpdate::ControlRequest(
+            client_id,
+            sender_demux_id,
+            request,
+        ));
+        if result.is_err() {
+            error!("{:?}", result.err());
+        }
+    }
+
+    fn handle_audio_levels(
+        &self,
+        client_id: group_call::ClientId,
+        captured_level: group_call::AudioLevel,
+        received_levels: Vec<group_call::ReceivedAudioLevel>,
+    ) {
+        // Not logged because it's called so often.
+        let result = self.send_group_update(GroupUpdate::AudioLevels(
+            client_id,
+            captured_level,
+            received_levels,
+        ));
+        if result.is_err() {
+            error!("{:?}", result.err());
+        }
+    }
+
+    fn handle_resumption_snapshot(&self, client_id: group_call::ClientId, snapshot: Vec<u8>) {
+        info!(
+            "NativePlatform::handle_resumption_snapshot(): id: {}",
+            client_id
+        );
+
+        let result = self.send_group_update(GroupUpdate::ResumptionSnapshot(client_id, snapshot));
+        if result.is_err() {
+            error!("{:?}", result.err());
+        }
+    }
+
+    fn handle_layout_changed(&self, client_id: group_call::ClientId, layout: group_call::Layout) {
+        info!("NativePlatform::handle_layout_changed(): id: {}", client_id);
+
+        let result = self.send_group_update(GroupUpdate::LayoutChanged(client_id, layout));
+        if result.is_err() {
+            error!("{:?}", result.err());
+        }
+    }
+
+    fn handle_pinned_videos_not_forwarded(
+        &self,
+        client_id: group_call::ClientId,
+        demux_ids: Vec<group_call::DemuxId>,
+    ) {
+        info!(
+            "NativePlatform::handle_pinned_videos_not_forwarded(): id: {}",
+            client_id
+        );
+
+        let result =
+            self.send_group_update(GroupUpdate::PinnedVideosNotForwarded(client_id, demux_ids));
+        if result.is_err() {
+            error!("{:?}", result.err());
+        }
+    }
+
+    fn handle_ring_statuses_changed(
+        &self,
+        client_id: group_call::ClientId,
+        statuses: Vec<group_call::RecipientRingStatus>,
+    ) {
+        info!(
+            "NativePlatform::handle_ring_statuses_changed(): id: {}",
+            client_id
+        );
+
+        let result = self.send_group_update(GroupUpdate::RingStatusesChanged(client_id, statuses));
+        if result.is_err() {
+            error!("{:?}", result.err());
+        }
+    }
+
     fn handle_incoming_video_track(
         &self,
         client_id: group_call::ClientId,
@@ -877,6 +1046,26 @@ impl Platform for NativePlatform {
         }
     }
 
+    fn handle_pending_devices_changed(
+        &self,
+        client_id: group_call::ClientId,
+        pending_devices: &[group_call::PeekDeviceInfo],
+    ) {
+        info!(
+            "NativePlatform::handle_pending_devices_changed(): id: {}, count: {}",
+            client_id,
+            pending_devices.len()
+        );
+
+        let result = self.send_group_update(GroupUpdate::PendingDevicesChanged(
+            client_id,
+            pending_devices.to_vec(),
+        ));
+        if result.is_err() {
+            error!("{:?}", result.err());
+        }
+    }
+
     // Response of peek_group_call without group_call::Client
     fn handle_peek_response(
         &self,
@@ -930,4 +1119,25 @@ impl Platform for NativePlatform {
             error!("{:?}", result.err());
         }
     }
+
+    fn handle_escalation_update(
+        &self,
+        call_id: CallId,
+        group_id: group_call::GroupId,
+        update: EscalationUpdate,
+    ) {
+        info!(
+            "NativePlatform::handle_escalation_update(): call_id: {}",
+            call_id
+        );
+
+        let result = self.send_group_update(GroupUpdate::Escalation {
+            call_id,
+            group_id,
+            update,
+        });
+        if result.is_err() {
+            error!("{:?}", result.err());
+        }
+    }
 }
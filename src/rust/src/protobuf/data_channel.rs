@@ -21,6 +21,7 @@ pub mod hangup {
         HangupDeclined = 2,
         HangupBusy = 3,
         HangupNeedPermission = 4,
+        HangupTransferred = 5,
     }
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -31,6 +32,9 @@ pub struct SenderStatus {
     pub video_enabled: ::std::option::Option<bool>,
     #[prost(bool, optional, tag="3")]
     pub sharing_screen: ::std::option::Option<bool>,
+    /// Set while the sender has put the call on hold to take another call.
+    #[prost(bool, optional, tag="4")]
+    pub held: ::std::option::Option<bool>,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ReceiverStatus {
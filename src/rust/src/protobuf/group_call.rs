@@ -8,6 +8,18 @@ pub struct DeviceToDevice {
     pub heartbeat: ::std::option::Option<device_to_device::Heartbeat>,
     #[prost(message, optional, tag="4")]
     pub leaving: ::std::option::Option<device_to_device::Leaving>,
+    #[prost(message, optional, tag="5")]
+    pub reaction: ::std::option::Option<device_to_device::Reaction>,
+    #[prost(message, optional, tag="6")]
+    pub raised_hand: ::std::option::Option<device_to_device::RaisedHand>,
+    #[prost(message, optional, tag="7")]
+    pub chat_message: ::std::option::Option<device_to_device::ChatMessage>,
+    #[prost(message, optional, tag="8")]
+    pub chat_retransmit_request: ::std::option::Option<device_to_device::ChatRetransmitRequest>,
+    #[prost(message, optional, tag="9")]
+    pub chat_state: ::std::option::Option<device_to_device::ChatState>,
+    #[prost(message, optional, tag="10")]
+    pub control_request: ::std::option::Option<device_to_device::ControlRequest>,
 }
 pub mod device_to_device {
     /// Sent over signaling
@@ -19,6 +31,9 @@ pub mod device_to_device {
         pub secret: ::std::option::Option<std::vec::Vec<u8>>,
         #[prost(uint32, optional, tag="3")]
         pub demux_id: ::std::option::Option<u32>,
+        /// An X25519 public key for authenticating control requests (see ControlRequest).
+        #[prost(bytes, optional, tag="4")]
+        pub control_public_key: ::std::option::Option<std::vec::Vec<u8>>,
     }
     /// Sent over RTP data
     #[derive(Clone, PartialEq, ::prost::Message)]
@@ -39,6 +54,81 @@ pub mod device_to_device {
         #[prost(uint32, optional, tag="1")]
         pub demux_id: ::std::option::Option<u32>,
     }
+    /// Sent over RTP data
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Reaction {
+        /// An emoji
+        #[prost(string, optional, tag="1")]
+        pub value: ::std::option::Option<std::string::String>,
+    }
+    /// Sent over RTP data along with every heartbeat
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct RaisedHand {
+        #[prost(bool, optional, tag="1")]
+        pub raised: ::std::option::Option<bool>,
+    }
+    /// Sent over RTP data, split into chunks that fit in one RTP packet
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ChatMessage {
+        /// Increments by one for each message a device sends
+        #[prost(uint64, optional, tag="1")]
+        pub message_id: ::std::option::Option<u64>,
+        #[prost(uint32, optional, tag="2")]
+        pub chunk_index: ::std::option::Option<u32>,
+        #[prost(uint32, optional, tag="3")]
+        pub chunk_count: ::std::option::Option<u32>,
+        #[prost(bytes, optional, tag="4")]
+        pub chunk: ::std::option::Option<std::vec::Vec<u8>>,
+    }
+    /// Sent over RTP data to ask the sender of a chat message to send chunks again
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ChatRetransmitRequest {
+        /// The device that sent the message
+        #[prost(uint32, optional, tag="1")]
+        pub demux_id: ::std::option::Option<u32>,
+        #[prost(uint64, optional, tag="2")]
+        pub message_id: ::std::option::Option<u64>,
+        /// When empty, all of the chunks are requested.
+        #[prost(uint32, repeated, packed="false", tag="3")]
+        pub chunk_indexes: ::std::vec::Vec<u32>,
+    }
+    /// Sent over RTP data along with every heartbeat once a chat message has been sent
+    /// so that receivers can notice when the latest messages were lost.
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ChatState {
+        #[prost(uint64, optional, tag="1")]
+        pub latest_message_id: ::std::option::Option<u64>,
+    }
+    /// Sent over RTP data by a device of the call creator to ask another device
+    /// to do something.  Every device in the call has every other device's media
+    /// key, so the encryption only shows that it came from someone in the call.
+    /// The MAC shows that it came from the creator's device: its key is derived
+    /// from the control keys of the sender and the target, which each sent the
+    /// other with its MediaKey over signaling.  Requests without a valid MAC are
+    /// ignored.
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ControlRequest {
+        #[prost(enumeration="control_request::Type", optional, tag="1")]
+        pub r#type: ::std::option::Option<i32>,
+        /// The device being asked
+        #[prost(uint32, optional, tag="2")]
+        pub target_demux_id: ::std::option::Option<u32>,
+        /// Keeps a request from being replayed in a later era of the call.
+        #[prost(string, optional, tag="3")]
+        pub era_id: ::std::option::Option<std::string::String>,
+        /// HMAC-SHA256 over the sender's demux ID and the fields above.
+        #[prost(bytes, optional, tag="4")]
+        pub mac: ::std::option::Option<std::vec::Vec<u8>>,
+    }
+    pub mod control_request {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+        #[repr(i32)]
+        pub enum Type {
+            Mute = 0,
+            LowerHand = 1,
+            Remove = 2,
+        }
+    }
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct DeviceToSfu {
@@ -63,6 +153,10 @@ pub mod device_to_sfu {
             pub short_device_id: ::std::option::Option<u64>,
             #[prost(uint32, optional, tag="2")]
             pub height: ::std::option::Option<u32>,
+            /// When the SFU can't forward everything requested, higher priorities
+            /// are forwarded first.  Unset is the lowest.
+            #[prost(uint32, optional, tag="3")]
+            pub priority: ::std::option::Option<u32>,
         }
     }
 }
@@ -101,3 +195,72 @@ pub mod sfu_to_device {
         pub demux_ids: ::std::vec::Vec<u32>,
     }
 }
+/// Saved by the app so that a device can rejoin a call with the same endpoint
+/// and media keys after its process restarts.  Never sent to other devices or
+/// to the SFU, and only stored encrypted.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ResumptionSnapshot {
+    #[prost(bytes, optional, tag="1")]
+    pub group_id: ::std::option::Option<std::vec::Vec<u8>>,
+    #[prost(uint32, optional, tag="2")]
+    pub demux_id: ::std::option::Option<u32>,
+    #[prost(string, optional, tag="3")]
+    pub long_device_id: ::std::option::Option<std::string::String>,
+    #[prost(uint32, optional, tag="4")]
+    pub send_ratchet_counter: ::std::option::Option<u32>,
+    #[prost(bytes, optional, tag="5")]
+    pub send_secret: ::std::option::Option<std::vec::Vec<u8>>,
+    /// tag 6 was previously "next_frame_counter", which is no longer used
+    #[prost(message, repeated, tag="7")]
+    pub remote_devices: ::std::vec::Vec<resumption_snapshot::RemoteDevice>,
+    #[prost(bytes, optional, tag="8")]
+    pub control_secret: ::std::option::Option<std::vec::Vec<u8>>,
+}
+pub mod resumption_snapshot {
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct RemoteDevice {
+        #[prost(bytes, optional, tag="1")]
+        pub user_id: ::std::option::Option<std::vec::Vec<u8>>,
+        #[prost(uint32, optional, tag="2")]
+        pub demux_id: ::std::option::Option<u32>,
+        /// Unset if the device hadn't sent its media key yet.
+        #[prost(uint32, optional, tag="3")]
+        pub ratchet_counter: ::std::option::Option<u32>,
+        #[prost(bytes, optional, tag="4")]
+        pub secret: ::std::option::Option<std::vec::Vec<u8>>,
+        /// Unset if the device hadn't sent its control public key yet.
+        #[prost(bytes, optional, tag="5")]
+        pub control_public_key: ::std::option::Option<std::vec::Vec<u8>>,
+    }
+}
+/// Saved by FileRingStore so that outstanding group rings survive a restart.
+/// Never sent to other devices.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RingStoreContents {
+    #[prost(message, repeated, tag="1")]
+    pub rings: ::std::vec::Vec<ring_store_contents::Ring>,
+    #[prost(message, repeated, tag="2")]
+    pub seen_rings: ::std::vec::Vec<ring_store_contents::SeenRing>,
+}
+pub mod ring_store_contents {
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Ring {
+        #[prost(bytes, optional, tag="1")]
+        pub group_id: ::std::option::Option<std::vec::Vec<u8>>,
+        #[prost(int64, optional, tag="2")]
+        pub ring_id: ::std::option::Option<i64>,
+        #[prost(bytes, optional, tag="3")]
+        pub sender_uuid: ::std::option::Option<std::vec::Vec<u8>>,
+        /// Since the Unix epoch
+        #[prost(uint64, optional, tag="4")]
+        pub received_millis: ::std::option::Option<u64>,
+    }
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SeenRing {
+        #[prost(int64, optional, tag="1")]
+        pub ring_id: ::std::option::Option<i64>,
+        /// Since the Unix epoch
+        #[prost(uint64, optional, tag="2")]
+        pub seen_millis: ::std::option::Option<u64>,
+    }
+}
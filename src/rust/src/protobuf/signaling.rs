@@ -4,6 +4,10 @@
 pub struct Offer {
     #[prost(message, optional, tag="4")]
     pub v4: ::std::option::Option<ConnectionParametersV4>,
+    /// Set when another of the sender's devices is taking over an established
+    /// call, rather than starting a new one.
+    #[prost(bool, optional, tag="5")]
+    pub transfer: ::std::option::Option<bool>,
 }
 /// A serialized one these goes in the "opaque" field of the CallingMessage::Offer in SignalService.proto
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -75,6 +79,8 @@ pub struct CallMessage {
     pub ring_intention: ::std::option::Option<call_message::RingIntention>,
     #[prost(message, optional, tag="3")]
     pub ring_response: ::std::option::Option<call_message::RingResponse>,
+    #[prost(message, optional, tag="4")]
+    pub escalation: ::std::option::Option<call_message::Escalation>,
 }
 pub mod call_message {
     #[derive(Clone, PartialEq, ::prost::Message)]
@@ -115,6 +121,26 @@ pub mod call_message {
             Busy = 3,
         }
     }
+    /// Sent between the two users of a direct call to move it to a group call.
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Escalation {
+        #[prost(bytes, optional, tag="1")]
+        pub group_id: ::std::option::Option<std::vec::Vec<u8>>,
+        /// The ID of the direct call being moved.
+        #[prost(uint64, optional, tag="2")]
+        pub call_id: ::std::option::Option<u64>,
+        #[prost(enumeration="escalation::Type", optional, tag="3")]
+        pub r#type: ::std::option::Option<i32>,
+    }
+    pub mod escalation {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+        #[repr(i32)]
+        pub enum Type {
+            Request = 0,
+            Accept = 1,
+            Decline = 2,
+        }
+    }
 }
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
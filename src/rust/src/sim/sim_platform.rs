@@ -23,6 +23,7 @@ use crate::core::bandwidth_mode::BandwidthMode;
 use crate::core::call::Call;
 use crate::core::call_manager::CallManager;
 use crate::core::connection::{Connection, ConnectionType};
+use crate::core::escalation::EscalationUpdate;
 use crate::core::platform::{Platform, PlatformItem};
 use crate::core::{group_call, signaling};
 use crate::sim::error::SimError;
@@ -40,6 +41,8 @@ impl PlatformItem for SimPlatformItem {}
 struct SimStats {
     /// Number of offers sent
     offers_sent:                  AtomicUsize,
+    /// Number of offers sent to take over a call from another device
+    transfer_offers_sent:         AtomicUsize,
     /// Number of answers sent
     answers_sent:                 AtomicUsize,
     /// Number of ICE candidates sent
@@ -54,6 +57,8 @@ struct SimStats {
     busy_hangups_sent:            AtomicUsize,
     /// Number of need permission hangups sent
     need_permission_hangups_sent: AtomicUsize,
+    /// Number of transferred hangups sent
+    transferred_hangups_sent:     AtomicUsize,
     /// Number of busy messages sent
     busys_sent:                   AtomicUsize,
     /// Number of start outgoing call events
@@ -74,6 +79,13 @@ pub struct GroupCallRingUpdate {
     pub update:   group_call::RingUpdate,
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub struct EscalationUpdateEvent {
+    pub call_id:  CallId,
+    pub group_id: group_call::GroupId,
+    pub update:   EscalationUpdate,
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct OutgoingCallMessage {
     pub recipient: group_call::UserId,
@@ -97,6 +109,8 @@ pub struct SimPlatform {
     incoming_media_disconnected:  Arc<AtomicBool>,
     /// Track group call ring updates
     group_call_ring_updates:      Arc<Mutex<Vec<GroupCallRingUpdate>>>,
+    /// Track updates on moving direct calls to group calls
+    escalation_updates:           Arc<Mutex<Vec<EscalationUpdateEvent>>>,
     /// Track outgoing opaque messages
     outgoing_call_messages:       Arc<Mutex<Vec<OutgoingCallMessage>>>,
     /// Call Manager
@@ -220,6 +234,12 @@ impl Platform for SimPlatform {
             Err(SimError::SendOfferError.into())
         } else {
             let _ = self.stats.offers_sent.fetch_add(1, Ordering::AcqRel);
+            if offer.is_transfer() {
+                let _ = self
+                    .stats
+                    .transfer_offers_sent
+                    .fetch_add(1, Ordering::AcqRel);
+            }
             if self.force_internal_fault.load(Ordering::Acquire) {
                 self.message_send_failure(call_id).unwrap();
             } else {
@@ -335,6 +355,12 @@ impl Platform for SimPlatform {
                         .need_permission_hangups_sent
                         .fetch_add(1, Ordering::AcqRel);
                 }
+                signaling::Hangup::TransferredToAnotherDevice(_) => {
+                    let _ = self
+                        .stats
+                        .transferred_hangups_sent
+                        .fetch_add(1, Ordering::AcqRel);
+                }
             }
             if self.force_internal_fault.load(Ordering::Acquire) {
                 self.message_send_failure(call_id).unwrap();
@@ -482,7 +508,6 @@ impl Platform for SimPlatform {
         _client_id: group_call::ClientId,
         _connection_state: group_call::ConnectionState,
     ) {
-        unimplemented!()
     }
 
 
@@ -509,6 +534,49 @@ impl Platform for SimPlatform {
     ) {
     }
 
+    fn handle_reaction(&self, _client_id: group_call::ClientId, _reaction: group_call::Reaction) {}
+
+    fn handle_message_received(
+        &self,
+        _client_id: group_call::ClientId,
+        _message: group_call::ChatMessage,
+    ) {
+    }
+
+    fn handle_control_request(
+        &self,
+        _client_id: group_call::ClientId,
+        _sender_demux_id: group_call::DemuxId,
+        _request: group_call::ControlRequest,
+    ) {
+    }
+
+    fn handle_audio_levels(
+        &self,
+        _client_id: group_call::ClientId,
+        _captured_level: group_call::AudioLevel,
+        _received_levels: Vec<group_call::ReceivedAudioLevel>,
+    ) {
+    }
+
+    fn handle_resumption_snapshot(&self, _client_id: group_call::ClientId, _snapshot: Vec<u8>) {}
+
+    fn handle_layout_changed(&self, _client_id: group_call::ClientId, _layout: group_call::Layout) {}
+
+    fn handle_pinned_videos_not_forwarded(
+        &self,
+        _client_id: group_call::ClientId,
+        _demux_ids: Vec<group_call::DemuxId>,
+    ) {
+    }
+
+    fn handle_ring_statuses_changed(
+        &self,
+        _client_id: group_call::ClientId,
+        _statuses: Vec<group_call::RecipientRingStatus>,
+    ) {
+    }
+
     fn handle_incoming_video_track(
         &self,
         _client_id: group_call::ClientId,
@@ -530,6 +598,13 @@ impl Platform for SimPlatform {
         unimplemented!()
     }
 
+    fn handle_pending_devices_changed(
+        &self,
+        _client_id: group_call::ClientId,
+        _pending_devices: &[group_call::PeekDeviceInfo],
+    ) {
+    }
+
     fn handle_peek_response(
         &self,
         _request_id: u32,
@@ -542,9 +617,7 @@ impl Platform for SimPlatform {
         unimplemented!()
     }
 
-    fn handle_ended(&self, _client_id: group_call::ClientId, _reason: group_call::EndReason) {
-        unimplemented!()
-    }
+    fn handle_ended(&self, _client_id: group_call::ClientId, _reason: group_call::EndReason) {}
 
     fn group_call_ring_update(
         &self,
@@ -563,6 +636,22 @@ impl Platform for SimPlatform {
                 update,
             });
     }
+
+    fn handle_escalation_update(
+        &self,
+        call_id: CallId,
+        group_id: group_call::GroupId,
+        update: EscalationUpdate,
+    ) {
+        self.escalation_updates
+            .lock()
+            .unwrap()
+            .push(EscalationUpdateEvent {
+                call_id,
+                group_id,
+                update,
+            });
+    }
 }
 
 impl SimPlatform {
@@ -639,6 +728,7 @@ impl SimPlatform {
             ApplicationEvent::EndedSignalingFailure,
             ApplicationEvent::EndedConnectionFailure,
             ApplicationEvent::EndedAppDroppedCall,
+            ApplicationEvent::EndedTransferredToAnotherDevice,
         ];
         for event in ended_events {
             ends += self.event_count(event);
@@ -651,6 +741,10 @@ impl SimPlatform {
         self.stats.offers_sent.load(Ordering::Acquire)
     }
 
+    pub fn transfer_offers_sent(&self) -> usize {
+        self.stats.transfer_offers_sent.load(Ordering::Acquire)
+    }
+
     pub fn answers_sent(&self) -> usize {
         self.stats.answers_sent.load(Ordering::Acquire)
     }
@@ -685,6 +779,10 @@ impl SimPlatform {
             .load(Ordering::Acquire)
     }
 
+    pub fn transferred_hangups_sent(&self) -> usize {
+        self.stats.transferred_hangups_sent.load(Ordering::Acquire)
+    }
+
     pub fn busys_sent(&self) -> usize {
         self.stats.busys_sent.load(Ordering::Acquire)
     }
@@ -713,6 +811,10 @@ impl SimPlatform {
         std::mem::take(&mut *self.group_call_ring_updates.lock().unwrap())
     }
 
+    pub fn take_escalation_updates(&self) -> Vec<EscalationUpdateEvent> {
+        std::mem::take(&mut *self.escalation_updates.lock().unwrap())
+    }
+
     pub fn take_outgoing_call_messages(&self) -> Vec<OutgoingCallMessage> {
         std::mem::take(&mut *self.outgoing_call_messages.lock().unwrap())
     }
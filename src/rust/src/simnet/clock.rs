@@ -0,0 +1,181 @@
+//
+// Copyright 2021 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! The source of time for simnet.
+
+use crate::common::actor::Actor;
+#[cfg(any(feature = "simnet", feature = "sim"))]
+use crate::core::util::ClockPauseGuard;
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    sync::{mpsc::channel, Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+// Either real time or virtual time.  Virtual time only moves
+// forward when advanced, which lets a long simulated call run
+// as fast as the CPU allows.  When using virtual time with a
+// CallManager, pause its clock with CallManager::pause_clock
+// and advance both together with advance_with.
+#[derive(Clone, Default)]
+pub struct SimClock {
+    virtual_time: Option<Arc<Mutex<VirtualTime>>>,
+}
+
+struct VirtualTime {
+    now:      Instant,
+    // Used to run events with the same deadline in the order they were scheduled.
+    next_seq: u64,
+    events:   BinaryHeap<Event>,
+}
+
+struct Event {
+    deadline: Instant,
+    seq:      u64,
+    run:      Box<dyn FnOnce() + Send>,
+}
+
+impl SimClock {
+    pub fn real() -> Self {
+        Self { virtual_time: None }
+    }
+
+    pub fn new_virtual() -> Self {
+        Self {
+            virtual_time: Some(Arc::new(Mutex::new(VirtualTime {
+                now:      Instant::now(),
+                next_seq: 0,
+                events:   BinaryHeap::new(),
+            }))),
+        }
+    }
+
+    pub fn is_virtual(&self) -> bool {
+        self.virtual_time.is_some()
+    }
+
+    pub fn now(&self) -> Instant {
+        match &self.virtual_time {
+            None => Instant::now(),
+            Some(virtual_time) => virtual_time.lock().expect("Lock virtual time").now,
+        }
+    }
+
+    // Like Actor::send_delayed, but according to this clock.
+    // With virtual time, the task runs while the clock is being
+    // advanced and the clock waits for it to finish, so now()
+    // is the deadline of the task while it runs.
+    pub fn send_delayed<State: 'static>(
+        &self,
+        actor: &Actor<State>,
+        delay: Duration,
+        run: impl FnOnce(&mut State) + Send + 'static,
+    ) {
+        match &self.virtual_time {
+            None => actor.send_delayed(delay, run),
+            Some(virtual_time) => {
+                let actor = actor.clone();
+                let mut virtual_time = virtual_time.lock().expect("Lock virtual time");
+                let deadline = virtual_time.now + delay;
+                let seq = virtual_time.next_seq;
+                virtual_time.next_seq += 1;
+                virtual_time.events.push(Event {
+                    deadline,
+                    seq,
+                    run: Box::new(move || {
+                        let (finished_sender, finished_receiver) = channel();
+                        actor.send(move |state| {
+                            run(state);
+                            let _ = finished_sender.send(());
+                        });
+                        // If the actor has stopped, this returns an error right away.
+                        let _ = finished_receiver.recv();
+                    }),
+                });
+            }
+        }
+    }
+
+    // With real time, sleeps.  With virtual time, runs every task
+    // scheduled up to now + duration.  Must not be called from an
+    // actor that has tasks scheduled on this clock.
+    pub fn advance(&self, duration: Duration) {
+        let virtual_time = match &self.virtual_time {
+            None => {
+                thread::sleep(duration);
+                return;
+            }
+            Some(virtual_time) => virtual_time,
+        };
+        let target = virtual_time.lock().expect("Lock virtual time").now + duration;
+        loop {
+            let event = {
+                let mut virtual_time = virtual_time.lock().expect("Lock virtual time");
+                match virtual_time.events.peek() {
+                    Some(event) if event.deadline <= target => {
+                        let event = virtual_time.events.pop().expect("Pop peeked event");
+                        virtual_time.now = virtual_time.now.max(event.deadline);
+                        event
+                    }
+                    _ => {
+                        virtual_time.now = target;
+                        return;
+                    }
+                }
+            };
+            // Run without the lock so the event can schedule more events.
+            (event.run)();
+        }
+    }
+
+    // Advances this clock and the paused clocks of CallManagers
+    // (or other TaskQueueRuntimes) together, one step at a time.
+    #[cfg(any(feature = "simnet", feature = "sim"))]
+    pub fn advance_with(
+        &self,
+        duration: Duration,
+        step: Duration,
+        paused_clocks: &[&ClockPauseGuard],
+    ) {
+        let mut remaining = duration;
+        while remaining > Duration::from_secs(0) {
+            let step = if step == Duration::from_secs(0) {
+                remaining
+            } else {
+                step.min(remaining)
+            };
+            self.advance(step);
+            for paused_clock in paused_clocks {
+                paused_clock.advance(step);
+            }
+            remaining -= step;
+        }
+    }
+}
+
+impl Ord for Event {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // We reverse the order because we want the earlier to go first in a BinaryHeap
+        (self.deadline, self.seq)
+            .cmp(&(other.deadline, other.seq))
+            .reverse()
+    }
+}
+
+impl PartialOrd for Event {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Event {
+    fn eq(&self, other: &Self) -> bool {
+        (self.deadline, self.seq) == (other.deadline, other.seq)
+    }
+}
+
+impl Eq for Event {}
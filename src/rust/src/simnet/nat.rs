@@ -0,0 +1,255 @@
+//
+// Copyright 2021 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! NATs and firewalls that can be put in front of simnet interfaces.
+
+use crate::simnet::router::{Packet, Protocol};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    net::{IpAddr, SocketAddr},
+};
+
+// Where mapped ports start.  Below are the well-known ports.
+const FIRST_MAPPED_PORT: u16 = 1024;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NatType {
+    // Once mapped, anyone can send to the mapped port.
+    FullCone,
+    // Only IPs that were sent to can send to the mapped port.
+    AddressRestricted,
+    // Only IP and port pairs that were sent to can send to the mapped port.
+    PortRestricted,
+    // Every remote address gets a different mapped port
+    // and only that remote address can send to it.
+    Symmetric,
+}
+
+#[derive(Clone, Debug)]
+pub struct NatConfig {
+    pub nat_type:  NatType,
+    // Where packets sent through the NAT appear to come from.
+    pub public_ip: IpAddr,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Firewall {
+    Open,
+    // Drops UDP in both directions unless the remote port is one
+    // of the given ones (such as 3478 for STUN and TURN).
+    // TCP is let through.
+    BlockUdp { allowed_remote_ports: Vec<u16> },
+}
+
+impl Firewall {
+    pub(super) fn allows(&self, protocol: Protocol, remote: SocketAddr) -> bool {
+        match self {
+            Firewall::Open => true,
+            Firewall::BlockUdp {
+                allowed_remote_ports,
+            } => protocol == Protocol::Tcp || allowed_remote_ports.contains(&remote.port()),
+        }
+    }
+}
+
+// Translates the addresses of the packets going out of and coming into one interface.
+pub(super) struct Nat {
+    config:                 NatConfig,
+    next_public_port:       u16,
+    // Keyed by the private address and, for symmetric NATs, the remote address.
+    public_port_by_key:     HashMap<(SocketAddr, Option<SocketAddr>), u16>,
+    mapping_by_public_port: HashMap<u16, NatMapping>,
+}
+
+struct NatMapping {
+    private_address:  SocketAddr,
+    // The remote addresses sent to through the mapping.
+    remote_addresses: HashSet<SocketAddr>,
+}
+
+impl Nat {
+    pub fn new(config: NatConfig) -> Self {
+        Self {
+            config,
+            next_public_port: FIRST_MAPPED_PORT,
+            public_port_by_key: HashMap::new(),
+            mapping_by_public_port: HashMap::new(),
+        }
+    }
+
+    pub fn public_ip(&self) -> IpAddr {
+        self.config.public_ip
+    }
+
+    // Maps the source of the packet to a public address, creating a mapping if needed,
+    // or returns None if every public port is already mapped.
+    pub fn translate_outgoing(&mut self, mut packet: Packet) -> Option<Packet> {
+        let remote_key = if self.config.nat_type == NatType::Symmetric {
+            Some(packet.dest)
+        } else {
+            None
+        };
+        let key = (packet.source, remote_key);
+        let public_port = match self.public_port_by_key.get(&key) {
+            Some(public_port) => *public_port,
+            None => {
+                let public_port = self.allocate_public_port()?;
+                self.public_port_by_key.insert(key, public_port);
+                self.mapping_by_public_port.insert(
+                    public_port,
+                    NatMapping {
+                        private_address:  packet.source,
+                        remote_addresses: HashSet::new(),
+                    },
+                );
+                public_port
+            }
+        };
+        if let Some(mapping) = self.mapping_by_public_port.get_mut(&public_port) {
+            mapping.remote_addresses.insert(packet.dest);
+        }
+        packet.source = SocketAddr::new(self.config.public_ip, public_port);
+        Some(packet)
+    }
+
+    // Finds the next public port that isn't mapped yet, wrapping around to the start.
+    fn allocate_public_port(&mut self) -> Option<u16> {
+        let pool_size = (u16::MAX - FIRST_MAPPED_PORT) as usize + 1;
+        for _ in 0..pool_size {
+            let public_port = self.next_public_port;
+            self.next_public_port = public_port.checked_add(1).unwrap_or(FIRST_MAPPED_PORT);
+            if !self.mapping_by_public_port.contains_key(&public_port) {
+                return Some(public_port);
+            }
+        }
+        None
+    }
+
+    // Maps the destination of the packet back to a private address,
+    // or returns None if the NAT filters it out.
+    pub fn translate_incoming(&self, mut packet: Packet) -> Option<Packet> {
+        let mapping = self.mapping_by_public_port.get(&packet.dest.port())?;
+        let remote = packet.source;
+        let allowed = match self.config.nat_type {
+            NatType::FullCone => true,
+            NatType::AddressRestricted => mapping
+                .remote_addresses
+                .iter()
+                .any(|address| address.ip() == remote.ip()),
+            NatType::PortRestricted | NatType::Symmetric => {
+                mapping.remote_addresses.contains(&remote)
+            }
+        };
+        if !allowed {
+            return None;
+        }
+        packet.dest = mapping.private_address;
+        Some(packet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(source: &str, dest: &str) -> Packet {
+        Packet {
+            source:   source.parse().unwrap(),
+            dest:     dest.parse().unwrap(),
+            protocol: Protocol::Udp,
+            data:     vec![],
+        }
+    }
+
+    fn nat(nat_type: NatType) -> Nat {
+        Nat::new(NatConfig {
+            nat_type,
+            public_ip: "5.0.0.1".parse().unwrap(),
+        })
+    }
+
+    // Sends from 10.0.0.1:1000 to the given remote and returns the mapped address.
+    fn send(nat: &mut Nat, remote: &str) -> SocketAddr {
+        nat.translate_outgoing(packet("10.0.0.1:1000", remote))
+            .unwrap()
+            .source
+    }
+
+    fn receive(nat: &Nat, remote: &str, public_address: SocketAddr) -> Option<SocketAddr> {
+        let mut packet = packet(remote, "5.0.0.1:1");
+        packet.dest = public_address;
+        nat.translate_incoming(packet).map(|packet| packet.dest)
+    }
+
+    #[test]
+    fn full_cone() {
+        let mut nat = nat(NatType::FullCone);
+        let mapped = send(&mut nat, "2.0.0.1:2000");
+        assert_eq!("5.0.0.1:1024".parse::<SocketAddr>().unwrap(), mapped);
+        assert_eq!(mapped, send(&mut nat, "3.0.0.1:3000"));
+        let private = Some("10.0.0.1:1000".parse().unwrap());
+        assert_eq!(private, receive(&nat, "4.0.0.1:4000", mapped));
+        assert_eq!(
+            None,
+            receive(&nat, "4.0.0.1:4000", "5.0.0.1:2000".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn address_restricted() {
+        let mut nat = nat(NatType::AddressRestricted);
+        let mapped = send(&mut nat, "2.0.0.1:2000");
+        assert!(receive(&nat, "2.0.0.1:2001", mapped).is_some());
+        assert!(receive(&nat, "3.0.0.1:2000", mapped).is_none());
+    }
+
+    #[test]
+    fn port_restricted() {
+        let mut nat = nat(NatType::PortRestricted);
+        let mapped = send(&mut nat, "2.0.0.1:2000");
+        assert!(receive(&nat, "2.0.0.1:2000", mapped).is_some());
+        assert!(receive(&nat, "2.0.0.1:2001", mapped).is_none());
+    }
+
+    #[test]
+    fn symmetric() {
+        let mut nat = nat(NatType::Symmetric);
+        let mapped1 = send(&mut nat, "2.0.0.1:2000");
+        let mapped2 = send(&mut nat, "3.0.0.1:3000");
+        assert_ne!(mapped1, mapped2);
+        assert!(receive(&nat, "2.0.0.1:2000", mapped1).is_some());
+        assert!(receive(&nat, "3.0.0.1:3000", mapped1).is_none());
+        assert!(receive(&nat, "3.0.0.1:3000", mapped2).is_some());
+    }
+
+    #[test]
+    fn symmetric_runs_out_of_ports() {
+        let mut nat = nat(NatType::Symmetric);
+        let first = send(&mut nat, "2.0.0.1:1");
+        for port in 2..=(u16::MAX - FIRST_MAPPED_PORT + 1) {
+            send(&mut nat, &format!("2.0.0.1:{}", port));
+        }
+        assert!(nat
+            .translate_outgoing(packet("10.0.0.1:1000", "3.0.0.1:3000"))
+            .is_none());
+        // The existing mappings are still there.
+        assert_eq!(first, send(&mut nat, "2.0.0.1:1"));
+        assert!(receive(&nat, "2.0.0.1:1", first).is_some());
+    }
+
+    #[test]
+    fn firewall_blocks_udp() {
+        let firewall = Firewall::BlockUdp {
+            allowed_remote_ports: vec![3478],
+        };
+        let remote = "2.0.0.1:2000".parse().unwrap();
+        assert!(!firewall.allows(Protocol::Udp, remote));
+        assert!(firewall.allows(Protocol::Tcp, remote));
+        assert!(firewall.allows(Protocol::Udp, "2.0.0.1:3478".parse().unwrap()));
+        assert!(Firewall::Open.allows(Protocol::Udp, remote));
+    }
+}
@@ -0,0 +1,285 @@
+//
+// Copyright 2021 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Writes simnet packets in the libpcap format so that
+//! captures can be opened in Wireshark.
+
+use crate::simnet::router::{Packet, Protocol};
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    net::{IpAddr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+
+const PCAP_MAGIC_NUMBER: u32 = 0xa1b2_c3d4;
+const PCAP_VERSION_MAJOR: u16 = 2;
+const PCAP_VERSION_MINOR: u16 = 4;
+const PCAP_SNAPLEN: u32 = 65535;
+// Each packet starts with its IPv4 or IPv6 header.
+const LINKTYPE_RAW: u32 = 101;
+
+const IP_PROTOCOL_TCP: u8 = 6;
+const IP_PROTOCOL_UDP: u8 = 17;
+const IP_TTL: u8 = 64;
+
+const TCP_FLAGS_PSH_ACK: u8 = 0x18;
+const TCP_WINDOW: u16 = 65535;
+
+// Since simnet packets only have addresses and a payload, the
+// IP and UDP or TCP headers are made up when writing them.
+pub struct PcapWriter<W: Write> {
+    writer:                W,
+    // So that TCP segments follow each other in Wireshark.
+    // Keyed by (source, dest).
+    next_sequence_by_flow: HashMap<(SocketAddr, SocketAddr), u32>,
+}
+
+impl<W: Write> PcapWriter<W> {
+    // Writes the file header right away.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(&PCAP_MAGIC_NUMBER.to_le_bytes())?;
+        writer.write_all(&PCAP_VERSION_MAJOR.to_le_bytes())?;
+        writer.write_all(&PCAP_VERSION_MINOR.to_le_bytes())?;
+        // Time zone offset and timestamp accuracy
+        writer.write_all(&0i32.to_le_bytes())?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.write_all(&PCAP_SNAPLEN.to_le_bytes())?;
+        writer.write_all(&LINKTYPE_RAW.to_le_bytes())?;
+        Ok(Self {
+            writer,
+            next_sequence_by_flow: HashMap::new(),
+        })
+    }
+
+    // The timestamp is since the UNIX epoch.
+    pub fn write_packet(&mut self, timestamp: Duration, packet: &Packet) -> io::Result<()> {
+        let segment = match packet.protocol {
+            Protocol::Udp => udp_datagram(packet),
+            Protocol::Tcp => {
+                let next_sequence = self
+                    .next_sequence_by_flow
+                    .entry((packet.source, packet.dest))
+                    .or_insert(0);
+                let sequence = *next_sequence;
+                *next_sequence = next_sequence.wrapping_add(packet.data.len() as u32);
+                tcp_segment(packet, sequence)
+            }
+        };
+        let data = ip_datagram(packet, segment);
+        let captured_len = data.len().min(PCAP_SNAPLEN as usize);
+        self.writer
+            .write_all(&(timestamp.as_secs() as u32).to_le_bytes())?;
+        self.writer
+            .write_all(&timestamp.subsec_micros().to_le_bytes())?;
+        self.writer
+            .write_all(&(captured_len as u32).to_le_bytes())?;
+        self.writer.write_all(&(data.len() as u32).to_le_bytes())?;
+        self.writer.write_all(&data[..captured_len])
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+// The checksum is left for ip_datagram to fill in.
+fn udp_datagram(packet: &Packet) -> Vec<u8> {
+    let udp_len = 8 + packet.data.len();
+    let mut udp = Vec::with_capacity(udp_len);
+    udp.extend_from_slice(&packet.source.port().to_be_bytes());
+    udp.extend_from_slice(&packet.dest.port().to_be_bytes());
+    udp.extend_from_slice(&(udp_len as u16).to_be_bytes());
+    udp.extend_from_slice(&[0, 0]);
+    udp.extend_from_slice(&packet.data);
+    udp
+}
+
+// The checksum is left for ip_datagram to fill in.
+// Every segment acknowledges nothing in particular.
+fn tcp_segment(packet: &Packet, sequence: u32) -> Vec<u8> {
+    let mut tcp = Vec::with_capacity(20 + packet.data.len());
+    tcp.extend_from_slice(&packet.source.port().to_be_bytes());
+    tcp.extend_from_slice(&packet.dest.port().to_be_bytes());
+    tcp.extend_from_slice(&sequence.to_be_bytes());
+    tcp.extend_from_slice(&0u32.to_be_bytes());
+    // A data offset of 5 words (no options)
+    tcp.extend_from_slice(&[5 << 4, TCP_FLAGS_PSH_ACK]);
+    tcp.extend_from_slice(&TCP_WINDOW.to_be_bytes());
+    // Checksum and urgent pointer
+    tcp.extend_from_slice(&[0, 0, 0, 0]);
+    tcp.extend_from_slice(&packet.data);
+    tcp
+}
+
+// Puts the UDP datagram or TCP segment into an IP datagram
+// and fills in its checksum.
+fn ip_datagram(packet: &Packet, mut segment: Vec<u8>) -> Vec<u8> {
+    let (ip_protocol, checksum_offset) = match packet.protocol {
+        Protocol::Udp => (IP_PROTOCOL_UDP, 6),
+        Protocol::Tcp => (IP_PROTOCOL_TCP, 16),
+    };
+    let segment_len = segment.len();
+
+    match (packet.source.ip(), packet.dest.ip()) {
+        (IpAddr::V4(source), IpAddr::V4(dest)) => {
+            let mut pseudo_header = Vec::with_capacity(12);
+            pseudo_header.extend_from_slice(&source.octets());
+            pseudo_header.extend_from_slice(&dest.octets());
+            pseudo_header.extend_from_slice(&[0, ip_protocol]);
+            pseudo_header.extend_from_slice(&(segment_len as u16).to_be_bytes());
+            set_checksum(&mut segment, checksum_offset, &pseudo_header);
+
+            let mut datagram = Vec::with_capacity(20 + segment_len);
+            datagram.extend_from_slice(&[0x45, 0]);
+            datagram.extend_from_slice(&((20 + segment_len) as u16).to_be_bytes());
+            // Identification, flags, and fragment offset
+            datagram.extend_from_slice(&[0, 0, 0, 0]);
+            datagram.extend_from_slice(&[IP_TTL, ip_protocol]);
+            datagram.extend_from_slice(&[0, 0]);
+            datagram.extend_from_slice(&source.octets());
+            datagram.extend_from_slice(&dest.octets());
+            let header_checksum = internet_checksum(&[&datagram]);
+            datagram[10..12].copy_from_slice(&header_checksum.to_be_bytes());
+            datagram.extend_from_slice(&segment);
+            datagram
+        }
+        (source, dest) => {
+            let source = ipv6(source);
+            let dest = ipv6(dest);
+            let mut pseudo_header = Vec::with_capacity(40);
+            pseudo_header.extend_from_slice(&source.octets());
+            pseudo_header.extend_from_slice(&dest.octets());
+            pseudo_header.extend_from_slice(&(segment_len as u32).to_be_bytes());
+            pseudo_header.extend_from_slice(&[0, 0, 0, ip_protocol]);
+            set_checksum(&mut segment, checksum_offset, &pseudo_header);
+
+            let mut datagram = Vec::with_capacity(40 + segment_len);
+            // Version, traffic class, and flow label
+            datagram.extend_from_slice(&[0x60, 0, 0, 0]);
+            datagram.extend_from_slice(&(segment_len as u16).to_be_bytes());
+            datagram.extend_from_slice(&[ip_protocol, IP_TTL]);
+            datagram.extend_from_slice(&source.octets());
+            datagram.extend_from_slice(&dest.octets());
+            datagram.extend_from_slice(&segment);
+            datagram
+        }
+    }
+}
+
+// Mixing IPv4 and IPv6 in one packet only happens with a misconfigured
+// simulation, but we still want to see such packets in the capture.
+fn ipv6(ip: IpAddr) -> Ipv6Addr {
+    match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+        IpAddr::V6(ip) => ip,
+    }
+}
+
+fn set_checksum(segment: &mut [u8], offset: usize, pseudo_header: &[u8]) {
+    let checksum = match internet_checksum(&[pseudo_header, segment]) {
+        // For UDP, 0 means no checksum, so it's sent as all ones.
+        0 => 0xffff,
+        checksum => checksum,
+    };
+    segment[offset..offset + 2].copy_from_slice(&checksum.to_be_bytes());
+}
+
+// The ones' complement of the ones' complement sum of 16-bit words (RFC 1071).
+fn internet_checksum(chunks: &[&[u8]]) -> u16 {
+    let mut sum = 0u32;
+    for chunk in chunks {
+        for word in chunk.chunks(2) {
+            let high = word[0] as u32;
+            let low = word.get(1).copied().unwrap_or(0) as u32;
+            sum += (high << 8) | low;
+        }
+    }
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(packet: &Packet) -> Vec<u8> {
+        let mut pcap = PcapWriter::new(Vec::new()).unwrap();
+        pcap.write_packet(Duration::from_micros(1_500_002), packet)
+            .unwrap();
+        pcap.writer
+    }
+
+    #[test]
+    fn write_ipv4_packet() {
+        let packet = Packet {
+            source:   "1.0.0.1:1000".parse().unwrap(),
+            dest:     "2.0.0.1:2000".parse().unwrap(),
+            protocol: Protocol::Udp,
+            data:     vec![1, 2, 3],
+        };
+        let pcap = write(&packet);
+        assert_eq!([0xd4, 0xc3, 0xb2, 0xa1, 2, 0, 4, 0], pcap[..8]);
+        assert_eq!(LINKTYPE_RAW.to_le_bytes(), pcap[20..24]);
+
+        let record = &pcap[24..];
+        assert_eq!(1u32.to_le_bytes(), record[0..4]);
+        assert_eq!(500_002u32.to_le_bytes(), record[4..8]);
+        assert_eq!(31u32.to_le_bytes(), record[8..12]);
+        assert_eq!(31u32.to_le_bytes(), record[12..16]);
+
+        let datagram = &record[16..];
+        assert_eq!(31, datagram.len());
+        assert_eq!(0x45, datagram[0]);
+        assert_eq!(IP_PROTOCOL_UDP, datagram[9]);
+        // A header with a valid checksum sums up to 0.
+        assert_eq!(0, internet_checksum(&[&datagram[..20]]));
+        assert_eq!([0x03, 0xe8, 0x07, 0xd0, 0, 11], datagram[20..26]);
+        assert_eq!([1, 2, 3], datagram[28..]);
+    }
+
+    #[test]
+    fn write_ipv6_packet() {
+        let packet = Packet {
+            source:   "[::1]:1000".parse().unwrap(),
+            dest:     "[::2]:2000".parse().unwrap(),
+            protocol: Protocol::Udp,
+            data:     vec![1, 2, 3, 4],
+        };
+        let datagram = write(&packet)[40..].to_vec();
+        assert_eq!(52, datagram.len());
+        assert_eq!(0x60, datagram[0]);
+        assert_eq!([0, 12, IP_PROTOCOL_UDP, IP_TTL], datagram[4..8]);
+        let mut pseudo_header = datagram[8..40].to_vec();
+        pseudo_header.extend_from_slice(&[0, 0, 0, 12, 0, 0, 0, IP_PROTOCOL_UDP]);
+        assert_eq!(0, internet_checksum(&[&pseudo_header, &datagram[40..]]));
+    }
+
+    #[test]
+    fn write_tcp_segments() {
+        let packet = Packet {
+            source:   "1.0.0.1:1000".parse().unwrap(),
+            dest:     "2.0.0.1:2000".parse().unwrap(),
+            protocol: Protocol::Tcp,
+            data:     vec![1, 2, 3],
+        };
+        let mut pcap = PcapWriter::new(Vec::new()).unwrap();
+        pcap.write_packet(Duration::from_secs(1), &packet).unwrap();
+        pcap.write_packet(Duration::from_secs(2), &packet).unwrap();
+        let record_len = 16 + 20 + 20 + 3;
+        assert_eq!(24 + 2 * record_len, pcap.writer.len());
+
+        let datagram = &pcap.writer[24 + record_len + 16..];
+        assert_eq!(IP_PROTOCOL_TCP, datagram[9]);
+        let segment = &datagram[20..];
+        // The second segment follows the first one.
+        assert_eq!(3u32.to_be_bytes(), segment[4..8]);
+        let mut pseudo_header = datagram[12..20].to_vec();
+        pseudo_header.extend_from_slice(&[0, IP_PROTOCOL_TCP, 0, 23]);
+        assert_eq!(0, internet_checksum(&[&pseudo_header, segment]));
+    }
+}
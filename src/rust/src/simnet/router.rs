@@ -3,35 +3,69 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
-use crate::common::{
-    actor::{Actor, Stopper},
-    units::{DataRate, DataSize},
-    Result,
+use crate::{
+    common::{
+        actor::{Actor, Stopper},
+        units::{DataRate, DataSize},
+        Result,
+    },
+    error::RingRtcError,
+    simnet::{
+        clock::SimClock,
+        nat::{Firewall, Nat, NatConfig},
+        pcap::PcapWriter,
+        stats::{InterfaceStats, LinkStats},
+        trace::{LinkTrace, TracePlayer},
+    },
+};
+use rand::{
+    distributions,
+    distributions::Distribution,
+    rngs::StdRng,
+    thread_rng,
+    Rng,
+    SeedableRng,
 };
-use rand::{distributions, distributions::Distribution, rngs::ThreadRng, thread_rng, Rng};
 use std::{
     collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
     net::{IpAddr, SocketAddr},
-    sync::{atomic, atomic::AtomicU64, Arc},
-    thread,
-    time::Duration,
+    path::Path,
+    sync::mpsc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 const OVERHEAD_IN_BYTES_IPV4: u64 = 20;
 
 const OVERHEAD_IN_BYTES_IPV6: u64 = 40;
 const OVERHEAD_IN_BYTES_UDP: u64 = 8;
-#[allow(dead_code)]
 const OVERHEAD_IN_BYTES_TCP: u64 = 20;
 
-#[derive(Debug)]
+// Like the minimum RTO of Linux.  Doubles with every retransmission.
+const MIN_RETRANSMISSION_TIMEOUT: Duration = Duration::from_millis(200);
+// After this many, the packet is dropped as if the connection timed out.
+const MAX_RETRANSMISSIONS: u32 = 8;
+// Heavy-tailed or wide delay distributions can sample absurd delays
+// (even infinity), so samples are capped at this.
+const MAX_DELAY: Duration = Duration::from_secs(3600);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Protocol {
+    Udp,
+    // Each packet is a segment of a TCP connection.  Segments aren't
+    // split or merged, so they keep the framing of what was sent.
+    Tcp,
+}
+
+#[derive(Clone, Debug)]
 pub struct Packet {
-    pub source: SocketAddr,
-    pub dest:   SocketAddr,
-    pub data:   Vec<u8>,
+    pub source:   SocketAddr,
+    pub dest:     SocketAddr,
+    pub protocol: Protocol,
+    pub data:     Vec<u8>,
 }
 
-// Just assume UDP for now.
 impl Packet {
     fn size(&self) -> DataSize {
         DataSize::from_bytes(self.data.len() as u64)
@@ -42,12 +76,18 @@ impl Packet {
             IpAddr::V4(_) => DataSize::from_bytes(OVERHEAD_IN_BYTES_IPV4),
             IpAddr::V6(_) => DataSize::from_bytes(OVERHEAD_IN_BYTES_IPV6),
         };
-        let udp_overhead = DataSize::from_bytes(OVERHEAD_IN_BYTES_UDP);
-        ip_overhead + udp_overhead
+        let transport_overhead = match self.protocol {
+            Protocol::Udp => DataSize::from_bytes(OVERHEAD_IN_BYTES_UDP),
+            Protocol::Tcp => DataSize::from_bytes(OVERHEAD_IN_BYTES_TCP),
+        };
+        ip_overhead + transport_overhead
     }
 
+    // Reliable packets aren't lost randomly or to a full queue.
+    // Instead, they are delayed by retransmissions (up to a limit)
+    // and they aren't reordered or duplicated.
     fn reliable(&self) -> bool {
-        false
+        self.protocol == Protocol::Tcp
     }
 }
 
@@ -64,16 +104,151 @@ impl<F: Fn(Packet) + Send> PacketReceiver for F {
 
 #[derive(Clone)]
 pub struct LinkConfig {
-    // Could also be mean + std_dev
-    // pub delay_mean:                Duration,
-    // pub delay_std_dev:             Duration,
-    pub delay_min:                 Duration,
-    pub delay_max:                 Duration,
-    pub loss_probabilty:           f64,
-    // If a packet is lost, the probably of the next one being a loss
-    pub repeated_loss_probability: f64,
-    pub rate:                      DataRate,
-    pub queue_size:                DataSize,
+    pub delay:                 DelayDistribution,
+    pub loss:                  LossModel,
+    // Delay alone doesn't reorder packets.  A reordered packet
+    // skips the delay and so gets ahead of the packets before it.
+    pub reorder_probability:   f64,
+    pub duplicate_probability: f64,
+    pub rate:                  DataRate,
+    pub queue_size:            DataSize,
+}
+
+#[derive(Clone, Debug)]
+pub enum DelayDistribution {
+    Uniform {
+        min: Duration,
+        max: Duration,
+    },
+    // Negative samples are treated as no delay.
+    Normal {
+        mean:    Duration,
+        std_dev: Duration,
+    },
+    // Never less than min (which must be more than 0), with a long tail
+    // of larger delays.  The smaller the shape, the longer the tail.
+    Pareto {
+        min:   Duration,
+        shape: f64,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub enum LossModel {
+    // Each packet is lost independently.
+    Random {
+        loss_probability: f64,
+    },
+    // Bursty loss: the link moves between a good and a bad state
+    // and each state has its own loss probability.
+    GilbertElliott {
+        good_to_bad_probability: f64,
+        bad_to_good_probability: f64,
+        good_loss_probability:   f64,
+        bad_loss_probability:    f64,
+    },
+}
+
+impl LinkConfig {
+    // Checks what would otherwise panic the router when the link is created.
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |reason: &str| RingRtcError::SimnetInvalidLinkConfig(reason.to_string());
+        let mut probabilities = vec![self.reorder_probability, self.duplicate_probability];
+        match self.loss {
+            LossModel::Random { loss_probability } => probabilities.push(loss_probability),
+            LossModel::GilbertElliott {
+                good_to_bad_probability,
+                bad_to_good_probability,
+                good_loss_probability,
+                bad_loss_probability,
+            } => probabilities.extend(&[
+                good_to_bad_probability,
+                bad_to_good_probability,
+                good_loss_probability,
+                bad_loss_probability,
+            ]),
+        }
+        if !probabilities
+            .iter()
+            .all(|probability| (0.0..=1.0).contains(probability))
+        {
+            return Err(invalid("probabilities must be between 0 and 1").into());
+        }
+        match self.delay {
+            DelayDistribution::Uniform { min, max } if min > max => {
+                return Err(invalid("the minimum delay must not be more than the maximum").into());
+            }
+            DelayDistribution::Pareto { min, shape }
+                if min == Duration::from_secs(0) || !(shape > 0.0 && shape.is_finite()) =>
+            {
+                return Err(invalid("the Pareto minimum and shape must be more than 0").into());
+            }
+            _ => {}
+        }
+        if self.rate.as_bps() == 0 {
+            return Err(invalid("the rate must be more than 0").into());
+        }
+        Ok(())
+    }
+}
+
+impl LossModel {
+    pub fn none() -> Self {
+        LossModel::Random {
+            loss_probability: 0.0,
+        }
+    }
+}
+
+/// A scripted sequence of link conditions, such as a train going
+/// through a tunnel or Wi-Fi degrading.  The first step is applied
+/// when the schedule is attached and the link stays in the last step
+/// once the schedule is done.
+#[derive(Clone, Default)]
+pub struct LinkSchedule {
+    pub steps: Vec<LinkScheduleStep>,
+}
+
+#[derive(Clone)]
+pub struct LinkScheduleStep {
+    // How long after the previous step this one is applied.
+    pub after:  Duration,
+    // None means the link is down and drops every packet.
+    pub config: Option<LinkConfig>,
+}
+
+impl LinkSchedule {
+    pub fn new(config: LinkConfig) -> Self {
+        Self {
+            steps: vec![LinkScheduleStep {
+                after:  Duration::from_secs(0),
+                config: Some(config),
+            }],
+        }
+    }
+
+    pub fn then(mut self, after: Duration, config: LinkConfig) -> Self {
+        self.steps.push(LinkScheduleStep {
+            after,
+            config: Some(config),
+        });
+        self
+    }
+
+    pub fn then_outage(mut self, after: Duration) -> Self {
+        self.steps.push(LinkScheduleStep {
+            after,
+            config: None,
+        });
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        for config in self.steps.iter().filter_map(|step| step.config.as_ref()) {
+            config.validate()?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone)]
@@ -82,27 +257,80 @@ pub struct Router {
 }
 
 struct RouterState {
+    // We keep a clone of the actor in the router state
+    // so we can schedule tasks based on the state.
+    actor: Actor<RouterState>,
+    clock: SimClock,
+    // Seeds the random number generator of each link
+    // so that a whole simulation can be reproduced from one seed.
+    rng:   StdRng,
+
     send_link_by_ip:    HashMap<IpAddr, Link>,
     receive_link_by_ip: HashMap<IpAddr, Link>,
+
+    // Keyed by the IP of the interface behind the NAT.
+    nat_by_ip:               HashMap<IpAddr, Nat>,
+    private_ip_by_public_ip: HashMap<IpAddr, IpAddr>,
+    firewall_by_ip:          HashMap<IpAddr, Firewall>,
+
+    pcap: Option<PacketCapture>,
+}
+
+struct PacketCapture {
+    writer:     PcapWriter<Box<dyn Write + Send>>,
+    // Maps the (possibly virtual) time of the clock to wall-clock timestamps.
+    started:    Instant,
+    started_at: SystemTime,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum LinkDirection {
+    Send,
+    Receive,
 }
 
 // Routes packets between the given interfaces with configurable links.
 // The path of the packet goes like this:
 // => Router.send_packet
-// => Router.actor.state.send_link_by_ip[ip].send_packet
-// => Link.actor.state.leaky_bucket
-// => Router.receive_packet
-// => Router.actor.state.receive_link_by_ip[ip].receive_packet
-// => Link.actor.state.leaky_bucket
+// => Router.actor.state.send_link_by_ip[ip] (drop, queue, and delay)
+// => Router.actor.state.firewall_by_ip/nat_by_ip (filter and translate)
+// => Router.actor.state.receive_link_by_ip[ip] (drop, queue, and delay)
 // => callback passed to Router.add_interface
+// Packets spend their time in the links as tasks scheduled on the SimClock,
+// so with a virtual clock and a fixed seed, the same packets sent in the
+// same order are always dropped and delivered the same way.
 // One could theoretically combine Routers for a larger, more complex graph.
 impl Router {
+    // Uses real time and a random seed, which gets logged so that
+    // a simulation can be reproduced with start_with_seed_and_clock.
     pub fn start(stopper: &Stopper) -> Result<Self> {
+        Self::start_with_clock(stopper, SimClock::real())
+    }
+
+    // Like start, but with the given clock.
+    pub fn start_with_clock(stopper: &Stopper, clock: SimClock) -> Result<Self> {
+        let seed = thread_rng().gen();
+        info!("Starting simnet router with seed {}", seed);
+        Self::start_with_seed_and_clock(stopper, seed, clock)
+    }
+
+    pub fn start_with_seed_and_clock(
+        stopper: &Stopper,
+        seed: u64,
+        clock: SimClock,
+    ) -> Result<Self> {
         Ok(Self {
-            actor: Actor::start(stopper.clone(), move |_| {
+            actor: Actor::start(stopper.clone(), move |actor| {
                 Ok(RouterState {
-                    send_link_by_ip:    HashMap::new(),
+                    actor,
+                    clock,
+                    rng: StdRng::seed_from_u64(seed),
+                    send_link_by_ip: HashMap::new(),
                     receive_link_by_ip: HashMap::new(),
+                    nat_by_ip: HashMap::new(),
+                    private_ip_by_public_ip: HashMap::new(),
+                    firewall_by_ip: HashMap::new(),
+                    pcap: None,
                 })
             })?,
         })
@@ -118,197 +346,1200 @@ impl Router {
         receive_config: LinkConfig,
         receiver: Box<dyn PacketReceiver>,
     ) -> Result<()> {
-        let send_link = Link::start(
-            send_config,
-            Box::new(self.clone()),
-            self.actor.stopper().clone(),
-        )?;
-        let receive_link = Link::start(receive_config, receiver, self.actor.stopper().clone())?;
+        send_config.validate()?;
+        receive_config.validate()?;
         self.actor.send(move |state| {
+            let now = state.clock.now();
+            let send_link = Link::new(send_config, None, state.rng.gen(), now);
+            let receive_link = Link::new(receive_config, Some(receiver), state.rng.gen(), now);
             state.send_link_by_ip.insert(ip, send_link);
             state.receive_link_by_ip.insert(ip, receive_link);
         });
         Ok(())
     }
 
-    pub fn send_packet(&self, packet: Packet) {
+    // Like add_interface, but the links follow the given schedules.
+    pub fn add_interface_with_schedules(
+        &self,
+        ip: IpAddr,
+        send_schedule: LinkSchedule,
+        receive_schedule: LinkSchedule,
+        receiver: Box<dyn PacketReceiver>,
+    ) -> Result<()> {
+        let initial_config = |schedule: &LinkSchedule| {
+            schedule
+                .steps
+                .iter()
+                .find_map(|step| step.config.clone())
+                .ok_or(RingRtcError::SimnetLinkScheduleWithoutConfig)
+        };
+        let send_config = initial_config(&send_schedule)?;
+        let receive_config = initial_config(&receive_schedule)?;
+        self.add_interface(ip, send_config, receive_config, receiver)?;
+        self.set_interface_schedules(ip, send_schedule, receive_schedule)
+    }
+
+    // Changes the conditions of the links of an interface while packets are flowing.
+    pub fn set_interface_configs(
+        &self,
+        ip: IpAddr,
+        send_config: LinkConfig,
+        receive_config: LinkConfig,
+    ) -> Result<()> {
+        self.set_interface_schedules(
+            ip,
+            LinkSchedule::new(send_config),
+            LinkSchedule::new(receive_config),
+        )
+    }
+
+    // Replaces any schedules the links of an interface were following.
+    pub fn set_interface_schedules(
+        &self,
+        ip: IpAddr,
+        send_schedule: LinkSchedule,
+        receive_schedule: LinkSchedule,
+    ) -> Result<()> {
+        send_schedule.validate()?;
+        receive_schedule.validate()?;
         self.actor.send(move |state| {
-            if let Some(send_link) = state.send_link_by_ip.get(&packet.source.ip()) {
-                send_link.send_packet(packet);
-            } else {
-                debug!(
-                    "Dropped send packet because the source IP ({:?}) was unknown.",
-                    packet.source.ip()
-                );
+            if !state.send_link_by_ip.contains_key(&ip) {
+                warn!("Can't set the link schedules of unknown IP ({:?}).", ip);
+                return;
+            }
+            state.start_schedule(LinkDirection::Send, ip, send_schedule);
+            state.start_schedule(LinkDirection::Receive, ip, receive_schedule);
+        });
+        Ok(())
+    }
+
+    // Replays the given traces on the links of an interface instead of
+    // randomly dropping and delaying according to their configs.
+    // The traces start now.  None goes back to using the configs.
+    pub fn set_interface_traces(
+        &self,
+        ip: IpAddr,
+        send_trace: Option<LinkTrace>,
+        receive_trace: Option<LinkTrace>,
+    ) {
+        self.actor.send(move |state| {
+            let now = state.clock.now();
+            match (
+                state.send_link_by_ip.get_mut(&ip),
+                state.receive_link_by_ip.get_mut(&ip),
+            ) {
+                (Some(send_link), Some(receive_link)) => {
+                    send_link.trace = send_trace.map(|trace| (now, TracePlayer::new(trace)));
+                    receive_link.trace = receive_trace.map(|trace| (now, TracePlayer::new(trace)));
+                }
+                _ => {
+                    warn!("Can't set the link traces of unknown IP ({:?}).", ip);
+                }
+            }
+        });
+    }
+
+    // Puts the interface behind a NAT, or takes it out with None.
+    // Other interfaces can then only reach it through the public IP of the NAT
+    // and only once it has sent them something (unless it's a full-cone NAT).
+    // Replacing a NAT forgets its mappings.
+    pub fn set_interface_nat(&self, ip: IpAddr, nat_config: Option<NatConfig>) {
+        self.actor.send(move |state| {
+            if let Some(nat) = state.nat_by_ip.remove(&ip) {
+                state.private_ip_by_public_ip.remove(&nat.public_ip());
+            }
+            if let Some(nat_config) = nat_config {
+                let nat = Nat::new(nat_config);
+                state.private_ip_by_public_ip.insert(nat.public_ip(), ip);
+                state.nat_by_ip.insert(ip, nat);
+            }
+        });
+    }
+
+    // Filters the packets sent from and received to the interface.
+    pub fn set_interface_firewall(&self, ip: IpAddr, firewall: Firewall) {
+        self.actor.send(move |state| {
+            state.firewall_by_ip.insert(ip, firewall);
+        });
+    }
+
+    // Waits until everything sent to the router so far has been processed.
+    // With virtual time, do this before advancing the clock so that
+    // packets sent just before are scheduled relative to the current time.
+    pub fn flush(&self) {
+        let (sender, receiver) = mpsc::channel();
+        self.actor.send(move |_state| {
+            let _ = sender.send(());
+        });
+        let _ = receiver.recv();
+    }
+
+    // Returns the counters of the links of an interface,
+    // or None if the interface is unknown.
+    pub fn interface_stats(&self, ip: IpAddr) -> Option<InterfaceStats> {
+        let (sender, receiver) = mpsc::channel();
+        self.actor.send(move |state| {
+            let stats = match (
+                state.send_link_by_ip.get(&ip),
+                state.receive_link_by_ip.get(&ip),
+            ) {
+                (Some(send_link), Some(receive_link)) => Some(InterfaceStats {
+                    send:    send_link.stats.clone(),
+                    receive: receive_link.stats.clone(),
+                }),
+                _ => None,
+            };
+            let _ = sender.send(stats);
+        });
+        receiver.recv().ok().flatten()
+    }
+
+    // Writes every packet given to the router to the pcap file,
+    // including the ones the links end up dropping.
+    // Addresses are as sent, before any NAT translates them.
+    pub fn start_pcap_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        let file = File::create(path)?;
+        self.start_pcap(Box::new(BufWriter::new(file)))
+    }
+
+    // Like start_pcap_file, but to any writer.
+    // Replaces the current capture, if any.
+    pub fn start_pcap(&self, writer: Box<dyn Write + Send>) -> Result<()> {
+        let writer = PcapWriter::new(writer)?;
+        self.actor.send(move |state| {
+            state.pcap = Some(PacketCapture {
+                writer,
+                started: state.clock.now(),
+                started_at: SystemTime::now(),
+            });
+        });
+        Ok(())
+    }
+
+    pub fn stop_pcap(&self) {
+        self.actor.send(move |state| {
+            if let Some(mut pcap) = state.pcap.take() {
+                if let Err(err) = pcap.writer.flush() {
+                    warn!("Failed to flush the pcap: {}", err);
+                }
             }
         });
     }
+
+    pub fn send_packet(&self, packet: Packet) {
+        self.actor.send(move |state| {
+            state.capture_packet(&packet);
+            let ip = packet.source.ip();
+            state.send_over_link(LinkDirection::Send, ip, packet);
+        });
+    }
 }
 
 impl PacketReceiver for Router {
     fn receive_packet(&self, packet: Packet) {
         self.actor.send(move |state| {
-            if let Some(receive_link) = state.receive_link_by_ip.get(&packet.dest.ip()) {
-                receive_link.send_packet(packet);
-            } else {
+            state.capture_packet(&packet);
+            let ip = packet.dest.ip();
+            state.send_over_link(LinkDirection::Receive, ip, packet);
+        });
+    }
+}
+
+impl RouterState {
+    fn links(&mut self, direction: LinkDirection) -> &mut HashMap<IpAddr, Link> {
+        match direction {
+            LinkDirection::Send => &mut self.send_link_by_ip,
+            LinkDirection::Receive => &mut self.receive_link_by_ip,
+        }
+    }
+
+    fn send_over_link(&mut self, direction: LinkDirection, ip: IpAddr, packet: Packet) {
+        let now = self.clock.now();
+        let (delay, duplicate_delay) = match self.links(direction).get_mut(&ip) {
+            Some(link) => link.send_packet(&packet, now),
+            None => {
                 debug!(
-                    "Dropped receive packet because the dest IP ({:?}) was unknown.",
-                    packet.dest.ip()
+                    "Dropped {:?} packet because the IP ({:?}) was unknown.",
+                    direction, ip
                 );
+                return;
             }
-        });
+        };
+        if let Some(duplicate_delay) = duplicate_delay {
+            let duplicate = packet.clone();
+            self.clock
+                .send_delayed(&self.actor, duplicate_delay, move |state| {
+                    state.deliver_packet(direction, ip, duplicate)
+                });
+        }
+        if let Some(delay) = delay {
+            self.clock.send_delayed(&self.actor, delay, move |state| {
+                state.deliver_packet(direction, ip, packet)
+            });
+        }
     }
-}
 
-struct Link {
-    actor: Actor<LinkState>,
+    fn capture_packet(&mut self, packet: &Packet) {
+        let now = self.clock.now();
+        if let Some(pcap) = &mut self.pcap {
+            let timestamp = (pcap.started_at + now.saturating_duration_since(pcap.started))
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            if let Err(err) = pcap.writer.write_packet(timestamp, packet) {
+                warn!("Stopping the pcap after failing to write to it: {}", err);
+                self.pcap = None;
+            }
+        }
+    }
+
+    fn deliver_packet(&mut self, direction: LinkDirection, ip: IpAddr, packet: Packet) {
+        match direction {
+            LinkDirection::Send => {
+                if let Some((ip, packet)) = self.route_packet(ip, packet) {
+                    self.send_over_link(LinkDirection::Receive, ip, packet);
+                }
+            }
+            LinkDirection::Receive => {
+                if let Some(receiver) = self
+                    .receive_link_by_ip
+                    .get(&ip)
+                    .and_then(|link| link.receiver.as_ref())
+                {
+                    receiver.receive_packet(packet);
+                }
+            }
+        }
+    }
+
+    fn start_schedule(&mut self, direction: LinkDirection, ip: IpAddr, schedule: LinkSchedule) {
+        if let Some(link) = self.links(direction).get_mut(&ip) {
+            link.schedule_generation += 1;
+            let generation = link.schedule_generation;
+            self.apply_schedule_step(direction, ip, generation, schedule.steps, 0);
+        }
+    }
+
+    // Passes the packet sent from the given IP through the firewalls and NATs
+    // and returns the IP of the interface it should be received by.
+    fn route_packet(&mut self, ip: IpAddr, packet: Packet) -> Option<(IpAddr, Packet)> {
+        if let Some(firewall) = self.firewall_by_ip.get(&ip) {
+            if !firewall.allows(packet.protocol, packet.dest) {
+                println!(
+                    "Dropped packet from {:?} to {:?} because of the sender's firewall",
+                    packet.source, packet.dest
+                );
+                return None;
+            }
+        }
+        let packet = match self.nat_by_ip.get_mut(&ip) {
+            Some(nat) => {
+                let source = packet.source;
+                let dest = packet.dest;
+                match nat.translate_outgoing(packet) {
+                    Some(packet) => packet,
+                    None => {
+                        println!(
+                            "Dropped packet from {:?} to {:?} because the sender's NAT is out of ports",
+                            source, dest
+                        );
+                        return None;
+                    }
+                }
+            }
+            None => packet,
+        };
+
+        let dest_ip = packet.dest.ip();
+        let (dest_ip, packet) = match self.private_ip_by_public_ip.get(&dest_ip) {
+            Some(private_ip) => {
+                let private_ip = *private_ip;
+                let nat = self.nat_by_ip.get(&private_ip)?;
+                let source = packet.source;
+                let dest = packet.dest;
+                match nat.translate_incoming(packet) {
+                    Some(packet) => (private_ip, packet),
+                    None => {
+                        println!(
+                            "Dropped packet from {:?} to {:?} because of the receiver's NAT",
+                            source, dest
+                        );
+                        return None;
+                    }
+                }
+            }
+            None if self.nat_by_ip.contains_key(&dest_ip) => {
+                println!(
+                    "Dropped packet from {:?} to {:?} because the receiver is behind a NAT",
+                    packet.source, packet.dest
+                );
+                return None;
+            }
+            None => (dest_ip, packet),
+        };
+
+        if let Some(firewall) = self.firewall_by_ip.get(&dest_ip) {
+            if !firewall.allows(packet.protocol, packet.source) {
+                println!(
+                    "Dropped packet from {:?} to {:?} because of the receiver's firewall",
+                    packet.source, packet.dest
+                );
+                return None;
+            }
+        }
+        Some((dest_ip, packet))
+    }
+
+    // Applies the step at the given index (and any right after it)
+    // and schedules the one after that.
+    fn apply_schedule_step(
+        &mut self,
+        direction: LinkDirection,
+        ip: IpAddr,
+        generation: u64,
+        steps: Vec<LinkScheduleStep>,
+        index: usize,
+    ) {
+        let link = match self.links(direction).get_mut(&ip) {
+            Some(link) => link,
+            None => return,
+        };
+        if generation != link.schedule_generation {
+            // The schedule was replaced.
+            return;
+        }
+        let mut index = index;
+        if let Some(step) = steps.get(index) {
+            link.apply_config(step.config.clone());
+        }
+        // Steps without a delay take effect before any more packets are sent.
+        while let Some(next_step) = steps.get(index + 1) {
+            if next_step.after > Duration::from_secs(0) {
+                break;
+            }
+            link.apply_config(next_step.config.clone());
+            index += 1;
+        }
+        if let Some(next_step) = steps.get(index + 1) {
+            self.clock
+                .send_delayed(&self.actor, next_step.after, move |state| {
+                    state.apply_schedule_step(direction, ip, generation, steps, index + 1);
+                });
+        }
+    }
 }
 
-struct LinkState {
+struct Link {
     config: LinkConfig,
+    // False during an outage.
+    up:     bool,
+
+    // Incremented every time a schedule is set so that steps
+    // of a replaced schedule don't get applied.
+    schedule_generation: u64,
 
     // A source of randomness for dropping and delaying.
-    rng: ThreadRng,
+    rng: StdRng,
 
-    // State for calculating dropping and delaying
-    // goes out side of the actor because it affects
-    // if and when we will send to the actor.
-    previous_packet_dropped: bool,
+    // For the Gilbert-Elliott loss model.
+    in_bad_state: bool,
 
-    #[allow(deprecated)]
-    delay_distribution: distributions::uniform::Uniform<u64>,
+    delay_sampler: DelaySampler,
 
-    // We keep a clone of the actor in the link state
-    // so we can schedule tasks based on the state.
-    actor: Actor<LinkState>,
+    // When the last packet that wasn't reordered comes out of the link.
+    // Later packets don't come out before it.
+    delivered_until:          Instant,
+    // The same, but for reliable packets, which are held
+    // back by retransmissions of the ones before them.
+    reliable_delivered_until: Instant,
+
+    // When the link will be done transmitting the queued packets.
+    // Takes the place of a leaky bucket.
+    transmitted_until: Instant,
+
+    // When set, replaces the random dropping and delaying and the queue.
+    // The Instant is when the trace started.
+    trace: Option<(Instant, TracePlayer)>,
 
-    leaky_bucket: LeakyBucket,
+    stats: LinkStats,
+
+    // Only receive links have a receiver.
+    // Send links deliver to the receive link of the destination.
+    receiver: Option<Box<dyn PacketReceiver>>,
 }
 
 impl Link {
-    pub fn start(
+    fn new(
         config: LinkConfig,
-        receiver: Box<dyn PacketReceiver>,
-        stopper: Stopper,
-    ) -> Result<Self> {
-        // Could also be mean + std_dev
-        // let delay_distribution = distributions::Normal::new(
-        //     config.delay_mean.as_secs_f64(),
-        //     config.delay_std_dev.as_secs_f64(),
-        // );
-        let delay_distribution = distributions::Uniform::from(
-            (config.delay_min.as_millis() as u64)..(config.delay_max.as_millis() as u64),
-        );
-        let leaky_bucket = LeakyBucket::start(config.clone(), receiver, stopper.clone())?;
-        Ok(Self {
-            actor: Actor::start(stopper, move |actor| {
-                Ok(LinkState {
-                    actor,
-                    config,
-                    rng: thread_rng(),
-                    previous_packet_dropped: false,
-                    delay_distribution,
-                    leaky_bucket,
-                })
-            })?,
-        })
+        receiver: Option<Box<dyn PacketReceiver>>,
+        seed: u64,
+        now: Instant,
+    ) -> Self {
+        Self {
+            delay_sampler: DelaySampler::new(&config.delay),
+            config,
+            up: true,
+            schedule_generation: 0,
+            rng: StdRng::seed_from_u64(seed),
+            in_bad_state: false,
+            transmitted_until: now,
+            delivered_until: now,
+            reliable_delivered_until: now,
+            trace: None,
+            stats: LinkStats::default(),
+            receiver,
+        }
     }
 
-    fn send_packet(&self, packet: Packet) {
-        self.actor.send(move |state| {
-            let loss_probabilty = if state.previous_packet_dropped {
-                state.config.repeated_loss_probability
-            } else {
-                state.config.loss_probabilty
-            };
-            if !packet.reliable() && state.rng.gen_bool(loss_probabilty) {
+    // None means the link goes down.
+    fn apply_config(&mut self, config: Option<LinkConfig>) {
+        match config {
+            Some(config) => {
+                self.up = true;
+                self.delay_sampler = DelaySampler::new(&config.delay);
+                self.config = config;
+            }
+            None => {
+                self.up = false;
+            }
+        }
+    }
+
+    // Returns how long until the packet and, if it gets duplicated,
+    // its copy come out the other end of the link.
+    // None means it gets dropped (or doesn't get duplicated).
+    fn send_packet(
+        &mut self,
+        packet: &Packet,
+        now: Instant,
+    ) -> (Option<Duration>, Option<Duration>) {
+        self.stats.packets_sent += 1;
+        self.stats.bytes_sent += packet.size().as_bytes();
+        let delay = self.delay_packet(packet, now);
+        let duplicate_delay = if delay.is_some() && !packet.reliable() && self.duplicate_packet() {
+            self.stats.packets_duplicated += 1;
+            self.delay_packet(packet, now)
+        } else {
+            None
+        };
+        (delay, duplicate_delay)
+    }
+
+    fn delay_packet(&mut self, packet: &Packet, now: Instant) -> Option<Duration> {
+        if !self.up {
+            println!(
+                "Dropped packet from {:?} to {:?} of size {} because the link is down",
+                packet.source,
+                packet.dest,
+                packet.size().as_bytes()
+            );
+            self.stats.packets_dropped_by_outage += 1;
+            return None;
+        }
+
+        // TODO: Make more accurate overhead calculation.
+        let overhead = packet.overhead();
+        let packet_size_with_overhead = packet.size() + overhead;
+
+        if let Some((trace_start, trace_player)) = &mut self.trace {
+            let delay = trace_player.delay_packet(
+                now.saturating_duration_since(*trace_start),
+                packet_size_with_overhead,
+                self.config.queue_size,
+            );
+            if delay.is_none() {
+                println!(
+                    "Dropped packet from {:?} to {:?} of size {} according to the trace",
+                    packet.source,
+                    packet.dest,
+                    packet.size().as_bytes()
+                );
+                self.stats.packets_dropped_by_trace += 1;
+            }
+            return delay;
+        }
+
+        let mut retransmission_delay = Duration::from_secs(0);
+        if self.lose_packet() {
+            if !packet.reliable() {
                 println!(
-                    "Dropped packet from {:?} to {:?} of size {} randomly (previous_packet_dropped={})",
-                    packet.source, packet.dest, packet.size().as_bytes(), state.previous_packet_dropped
+                    "Dropped packet from {:?} to {:?} of size {} randomly (in_bad_state={})",
+                    packet.source,
+                    packet.dest,
+                    packet.size().as_bytes(),
+                    self.in_bad_state
                 );
-                state.previous_packet_dropped = true;
+                self.stats.packets_dropped_randomly += 1;
                 // Drop the packet
-                return;
+                return None;
+            }
+            match self.retransmit_packet() {
+                Some(delay) => retransmission_delay = delay,
+                None => {
+                    println!(
+                        "Dropped reliable packet from {:?} to {:?} of size {} after {} retransmissions",
+                        packet.source,
+                        packet.dest,
+                        packet.size().as_bytes(),
+                        MAX_RETRANSMISSIONS
+                    );
+                    self.stats.packets_dropped_randomly += 1;
+                    return None;
+                }
             }
-            state.previous_packet_dropped = false;
+        }
 
-            // Delay the packet
-            let delay = Duration::from_millis(state.delay_distribution.sample(&mut state.rng));
-            state
-                .actor
-                .send_delayed(delay, move |state| state.leaky_bucket.send_packet(packet));
-        });
+        // Queue the packet behind the ones that haven't been transmitted yet.
+        // A TCP sender backs off and retransmits until there is room
+        // instead of overflowing the queue.
+        let mut enqueued_at = now;
+        let mut timeout = MIN_RETRANSMISSION_TIMEOUT;
+        let mut retransmissions = 0;
+        loop {
+            let queued_size = self.config.rate
+                * self
+                    .transmitted_until
+                    .saturating_duration_since(enqueued_at);
+            if (queued_size + packet_size_with_overhead) <= self.config.queue_size {
+                break;
+            }
+            if !packet.reliable() || retransmissions == MAX_RETRANSMISSIONS {
+                println!(
+                    "Dropped packet (size: {} overhead: {}) from full queue (queued_size={}/{})",
+                    packet.size().as_bytes(),
+                    overhead.as_bytes(),
+                    queued_size.as_bytes(),
+                    self.config.queue_size.as_bytes()
+                );
+                self.stats.packets_dropped_by_full_queue += 1;
+                return None; // Drop the packet!
+            }
+            enqueued_at += timeout;
+            timeout *= 2;
+            retransmissions += 1;
+            self.stats.packets_retransmitted += 1;
+        }
+        self.stats.queueing_delay.record(
+            self.transmitted_until
+                .saturating_duration_since(enqueued_at),
+        );
+        // Simulates the time it takes to transmit a packet.
+        self.transmitted_until =
+            self.transmitted_until.max(enqueued_at) + packet_size_with_overhead / self.config.rate;
+
+        if !packet.reliable() && self.rng.gen_bool(self.config.reorder_probability) {
+            return Some(self.transmitted_until.saturating_duration_since(now));
+        }
+
+        // Delay the packet
+        let delay = self.delay_sampler.sample(&mut self.rng) + retransmission_delay;
+        let delivered_until = if packet.reliable() {
+            &mut self.reliable_delivered_until
+        } else {
+            &mut self.delivered_until
+        };
+        *delivered_until = (*delivered_until).max(self.transmitted_until + delay);
+        Some(delivered_until.saturating_duration_since(now))
+    }
+
+    // Called when a reliable packet was lost.  Retransmits it, with
+    // exponential backoff, until a retransmission isn't lost and
+    // returns how long that took, or None if it gave up.
+    fn retransmit_packet(&mut self) -> Option<Duration> {
+        let mut timeout = MIN_RETRANSMISSION_TIMEOUT;
+        let mut delay = Duration::from_secs(0);
+        for _ in 0..MAX_RETRANSMISSIONS {
+            delay += timeout;
+            timeout *= 2;
+            self.stats.packets_retransmitted += 1;
+            if !self.lose_packet() {
+                return Some(delay);
+            }
+        }
+        None
+    }
+
+    fn lose_packet(&mut self) -> bool {
+        match self.config.loss {
+            LossModel::Random { loss_probability } => self.rng.gen_bool(loss_probability),
+            LossModel::GilbertElliott {
+                good_to_bad_probability,
+                bad_to_good_probability,
+                good_loss_probability,
+                bad_loss_probability,
+            } => {
+                let transition_probability = if self.in_bad_state {
+                    bad_to_good_probability
+                } else {
+                    good_to_bad_probability
+                };
+                if self.rng.gen_bool(transition_probability) {
+                    self.in_bad_state = !self.in_bad_state;
+                }
+                let loss_probability = if self.in_bad_state {
+                    bad_loss_probability
+                } else {
+                    good_loss_probability
+                };
+                self.rng.gen_bool(loss_probability)
+            }
+        }
+    }
+
+    fn duplicate_packet(&mut self) -> bool {
+        self.rng.gen_bool(self.config.duplicate_probability)
     }
 }
 
-struct LeakyBucket {
-    config:      LinkConfig,
-    actor:       Actor<LeakyBucketState>,
-    // Shared with LeakyBucketState so we can
-    // see the queued size on both sides.
-    queued_size: Arc<AtomicU64>,
+#[allow(deprecated)]
+enum DelaySampler {
+    // In microseconds
+    Uniform(distributions::uniform::Uniform<u64>),
+    // In seconds
+    Normal(distributions::Normal),
+    // In seconds
+    Pareto(distributions::Pareto),
 }
 
-struct LeakyBucketState {
-    queued_size: Arc<AtomicU64>,
-    receiver:    Box<dyn PacketReceiver>,
+#[allow(deprecated)]
+impl DelaySampler {
+    fn new(distribution: &DelayDistribution) -> Self {
+        match distribution {
+            DelayDistribution::Uniform { min, max } => {
+                DelaySampler::Uniform(distributions::Uniform::new_inclusive(
+                    min.as_micros() as u64,
+                    max.as_micros() as u64,
+                ))
+            }
+            DelayDistribution::Normal { mean, std_dev } => DelaySampler::Normal(
+                distributions::Normal::new(mean.as_secs_f64(), std_dev.as_secs_f64()),
+            ),
+            DelayDistribution::Pareto { min, shape } => {
+                DelaySampler::Pareto(distributions::Pareto::new(min.as_secs_f64(), *shape))
+            }
+        }
+    }
+
+    fn sample(&self, rng: &mut StdRng) -> Duration {
+        match self {
+            DelaySampler::Uniform(uniform) => Duration::from_micros(uniform.sample(rng)),
+            DelaySampler::Normal(normal) => {
+                Duration::from_secs_f64(normal.sample(rng).max(0.0).min(MAX_DELAY.as_secs_f64()))
+            }
+            DelaySampler::Pareto(pareto) => {
+                Duration::from_secs_f64(pareto.sample(rng).min(MAX_DELAY.as_secs_f64()))
+            }
+        }
+    }
 }
 
-impl LeakyBucket {
-    pub fn start(
-        config: LinkConfig,
-        receiver: Box<dyn PacketReceiver>,
-        stopper: Stopper,
-    ) -> Result<Self> {
-        let queued_size = Arc::new(AtomicU64::new(0));
-        let queued_size_clone = queued_size.clone();
-        Ok(Self {
-            config,
-            actor: Actor::start(stopper, move |_| {
-                Ok(LeakyBucketState {
-                    queued_size: queued_size_clone,
-                    receiver,
-                })
-            })?,
-            queued_size,
-        })
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simnet::nat::NatType;
+
+    fn fast_link() -> LinkConfig {
+        LinkConfig {
+            delay:                 DelayDistribution::Uniform {
+                min: Duration::from_millis(0),
+                max: Duration::from_millis(1),
+            },
+            loss:                  LossModel::none(),
+            reorder_probability:   0.0,
+            duplicate_probability: 0.0,
+            rate:                  DataRate::from_mbps(10),
+            queue_size:            DataSize::from_bytes(100_000),
+        }
     }
 
-    pub fn send_packet(&self, packet: Packet) {
-        // Using the most strict ordering because perf doesn't matter,
-        // but I'm not sure if that's the right choice.
-        let ordering = atomic::Ordering::SeqCst;
+    fn packet(source: &str, dest: &str) -> Packet {
+        Packet {
+            source:   source.parse().unwrap(),
+            dest:     dest.parse().unwrap(),
+            protocol: Protocol::Udp,
+            data:     vec![0u8; 100],
+        }
+    }
 
-        // TODO: Make more accurate overhead calculation.
-        let overhead = packet.overhead();
-        let packet_size_without_overhead = DataSize::from_bytes(packet.data.len() as u64);
-        let packet_size_with_overhead = packet_size_without_overhead + overhead;
-        let queued_size = DataSize::from_bytes(self.queued_size.load(ordering));
-        let max_size = self.config.queue_size;
-        let rate = self.config.rate;
-        if (queued_size + packet_size_with_overhead) > max_size {
-            println!(
-                "Dropped packet (size: {} overhead: {}) from full queue (queued_size={}/{})",
-                packet_size_without_overhead.as_bytes(),
-                overhead.as_bytes(),
-                queued_size.as_bytes(),
-                self.config.queue_size.as_bytes()
-            );
-            return; // Drop the packet!
+    fn start_router_with_receiver(stopper: &Stopper) -> (Router, mpsc::Receiver<Packet>) {
+        let router = Router::start(stopper).unwrap();
+        let receiver = add_interfaces(&router, fast_link());
+        (router, receiver)
+    }
+
+    // Packets sent from 1.0.0.1 to 2.0.0.1 come out of the returned receiver.
+    fn add_interfaces(router: &Router, link: LinkConfig) -> mpsc::Receiver<Packet> {
+        router
+            .add_interface(
+                "1.0.0.1".parse().unwrap(),
+                link.clone(),
+                link.clone(),
+                Box::new(|_packet: Packet| {}),
+            )
+            .unwrap();
+        let (sender, receiver) = mpsc::channel();
+        router
+            .add_interface(
+                "2.0.0.1".parse().unwrap(),
+                link.clone(),
+                link,
+                Box::new(move |packet: Packet| {
+                    let _ = sender.send(packet);
+                }),
+            )
+            .unwrap();
+        receiver
+    }
+
+    #[test]
+    fn scheduled_outage_drops_packets_until_link_comes_back() {
+        let stopper = Stopper::new();
+        let (router, receiver) = start_router_with_receiver(&stopper);
+        let timeout = Duration::from_millis(200);
+
+        router.send_packet(packet("1.0.0.1:1", "2.0.0.1:2"));
+        assert!(receiver.recv_timeout(timeout).is_ok());
+
+        router
+            .set_interface_schedules(
+                "1.0.0.1".parse().unwrap(),
+                LinkSchedule::new(fast_link())
+                    .then_outage(Duration::from_millis(0))
+                    .then(Duration::from_millis(300), fast_link()),
+                LinkSchedule::new(fast_link()),
+            )
+            .unwrap();
+        router.send_packet(packet("1.0.0.1:1", "2.0.0.1:2"));
+        assert!(receiver.recv_timeout(timeout).is_err());
+
+        std::thread::sleep(Duration::from_millis(500));
+        router.send_packet(packet("1.0.0.1:1", "2.0.0.1:2"));
+        assert!(receiver.recv_timeout(timeout).is_ok());
+
+        stopper.stop_all_and_join();
+    }
+
+    #[test]
+    fn setting_configs_replaces_schedule() {
+        let stopper = Stopper::new();
+        let (router, receiver) = start_router_with_receiver(&stopper);
+        let timeout = Duration::from_millis(200);
+
+        router
+            .set_interface_schedules(
+                "2.0.0.1".parse().unwrap(),
+                LinkSchedule::new(fast_link()),
+                LinkSchedule::new(fast_link()).then_outage(Duration::from_millis(300)),
+            )
+            .unwrap();
+        router
+            .set_interface_configs("2.0.0.1".parse().unwrap(), fast_link(), fast_link())
+            .unwrap();
+
+        std::thread::sleep(Duration::from_millis(500));
+        router.send_packet(packet("1.0.0.1:1", "2.0.0.1:2"));
+        assert!(receiver.recv_timeout(timeout).is_ok());
+
+        stopper.stop_all_and_join();
+    }
+
+    #[test]
+    fn invalid_link_configs_are_rejected() {
+        let stopper = Stopper::new();
+        let (router, _receiver) = start_router_with_receiver(&stopper);
+        let ip: IpAddr = "2.0.0.1".parse().unwrap();
+
+        let bad_probability = LinkConfig {
+            reorder_probability: 1.5,
+            ..fast_link()
+        };
+        let bad_loss = LinkConfig {
+            loss: LossModel::Random {
+                loss_probability: -0.1,
+            },
+            ..fast_link()
+        };
+        let inverted_delay = LinkConfig {
+            delay: DelayDistribution::Uniform {
+                min: Duration::from_millis(20),
+                max: Duration::from_millis(10),
+            },
+            ..fast_link()
+        };
+        let bad_pareto = LinkConfig {
+            delay: DelayDistribution::Pareto {
+                min:   Duration::from_millis(10),
+                shape: 0.0,
+            },
+            ..fast_link()
+        };
+        let no_rate = LinkConfig {
+            rate: DataRate::from_bps(0),
+            ..fast_link()
+        };
+        for config in &[
+            bad_probability,
+            bad_loss,
+            inverted_delay,
+            bad_pareto,
+            no_rate,
+        ] {
+            assert!(config.validate().is_err());
+            assert!(router
+                .set_interface_configs(ip, config.clone(), fast_link())
+                .is_err());
+            assert!(router
+                .set_interface_schedules(
+                    ip,
+                    LinkSchedule::new(fast_link()).then(Duration::from_secs(1), config.clone()),
+                    LinkSchedule::new(fast_link()),
+                )
+                .is_err());
         }
-        self.queued_size
-            .fetch_add(packet_size_with_overhead.as_bytes(), ordering);
-        self.actor.send(move |state| {
-            state
-                .queued_size
-                .fetch_sub(packet_size_with_overhead.as_bytes(), ordering);
+        assert!(fast_link().validate().is_ok());
+
+        stopper.stop_all_and_join();
+    }
+
+    #[test]
+    fn extreme_delay_distributions_are_capped() {
+        let mut rng = StdRng::seed_from_u64(1234);
+        for distribution in &[
+            DelayDistribution::Pareto {
+                min:   Duration::from_millis(10),
+                shape: 0.01,
+            },
+            DelayDistribution::Normal {
+                mean:    Duration::from_secs(u64::MAX / 2),
+                std_dev: Duration::from_secs(u64::MAX / 2),
+            },
+        ] {
+            let sampler = DelaySampler::new(distribution);
+            for _ in 0..1000 {
+                assert!(sampler.sample(&mut rng) <= MAX_DELAY);
+            }
+        }
+    }
 
-            // Simulates the time it takes to transmit a packet.
-            // TODO: accumulate sleep amounts and only sleep when more
-            // than some threshold for systems that have inprecise sleep.
-            thread::sleep(packet_size_with_overhead / rate);
+    #[test]
+    fn trace_drives_link() {
+        let stopper = Stopper::new();
+        let (router, receiver) = start_router_with_receiver(&stopper);
+        let timeout = Duration::from_millis(200);
 
-            state.receiver.receive_packet(packet);
-        })
+        router.set_interface_traces(
+            "1.0.0.1".parse().unwrap(),
+            Some(LinkTrace::parse_packet_records("drop\n10\n").unwrap()),
+            None,
+        );
+        router.send_packet(packet("1.0.0.1:1", "2.0.0.1:2"));
+        assert!(receiver.recv_timeout(timeout).is_err());
+        router.send_packet(packet("1.0.0.1:1", "2.0.0.1:2"));
+        assert!(receiver.recv_timeout(timeout).is_ok());
+
+        stopper.stop_all_and_join();
+    }
+
+    fn lossy_link() -> LinkConfig {
+        LinkConfig {
+            delay:                 DelayDistribution::Pareto {
+                min:   Duration::from_millis(50),
+                shape: 2.0,
+            },
+            loss:                  LossModel::GilbertElliott {
+                good_to_bad_probability: 0.05,
+                bad_to_good_probability: 0.5,
+                good_loss_probability:   0.01,
+                bad_loss_probability:    0.8,
+            },
+            reorder_probability:   0.05,
+            duplicate_probability: 0.05,
+            rate:                  DataRate::from_kbps(100),
+            queue_size:            DataSize::from_bytes(3_000),
+        }
+    }
+
+    // Sends numbered packets over a lossy link with virtual time
+    // and returns the numbers of the packets received, in order.
+    fn run_virtual_simulation(seed: u64) -> Vec<u8> {
+        let stopper = Stopper::new();
+        let clock = SimClock::new_virtual();
+        let router = Router::start_with_seed_and_clock(&stopper, seed, clock.clone()).unwrap();
+        let receiver = add_interfaces(&router, lossy_link());
+        for i in 0..100u8 {
+            let mut packet = packet("1.0.0.1:1", "2.0.0.1:2");
+            packet.data[0] = i;
+            router.send_packet(packet);
+        }
+        // Let the router process the sends before time moves.
+        router.flush();
+        clock.advance(Duration::from_secs(60));
+        stopper.stop_all_and_join();
+        receiver.try_iter().map(|packet| packet.data[0]).collect()
+    }
+
+    #[test]
+    fn same_seed_gives_same_simulation() {
+        let received = run_virtual_simulation(1234);
+        // Some get dropped randomly or from the full queue.
+        assert!(received.len() > 10);
+        assert!(received.len() < 100);
+        assert_eq!(received, run_virtual_simulation(1234));
+        assert_ne!(received, run_virtual_simulation(5678));
+    }
+
+    #[test]
+    fn virtual_time_runs_faster_than_real_time() {
+        let stopper = Stopper::new();
+        let clock = SimClock::new_virtual();
+        let router = Router::start_with_seed_and_clock(&stopper, 1, clock.clone()).unwrap();
+        let receiver = add_interfaces(&router, fast_link());
+        router
+            .set_interface_schedules(
+                "1.0.0.1".parse().unwrap(),
+                LinkSchedule::new(fast_link())
+                    .then_outage(Duration::from_secs(60))
+                    .then(Duration::from_secs(9 * 60), fast_link()),
+                LinkSchedule::new(fast_link()),
+            )
+            .unwrap();
+        router.flush();
+
+        let started = Instant::now();
+        clock.advance(Duration::from_secs(5 * 60));
+        router.send_packet(packet("1.0.0.1:1", "2.0.0.1:2"));
+        router.flush();
+        clock.advance(Duration::from_secs(5 * 60));
+        assert!(receiver.try_recv().is_err());
+
+        router.send_packet(packet("1.0.0.1:1", "2.0.0.1:2"));
+        router.flush();
+        clock.advance(Duration::from_secs(1));
+        assert!(receiver.try_recv().is_ok());
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        stopper.stop_all_and_join();
+    }
+
+    #[test]
+    fn jitter_keeps_order_and_duplicates_arrive_twice() {
+        let stopper = Stopper::new();
+        let clock = SimClock::new_virtual();
+        let router = Router::start_with_seed_and_clock(&stopper, 1, clock.clone()).unwrap();
+        let receiver = add_interfaces(
+            &router,
+            LinkConfig {
+                delay: DelayDistribution::Normal {
+                    mean:    Duration::from_millis(100),
+                    std_dev: Duration::from_millis(50),
+                },
+                duplicate_probability: 1.0,
+                ..fast_link()
+            },
+        );
+        for i in 0..10u8 {
+            let mut packet = packet("1.0.0.1:1", "2.0.0.1:2");
+            packet.data[0] = i;
+            router.send_packet(packet);
+        }
+        router.flush();
+        clock.advance(Duration::from_secs(10));
+        stopper.stop_all_and_join();
+
+        let received: Vec<u8> = receiver.try_iter().map(|packet| packet.data[0]).collect();
+        // Each packet is duplicated on both links.
+        assert_eq!(40, received.len());
+        let mut sorted = received.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, received);
+    }
+
+    #[test]
+    fn nat_translates_and_filters() {
+        let stopper = Stopper::new();
+        let clock = SimClock::new_virtual();
+        let router = Router::start_with_seed_and_clock(&stopper, 1, clock.clone()).unwrap();
+        let receiver2 = add_interfaces(&router, fast_link());
+        let (sender1, receiver1) = mpsc::channel();
+        router
+            .add_interface(
+                "10.0.0.1".parse().unwrap(),
+                fast_link(),
+                fast_link(),
+                Box::new(move |packet: Packet| {
+                    let _ = sender1.send(packet);
+                }),
+            )
+            .unwrap();
+        router.set_interface_nat(
+            "10.0.0.1".parse().unwrap(),
+            Some(NatConfig {
+                nat_type:  NatType::PortRestricted,
+                public_ip: "5.0.0.1".parse().unwrap(),
+            }),
+        );
+        let send_and_advance = |packet: Packet| {
+            router.send_packet(packet);
+            router.flush();
+            clock.advance(Duration::from_secs(1));
+        };
+
+        // Not reachable directly or before a mapping exists.
+        send_and_advance(packet("2.0.0.1:2", "10.0.0.1:1"));
+        send_and_advance(packet("2.0.0.1:2", "5.0.0.1:1024"));
+        assert!(receiver1.try_recv().is_err());
+
+        send_and_advance(packet("10.0.0.1:1", "2.0.0.1:2"));
+        let received = receiver2.try_recv().unwrap();
+        assert_eq!(
+            "5.0.0.1:1024".parse::<SocketAddr>().unwrap(),
+            received.source
+        );
+
+        send_and_advance(packet("2.0.0.1:2", "5.0.0.1:1024"));
+        let received = receiver1.try_recv().unwrap();
+        assert_eq!("10.0.0.1:1".parse::<SocketAddr>().unwrap(), received.dest);
+
+        // Port-restricted
+        send_and_advance(packet("2.0.0.1:3", "5.0.0.1:1024"));
+        assert!(receiver1.try_recv().is_err());
+
+        router.set_interface_firewall(
+            "2.0.0.1".parse().unwrap(),
+            Firewall::BlockUdp {
+                allowed_remote_ports: vec![],
+            },
+        );
+        send_and_advance(packet("10.0.0.1:1", "2.0.0.1:2"));
+        assert!(receiver2.try_recv().is_err());
+
+        stopper.stop_all_and_join();
+    }
+
+    #[test]
+    fn reliable_packets_are_retransmitted_in_order() {
+        let stopper = Stopper::new();
+        let clock = SimClock::new_virtual();
+        let router = Router::start_with_seed_and_clock(&stopper, 1, clock.clone()).unwrap();
+        let receiver = add_interfaces(
+            &router,
+            LinkConfig {
+                loss: LossModel::Random {
+                    loss_probability: 0.3,
+                },
+                reorder_probability: 0.5,
+                duplicate_probability: 0.5,
+                ..fast_link()
+            },
+        );
+        for i in 0..50u8 {
+            let mut packet = packet("1.0.0.1:1", "2.0.0.1:2");
+            packet.protocol = Protocol::Tcp;
+            packet.data[0] = i;
+            router.send_packet(packet);
+        }
+        router.flush();
+        clock.advance(Duration::from_secs(60));
+
+        let received: Vec<u8> = receiver.try_iter().map(|packet| packet.data[0]).collect();
+        assert_eq!((0..50).collect::<Vec<u8>>(), received);
+        let stats = router.interface_stats("1.0.0.1".parse().unwrap()).unwrap();
+        assert!(stats.send.packets_retransmitted > 0);
+        assert_eq!(0, stats.send.packets_dropped());
+        assert_eq!(0, stats.send.packets_duplicated);
+
+        stopper.stop_all_and_join();
+    }
+
+    #[test]
+    fn reliable_packets_wait_for_room_in_the_queue() {
+        let stopper = Stopper::new();
+        let clock = SimClock::new_virtual();
+        let router = Router::start_with_seed_and_clock(&stopper, 1, clock.clone()).unwrap();
+        // Fits 2 packets of 100 bytes (plus 40 bytes of overhead) at a time.
+        let receiver = add_interfaces(
+            &router,
+            LinkConfig {
+                rate: DataRate::from_kbps(100),
+                queue_size: DataSize::from_bytes(300),
+                ..fast_link()
+            },
+        );
+        for i in 0..5u8 {
+            let mut packet = packet("1.0.0.1:1", "2.0.0.1:2");
+            packet.protocol = Protocol::Tcp;
+            packet.data[0] = i;
+            router.send_packet(packet);
+        }
+        router.flush();
+        clock.advance(Duration::from_secs(10));
+
+        let received: Vec<u8> = receiver.try_iter().map(|packet| packet.data[0]).collect();
+        assert_eq!((0..5).collect::<Vec<u8>>(), received);
+        let stats = router.interface_stats("1.0.0.1".parse().unwrap()).unwrap();
+        assert_eq!(4, stats.send.packets_retransmitted);
+        assert_eq!(0, stats.send.packets_dropped());
+        // No packet waited behind more than a full queue.
+        assert_eq!(5, stats.send.queueing_delay.counts[..4].iter().sum::<u64>());
+
+        stopper.stop_all_and_join();
+    }
+
+    // Lets a test look at what was written to a boxed writer.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(data)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn stats_and_pcap() {
+        let stopper = Stopper::new();
+        let clock = SimClock::new_virtual();
+        let router = Router::start_with_seed_and_clock(&stopper, 1, clock.clone()).unwrap();
+        // Fits 2 packets of 100 bytes (plus 28 bytes of overhead) at a time.
+        let link = LinkConfig {
+            rate: DataRate::from_kbps(100),
+            queue_size: DataSize::from_bytes(300),
+            ..fast_link()
+        };
+        let receiver = add_interfaces(&router, link);
+        let pcap = SharedBuffer::default();
+        router.start_pcap(Box::new(pcap.clone())).unwrap();
+
+        for _ in 0..5 {
+            router.send_packet(packet("1.0.0.1:1", "2.0.0.1:2"));
+        }
+        router.flush();
+        clock.advance(Duration::from_secs(1));
+        router.stop_pcap();
+        assert_eq!(2, receiver.try_iter().count());
+
+        let stats = router.interface_stats("1.0.0.1".parse().unwrap()).unwrap();
+        assert_eq!(5, stats.send.packets_sent);
+        assert_eq!(500, stats.send.bytes_sent);
+        assert_eq!(3, stats.send.packets_dropped_by_full_queue);
+        assert_eq!(3, stats.send.packets_dropped());
+        // The second packet waits ~10ms for the first one to be transmitted.
+        assert_eq!(
+            [1, 0, 0, 1, 0, 0, 0, 0, 0],
+            stats.send.queueing_delay.counts
+        );
+        let stats = router.interface_stats("2.0.0.1".parse().unwrap()).unwrap();
+        assert_eq!(2, stats.receive.packets_sent);
+        assert_eq!(0, stats.receive.packets_dropped());
+        assert!(router.interface_stats("3.0.0.1".parse().unwrap()).is_none());
+
+        // The file header and one record per packet sent, dropped or not.
+        let record_size = 16 + 20 + 8 + 100;
+        assert_eq!(24 + 5 * record_size, pcap.0.lock().unwrap().len());
+
+        stopper.stop_all_and_join();
     }
 }
@@ -0,0 +1,592 @@
+//
+// Copyright 2021 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Declarative call scenarios, loaded from JSON, that the cli can run
+//! so that regression scenarios can be written without recompiling.
+//!
+//! A scenario has named link profiles, endpoints with network interfaces
+//! that use those profiles, and a timeline of actions.  Running one
+//! results in a ScenarioReport with the call states each endpoint went
+//! through and whether the expect_state actions passed.  With
+//! "virtual_time", a long call, such as scenarios/long_call.json,
+//! runs in seconds.  For example:
+//!
+//! {
+//!   "name": "accept after ringing",
+//!   "links": {
+//!     "good": { "delay_ms": 20, "rate_kbps": 5000, "queue_ms": 500 }
+//!   },
+//!   "endpoints": [
+//!     { "name": "caller", "interfaces": [{ "name": "wifi", "type": "wifi", "ip": "1.1.0.1", "link": "good" }] },
+//!     { "name": "callee", "interfaces": [{ "name": "wifi", "type": "wifi", "ip": "2.1.0.1", "link": "good" }] }
+//!   ],
+//!   "timeline": [
+//!     { "at_ms": 0,     "action": "call",   "endpoint": "caller", "callee": "callee" },
+//!     { "at_ms": 5000,  "action": "accept", "endpoint": "callee" },
+//!     { "at_ms": 10000, "action": "expect_state", "endpoint": "caller", "state": "connected" },
+//!     { "at_ms": 10000, "action": "hangup", "endpoint": "caller" }
+//!   ]
+//! }
+
+use crate::{
+    common::{
+        units::{DataRate, DataSize},
+        DeviceId,
+        Result,
+    },
+    error::RingRtcError,
+    simnet::{
+        nat::NatType,
+        router::{DelayDistribution, LinkConfig, LossModel},
+    },
+};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    fs,
+    net::IpAddr,
+    path::Path,
+    time::Duration,
+};
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Scenario {
+    #[serde(default)]
+    pub name:         String,
+    // When set, the router uses it so that the network behaves the same every run.
+    #[serde(default)]
+    pub seed:         Option<u64>,
+    // When set, the router and the CallManagers run on virtual time,
+    // so a long call finishes in a fraction of its duration.
+    // WebRTC keeps its own real-time clock, so media timing isn't simulated.
+    #[serde(default)]
+    pub virtual_time: bool,
+    pub links:        HashMap<String, LinkProfile>,
+    pub endpoints:    Vec<EndpointConfig>,
+    pub timeline:     Vec<TimelineEntry>,
+    // How long to keep running after the last action.
+    #[serde(default = "default_settle_ms")]
+    pub settle_ms:    u64,
+}
+
+fn default_settle_ms() -> u64 {
+    1000
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct LinkProfile {
+    pub delay_ms:              u64,
+    // The delay varies uniformly by up to this much either way.
+    #[serde(default)]
+    pub jitter_ms:             u64,
+    #[serde(default)]
+    pub loss_probability:      f64,
+    // When set, the link also loses every packet while in a bad state.
+    #[serde(default)]
+    pub burst_loss:            Option<BurstLoss>,
+    #[serde(default)]
+    pub reorder_probability:   f64,
+    #[serde(default)]
+    pub duplicate_probability: f64,
+    pub rate_kbps:             u64,
+    // How long it takes to transmit a full queue.
+    pub queue_ms:              u64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct BurstLoss {
+    pub good_to_bad_probability: f64,
+    pub bad_to_good_probability: f64,
+}
+
+impl LinkProfile {
+    pub fn to_link_config(&self) -> LinkConfig {
+        let loss = match &self.burst_loss {
+            None => LossModel::Random {
+                loss_probability: self.loss_probability,
+            },
+            Some(burst_loss) => LossModel::GilbertElliott {
+                good_to_bad_probability: burst_loss.good_to_bad_probability,
+                bad_to_good_probability: burst_loss.bad_to_good_probability,
+                good_loss_probability:   self.loss_probability,
+                bad_loss_probability:    1.0,
+            },
+        };
+        let rate = DataRate::from_kbps(self.rate_kbps);
+        LinkConfig {
+            delay: DelayDistribution::Uniform {
+                min: Duration::from_millis(self.delay_ms.saturating_sub(self.jitter_ms)),
+                max: Duration::from_millis(self.delay_ms + self.jitter_ms),
+            },
+            loss,
+            reorder_probability: self.reorder_probability,
+            duplicate_probability: self.duplicate_probability,
+            rate,
+            queue_size: rate * Duration::from_millis(self.queue_ms),
+        }
+    }
+
+    fn validate(&self) -> std::result::Result<(), String> {
+        self.to_link_config()
+            .validate()
+            .map_err(|err| err.to_string())?;
+        // The link can't transmit anything without room for at least one packet.
+        if DataRate::from_kbps(self.rate_kbps) * Duration::from_millis(self.queue_ms)
+            < DataSize::from_bytes(1500)
+        {
+            return Err("queue_ms must fit at least one 1500 byte packet".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct EndpointConfig {
+    // Also the peer ID, so other endpoints call it by this name.
+    pub name:       String,
+    #[serde(default = "default_device_id")]
+    pub device_id:  DeviceId,
+    pub interfaces: Vec<InterfaceConfig>,
+}
+
+fn default_device_id() -> DeviceId {
+    1
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct InterfaceConfig {
+    pub name:       String,
+    #[serde(rename = "type")]
+    pub typ:        InterfaceType,
+    pub ip:         IpAddr,
+    // Higher is more preferred.
+    #[serde(default)]
+    pub preference: u16,
+    // The name of the link profile used in both directions.
+    pub link:       String,
+    #[serde(default)]
+    pub nat:        Option<NatSpec>,
+    // Interfaces that start down can be brought up with switch_network.
+    #[serde(default = "default_up")]
+    pub up:         bool,
+}
+
+fn default_up() -> bool {
+    true
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum InterfaceType {
+    Ethernet,
+    Wifi,
+    Cellular,
+    Vpn,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct NatSpec {
+    #[serde(rename = "type")]
+    pub nat_type:  NatType,
+    pub public_ip: IpAddr,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TimelineEntry {
+    pub at_ms:  u64,
+    #[serde(flatten)]
+    pub action: Action,
+}
+
+impl TimelineEntry {
+    pub fn at(&self) -> Duration {
+        Duration::from_millis(self.at_ms)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Action {
+    Call {
+        endpoint: String,
+        callee:   String,
+        #[serde(default)]
+        video:    bool,
+    },
+    // Accepts the latest incoming call.
+    Accept {
+        endpoint: String,
+    },
+    Hangup {
+        endpoint: String,
+    },
+    Mute {
+        endpoint: String,
+        muted:    bool,
+    },
+    SendVideo {
+        endpoint: String,
+    },
+    SetBandwidthMode {
+        endpoint: String,
+        mode:     BandwidthModeName,
+    },
+    // Brings down one interface and brings up another.
+    SwitchNetwork {
+        endpoint: String,
+        from:     String,
+        to:       String,
+    },
+    // Changes the link profile of an interface.  No link means an outage.
+    SetLink {
+        endpoint:  String,
+        interface: String,
+        #[serde(default)]
+        link:      Option<String>,
+    },
+    ExpectState {
+        endpoint: String,
+        state:    CallStateKind,
+    },
+}
+
+impl Action {
+    pub fn endpoint(&self) -> &str {
+        match self {
+            Action::Call { endpoint, .. }
+            | Action::Accept { endpoint }
+            | Action::Hangup { endpoint }
+            | Action::Mute { endpoint, .. }
+            | Action::SendVideo { endpoint }
+            | Action::SetBandwidthMode { endpoint, .. }
+            | Action::SwitchNetwork { endpoint, .. }
+            | Action::SetLink { endpoint, .. }
+            | Action::ExpectState { endpoint, .. } => endpoint,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BandwidthModeName {
+    VeryLow,
+    Low,
+    Normal,
+}
+
+// The call states without their details, as they are expected in a scenario.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CallStateKind {
+    // Before the first call state
+    Idle,
+    Incoming,
+    Outgoing,
+    Ringing,
+    Connecting,
+    Connected,
+    Ended,
+    Concluded,
+}
+
+impl Scenario {
+    pub fn parse(json: &str) -> Result<Self> {
+        let scenario: Self = serde_json::from_str(json)
+            .map_err(|err| RingRtcError::SimnetInvalidScenario(err.to_string()))?;
+        scenario
+            .validate()
+            .map_err(RingRtcError::SimnetInvalidScenario)?;
+        Ok(scenario)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn endpoint(&self, name: &str) -> Option<&EndpointConfig> {
+        self.endpoints.iter().find(|endpoint| endpoint.name == name)
+    }
+
+    // The timeline ordered by time.  Actions at the same time keep their order.
+    pub fn sorted_timeline(&self) -> Vec<TimelineEntry> {
+        let mut timeline = self.timeline.clone();
+        timeline.sort_by_key(|entry| entry.at_ms);
+        timeline
+    }
+
+    pub fn duration(&self) -> Duration {
+        let last_ms = self.timeline.iter().map(|entry| entry.at_ms).max();
+        Duration::from_millis(last_ms.unwrap_or(0) + self.settle_ms)
+    }
+
+    fn validate(&self) -> std::result::Result<(), String> {
+        for (name, link) in &self.links {
+            link.validate()
+                .map_err(|err| format!("link {:?}: {}", name, err))?;
+        }
+        let mut names = HashSet::new();
+        let mut ips = HashSet::new();
+        for endpoint in &self.endpoints {
+            if !names.insert(&endpoint.name) {
+                return Err(format!("endpoint {:?} is declared twice", endpoint.name));
+            }
+            for interface in &endpoint.interfaces {
+                if !self.links.contains_key(&interface.link) {
+                    return Err(format!(
+                        "interface {:?} of endpoint {:?} uses unknown link {:?}",
+                        interface.name, endpoint.name, interface.link
+                    ));
+                }
+                if !ips.insert(interface.ip) {
+                    return Err(format!("IP {} is used twice", interface.ip));
+                }
+            }
+        }
+        for entry in &self.timeline {
+            let endpoint = self.endpoint(entry.action.endpoint()).ok_or_else(|| {
+                format!(
+                    "action at {}ms uses unknown endpoint {:?}",
+                    entry.at_ms,
+                    entry.action.endpoint()
+                )
+            })?;
+            let has_interface = |name: &str| {
+                endpoint
+                    .interfaces
+                    .iter()
+                    .any(|interface| interface.name == name)
+            };
+            let unknown = match &entry.action {
+                Action::Call { callee, .. } if self.endpoint(callee).is_none() => {
+                    Some(format!("callee {:?}", callee))
+                }
+                Action::SwitchNetwork { from, to, .. } => [from, to]
+                    .iter()
+                    .find(|name| !has_interface(name))
+                    .map(|name| format!("interface {:?}", name)),
+                Action::SetLink {
+                    interface, link, ..
+                } => {
+                    if !has_interface(interface) {
+                        Some(format!("interface {:?}", interface))
+                    } else {
+                        link.as_ref()
+                            .filter(|link| !self.links.contains_key(*link))
+                            .map(|link| format!("link {:?}", link))
+                    }
+                }
+                _ => None,
+            };
+            if let Some(unknown) = unknown {
+                return Err(format!(
+                    "action at {}ms uses unknown {}",
+                    entry.at_ms, unknown
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+// What happened while running a scenario.
+#[derive(Clone, Debug, Default)]
+pub struct ScenarioReport {
+    pub name:      String,
+    // For each endpoint, in the order they were declared.
+    pub timelines: Vec<(String, Vec<StateChange>)>,
+    pub checks:    Vec<StateCheck>,
+}
+
+#[derive(Clone, Debug)]
+pub struct StateChange {
+    // Since the start of the scenario
+    pub at:          Duration,
+    pub kind:        CallStateKind,
+    // With the details, such as why the call ended.
+    pub description: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct StateCheck {
+    pub at:       Duration,
+    pub endpoint: String,
+    pub expected: CallStateKind,
+    pub actual:   CallStateKind,
+}
+
+impl StateCheck {
+    pub fn passed(&self) -> bool {
+        self.expected == self.actual
+    }
+}
+
+impl ScenarioReport {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(StateCheck::passed)
+    }
+}
+
+impl fmt::Display for ScenarioReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Scenario {:?}", self.name)?;
+        for (endpoint, changes) in &self.timelines {
+            writeln!(f, "  {}:", endpoint)?;
+            for change in changes {
+                writeln!(
+                    f,
+                    "    {:>8}ms {}",
+                    change.at.as_millis(),
+                    change.description
+                )?;
+            }
+        }
+        for check in &self.checks {
+            writeln!(
+                f,
+                "  {} at {}ms: {} expected {:?}, was {:?}",
+                if check.passed() { "PASS" } else { "FAIL" },
+                check.at.as_millis(),
+                check.endpoint,
+                check.expected,
+                check.actual
+            )?;
+        }
+        write!(f, "{}", if self.passed() { "PASSED" } else { "FAILED" })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENARIO: &str = r#"{
+        "name": "test",
+        "links": {
+            "good": { "delay_ms": 20, "jitter_ms": 5, "rate_kbps": 5000, "queue_ms": 500 },
+            "bursty": {
+                "delay_ms": 100, "loss_probability": 0.01,
+                "burst_loss": { "good_to_bad_probability": 0.01, "bad_to_good_probability": 0.3 },
+                "rate_kbps": 256, "queue_ms": 1000
+            }
+        },
+        "endpoints": [
+            {
+                "name": "caller",
+                "interfaces": [
+                    { "name": "cell", "type": "cellular", "ip": "1.1.0.1", "link": "bursty",
+                      "nat": { "type": "symmetric", "public_ip": "1.1.255.1" } },
+                    { "name": "wifi", "type": "wifi", "ip": "1.1.0.2", "link": "good", "up": false }
+                ]
+            },
+            { "name": "callee", "device_id": 2, "interfaces": [{ "name": "wifi", "type": "wifi", "ip": "2.1.0.1", "link": "good" }] }
+        ],
+        "timeline": [
+            { "at_ms": 5000, "action": "accept", "endpoint": "callee" },
+            { "at_ms": 0, "action": "call", "endpoint": "caller", "callee": "callee", "video": true },
+            { "at_ms": 6000, "action": "switch_network", "endpoint": "caller", "from": "cell", "to": "wifi" },
+            { "at_ms": 7000, "action": "set_link", "endpoint": "caller", "interface": "cell" },
+            { "at_ms": 8000, "action": "set_bandwidth_mode", "endpoint": "caller", "mode": "very_low" },
+            { "at_ms": 9000, "action": "expect_state", "endpoint": "caller", "state": "connected" }
+        ]
+    }"#;
+
+    #[test]
+    fn parse() {
+        let scenario = Scenario::parse(SCENARIO).unwrap();
+        assert_eq!("test", scenario.name);
+        assert!(!scenario.virtual_time);
+        assert_eq!(Duration::from_millis(10_000), scenario.duration());
+
+        let caller = scenario.endpoint("caller").unwrap();
+        assert_eq!(1, caller.device_id);
+        assert_eq!(InterfaceType::Cellular, caller.interfaces[0].typ);
+        assert_eq!(
+            NatType::Symmetric,
+            caller.interfaces[0].nat.as_ref().unwrap().nat_type
+        );
+        assert!(!caller.interfaces[1].up);
+        assert_eq!(2, scenario.endpoint("callee").unwrap().device_id);
+
+        let timeline = scenario.sorted_timeline();
+        assert!(matches!(
+            &timeline[0].action,
+            Action::Call { callee, video: true, .. } if callee == "callee"
+        ));
+        assert!(matches!(
+            &timeline[3].action,
+            Action::SetLink { link, .. } if link.is_none()
+        ));
+        assert!(matches!(
+            &timeline[5].action,
+            Action::ExpectState { state, .. } if *state == CallStateKind::Connected
+        ));
+
+        let config = scenario.links["bursty"].to_link_config();
+        assert!(matches!(
+            config.loss,
+            LossModel::GilbertElliott {
+                bad_loss_probability,
+                ..
+            } if bad_loss_probability == 1.0
+        ));
+        assert_eq!(
+            DataRate::from_kbps(256) * Duration::from_secs(1),
+            config.queue_size
+        );
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(Scenario::parse("{}").is_err());
+        let unknown_endpoint =
+            SCENARIO.replace(r#""endpoint": "callee""#, r#""endpoint": "nobody""#);
+        assert!(Scenario::parse(&unknown_endpoint).is_err());
+        let unknown_link = SCENARIO.replace(r#""link": "good""#, r#""link": "great""#);
+        assert!(Scenario::parse(&unknown_link).is_err());
+        let unknown_interface = SCENARIO.replace(r#""to": "wifi""#, r#""to": "ethernet""#);
+        assert!(Scenario::parse(&unknown_interface).is_err());
+        let bad_probability = SCENARIO.replace("0.01", "1.5");
+        assert!(Scenario::parse(&bad_probability).is_err());
+        let unknown_action = SCENARIO.replace("\"accept\"", "\"answer\"");
+        assert!(Scenario::parse(&unknown_action).is_err());
+    }
+
+    #[test]
+    fn parse_bundled_scenarios() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenarios");
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if let Err(err) = Scenario::load(&path) {
+                panic!("{:?}: {:?}", path, err);
+            }
+        }
+    }
+
+    #[test]
+    fn report() {
+        let report = ScenarioReport {
+            name:      "test".to_string(),
+            timelines: vec![(
+                "caller".to_string(),
+                vec![StateChange {
+                    at:          Duration::from_millis(12),
+                    kind:        CallStateKind::Outgoing,
+                    description: "(Outgoing(0x1, Audio))".to_string(),
+                }],
+            )],
+            checks:    vec![StateCheck {
+                at:       Duration::from_millis(5000),
+                endpoint: "caller".to_string(),
+                expected: CallStateKind::Connected,
+                actual:   CallStateKind::Outgoing,
+            }],
+        };
+        assert!(!report.passed());
+        let text = report.to_string();
+        assert!(text.contains("FAIL at 5000ms: caller expected Connected, was Outgoing"));
+        assert!(text.ends_with("FAILED"));
+    }
+}
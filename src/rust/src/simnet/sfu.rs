@@ -0,0 +1,955 @@
+//
+// Copyright 2019-2021 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! A mock of the group calling SFU (selective forwarding unit) so that
+//! group calls can be run without any real servers.
+//!
+//! The mock speaks the same HTTP/JSON protocol as the real SFU frontend
+//! (see core::sfu_client::SfuClient) by implementing HttpClient, and it
+//! forwards RTP data messages between the devices that have joined.
+//! It does not terminate ICE, DTLS or SRTP, so media sent to its address
+//! over the Router is counted but not forwarded.
+//!
+//! The real SFU frontend derives the endpoint ID prefix of a device from
+//! the user ID ciphertext contained in the membership proof.  The mock
+//! treats the user part of the membership proof (everything before the
+//! first ':') as that ciphertext.  So group members should be given a
+//! GroupMemberInfo.user_id_ciphertext equal to that user part.
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{atomic, atomic::AtomicU64, Arc},
+};
+
+use prost::Message;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    common::{
+        actor::{Actor, Stopper},
+        HttpMethod,
+        HttpResponse,
+        Result,
+    },
+    core::{
+        group_call::{self, DemuxId, DtlsFingerprint},
+        http_client::HttpClient,
+        util::sha256_as_hexstring,
+    },
+    protobuf,
+    simnet::router::{LinkConfig, Packet, Router},
+    webrtc::rtp,
+};
+
+const PARTICIPANTS_PATH: &str = "/v1/conference/participants";
+
+// These must stay in sync with the values in group_call.rs.
+const RTP_DATA_PAYLOAD_TYPE: rtp::PayloadType = 101;
+const RTP_DATA_TO_SFU_SSRC: rtp::Ssrc = 1;
+
+// Demux IDs must leave room for the SSRCs derived from them.
+const DEMUX_ID_GAP: DemuxId = 16;
+
+const RESPONSE_CODE_OK: u16 = 200;
+const RESPONSE_CODE_BAD_REQUEST: u16 = 400;
+const RESPONSE_CODE_UNAUTHORIZED: u16 = 401;
+const RESPONSE_CODE_FORBIDDEN: u16 = 403;
+const RESPONSE_CODE_NOT_FOUND: u16 = 404;
+const RESPONSE_CODE_MAX_PARTICIPANTS_REACHED: u16 = 413;
+
+/// Receives RTP packets the mock SFU sends to a device.
+pub type BoxedRtpReceiver = Box<dyn Fn(rtp::Header, &[u8]) + Send>;
+
+#[derive(Clone, Debug)]
+pub struct MockSfuConfig {
+    /// The address given to devices as the SFU's (only) candidate.
+    pub udp_address:       SocketAddr,
+    pub ice_ufrag:         String,
+    pub ice_pwd:           String,
+    pub dtls_fingerprint:  DtlsFingerprint,
+    /// If set, joins beyond this number of devices fail like they do with the real SFU.
+    pub max_devices:       Option<u32>,
+    /// If set, devices joining a call that already has devices wait in the lobby
+    /// until a joined device approves them.
+    pub approval_required: bool,
+}
+
+impl MockSfuConfig {
+    pub fn new(udp_address: SocketAddr) -> Self {
+        Self {
+            udp_address,
+            ice_ufrag: "mocksfu".to_string(),
+            ice_pwd: "mocksfupasswordmocksfupassword".to_string(),
+            dtls_fingerprint: [0xAB; 32],
+            max_devices: None,
+            approval_required: false,
+        }
+    }
+}
+
+/// A device joined to the mock SFU (or waiting in its lobby).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MockSfuParticipant {
+    pub endpoint_id: String,
+    pub demux_id:    DemuxId,
+}
+
+#[derive(Clone)]
+pub struct MockSfu {
+    actor:                   Actor<MockSfuState>,
+    // Shared with the Router interface so tests can check that
+    // packets arrived without going through the actor.
+    received_router_packets: Arc<AtomicU64>,
+}
+
+struct MockSfuState {
+    config:       MockSfuConfig,
+    participants: Vec<MockSfuParticipant>,
+    // Devices waiting in the lobby.
+    pending:      Vec<MockSfuParticipant>,
+    // The endpoint ID prefix of the device that started the current era.
+    creator:      Option<String>,
+    era_id:       Option<String>,
+    era_count:    u64,
+
+    next_demux_id:       DemuxId,
+    next_short_id:       u64,
+    next_rtp_seqnum:     u32,
+    rtp_receivers:       HashMap<DemuxId, BoxedRtpReceiver>,
+    last_video_requests: HashMap<DemuxId, protobuf::group_call::device_to_sfu::VideoRequestMessage>,
+}
+
+#[derive(Deserialize, Debug)]
+struct JoinRequest {
+    transport:          JoinRequestTransport,
+    #[serde(rename = "resumeEndpointId", default)]
+    resume_endpoint_id: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct JoinRequestTransport {
+    ufrag: String,
+    pwd:   String,
+}
+
+impl MockSfu {
+    pub fn start(stopper: &Stopper, config: MockSfuConfig) -> Result<Self> {
+        Ok(Self {
+            actor:                   Actor::start(stopper.clone(), move |_| {
+                Ok(MockSfuState {
+                    config,
+                    participants: Vec::new(),
+                    pending: Vec::new(),
+                    creator: None,
+                    era_id: None,
+                    era_count: 0,
+                    next_demux_id: DEMUX_ID_GAP,
+                    next_short_id: 1,
+                    next_rtp_seqnum: 1,
+                    rtp_receivers: HashMap::new(),
+                    last_video_requests: HashMap::new(),
+                })
+            })?,
+            received_router_packets: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    /// The URL to give to core::sfu_client::SfuClient.
+    pub fn url(&self) -> String {
+        "https://sfu.mock".to_string()
+    }
+
+    /// Attaches the SFU's address to the Router so packets sent to it
+    /// are routed (and counted) rather than dropped as unknown.
+    pub fn add_to_router(
+        &self,
+        router: &Router,
+        udp_address: SocketAddr,
+        config: LinkConfig,
+    ) -> Result<()> {
+        let received_router_packets = self.received_router_packets.clone();
+        router.add_interface(
+            udp_address.ip(),
+            config.clone(),
+            config,
+            Box::new(move |packet: Packet| {
+                debug!(
+                    "MockSfu: received {} bytes from {:?} over the router",
+                    packet.data.len(),
+                    packet.source
+                );
+                received_router_packets.fetch_add(1, atomic::Ordering::SeqCst);
+            }),
+        )
+    }
+
+    pub fn received_router_packet_count(&self) -> u64 {
+        self.received_router_packets.load(atomic::Ordering::SeqCst)
+    }
+
+    pub fn set_max_devices(&self, max_devices: Option<u32>) {
+        self.actor.send(move |state| {
+            state.config.max_devices = max_devices;
+        });
+    }
+
+    /// Registers where RTP packets for the device with the given demux ID should go.
+    pub fn set_rtp_receiver(&self, demux_id: DemuxId, receiver: BoxedRtpReceiver) {
+        self.actor.send(move |state| {
+            state.rtp_receivers.insert(demux_id, receiver);
+        });
+    }
+
+    /// Handles an RTP packet sent by the device with the given demux ID.
+    /// Messages addressed to the SFU are processed and everything else
+    /// is forwarded to all other joined devices.
+    pub fn receive_rtp(&self, sender_demux_id: DemuxId, header: rtp::Header, payload: Vec<u8>) {
+        self.actor.send(move |state| {
+            if !state
+                .participants
+                .iter()
+                .any(|p| p.demux_id == sender_demux_id)
+            {
+                debug!(
+                    "MockSfu: dropping RTP from {} because it hasn't joined",
+                    sender_demux_id
+                );
+                return;
+            }
+            if header.pt != RTP_DATA_PAYLOAD_TYPE {
+                return;
+            }
+            if header.ssrc == RTP_DATA_TO_SFU_SSRC {
+                match protobuf::group_call::DeviceToSfu::decode(&payload[..]) {
+                    Ok(protobuf::group_call::DeviceToSfu {
+                        video_request: Some(video_request),
+                    }) => {
+                        state
+                            .last_video_requests
+                            .insert(sender_demux_id, video_request);
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        warn!("MockSfu: failed to decode DeviceToSfu: {:?}", e);
+                    }
+                }
+            } else {
+                // Devices in the lobby don't get anything from joined devices.
+                for (demux_id, receiver) in &state.rtp_receivers {
+                    if *demux_id != sender_demux_id
+                        && state.participants.iter().any(|p| p.demux_id == *demux_id)
+                    {
+                        receiver(header.clone(), &payload);
+                    }
+                }
+            }
+        });
+    }
+
+    /// Tells every joined device that the given device is now the primary speaker.
+    pub fn send_speaker(&self, speaker_demux_id: DemuxId) {
+        self.actor.send(move |state| {
+            let long_device_id = state
+                .participants
+                .iter()
+                .find(|p| p.demux_id == speaker_demux_id)
+                .map(|p| p.endpoint_id.clone());
+            if let Some(long_device_id) = long_device_id {
+                state.send_to_all_devices(protobuf::group_call::SfuToDevice {
+                    speaker: Some(protobuf::group_call::sfu_to_device::Speaker {
+                        long_device_id: Some(long_device_id),
+                    }),
+                    ..Default::default()
+                });
+            }
+        });
+    }
+
+    pub fn participants(&self) -> Vec<MockSfuParticipant> {
+        self.query(|state| state.participants.clone())
+    }
+
+    /// The devices waiting in the lobby.
+    pub fn pending_participants(&self) -> Vec<MockSfuParticipant> {
+        self.query(|state| state.pending.clone())
+    }
+
+    /// The most recent video request received from the given device.
+    pub fn last_video_request(
+        &self,
+        demux_id: DemuxId,
+    ) -> Option<protobuf::group_call::device_to_sfu::VideoRequestMessage> {
+        self.query(move |state| state.last_video_requests.get(&demux_id).cloned())
+    }
+
+    // Runs the given function on the actor and waits for the result.
+    fn query<T: Send + 'static>(&self, f: impl FnOnce(&MockSfuState) -> T + Send + 'static) -> T {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.actor.send(move |state| {
+            let _ = sender.send(f(state));
+        });
+        receiver.recv().expect("MockSfu actor stopped")
+    }
+}
+
+impl MockSfuState {
+    fn handle_request(
+        &mut self,
+        url: &str,
+        method: HttpMethod,
+        headers: &HashMap<String, String>,
+        body: Option<Vec<u8>>,
+    ) -> HttpResponse {
+        let user_prefix = match headers
+            .get("Authorization")
+            .and_then(|header| Self::parse_user_prefix(header))
+        {
+            Some(user_prefix) => user_prefix,
+            None => {
+                warn!("MockSfu: request without valid authorization");
+                return Self::response(RESPONSE_CODE_UNAUTHORIZED, None);
+            }
+        };
+
+        let path = match url.find(PARTICIPANTS_PATH) {
+            Some(index) => &url[(index + PARTICIPANTS_PATH.len())..],
+            None => {
+                return Self::response(RESPONSE_CODE_NOT_FOUND, None);
+            }
+        };
+        match (method, path) {
+            (HttpMethod::Get, "") => self.get_participants(),
+            (HttpMethod::Put, "") => self.join(user_prefix, body),
+            (HttpMethod::Delete, endpoint_id) if endpoint_id.starts_with('/') => {
+                self.remove(&user_prefix, &endpoint_id[1..])
+            }
+            (HttpMethod::Post, action) if action.starts_with('/') => {
+                match action[1..].rsplit_once('/') {
+                    Some((endpoint_id, "approve")) => {
+                        self.respond_to_pending(&user_prefix, endpoint_id, true)
+                    }
+                    Some((endpoint_id, "deny")) => {
+                        self.respond_to_pending(&user_prefix, endpoint_id, false)
+                    }
+                    _ => Self::response(RESPONSE_CODE_BAD_REQUEST, None),
+                }
+            }
+            _ => Self::response(RESPONSE_CODE_BAD_REQUEST, None),
+        }
+    }
+
+    // The Authorization header is "Basic base64(user:token)".
+    fn parse_user_prefix(header: &str) -> Option<String> {
+        let encoded = header.strip_prefix("Basic ")?;
+        let decoded = base64::decode(encoded).ok()?;
+        let decoded = std::str::from_utf8(&decoded).ok()?;
+        let user = decoded.split(':').next()?;
+        if user.is_empty() {
+            return None;
+        }
+        Some(sha256_as_hexstring(user.as_bytes()))
+    }
+
+    fn response(status_code: u16, body: Option<serde_json::Value>) -> HttpResponse {
+        HttpResponse {
+            status_code,
+            body: body
+                .map(|body| body.to_string().into_bytes())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn get_participants(&self) -> HttpResponse {
+        if self.participants.is_empty() {
+            return Self::response(RESPONSE_CODE_NOT_FOUND, None);
+        }
+        let to_json = |p: &MockSfuParticipant| {
+            json!({
+                "endpointId": p.endpoint_id,
+                "ssrcPrefix": p.demux_id,
+            })
+        };
+        let participants: Vec<serde_json::Value> = self.participants.iter().map(to_json).collect();
+        let pending_clients: Vec<serde_json::Value> = self.pending.iter().map(to_json).collect();
+        Self::response(
+            RESPONSE_CODE_OK,
+            Some(json!({
+                "conferenceId": self.era_id,
+                "maxConferenceSize": self.config.max_devices,
+                "participants": participants,
+                "pendingClients": pending_clients,
+                "creator": self.creator,
+            })),
+        )
+    }
+
+    fn join(&mut self, user_prefix: String, body: Option<Vec<u8>>) -> HttpResponse {
+        let request: Option<JoinRequest> = body.and_then(|body| serde_json::from_slice(&body).ok());
+        let resume_endpoint_id = match request {
+            Some(request) => {
+                debug!(
+                    "MockSfu: join request with ufrag {} (pwd length {})",
+                    request.transport.ufrag,
+                    request.transport.pwd.len()
+                );
+                request.resume_endpoint_id
+            }
+            None => {
+                warn!("MockSfu: unparsable join request");
+                return Self::response(RESPONSE_CODE_BAD_REQUEST, None);
+            }
+        };
+
+        // A device can take back its own endpoint (say, after restarting) as long as
+        // it hasn't left.  No one else notices, so there's no notification.
+        if let Some(resume_endpoint_id) = resume_endpoint_id {
+            let resumed = self.participants.iter().find(|p| {
+                p.endpoint_id == resume_endpoint_id
+                    && p.endpoint_id.starts_with(&format!("{}-", user_prefix))
+            });
+            if let Some(participant) = resumed {
+                info!(
+                    "MockSfu: {} resumed with demux ID {}",
+                    participant.endpoint_id, participant.demux_id
+                );
+                return self.join_response(
+                    &participant.endpoint_id,
+                    participant.demux_id,
+                    "ACTIVE",
+                );
+            }
+            info!(
+                "MockSfu: can't resume {}, so joining as a new device",
+                resume_endpoint_id
+            );
+        }
+
+        if let Some(max_devices) = self.config.max_devices {
+            if self.participants.len() as u32 >= max_devices {
+                info!("MockSfu: rejecting join because the call is full");
+                return Self::response(RESPONSE_CODE_MAX_PARTICIPANTS_REACHED, None);
+            }
+        }
+
+        if self.participants.is_empty() {
+            self.era_count += 1;
+            self.era_id = Some(format!("mock-era-{}", self.era_count));
+            self.creator = Some(user_prefix.clone());
+        }
+
+        let demux_id = self.next_demux_id;
+        self.next_demux_id = self.next_demux_id.wrapping_add(DEMUX_ID_GAP);
+        let short_id = self.next_short_id;
+        self.next_short_id += 1;
+        let endpoint_id = format!("{}-{}", user_prefix, short_id);
+
+        let participant = MockSfuParticipant {
+            endpoint_id: endpoint_id.clone(),
+            demux_id,
+        };
+        let client_status = if self.config.approval_required && !self.participants.is_empty() {
+            info!(
+                "MockSfu: {} is waiting in the lobby with demux ID {}",
+                endpoint_id, demux_id
+            );
+            self.pending.push(participant);
+            "PENDING"
+        } else {
+            info!("MockSfu: {} joined with demux ID {}", endpoint_id, demux_id);
+            self.participants.push(participant);
+            "ACTIVE"
+        };
+        self.send_device_joined_or_left();
+        self.join_response(&endpoint_id, demux_id, client_status)
+    }
+
+    fn join_response(
+        &self,
+        endpoint_id: &str,
+        demux_id: DemuxId,
+        client_status: &str,
+    ) -> HttpResponse {
+        let fingerprint = group_call::encode_fingerprint(&self.config.dtls_fingerprint);
+        Self::response(
+            RESPONSE_CODE_OK,
+            Some(json!({
+                "endpointId": endpoint_id,
+                "ssrcPrefix": demux_id,
+                "clientStatus": client_status,
+                "transport": {
+                    "ufrag": self.config.ice_ufrag,
+                    "pwd": self.config.ice_pwd,
+                    "fingerprints": [
+                        {
+                            "hash": "sha-256",
+                            "fingerprint": fingerprint,
+                        },
+                    ],
+                    "candidates": [
+                        {
+                            "ip": self.config.udp_address.ip().to_string(),
+                            "port": self.config.udp_address.port(),
+                            "type": "host",
+                        },
+                    ],
+                },
+            })),
+        )
+    }
+
+    // Devices can remove themselves (leave), and the creator can remove anyone.
+    fn remove(&mut self, user_prefix: &str, endpoint_id: &str) -> HttpResponse {
+        let is_own_device = endpoint_id.starts_with(&format!("{}-", user_prefix));
+        if let Some(index) = self
+            .pending
+            .iter()
+            .position(|p| p.endpoint_id == endpoint_id)
+        {
+            // Others turn away devices in the lobby by denying them instead.
+            if !is_own_device {
+                return Self::response(RESPONSE_CODE_FORBIDDEN, None);
+            }
+            let device = self.pending.remove(index);
+            self.rtp_receivers.remove(&device.demux_id);
+            info!("MockSfu: {} left the lobby", device.endpoint_id);
+            self.send_device_joined_or_left();
+            return Self::response(RESPONSE_CODE_OK, None);
+        }
+        let index = match self
+            .participants
+            .iter()
+            .position(|p| p.endpoint_id == endpoint_id)
+        {
+            Some(index) => index,
+            None => {
+                return Self::response(RESPONSE_CODE_NOT_FOUND, None);
+            }
+        };
+        if !is_own_device && self.creator.as_deref() != Some(user_prefix) {
+            warn!(
+                "MockSfu: rejecting removal of {} by someone other than the creator",
+                endpoint_id
+            );
+            return Self::response(RESPONSE_CODE_FORBIDDEN, None);
+        }
+        let participant = self.participants.remove(index);
+        self.rtp_receivers.remove(&participant.demux_id);
+        self.last_video_requests.remove(&participant.demux_id);
+        info!("MockSfu: {} left", participant.endpoint_id);
+
+        if self.participants.is_empty() {
+            self.era_id = None;
+            self.creator = None;
+            // No one is left to let in the devices in the lobby.
+            for device in std::mem::take(&mut self.pending) {
+                self.rtp_receivers.remove(&device.demux_id);
+            }
+        }
+        self.send_device_joined_or_left();
+        Self::response(RESPONSE_CODE_OK, None)
+    }
+
+    // Any joined device can approve or deny a device in the lobby.
+    fn respond_to_pending(
+        &mut self,
+        user_prefix: &str,
+        endpoint_id: &str,
+        approve: bool,
+    ) -> HttpResponse {
+        let device_prefix = format!("{}-", user_prefix);
+        if !self
+            .participants
+            .iter()
+            .any(|p| p.endpoint_id.starts_with(&device_prefix))
+        {
+            warn!(
+                "MockSfu: rejecting approval or denial of {} by someone who hasn't joined",
+                endpoint_id
+            );
+            return Self::response(RESPONSE_CODE_FORBIDDEN, None);
+        }
+        let index = match self
+            .pending
+            .iter()
+            .position(|p| p.endpoint_id == endpoint_id)
+        {
+            Some(index) => index,
+            None => {
+                return Self::response(RESPONSE_CODE_NOT_FOUND, None);
+            }
+        };
+        let device = self.pending.remove(index);
+        if approve {
+            info!("MockSfu: {} was approved and joined", device.endpoint_id);
+            self.participants.push(device);
+        } else {
+            info!("MockSfu: {} was denied", device.endpoint_id);
+            self.rtp_receivers.remove(&device.demux_id);
+        }
+        self.send_device_joined_or_left();
+        Self::response(RESPONSE_CODE_OK, None)
+    }
+
+    fn send_device_joined_or_left(&mut self) {
+        self.send_to_all_devices(protobuf::group_call::SfuToDevice {
+            device_joined_or_left: Some(
+                protobuf::group_call::sfu_to_device::DeviceJoinedOrLeft::default(),
+            ),
+            ..Default::default()
+        });
+    }
+
+    fn send_to_all_devices(&mut self, message: protobuf::group_call::SfuToDevice) {
+        let mut payload = Vec::with_capacity(message.encoded_len());
+        if let Err(e) = message.encode(&mut payload) {
+            warn!("MockSfu: failed to encode SfuToDevice: {:?}", e);
+            return;
+        }
+        let seqnum = self.next_rtp_seqnum;
+        self.next_rtp_seqnum = self.next_rtp_seqnum.wrapping_add(1);
+        let header = rtp::Header {
+            pt:        RTP_DATA_PAYLOAD_TYPE,
+            ssrc:      RTP_DATA_TO_SFU_SSRC,
+            seqnum:    seqnum as u16,
+            timestamp: seqnum,
+        };
+        // Devices in the lobby learn about changes by peeking instead.
+        for (demux_id, receiver) in &self.rtp_receivers {
+            if self.participants.iter().any(|p| p.demux_id == *demux_id) {
+                receiver(header.clone(), &payload);
+            }
+        }
+    }
+}
+
+impl HttpClient for MockSfu {
+    fn make_request(
+        &self,
+        url: String,
+        method: HttpMethod,
+        headers: HashMap<String, String>,
+        body: Option<Vec<u8>>,
+        on_response: Box<dyn FnOnce(Option<HttpResponse>) + Send>,
+    ) {
+        debug!("MockSfu: {} {}", method, url);
+        self.actor.send(move |state| {
+            let response = state.handle_request(&url, method, &headers, body);
+            on_response(Some(response));
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        group_call::{GroupMemberInfo, PeekInfo, SfuClient as _},
+        sfu_client::SfuClient,
+    };
+    use std::sync::mpsc;
+
+    fn start_sfu(stopper: &Stopper) -> MockSfu {
+        MockSfu::start(
+            stopper,
+            MockSfuConfig::new("10.0.0.1:10000".parse().unwrap()),
+        )
+        .expect("start MockSfu")
+    }
+
+    fn sfu_client_for(sfu: &MockSfu, user: &str) -> SfuClient {
+        let mut sfu_client = SfuClient::new(Box::new(sfu.clone()), sfu.url());
+        sfu_client.set_membership_proof(format!("{}:proof", user).into_bytes());
+        sfu_client.set_group_members(
+            ["alice", "bob"]
+                .iter()
+                .map(|user| GroupMemberInfo {
+                    user_id:            user.as_bytes().to_vec(),
+                    user_id_ciphertext: user.as_bytes().to_vec(),
+                })
+                .collect(),
+        );
+        sfu_client
+    }
+
+    fn auth_headers(user: &str) -> HashMap<String, String> {
+        let mut headers = HashMap::new();
+        headers.insert(
+            "Authorization".to_string(),
+            format!(
+                "Basic {}",
+                base64::encode(format!("{}:{}:proof", user, user))
+            ),
+        );
+        headers
+    }
+
+    fn join(sfu: &MockSfu, user: &str) -> u16 {
+        let (sender, receiver) = mpsc::channel();
+        sfu.make_request(
+            format!("{}{}", sfu.url(), PARTICIPANTS_PATH),
+            HttpMethod::Put,
+            auth_headers(user),
+            Some(br#"{"transport": {"ufrag": "u", "pwd": "p"}}"#.to_vec()),
+            Box::new(move |response| {
+                sender.send(response.unwrap().status_code).unwrap();
+            }),
+        );
+        receiver.recv().unwrap()
+    }
+
+    fn resume(sfu: &MockSfu, user: &str, endpoint_id: &str) -> (String, DemuxId) {
+        let (sender, receiver) = mpsc::channel();
+        sfu.make_request(
+            format!("{}{}", sfu.url(), PARTICIPANTS_PATH),
+            HttpMethod::Put,
+            auth_headers(user),
+            Some(
+                json!({
+                    "transport": {"ufrag": "u", "pwd": "p"},
+                    "resumeEndpointId": endpoint_id,
+                })
+                .to_string()
+                .into_bytes(),
+            ),
+            Box::new(move |response| {
+                sender.send(response.unwrap().body).unwrap();
+            }),
+        );
+        let body: serde_json::Value = serde_json::from_slice(&receiver.recv().unwrap()).unwrap();
+        (
+            body["endpointId"].as_str().unwrap().to_string(),
+            body["ssrcPrefix"].as_u64().unwrap() as DemuxId,
+        )
+    }
+
+    fn peek(sfu_client: &mut SfuClient) -> PeekInfo {
+        let (sender, receiver) = mpsc::channel();
+        sfu_client.peek(Box::new(move |result| {
+            sender.send(result.expect("peek")).unwrap();
+        }));
+        receiver.recv().unwrap()
+    }
+
+    #[test]
+    fn peek_empty_call() {
+        let stopper = Stopper::new();
+        let sfu = start_sfu(&stopper);
+        let mut alice = sfu_client_for(&sfu, "alice");
+
+        let peek_info = peek(&mut alice);
+        assert!(peek_info.devices.is_empty());
+        assert_eq!(None, peek_info.era_id);
+        stopper.stop_all_and_join();
+    }
+
+    #[test]
+    fn join_peek_and_leave() {
+        let stopper = Stopper::new();
+        let sfu = start_sfu(&stopper);
+        let mut alice = sfu_client_for(&sfu, "alice");
+        let mut bob = sfu_client_for(&sfu, "bob");
+
+        assert_eq!(RESPONSE_CODE_OK, join(&sfu, "alice"));
+        assert_eq!(RESPONSE_CODE_OK, join(&sfu, "bob"));
+
+        let peek_info = peek(&mut alice);
+        assert_eq!(2, peek_info.device_count);
+        assert_eq!(Some(b"alice".to_vec()), peek_info.creator);
+        assert_eq!(Some("mock-era-1".to_string()), peek_info.era_id);
+        let user_ids: Vec<_> = peek_info
+            .devices
+            .iter()
+            .map(|device| device.user_id.clone())
+            .collect();
+        assert_eq!(
+            vec![Some(b"alice".to_vec()), Some(b"bob".to_vec())],
+            user_ids
+        );
+
+        let participants = sfu.participants();
+        assert_eq!(
+            vec![16, 32],
+            participants.iter().map(|p| p.demux_id).collect::<Vec<_>>()
+        );
+        bob.leave(participants[1].endpoint_id.clone());
+        alice.leave(participants[0].endpoint_id.clone());
+        assert!(sfu.participants().is_empty());
+        assert_eq!(None, peek(&mut bob).era_id);
+        stopper.stop_all_and_join();
+    }
+
+    #[test]
+    fn only_creator_can_remove_others() {
+        let stopper = Stopper::new();
+        let sfu = start_sfu(&stopper);
+        let mut alice = sfu_client_for(&sfu, "alice");
+        let mut bob = sfu_client_for(&sfu, "bob");
+
+        assert_eq!(RESPONSE_CODE_OK, join(&sfu, "alice"));
+        assert_eq!(RESPONSE_CODE_OK, join(&sfu, "bob"));
+        let participants = sfu.participants();
+
+        bob.remove_device(participants[0].endpoint_id.clone());
+        assert_eq!(participants, sfu.participants());
+
+        alice.remove_device(participants[1].endpoint_id.clone());
+        assert_eq!(participants[..1].to_vec(), sfu.participants());
+        stopper.stop_all_and_join();
+    }
+
+    #[test]
+    fn lobby_requires_approval() {
+        let stopper = Stopper::new();
+        let mut config = MockSfuConfig::new("10.0.0.1:10000".parse().unwrap());
+        config.approval_required = true;
+        let sfu = MockSfu::start(&stopper, config).expect("start MockSfu");
+        let mut alice = sfu_client_for(&sfu, "alice");
+        let mut bob = sfu_client_for(&sfu, "bob");
+
+        assert_eq!(RESPONSE_CODE_OK, join(&sfu, "alice"));
+        assert_eq!(RESPONSE_CODE_OK, join(&sfu, "bob"));
+        assert_eq!(RESPONSE_CODE_OK, join(&sfu, "bob"));
+        let pending = sfu.pending_participants();
+        assert_eq!(2, pending.len());
+
+        let peek_info = peek(&mut alice);
+        assert_eq!(1, peek_info.device_count);
+        assert_eq!(
+            vec![Some(b"bob".to_vec()), Some(b"bob".to_vec())],
+            peek_info
+                .pending_devices
+                .iter()
+                .map(|device| device.user_id.clone())
+                .collect::<Vec<_>>()
+        );
+
+        // Devices in the lobby can't let themselves in.
+        bob.approve_device(pending[0].endpoint_id.clone());
+        assert_eq!(pending, sfu.pending_participants());
+
+        alice.approve_device(pending[0].endpoint_id.clone());
+        assert_eq!(2, sfu.participants().len());
+        bob.deny_device(pending[1].endpoint_id.clone());
+        assert!(sfu.pending_participants().is_empty());
+        assert_eq!(2, peek(&mut bob).device_count);
+        stopper.stop_all_and_join();
+    }
+
+    #[test]
+    fn resume_own_endpoint_only() {
+        let stopper = Stopper::new();
+        let sfu = start_sfu(&stopper);
+
+        assert_eq!(RESPONSE_CODE_OK, join(&sfu, "alice"));
+        assert_eq!(RESPONSE_CODE_OK, join(&sfu, "bob"));
+        let participants = sfu.participants();
+        let alice_endpoint_id = &participants[0].endpoint_id;
+
+        assert_eq!(
+            (alice_endpoint_id.clone(), participants[0].demux_id),
+            resume(&sfu, "alice", alice_endpoint_id)
+        );
+        assert_eq!(participants, sfu.participants());
+
+        // Bob can't take over Alice's endpoint, so Bob gets a new one.
+        let (endpoint_id, demux_id) = resume(&sfu, "bob", alice_endpoint_id);
+        let participants = sfu.participants();
+        assert_eq!(3, participants.len());
+        assert_eq!(
+            (
+                participants[2].endpoint_id.clone(),
+                participants[2].demux_id
+            ),
+            (endpoint_id, demux_id)
+        );
+        stopper.stop_all_and_join();
+    }
+
+    #[test]
+    fn join_fails_when_full() {
+        let stopper = Stopper::new();
+        let mut config = MockSfuConfig::new("10.0.0.1:10000".parse().unwrap());
+        config.max_devices = Some(1);
+        let sfu = MockSfu::start(&stopper, config).expect("start MockSfu");
+
+        assert_eq!(RESPONSE_CODE_OK, join(&sfu, "alice"));
+        assert_eq!(RESPONSE_CODE_MAX_PARTICIPANTS_REACHED, join(&sfu, "bob"));
+        stopper.stop_all_and_join();
+    }
+
+    #[test]
+    fn forwards_rtp_data_between_devices() {
+        let stopper = Stopper::new();
+        let sfu = start_sfu(&stopper);
+        assert_eq!(RESPONSE_CODE_OK, join(&sfu, "alice"));
+        assert_eq!(RESPONSE_CODE_OK, join(&sfu, "bob"));
+
+        let (sender, receiver) = mpsc::channel();
+        sfu.set_rtp_receiver(
+            32,
+            Box::new(move |header, payload| {
+                sender.send((header.ssrc, payload.to_vec())).unwrap();
+            }),
+        );
+        let header = rtp::Header {
+            pt:        RTP_DATA_PAYLOAD_TYPE,
+            ssrc:      16 + 0xD,
+            seqnum:    1,
+            timestamp: 1,
+        };
+        sfu.receive_rtp(16, header, b"hello".to_vec());
+        assert_eq!((16 + 0xD, b"hello".to_vec()), receiver.recv().unwrap());
+
+        // Devices that haven't joined can't send through the SFU.
+        let header = rtp::Header {
+            pt:        RTP_DATA_PAYLOAD_TYPE,
+            ssrc:      48 + 0xD,
+            seqnum:    1,
+            timestamp: 1,
+        };
+        sfu.receive_rtp(48, header, b"ignored".to_vec());
+        sfu.send_speaker(16);
+        let (ssrc, payload) = receiver.recv().unwrap();
+        assert_eq!(RTP_DATA_TO_SFU_SSRC, ssrc);
+        let message = protobuf::group_call::SfuToDevice::decode(&payload[..]).unwrap();
+        assert_eq!(
+            Some(format!("{}-1", sha256_as_hexstring(b"alice"))),
+            message.speaker.unwrap().long_device_id
+        );
+        stopper.stop_all_and_join();
+    }
+
+    #[test]
+    fn devices_in_lobby_get_nothing_from_sfu() {
+        let stopper = Stopper::new();
+        let mut config = MockSfuConfig::new("10.0.0.1:10000".parse().unwrap());
+        config.approval_required = true;
+        let sfu = MockSfu::start(&stopper, config).expect("start MockSfu");
+        assert_eq!(RESPONSE_CODE_OK, join(&sfu, "alice"));
+        assert_eq!(RESPONSE_CODE_OK, join(&sfu, "bob"));
+
+        let (alice_sender, alice_receiver) = mpsc::channel();
+        sfu.set_rtp_receiver(
+            16,
+            Box::new(move |header, _payload| {
+                alice_sender.send(header.ssrc).unwrap();
+            }),
+        );
+        let (bob_sender, bob_receiver) = mpsc::channel();
+        sfu.set_rtp_receiver(
+            32,
+            Box::new(move |header, _payload| {
+                bob_sender.send(header.ssrc).unwrap();
+            }),
+        );
+        sfu.send_speaker(16);
+        // Both receivers are called from the same loop, so Bob's would be called by now.
+        assert_eq!(RTP_DATA_TO_SFU_SSRC, alice_receiver.recv().unwrap());
+        assert!(bob_receiver.try_recv().is_err());
+        stopper.stop_all_and_join();
+    }
+}
@@ -0,0 +1,134 @@
+//
+// Copyright 2021 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Counters kept by simnet links so that a misbehaving
+//! simulation can be looked at after the fact.
+
+use std::{fmt, time::Duration};
+
+// The upper bounds of the queueing delay histogram buckets.
+// The last bucket holds everything longer.
+pub const QUEUEING_DELAY_BUCKET_BOUNDS: [Duration; 8] = [
+    Duration::from_millis(1),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(20),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(200),
+    Duration::from_millis(500),
+];
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LinkStats {
+    // Every packet sent over the link, whether or not it got through.
+    pub packets_sent:                  u64,
+    // Not including the IP and UDP or TCP headers.
+    pub bytes_sent:                    u64,
+    pub packets_dropped_by_outage:     u64,
+    pub packets_dropped_by_trace:      u64,
+    pub packets_dropped_randomly:      u64,
+    pub packets_dropped_by_full_queue: u64,
+    pub packets_duplicated:            u64,
+    // Reliable packets are retransmitted instead of dropped randomly.
+    pub packets_retransmitted:         u64,
+    // How long packets waited for the ones ahead of them to be transmitted.
+    pub queueing_delay:                DelayHistogram,
+}
+
+impl LinkStats {
+    pub fn packets_dropped(&self) -> u64 {
+        self.packets_dropped_by_outage
+            + self.packets_dropped_by_trace
+            + self.packets_dropped_randomly
+            + self.packets_dropped_by_full_queue
+    }
+}
+
+impl fmt::Display for LinkStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "sent: {} packets ({} bytes), dropped: {} (outage: {} trace: {} random: {} full queue: {}), duplicated: {}, retransmitted: {}, queueing delay: {}",
+            self.packets_sent,
+            self.bytes_sent,
+            self.packets_dropped(),
+            self.packets_dropped_by_outage,
+            self.packets_dropped_by_trace,
+            self.packets_dropped_randomly,
+            self.packets_dropped_by_full_queue,
+            self.packets_duplicated,
+            self.packets_retransmitted,
+            self.queueing_delay
+        )
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InterfaceStats {
+    pub send:    LinkStats,
+    pub receive: LinkStats,
+}
+
+// Counts of delays, bucketed by QUEUEING_DELAY_BUCKET_BOUNDS.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DelayHistogram {
+    pub counts: [u64; QUEUEING_DELAY_BUCKET_BOUNDS.len() + 1],
+    pub max:    Duration,
+}
+
+impl DelayHistogram {
+    pub fn record(&mut self, delay: Duration) {
+        let bucket = QUEUEING_DELAY_BUCKET_BOUNDS
+            .iter()
+            .position(|bound| delay < *bound)
+            .unwrap_or(QUEUEING_DELAY_BUCKET_BOUNDS.len());
+        self.counts[bucket] += 1;
+        self.max = self.max.max(delay);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+}
+
+impl fmt::Display for DelayHistogram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut lower_bound = Duration::from_millis(0);
+        for (bucket, count) in self.counts.iter().enumerate() {
+            if *count > 0 {
+                match QUEUEING_DELAY_BUCKET_BOUNDS.get(bucket) {
+                    Some(bound) => write!(f, "[{:?}, {:?}): {} ", lower_bound, bound, count)?,
+                    None => write!(f, "[{:?}, ...): {} ", lower_bound, count)?,
+                }
+            }
+            if let Some(bound) = QUEUEING_DELAY_BUCKET_BOUNDS.get(bucket) {
+                lower_bound = *bound;
+            }
+        }
+        write!(f, "max: {:?}", self.max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_histogram_buckets() {
+        let mut histogram = DelayHistogram::default();
+        histogram.record(Duration::from_millis(0));
+        histogram.record(Duration::from_millis(5));
+        histogram.record(Duration::from_millis(7));
+        histogram.record(Duration::from_secs(2));
+        assert_eq!([1, 0, 2, 0, 0, 0, 0, 0, 1], histogram.counts);
+        assert_eq!(4, histogram.count());
+        assert_eq!(Duration::from_secs(2), histogram.max);
+        assert_eq!(
+            "[0ns, 1ms): 1 [5ms, 10ms): 2 [500ms, ...): 1 max: 2s",
+            histogram.to_string()
+        );
+    }
+}
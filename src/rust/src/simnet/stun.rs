@@ -0,0 +1,364 @@
+//
+// Copyright 2021 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! A STUN binding responder (RFC 5389) that can be attached to a simnet
+//! Router so simulated clients can gather server-reflexive candidates,
+//! plus the message encoding shared with the TURN relay in simnet::turn.
+
+use crate::{
+    common::Result,
+    simnet::router::{LinkConfig, Packet, Router},
+};
+use hmac::{Hmac, Mac, NewMac};
+use md5::{Digest, Md5};
+use sha1::Sha1;
+use std::{
+    convert::TryInto,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+
+const HEADER_LEN: usize = 20;
+const MAGIC_COOKIE: u32 = 0x2112_A442;
+const MESSAGE_INTEGRITY_LEN: usize = 20;
+
+pub(super) const BINDING_REQUEST: u16 = 0x0001;
+pub(super) const BINDING_SUCCESS_RESPONSE: u16 = 0x0101;
+
+pub(super) const ATTR_USERNAME: u16 = 0x0006;
+pub(super) const ATTR_MESSAGE_INTEGRITY: u16 = 0x0008;
+pub(super) const ATTR_ERROR_CODE: u16 = 0x0009;
+pub(super) const ATTR_REALM: u16 = 0x0014;
+pub(super) const ATTR_NONCE: u16 = 0x0015;
+pub(super) const ATTR_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+
+// Responds to binding requests with the address they came from.
+pub struct StunServer;
+
+impl StunServer {
+    /// Attaches a STUN server at the given address to the Router.
+    /// It can be used with an IceServer URL of "stun:<address>".
+    /// Requests over TCP are answered over TCP.
+    pub fn add_to_router(router: &Router, address: SocketAddr, config: LinkConfig) -> Result<()> {
+        let router_as_sender = router.clone();
+        router.add_interface(
+            address.ip(),
+            config.clone(),
+            config,
+            Box::new(move |packet: Packet| {
+                if packet.dest != address {
+                    return;
+                }
+                if let Some(response) = binding_response(&packet) {
+                    router_as_sender.send_packet(Packet {
+                        source:   address,
+                        dest:     packet.source,
+                        protocol: packet.protocol,
+                        data:     response,
+                    });
+                }
+            }),
+        )
+    }
+}
+
+// Also used by the TURN relay, which answers binding requests too.
+pub(super) fn binding_response(packet: &Packet) -> Option<Vec<u8>> {
+    let request = StunMessage::parse(&packet.data)?;
+    if request.message_type != BINDING_REQUEST {
+        return None;
+    }
+    let mut response = StunMessage::new(BINDING_SUCCESS_RESPONSE, request.transaction_id);
+    response.add_xor_address(ATTR_XOR_MAPPED_ADDRESS, packet.source);
+    Some(response.encode())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct StunMessage {
+    pub message_type:   u16,
+    pub transaction_id: [u8; 12],
+    pub attributes:     Vec<(u16, Vec<u8>)>,
+}
+
+impl StunMessage {
+    pub fn new(message_type: u16, transaction_id: [u8; 12]) -> Self {
+        Self {
+            message_type,
+            transaction_id,
+            attributes: Vec::new(),
+        }
+    }
+
+    pub fn is_stun(data: &[u8]) -> bool {
+        data.len() >= HEADER_LEN
+            && data[0] & 0xC0 == 0
+            && u32::from_be_bytes([data[4], data[5], data[6], data[7]]) == MAGIC_COOKIE
+    }
+
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if !Self::is_stun(data) {
+            return None;
+        }
+        let message_type = u16::from_be_bytes([data[0], data[1]]);
+        let length = u16::from_be_bytes([data[2], data[3]]) as usize;
+        if data.len() != HEADER_LEN + length {
+            return None;
+        }
+        let transaction_id = data[8..HEADER_LEN].try_into().ok()?;
+        let mut attributes = Vec::new();
+        for (attribute_type, _offset, value) in AttributeIter::new(data) {
+            attributes.push((attribute_type, value.to_vec()));
+        }
+        Some(Self {
+            message_type,
+            transaction_id,
+            attributes,
+        })
+    }
+
+    pub fn attribute(&self, attribute_type: u16) -> Option<&[u8]> {
+        self.attributes
+            .iter()
+            .find(|(typ, _)| *typ == attribute_type)
+            .map(|(_, value)| value.as_slice())
+    }
+
+    pub fn add_attribute(&mut self, attribute_type: u16, value: Vec<u8>) {
+        self.attributes.push((attribute_type, value));
+    }
+
+    pub fn xor_address(&self, attribute_type: u16) -> Option<SocketAddr> {
+        decode_xor_address(self.attribute(attribute_type)?, &self.transaction_id)
+    }
+
+    pub fn xor_addresses(&self, attribute_type: u16) -> Vec<SocketAddr> {
+        self.attributes
+            .iter()
+            .filter(|(typ, _)| *typ == attribute_type)
+            .filter_map(|(_, value)| decode_xor_address(value, &self.transaction_id))
+            .collect()
+    }
+
+    pub fn add_xor_address(&mut self, attribute_type: u16, address: SocketAddr) {
+        let value = encode_xor_address(address, &self.transaction_id);
+        self.add_attribute(attribute_type, value);
+    }
+
+    pub fn add_error_code(&mut self, code: u16, reason: &str) {
+        let mut value = vec![0, 0, (code / 100) as u8, (code % 100) as u8];
+        value.extend_from_slice(reason.as_bytes());
+        self.add_attribute(ATTR_ERROR_CODE, value);
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&self.message_type.to_be_bytes());
+        // The length is filled in below.
+        data.extend_from_slice(&[0, 0]);
+        data.extend_from_slice(&MAGIC_COOKIE.to_be_bytes());
+        data.extend_from_slice(&self.transaction_id);
+        for (attribute_type, value) in &self.attributes {
+            data.extend_from_slice(&attribute_type.to_be_bytes());
+            data.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            data.extend_from_slice(value);
+            while data.len() % 4 != 0 {
+                data.push(0);
+            }
+        }
+        let length = data.len() - HEADER_LEN;
+        set_length(&mut data, length);
+        data
+    }
+
+    // Encodes and then adds a MESSAGE-INTEGRITY attribute at the end.
+    pub fn encode_with_integrity(&self, key: &[u8]) -> Vec<u8> {
+        let mut data = self.encode();
+        let length = data.len() - HEADER_LEN + 4 + MESSAGE_INTEGRITY_LEN;
+        set_length(&mut data, length);
+        let mut mac = hmac_sha1(key);
+        mac.update(&data);
+        let hmac = mac.finalize().into_bytes();
+        data.extend_from_slice(&ATTR_MESSAGE_INTEGRITY.to_be_bytes());
+        data.extend_from_slice(&(MESSAGE_INTEGRITY_LEN as u16).to_be_bytes());
+        data.extend_from_slice(&hmac);
+        data
+    }
+
+    // Checks the MESSAGE-INTEGRITY attribute of an encoded message.
+    // Attributes after it (such as FINGERPRINT) aren't covered.
+    pub fn verify_integrity(data: &[u8], key: &[u8]) -> bool {
+        let (offset, hmac) = match AttributeIter::new(data)
+            .find(|(attribute_type, _, _)| *attribute_type == ATTR_MESSAGE_INTEGRITY)
+        {
+            Some((_, offset, value)) if value.len() == MESSAGE_INTEGRITY_LEN => (offset, value),
+            _ => return false,
+        };
+        let mut covered = data[..offset].to_vec();
+        set_length(
+            &mut covered,
+            offset - HEADER_LEN + 4 + MESSAGE_INTEGRITY_LEN,
+        );
+        let mut mac = hmac_sha1(key);
+        mac.update(&covered);
+        mac.verify(hmac).is_ok()
+    }
+}
+
+// Iterates over (type, offset of the attribute, value) of an encoded message.
+struct AttributeIter<'a> {
+    data:   &'a [u8],
+    offset: usize,
+}
+
+impl<'a> AttributeIter<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            offset: HEADER_LEN,
+        }
+    }
+}
+
+impl<'a> Iterator for AttributeIter<'a> {
+    type Item = (u16, usize, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let offset = self.offset;
+        let header = self.data.get(offset..offset + 4)?;
+        let attribute_type = u16::from_be_bytes([header[0], header[1]]);
+        let length = u16::from_be_bytes([header[2], header[3]]) as usize;
+        let value = self.data.get(offset + 4..offset + 4 + length)?;
+        // Values are padded to a multiple of 4 bytes.
+        self.offset = offset + 4 + ((length + 3) & !3);
+        Some((attribute_type, offset, value))
+    }
+}
+
+fn set_length(data: &mut [u8], length: usize) {
+    data[2..4].copy_from_slice(&(length as u16).to_be_bytes());
+}
+
+fn encode_xor_address(address: SocketAddr, transaction_id: &[u8; 12]) -> Vec<u8> {
+    let cookie = MAGIC_COOKIE.to_be_bytes();
+    let port = address.port() ^ ((MAGIC_COOKIE >> 16) as u16);
+    let mut value = vec![0];
+    match address.ip() {
+        IpAddr::V4(ip) => {
+            value.push(0x01);
+            value.extend_from_slice(&port.to_be_bytes());
+            value.extend(ip.octets().iter().zip(cookie.iter()).map(|(a, b)| a ^ b));
+        }
+        IpAddr::V6(ip) => {
+            value.push(0x02);
+            value.extend_from_slice(&port.to_be_bytes());
+            let mask = cookie.iter().chain(transaction_id.iter());
+            value.extend(ip.octets().iter().zip(mask).map(|(a, b)| a ^ b));
+        }
+    }
+    value
+}
+
+fn decode_xor_address(value: &[u8], transaction_id: &[u8; 12]) -> Option<SocketAddr> {
+    let cookie = MAGIC_COOKIE.to_be_bytes();
+    let port = u16::from_be_bytes([*value.get(2)?, *value.get(3)?]) ^ ((MAGIC_COOKIE >> 16) as u16);
+    let ip = match value.get(1)? {
+        0x01 => {
+            let octets: [u8; 4] = value.get(4..8)?.try_into().ok()?;
+            let mut ip = [0u8; 4];
+            for (i, (a, b)) in octets.iter().zip(cookie.iter()).enumerate() {
+                ip[i] = a ^ b;
+            }
+            IpAddr::V4(Ipv4Addr::from(ip))
+        }
+        0x02 => {
+            let octets: [u8; 16] = value.get(4..20)?.try_into().ok()?;
+            let mask = cookie.iter().chain(transaction_id.iter());
+            let mut ip = [0u8; 16];
+            for (i, (a, b)) in octets.iter().zip(mask).enumerate() {
+                ip[i] = a ^ b;
+            }
+            IpAddr::V6(Ipv6Addr::from(ip))
+        }
+        _ => return None,
+    };
+    Some(SocketAddr::new(ip, port))
+}
+
+// The key for long-term credentials (RFC 5389 section 15.4).
+pub(super) fn long_term_key(username: &str, realm: &str, password: &str) -> [u8; 16] {
+    Md5::digest(format!("{}:{}:{}", username, realm, password).as_bytes()).into()
+}
+
+type HmacSha1 = Hmac<Sha1>;
+
+fn hmac_sha1(key: &[u8]) -> HmacSha1 {
+    HmacSha1::new_from_slice(key).expect("HMAC accepts keys of any length")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simnet::router::Protocol;
+
+    fn hex(data: &[u8]) -> String {
+        data.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn long_term_key_is_md5_of_credentials() {
+        assert_eq!(
+            "8493fbc53ba582fb4c044c456bdc40eb",
+            hex(&long_term_key("user", "realm", "pass"))
+        );
+    }
+
+    #[test]
+    fn encode_and_parse() {
+        let transaction_id = [7u8; 12];
+        let mut message = StunMessage::new(BINDING_SUCCESS_RESPONSE, transaction_id);
+        let v4: SocketAddr = "1.2.3.4:5678".parse().unwrap();
+        let v6: SocketAddr = "[2001:db8::1]:9999".parse().unwrap();
+        message.add_xor_address(ATTR_XOR_MAPPED_ADDRESS, v4);
+        message.add_xor_address(ATTR_XOR_MAPPED_ADDRESS, v6);
+        message.add_attribute(ATTR_USERNAME, b"odd".to_vec());
+
+        let encoded = message.encode();
+        assert_eq!(0, encoded.len() % 4);
+        let parsed = StunMessage::parse(&encoded).unwrap();
+        assert_eq!(message, parsed);
+        assert_eq!(vec![v4, v6], parsed.xor_addresses(ATTR_XOR_MAPPED_ADDRESS));
+    }
+
+    #[test]
+    fn integrity() {
+        let key = long_term_key("user", "realm", "pass");
+        let mut message = StunMessage::new(BINDING_REQUEST, [1u8; 12]);
+        message.add_attribute(ATTR_USERNAME, b"user".to_vec());
+        let mut encoded = message.encode_with_integrity(&key);
+        assert!(StunMessage::parse(&encoded).is_some());
+        assert!(StunMessage::verify_integrity(&encoded, &key));
+        assert!(!StunMessage::verify_integrity(&encoded, b"wrong"));
+        encoded[HEADER_LEN + 4] ^= 1;
+        assert!(!StunMessage::verify_integrity(&encoded, &key));
+    }
+
+    #[test]
+    fn binding() {
+        let mut request = StunMessage::new(BINDING_REQUEST, [2u8; 12]);
+        request.add_attribute(ATTR_USERNAME, b"ignored".to_vec());
+        let packet = Packet {
+            source:   "5.0.0.1:1024".parse().unwrap(),
+            dest:     "9.0.0.1:3478".parse().unwrap(),
+            protocol: Protocol::Udp,
+            data:     request.encode(),
+        };
+        let response = StunMessage::parse(&binding_response(&packet).unwrap()).unwrap();
+        assert_eq!(BINDING_SUCCESS_RESPONSE, response.message_type);
+        assert_eq!(request.transaction_id, response.transaction_id);
+        assert_eq!(
+            Some(packet.source),
+            response.xor_address(ATTR_XOR_MAPPED_ADDRESS)
+        );
+    }
+}
@@ -0,0 +1,250 @@
+//
+// Copyright 2021 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Recorded network traces that can drive a simnet link
+//! instead of its randomized LinkConfig.
+
+use crate::{
+    common::{units::DataSize, Result},
+    error::RingRtcError,
+};
+use std::{collections::VecDeque, fs, path::Path, time::Duration};
+
+// Each Mahimahi delivery opportunity carries one MTU-sized packet.
+const MAHIMAHI_BYTES_PER_OPPORTUNITY: u64 = 1500;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PacketRecord {
+    Delivered { delay: Duration },
+    Dropped,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LinkTrace {
+    // Mahimahi-style: times (relative to the start of the trace) at which
+    // the link can deliver one MTU worth of data.  The trace loops, with
+    // the last time being the length of the loop.
+    DeliveryOpportunities(Vec<Duration>),
+    // The fate of each packet sent over the link, in order.
+    // The trace loops once every record has been used.
+    PacketRecords(Vec<PacketRecord>),
+}
+
+impl LinkTrace {
+    // Parses a Mahimahi trace: one millisecond timestamp per line.
+    pub fn parse_mahimahi(text: &str) -> Result<Self> {
+        let mut opportunities = Vec::new();
+        for (index, line) in trace_lines(text) {
+            let millis: u64 = line
+                .parse()
+                .map_err(|_| invalid_trace(index, "expected a timestamp in milliseconds"))?;
+            let time = Duration::from_millis(millis);
+            if matches!(opportunities.last(), Some(last) if time < *last) {
+                return Err(invalid_trace(index, "timestamps must not decrease").into());
+            }
+            opportunities.push(time);
+        }
+        match opportunities.last() {
+            Some(last) if *last > Duration::from_millis(0) => {
+                Ok(LinkTrace::DeliveryOpportunities(opportunities))
+            }
+            _ => Err(invalid_trace(0, "trace must end after 0ms").into()),
+        }
+    }
+
+    // Parses per-packet records: one line per packet that is either the
+    // one-way delay in milliseconds or "drop".
+    pub fn parse_packet_records(text: &str) -> Result<Self> {
+        let mut records = Vec::new();
+        for (index, line) in trace_lines(text) {
+            let record = if line.eq_ignore_ascii_case("drop") {
+                PacketRecord::Dropped
+            } else {
+                let millis: u64 = line.parse().map_err(|_| {
+                    invalid_trace(index, "expected a delay in milliseconds or drop")
+                })?;
+                PacketRecord::Delivered {
+                    delay: Duration::from_millis(millis),
+                }
+            };
+            records.push(record);
+        }
+        if records.is_empty() {
+            return Err(invalid_trace(0, "trace has no records").into());
+        }
+        Ok(LinkTrace::PacketRecords(records))
+    }
+
+    pub fn load_mahimahi(path: impl AsRef<Path>) -> Result<Self> {
+        Self::parse_mahimahi(&fs::read_to_string(path)?)
+    }
+
+    pub fn load_packet_records(path: impl AsRef<Path>) -> Result<Self> {
+        Self::parse_packet_records(&fs::read_to_string(path)?)
+    }
+}
+
+// Skips blank lines and # comments and returns 1-based line numbers.
+fn trace_lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+}
+
+fn invalid_trace(line: usize, reason: &str) -> RingRtcError {
+    RingRtcError::SimnetInvalidTrace(line, reason.to_string())
+}
+
+// Replays a LinkTrace for the packets sent over a link.
+pub(super) struct TracePlayer {
+    trace:             LinkTrace,
+    // For PacketRecords, the index of the next record to use.
+    // For DeliveryOpportunities, the index of the next unused
+    // opportunity, counting across loops of the trace.
+    next_index:        u64,
+    // Packets waiting for a delivery opportunity, as
+    // (delivery time relative to the start, size).
+    queued_deliveries: VecDeque<(Duration, DataSize)>,
+}
+
+impl TracePlayer {
+    pub fn new(trace: LinkTrace) -> Self {
+        Self {
+            trace,
+            next_index: 0,
+            queued_deliveries: VecDeque::new(),
+        }
+    }
+
+    // Returns how long after elapsed (the time since the trace
+    // started) the packet gets delivered, or None if it's dropped.
+    pub fn delay_packet(
+        &mut self,
+        elapsed: Duration,
+        size: DataSize,
+        queue_size: DataSize,
+    ) -> Option<Duration> {
+        match &self.trace {
+            LinkTrace::PacketRecords(records) => {
+                let record = &records[(self.next_index % records.len() as u64) as usize];
+                self.next_index += 1;
+                match record {
+                    PacketRecord::Delivered { delay } => Some(*delay),
+                    PacketRecord::Dropped => None,
+                }
+            }
+            LinkTrace::DeliveryOpportunities(opportunities) => {
+                while let Some((delivery_time, _)) = self.queued_deliveries.front() {
+                    if *delivery_time > elapsed {
+                        break;
+                    }
+                    self.queued_deliveries.pop_front();
+                }
+                let queued_size = self
+                    .queued_deliveries
+                    .iter()
+                    .fold(DataSize::from_bytes(0), |sum, (_, size)| sum + *size);
+                if queued_size + size > queue_size {
+                    return None;
+                }
+
+                let count = opportunities.len() as u64;
+                let period = *opportunities.last()?;
+                let opportunity_time = |index: u64| {
+                    period * ((index / count) as u32) + opportunities[(index % count) as usize]
+                };
+
+                // Skip the opportunities that passed while nothing was queued.
+                if opportunity_time(self.next_index) < elapsed {
+                    let loops = (elapsed.as_nanos() / period.as_nanos()) as u64;
+                    let within_loop = elapsed - period * (loops as u32);
+                    let position = opportunities.partition_point(|time| *time < within_loop) as u64;
+                    self.next_index = self.next_index.max(loops * count + position);
+                }
+
+                // Rounds up and uses at least one opportunity.
+                let needed = (size.as_bytes().max(1) - 1) / MAHIMAHI_BYTES_PER_OPPORTUNITY + 1;
+                let delivery_index = self.next_index + needed - 1;
+                self.next_index += needed;
+                let delivery_time = opportunity_time(delivery_index);
+                self.queued_deliveries.push_back((delivery_time, size));
+                Some(delivery_time.saturating_sub(elapsed))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn parse_mahimahi() {
+        let trace = LinkTrace::parse_mahimahi("# comment\n1\n1\n\n5\n").unwrap();
+        assert_eq!(
+            LinkTrace::DeliveryOpportunities(vec![ms(1), ms(1), ms(5)]),
+            trace
+        );
+        assert!(LinkTrace::parse_mahimahi("5\n1\n").is_err());
+        assert!(LinkTrace::parse_mahimahi("0\n").is_err());
+        assert!(LinkTrace::parse_mahimahi("abc\n").is_err());
+    }
+
+    #[test]
+    fn parse_packet_records() {
+        let trace = LinkTrace::parse_packet_records("20\ndrop\n35\n").unwrap();
+        assert_eq!(
+            LinkTrace::PacketRecords(vec![
+                PacketRecord::Delivered { delay: ms(20) },
+                PacketRecord::Dropped,
+                PacketRecord::Delivered { delay: ms(35) },
+            ]),
+            trace
+        );
+        assert!(LinkTrace::parse_packet_records("").is_err());
+        assert!(LinkTrace::parse_packet_records("lost\n").is_err());
+    }
+
+    #[test]
+    fn replay_packet_records_loops() {
+        let mut player = TracePlayer::new(LinkTrace::parse_packet_records("20\ndrop\n").unwrap());
+        let size = DataSize::from_bytes(100);
+        let queue_size = DataSize::from_bytes(1000);
+        assert_eq!(Some(ms(20)), player.delay_packet(ms(0), size, queue_size));
+        assert_eq!(None, player.delay_packet(ms(0), size, queue_size));
+        assert_eq!(Some(ms(20)), player.delay_packet(ms(50), size, queue_size));
+    }
+
+    #[test]
+    fn replay_delivery_opportunities() {
+        let mut player = TracePlayer::new(LinkTrace::parse_mahimahi("10\n20\n30\n").unwrap());
+        let size = DataSize::from_bytes(1000);
+        let queue_size = DataSize::from_bytes(2000);
+
+        // Packets take the next unused opportunities.
+        assert_eq!(Some(ms(10)), player.delay_packet(ms(0), size, queue_size));
+        assert_eq!(Some(ms(20)), player.delay_packet(ms(0), size, queue_size));
+        // The queue is full.
+        assert_eq!(None, player.delay_packet(ms(0), size, queue_size));
+        // The first packet has been delivered, making room.
+        assert_eq!(Some(ms(20)), player.delay_packet(ms(10), size, queue_size));
+        // The trace loops, skipping opportunities missed while idle.
+        assert_eq!(Some(ms(5)), player.delay_packet(ms(65), size, queue_size));
+        // Large packets use several opportunities.
+        assert_eq!(
+            Some(ms(25)),
+            player.delay_packet(
+                ms(65),
+                DataSize::from_bytes(1600),
+                DataSize::from_bytes(4000)
+            )
+        );
+    }
+}
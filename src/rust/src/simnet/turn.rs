@@ -0,0 +1,744 @@
+//
+// Copyright 2021 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! A minimal TURN relay (RFC 5766) that can be attached to a simnet Router
+//! so simulated clients can gather and use relay candidates.  Clients can
+//! reach it over UDP or TCP, but it always relays to peers over UDP.
+//!
+//! It supports what WebRTC needs: Allocate with long-term credentials,
+//! Refresh, CreatePermission, ChannelBind, Send and Data indications and
+//! ChannelData.  Relayed addresses use the IP of the server.  Allocations
+//! and permissions never expire on their own.
+
+use crate::{
+    common::{
+        actor::{Actor, Stopper},
+        Result,
+    },
+    simnet::{
+        router::{LinkConfig, Packet, Protocol, Router},
+        stun::{
+            binding_response,
+            long_term_key,
+            StunMessage,
+            ATTR_MESSAGE_INTEGRITY,
+            ATTR_NONCE,
+            ATTR_REALM,
+            ATTR_USERNAME,
+            ATTR_XOR_MAPPED_ADDRESS,
+        },
+    },
+};
+use std::{
+    collections::{HashMap, HashSet},
+    net::{IpAddr, SocketAddr},
+};
+
+const ALLOCATE_REQUEST: u16 = 0x0003;
+const REFRESH_REQUEST: u16 = 0x0004;
+const SEND_INDICATION: u16 = 0x0016;
+const DATA_INDICATION: u16 = 0x0017;
+const CREATE_PERMISSION_REQUEST: u16 = 0x0008;
+const CHANNEL_BIND_REQUEST: u16 = 0x0009;
+// Add these to a request type to get the response type.
+const SUCCESS_RESPONSE_BITS: u16 = 0x0100;
+const ERROR_RESPONSE_BITS: u16 = 0x0110;
+
+const ATTR_CHANNEL_NUMBER: u16 = 0x000C;
+const ATTR_LIFETIME: u16 = 0x000D;
+const ATTR_XOR_PEER_ADDRESS: u16 = 0x0012;
+const ATTR_DATA: u16 = 0x0013;
+const ATTR_XOR_RELAYED_ADDRESS: u16 = 0x0016;
+
+const DEFAULT_LIFETIME_SECS: u32 = 600;
+const FIRST_RELAY_PORT: u16 = 50000;
+const NONCE: &str = "simnet-nonce";
+
+#[derive(Clone, Debug)]
+pub struct TurnServerConfig {
+    // Use an IceServer URL of "turn:<address>" to reach it,
+    // or "turn:<address>?transport=tcp" to reach it over TCP.
+    pub address:  SocketAddr,
+    pub username: String,
+    pub password: String,
+    pub realm:    String,
+}
+
+#[derive(Clone)]
+pub struct TurnServer {
+    actor:   Actor<TurnServerState>,
+    // A copy of the config's address so it can be known outside the actor.
+    address: SocketAddr,
+}
+
+struct TurnServerState {
+    config:               TurnServerConfig,
+    key:                  [u8; 16],
+    // Set when added to a Router.
+    router:               Option<Router>,
+    next_relay_port:      u16,
+    // Data indications are the only messages the server originates.
+    next_transaction_id:  u64,
+    allocation_by_client: HashMap<SocketAddr, Allocation>,
+    client_by_relay_port: HashMap<u16, SocketAddr>,
+}
+
+struct Allocation {
+    // How the client reached the server.
+    client_protocol: Protocol,
+    relay_address:   SocketAddr,
+    permissions:     HashSet<IpAddr>,
+    peer_by_channel: HashMap<u16, SocketAddr>,
+    channel_by_peer: HashMap<SocketAddr, u16>,
+}
+
+impl TurnServer {
+    pub fn start(stopper: &Stopper, config: TurnServerConfig) -> Result<Self> {
+        let key = long_term_key(&config.username, &config.realm, &config.password);
+        let address = config.address;
+        Ok(Self {
+            address,
+            actor: Actor::start(stopper.clone(), move |_| {
+                Ok(TurnServerState {
+                    config,
+                    key,
+                    router: None,
+                    next_relay_port: FIRST_RELAY_PORT,
+                    next_transaction_id: 0,
+                    allocation_by_client: HashMap::new(),
+                    client_by_relay_port: HashMap::new(),
+                })
+            })?,
+        })
+    }
+
+    pub fn add_to_router(&self, router: &Router, config: LinkConfig) -> Result<()> {
+        let router_to_store = router.clone();
+        self.actor
+            .send(move |state| state.router = Some(router_to_store));
+        let actor = self.actor.clone();
+        router.add_interface(
+            self.address.ip(),
+            config.clone(),
+            config,
+            Box::new(move |packet: Packet| {
+                actor.send(move |state| state.handle_packet(packet));
+            }),
+        )
+    }
+
+    // The number of allocations that currently exist.
+    pub fn allocation_count(&self) -> usize {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.actor.send(move |state| {
+            let _ = sender.send(state.allocation_by_client.len());
+        });
+        receiver.recv().unwrap_or(0)
+    }
+}
+
+impl TurnServerState {
+    fn send(&self, protocol: Protocol, source: SocketAddr, dest: SocketAddr, data: Vec<u8>) {
+        if let Some(router) = &self.router {
+            router.send_packet(Packet {
+                source,
+                dest,
+                protocol,
+                data,
+            });
+        }
+    }
+
+    fn handle_packet(&mut self, packet: Packet) {
+        if packet.dest == self.config.address {
+            self.handle_packet_from_client(packet);
+        } else if packet.protocol != Protocol::Udp {
+            // Peers can only reach the relayed address over UDP.
+        } else if let Some(client) = self.client_by_relay_port.get(&packet.dest.port()) {
+            let client = *client;
+            self.handle_packet_from_peer(client, packet);
+        }
+    }
+
+    fn handle_packet_from_client(&mut self, packet: Packet) {
+        let client = packet.source;
+        let protocol = packet.protocol;
+        if !StunMessage::is_stun(&packet.data) {
+            self.relay_channel_data(client, protocol, &packet.data);
+            return;
+        }
+        if let Some(response) = binding_response(&packet) {
+            self.send(protocol, self.config.address, client, response);
+            return;
+        }
+        let request = match StunMessage::parse(&packet.data) {
+            Some(request) => request,
+            None => return,
+        };
+        if request.message_type == SEND_INDICATION {
+            // Indications aren't authenticated.
+            if let (Some(peer), Some(data)) = (
+                request.xor_address(ATTR_XOR_PEER_ADDRESS),
+                request.attribute(ATTR_DATA),
+            ) {
+                self.relay_to_peer(client, peer, data.to_vec());
+            }
+            return;
+        }
+        if !self.is_authenticated(&request, &packet.data) {
+            let mut response = StunMessage::new(
+                request.message_type | ERROR_RESPONSE_BITS,
+                request.transaction_id,
+            );
+            response.add_error_code(401, "Unauthorized");
+            response.add_attribute(ATTR_REALM, self.config.realm.as_bytes().to_vec());
+            response.add_attribute(ATTR_NONCE, NONCE.as_bytes().to_vec());
+            self.send(protocol, self.config.address, client, response.encode());
+            return;
+        }
+
+        let mut response = StunMessage::new(
+            request.message_type | SUCCESS_RESPONSE_BITS,
+            request.transaction_id,
+        );
+        match request.message_type {
+            ALLOCATE_REQUEST => {
+                let relay_address = match self.allocate(client, protocol) {
+                    Some(relay_address) => relay_address,
+                    None => {
+                        self.send_error(protocol, client, &request, 508, "Insufficient Capacity");
+                        return;
+                    }
+                };
+                response.add_xor_address(ATTR_XOR_RELAYED_ADDRESS, relay_address);
+                response.add_xor_address(ATTR_XOR_MAPPED_ADDRESS, client);
+                response.add_attribute(ATTR_LIFETIME, DEFAULT_LIFETIME_SECS.to_be_bytes().to_vec());
+            }
+            REFRESH_REQUEST => {
+                let lifetime = request
+                    .attribute(ATTR_LIFETIME)
+                    .and_then(|value| value.get(0..4))
+                    .map(|value| u32::from_be_bytes([value[0], value[1], value[2], value[3]]))
+                    .unwrap_or(DEFAULT_LIFETIME_SECS);
+                if lifetime == 0 {
+                    self.deallocate(client);
+                }
+                response.add_attribute(ATTR_LIFETIME, lifetime.to_be_bytes().to_vec());
+            }
+            CREATE_PERMISSION_REQUEST => {
+                let peers = request.xor_addresses(ATTR_XOR_PEER_ADDRESS);
+                match self.allocation_by_client.get_mut(&client) {
+                    Some(allocation) if !peers.is_empty() => {
+                        allocation
+                            .permissions
+                            .extend(peers.iter().map(|peer| peer.ip()));
+                    }
+                    _ => {
+                        self.send_error(protocol, client, &request, 400, "Bad Request");
+                        return;
+                    }
+                }
+            }
+            CHANNEL_BIND_REQUEST => {
+                let channel = request
+                    .attribute(ATTR_CHANNEL_NUMBER)
+                    .and_then(|value| value.get(0..2))
+                    .map(|value| u16::from_be_bytes([value[0], value[1]]));
+                let peer = request.xor_address(ATTR_XOR_PEER_ADDRESS);
+                match (self.allocation_by_client.get_mut(&client), channel, peer) {
+                    (Some(allocation), Some(channel), Some(peer))
+                        if (0x4000..=0x7FFF).contains(&channel) =>
+                    {
+                        allocation.permissions.insert(peer.ip());
+                        allocation.peer_by_channel.insert(channel, peer);
+                        allocation.channel_by_peer.insert(peer, channel);
+                    }
+                    _ => {
+                        self.send_error(protocol, client, &request, 400, "Bad Request");
+                        return;
+                    }
+                }
+            }
+            _ => {
+                self.send_error(protocol, client, &request, 400, "Bad Request");
+                return;
+            }
+        }
+        self.send(
+            protocol,
+            self.config.address,
+            client,
+            response.encode_with_integrity(&self.key),
+        );
+    }
+
+    fn is_authenticated(&self, request: &StunMessage, data: &[u8]) -> bool {
+        request.attribute(ATTR_USERNAME) == Some(self.config.username.as_bytes())
+            && request.attribute(ATTR_REALM) == Some(self.config.realm.as_bytes())
+            && request.attribute(ATTR_NONCE) == Some(NONCE.as_bytes())
+            && request.attribute(ATTR_MESSAGE_INTEGRITY).is_some()
+            && StunMessage::verify_integrity(data, &self.key)
+    }
+
+    fn send_error(
+        &self,
+        protocol: Protocol,
+        client: SocketAddr,
+        request: &StunMessage,
+        code: u16,
+        reason: &str,
+    ) {
+        let mut response = StunMessage::new(
+            request.message_type | ERROR_RESPONSE_BITS,
+            request.transaction_id,
+        );
+        response.add_error_code(code, reason);
+        self.send(
+            protocol,
+            self.config.address,
+            client,
+            response.encode_with_integrity(&self.key),
+        );
+    }
+
+    // Returns the relayed address, which is the same if the client already has an allocation,
+    // or None if every relay port is already allocated.
+    fn allocate(&mut self, client: SocketAddr, client_protocol: Protocol) -> Option<SocketAddr> {
+        if let Some(allocation) = self.allocation_by_client.get(&client) {
+            return Some(allocation.relay_address);
+        }
+        let relay_port = self.allocate_relay_port()?;
+        let relay_address = SocketAddr::new(self.config.address.ip(), relay_port);
+        self.client_by_relay_port.insert(relay_port, client);
+        self.allocation_by_client.insert(
+            client,
+            Allocation {
+                client_protocol,
+                relay_address,
+                permissions: HashSet::new(),
+                peer_by_channel: HashMap::new(),
+                channel_by_peer: HashMap::new(),
+            },
+        );
+        Some(relay_address)
+    }
+
+    // Finds the next relay port that isn't allocated yet, wrapping around to the start.
+    fn allocate_relay_port(&mut self) -> Option<u16> {
+        let pool_size = (u16::MAX - FIRST_RELAY_PORT) as usize + 1;
+        for _ in 0..pool_size {
+            let relay_port = self.next_relay_port;
+            self.next_relay_port = relay_port.checked_add(1).unwrap_or(FIRST_RELAY_PORT);
+            if !self.client_by_relay_port.contains_key(&relay_port) {
+                return Some(relay_port);
+            }
+        }
+        None
+    }
+
+    fn deallocate(&mut self, client: SocketAddr) {
+        if let Some(allocation) = self.allocation_by_client.remove(&client) {
+            self.client_by_relay_port
+                .remove(&allocation.relay_address.port());
+        }
+    }
+
+    fn relay_to_peer(&self, client: SocketAddr, peer: SocketAddr, data: Vec<u8>) {
+        if let Some(allocation) = self.allocation_by_client.get(&client) {
+            if allocation.permissions.contains(&peer.ip()) {
+                self.send(Protocol::Udp, allocation.relay_address, peer, data);
+            }
+        }
+    }
+
+    // Over TCP, there may be more than one ChannelData message in a packet,
+    // each padded to a multiple of 4 bytes (RFC 5766 section 11.5).
+    fn relay_channel_data(&self, client: SocketAddr, protocol: Protocol, mut data: &[u8]) {
+        while data.len() >= 4 {
+            let channel = u16::from_be_bytes([data[0], data[1]]);
+            let length = u16::from_be_bytes([data[2], data[3]]) as usize;
+            let payload = match data.get(4..4 + length) {
+                Some(payload) => payload.to_vec(),
+                None => return,
+            };
+            let peer = self
+                .allocation_by_client
+                .get(&client)
+                .and_then(|allocation| allocation.peer_by_channel.get(&channel));
+            if let Some(peer) = peer {
+                self.relay_to_peer(client, *peer, payload);
+            }
+            if protocol != Protocol::Tcp {
+                return;
+            }
+            data = data.get(channel_data_padded_len(length)..).unwrap_or(&[]);
+        }
+    }
+
+    fn handle_packet_from_peer(&mut self, client: SocketAddr, packet: Packet) {
+        let allocation = match self.allocation_by_client.get(&client) {
+            Some(allocation) => allocation,
+            None => return,
+        };
+        if !allocation.permissions.contains(&packet.source.ip()) {
+            return;
+        }
+        let data = match allocation.channel_by_peer.get(&packet.source) {
+            Some(channel) => {
+                let mut data = Vec::with_capacity(channel_data_padded_len(packet.data.len()));
+                data.extend_from_slice(&channel.to_be_bytes());
+                data.extend_from_slice(&(packet.data.len() as u16).to_be_bytes());
+                data.extend_from_slice(&packet.data);
+                if allocation.client_protocol == Protocol::Tcp {
+                    data.resize(channel_data_padded_len(packet.data.len()), 0);
+                }
+                data
+            }
+            None => {
+                self.next_transaction_id += 1;
+                let mut transaction_id = [0u8; 12];
+                transaction_id[4..].copy_from_slice(&self.next_transaction_id.to_be_bytes());
+                let mut indication = StunMessage::new(DATA_INDICATION, transaction_id);
+                indication.add_xor_address(ATTR_XOR_PEER_ADDRESS, packet.source);
+                indication.add_attribute(ATTR_DATA, packet.data);
+                indication.encode()
+            }
+        };
+        let client_protocol = allocation.client_protocol;
+        self.send(client_protocol, self.config.address, client, data);
+    }
+}
+
+// The length of a ChannelData message with the given payload length,
+// including the padding needed over TCP.
+fn channel_data_padded_len(payload_len: usize) -> usize {
+    (4 + payload_len + 3) / 4 * 4
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simnet::{
+        clock::SimClock,
+        router::{DelayDistribution, LossModel},
+    };
+    use std::{sync::mpsc, time::Duration};
+
+    const CLIENT: &str = "1.0.0.1:1000";
+    const PEER: &str = "2.0.0.1:2000";
+    const SERVER: &str = "9.0.0.1:3478";
+
+    fn link() -> LinkConfig {
+        LinkConfig {
+            delay:                 DelayDistribution::Uniform {
+                min: Duration::from_millis(10),
+                max: Duration::from_millis(10),
+            },
+            loss:                  LossModel::none(),
+            reorder_probability:   0.0,
+            duplicate_probability: 0.0,
+            rate:                  crate::common::units::DataRate::from_mbps(10),
+            queue_size:            crate::common::units::DataSize::from_bytes(100_000),
+        }
+    }
+
+    fn add_endpoint(router: &Router, address: &str) -> mpsc::Receiver<Packet> {
+        let (sender, receiver) = mpsc::channel();
+        let address: SocketAddr = address.parse().unwrap();
+        router
+            .add_interface(
+                address.ip(),
+                link(),
+                link(),
+                Box::new(move |packet: Packet| {
+                    let _ = sender.send(packet);
+                }),
+            )
+            .unwrap();
+        receiver
+    }
+
+    struct Test {
+        stopper: Stopper,
+        clock:   SimClock,
+        router:  Router,
+        server:  TurnServer,
+        key:     [u8; 16],
+        client:  mpsc::Receiver<Packet>,
+        peer:    mpsc::Receiver<Packet>,
+    }
+
+    impl Test {
+        fn new() -> Self {
+            let stopper = Stopper::new();
+            let clock = SimClock::new_virtual();
+            let router = Router::start_with_seed_and_clock(&stopper, 1, clock.clone()).unwrap();
+            let config = TurnServerConfig {
+                address:  SERVER.parse().unwrap(),
+                username: "user".to_string(),
+                password: "pass".to_string(),
+                realm:    "realm".to_string(),
+            };
+            let key = long_term_key(&config.username, &config.realm, &config.password);
+            let server = TurnServer::start(&stopper, config).unwrap();
+            server.add_to_router(&router, link()).unwrap();
+            let client = add_endpoint(&router, CLIENT);
+            let peer = add_endpoint(&router, PEER);
+            Self {
+                stopper,
+                clock,
+                router,
+                server,
+                key,
+                client,
+                peer,
+            }
+        }
+
+        // Sends and waits for the packet (and anything sent in response) to be delivered.
+        fn send(&self, source: &str, dest: SocketAddr, data: Vec<u8>) {
+            self.send_with_protocol(Protocol::Udp, source, dest, data);
+        }
+
+        fn send_with_protocol(
+            &self,
+            protocol: Protocol,
+            source: &str,
+            dest: SocketAddr,
+            data: Vec<u8>,
+        ) {
+            self.router.send_packet(Packet {
+                source: source.parse().unwrap(),
+                dest,
+                protocol,
+                data,
+            });
+            // The packet goes through the router to the server and back.
+            for _ in 0..2 {
+                self.router.flush();
+                self.clock.advance(Duration::from_millis(50));
+                // Let the server send anything in response.
+                self.server.allocation_count();
+            }
+        }
+
+        fn request(
+            &self,
+            message_type: u16,
+            add_attributes: impl FnOnce(&mut StunMessage),
+        ) -> StunMessage {
+            let mut request = StunMessage::new(message_type, [message_type as u8; 12]);
+            request.add_attribute(ATTR_USERNAME, b"user".to_vec());
+            request.add_attribute(ATTR_REALM, b"realm".to_vec());
+            request.add_attribute(ATTR_NONCE, NONCE.as_bytes().to_vec());
+            add_attributes(&mut request);
+            self.send(
+                CLIENT,
+                SERVER.parse().unwrap(),
+                request.encode_with_integrity(&self.key),
+            );
+            let response = self.client.try_recv().unwrap();
+            assert!(StunMessage::verify_integrity(&response.data, &self.key));
+            let response = StunMessage::parse(&response.data).unwrap();
+            assert_eq!(message_type | SUCCESS_RESPONSE_BITS, response.message_type);
+            response
+        }
+    }
+
+    #[test]
+    fn allocate_and_relay() {
+        let test = Test::new();
+        let server: SocketAddr = SERVER.parse().unwrap();
+        let peer: SocketAddr = PEER.parse().unwrap();
+
+        // Without credentials, the client is challenged.
+        let request = StunMessage::new(ALLOCATE_REQUEST, [9u8; 12]);
+        test.send(CLIENT, server, request.encode());
+        let response = StunMessage::parse(&test.client.try_recv().unwrap().data).unwrap();
+        assert_eq!(
+            ALLOCATE_REQUEST | ERROR_RESPONSE_BITS,
+            response.message_type
+        );
+        assert_eq!(Some(NONCE.as_bytes()), response.attribute(ATTR_NONCE));
+
+        let response = test.request(ALLOCATE_REQUEST, |_| {});
+        let relay = response.xor_address(ATTR_XOR_RELAYED_ADDRESS).unwrap();
+        assert_eq!(server.ip(), relay.ip());
+        assert_eq!(
+            Some(CLIENT.parse().unwrap()),
+            response.xor_address(ATTR_XOR_MAPPED_ADDRESS)
+        );
+        assert_eq!(1, test.server.allocation_count());
+
+        // Without a permission, the peer can't reach the client.
+        test.send(PEER, relay, b"blocked".to_vec());
+        assert!(test.client.try_recv().is_err());
+
+        test.request(CREATE_PERMISSION_REQUEST, |request| {
+            request.add_xor_address(ATTR_XOR_PEER_ADDRESS, peer)
+        });
+        let mut indication = StunMessage::new(SEND_INDICATION, [3u8; 12]);
+        indication.add_xor_address(ATTR_XOR_PEER_ADDRESS, peer);
+        indication.add_attribute(ATTR_DATA, b"to peer".to_vec());
+        test.send(CLIENT, server, indication.encode());
+        let received = test.peer.try_recv().unwrap();
+        assert_eq!(relay, received.source);
+        assert_eq!(b"to peer".to_vec(), received.data);
+
+        test.send(PEER, relay, b"to client".to_vec());
+        let indication = StunMessage::parse(&test.client.try_recv().unwrap().data).unwrap();
+        assert_eq!(DATA_INDICATION, indication.message_type);
+        assert_eq!(Some(peer), indication.xor_address(ATTR_XOR_PEER_ADDRESS));
+        assert_eq!(Some(&b"to client"[..]), indication.attribute(ATTR_DATA));
+
+        test.request(CHANNEL_BIND_REQUEST, |request| {
+            request.add_attribute(ATTR_CHANNEL_NUMBER, vec![0x40, 0x01, 0, 0]);
+            request.add_xor_address(ATTR_XOR_PEER_ADDRESS, peer)
+        });
+        test.send(CLIENT, server, vec![0x40, 0x01, 0, 2, b'h', b'i']);
+        assert_eq!(b"hi".to_vec(), test.peer.try_recv().unwrap().data);
+        test.send(PEER, relay, b"yo".to_vec());
+        assert_eq!(
+            vec![0x40, 0x01, 0, 2, b'y', b'o'],
+            test.client.try_recv().unwrap().data
+        );
+
+        test.request(REFRESH_REQUEST, |request| {
+            request.add_attribute(ATTR_LIFETIME, vec![0, 0, 0, 0])
+        });
+        assert_eq!(0, test.server.allocation_count());
+
+        test.stopper.stop_all_and_join();
+    }
+
+    #[test]
+    fn allocate_over_tcp_and_relay_over_udp() {
+        let test = Test::new();
+        let server: SocketAddr = SERVER.parse().unwrap();
+        let peer: SocketAddr = PEER.parse().unwrap();
+
+        let mut request = StunMessage::new(ALLOCATE_REQUEST, [1u8; 12]);
+        request.add_attribute(ATTR_USERNAME, b"user".to_vec());
+        request.add_attribute(ATTR_REALM, b"realm".to_vec());
+        request.add_attribute(ATTR_NONCE, NONCE.as_bytes().to_vec());
+        test.send_with_protocol(
+            Protocol::Tcp,
+            CLIENT,
+            server,
+            request.encode_with_integrity(&test.key),
+        );
+        let response = test.client.try_recv().unwrap();
+        assert_eq!(Protocol::Tcp, response.protocol);
+        let response = StunMessage::parse(&response.data).unwrap();
+        let relay = response.xor_address(ATTR_XOR_RELAYED_ADDRESS).unwrap();
+
+        let mut request = StunMessage::new(CREATE_PERMISSION_REQUEST, [2u8; 12]);
+        request.add_attribute(ATTR_USERNAME, b"user".to_vec());
+        request.add_attribute(ATTR_REALM, b"realm".to_vec());
+        request.add_attribute(ATTR_NONCE, NONCE.as_bytes().to_vec());
+        request.add_xor_address(ATTR_XOR_PEER_ADDRESS, peer);
+        test.send_with_protocol(
+            Protocol::Tcp,
+            CLIENT,
+            server,
+            request.encode_with_integrity(&test.key),
+        );
+        assert!(test.client.try_recv().is_ok());
+
+        let mut indication = StunMessage::new(SEND_INDICATION, [3u8; 12]);
+        indication.add_xor_address(ATTR_XOR_PEER_ADDRESS, peer);
+        indication.add_attribute(ATTR_DATA, b"to peer".to_vec());
+        test.send_with_protocol(Protocol::Tcp, CLIENT, server, indication.encode());
+        assert_eq!(Protocol::Udp, test.peer.try_recv().unwrap().protocol);
+
+        test.send(PEER, relay, b"to client".to_vec());
+        assert_eq!(Protocol::Tcp, test.client.try_recv().unwrap().protocol);
+
+        // Peers can't use TCP to reach the relayed address.
+        test.send_with_protocol(Protocol::Tcp, PEER, relay, b"dropped".to_vec());
+        assert!(test.client.try_recv().is_err());
+
+        // Over TCP, ChannelData is padded to a multiple of 4 bytes.
+        let mut request = StunMessage::new(CHANNEL_BIND_REQUEST, [4u8; 12]);
+        request.add_attribute(ATTR_USERNAME, b"user".to_vec());
+        request.add_attribute(ATTR_REALM, b"realm".to_vec());
+        request.add_attribute(ATTR_NONCE, NONCE.as_bytes().to_vec());
+        request.add_attribute(ATTR_CHANNEL_NUMBER, vec![0x40, 0x01, 0, 0]);
+        request.add_xor_address(ATTR_XOR_PEER_ADDRESS, peer);
+        test.send_with_protocol(
+            Protocol::Tcp,
+            CLIENT,
+            server,
+            request.encode_with_integrity(&test.key),
+        );
+        assert!(test.client.try_recv().is_ok());
+        test.send_with_protocol(
+            Protocol::Tcp,
+            CLIENT,
+            server,
+            vec![
+                0x40, 0x01, 0, 2, b'h', b'i', 0, 0, 0x40, 0x01, 0, 4, b'h', b'e', b'y', b'!',
+            ],
+        );
+        assert_eq!(b"hi".to_vec(), test.peer.try_recv().unwrap().data);
+        assert_eq!(b"hey!".to_vec(), test.peer.try_recv().unwrap().data);
+        test.send(PEER, relay, b"yo".to_vec());
+        assert_eq!(
+            vec![0x40, 0x01, 0, 2, b'y', b'o', 0, 0],
+            test.client.try_recv().unwrap().data
+        );
+
+        test.stopper.stop_all_and_join();
+    }
+
+    #[test]
+    fn relay_ports_in_use_are_skipped() {
+        let config = TurnServerConfig {
+            address:  SERVER.parse().unwrap(),
+            username: "user".to_string(),
+            password: "pass".to_string(),
+            realm:    "realm".to_string(),
+        };
+        let mut state = TurnServerState {
+            key: long_term_key(&config.username, &config.realm, &config.password),
+            config,
+            router: None,
+            next_relay_port: u16::MAX,
+            next_transaction_id: 0,
+            allocation_by_client: HashMap::new(),
+            client_by_relay_port: HashMap::new(),
+        };
+        let client = |port: u16| SocketAddr::new("1.0.0.1".parse().unwrap(), port);
+
+        // The first relay port is still in use when the pool wraps around.
+        state.next_relay_port = FIRST_RELAY_PORT;
+        state.allocate(client(1), Protocol::Udp).unwrap();
+        state.next_relay_port = u16::MAX;
+        assert_eq!(
+            u16::MAX,
+            state.allocate(client(2), Protocol::Udp).unwrap().port()
+        );
+        assert_eq!(
+            FIRST_RELAY_PORT + 1,
+            state.allocate(client(3), Protocol::Udp).unwrap().port()
+        );
+        assert_eq!(
+            Some(&client(1)),
+            state.client_by_relay_port.get(&FIRST_RELAY_PORT)
+        );
+
+        // Once every port is allocated, allocating fails.
+        for port in (FIRST_RELAY_PORT + 2)..u16::MAX {
+            state.allocate(client(port), Protocol::Udp).unwrap();
+        }
+        assert_eq!(None, state.allocate(client(4), Protocol::Udp));
+        // Deallocating frees the port for the next allocation.
+        state.deallocate(client(2));
+        assert_eq!(
+            u16::MAX,
+            state.allocate(client(4), Protocol::Udp).unwrap().port()
+        );
+    }
+}
@@ -12,19 +12,24 @@ use crate::core::util::CppObject;
 use crate::webrtc::network::{NetworkInterfaceType, RffiIp, RffiIpPort};
 use crate::webrtc::peer_connection_factory::PeerConnectionFactory;
 
+// Each TCP packet is what was passed to one call to send on a TCP socket,
+// which is the same framing the simnet router keeps.
+pub use crate::simnet::router::Protocol;
+
 #[derive(Debug)]
 pub struct Packet {
-    pub source: SocketAddr,
-    pub dest:   SocketAddr,
-    pub data:   Vec<u8>,
+    pub source:   SocketAddr,
+    pub dest:     SocketAddr,
+    pub protocol: Protocol,
+    pub data:     Vec<u8>,
 }
 
 pub trait PacketSender {
-    fn send_udp(&self, packet: Packet);
+    fn send_packet(&self, packet: Packet);
 }
 
 impl<F: Fn(Packet)> PacketSender for F {
-    fn send_udp(&self, packet: Packet) {
+    fn send_packet(&self, packet: Packet) {
         self(packet)
     }
 }
@@ -54,6 +59,7 @@ impl InjectableNetwork {
             // are fat (double size) pointers.
             object:   Box::into_raw(Box::new(sender)),
             send_udp: Rust_InjectableNetworkSender_SendUdp,
+            send_tcp: Rust_InjectableNetworkSender_SendTcp,
             release:  Rust_InjectableNetworkSender_Release,
         } as *const RffiInjectableNetworkSender as CppObject;
         unsafe {
@@ -87,10 +93,14 @@ impl InjectableNetwork {
         }
     }
 
-    pub fn receive_udp(&self, packet: Packet) {
+    pub fn receive_packet(&self, packet: Packet) {
+        let receive = match packet.protocol {
+            Protocol::Udp => Rust_InjectableNetwork_ReceiveUdp,
+            Protocol::Tcp => Rust_InjectableNetwork_ReceiveTcp,
+        };
         unsafe {
-            // Rust_receiveUdp is expected to copy it because it's going to get dropped.
-            Rust_InjectableNetwork_ReceiveUdp(
+            // Rust_InjectableNetwork_Receive* is expected to copy it because it's going to get dropped.
+            receive(
                 self.rffi,
                 packet.source.into(),
                 packet.dest.into(),
@@ -130,6 +140,13 @@ extern "C" {
         data: *const u8,
         size: usize,
     );
+    pub fn Rust_InjectableNetwork_ReceiveTcp(
+        network: *const RffiInjectableNetwork,
+        source: RffiIpPort,
+        dest: RffiIpPort,
+        data: *const u8,
+        size: usize,
+    );
 }
 
 #[repr(C)]
@@ -142,6 +159,13 @@ struct RffiInjectableNetworkSender {
         data: *const u8,
         size: usize,
     ),
+    send_tcp: extern "C" fn(
+        object: *mut Box<dyn PacketSender>,
+        source: RffiIpPort,
+        dest: RffiIpPort,
+        data: *const u8,
+        size: usize,
+    ),
     release:  extern "C" fn(object: *mut Box<dyn PacketSender>),
 }
 
@@ -154,16 +178,39 @@ extern "C" fn Rust_InjectableNetworkSender_SendUdp(
     size: usize,
 ) {
     debug!("Send UDP {:?} => {:?} of size {}", source, dest, size);
+    send_packet(sender, Protocol::Udp, source, dest, data, size);
+}
 
+#[allow(non_snake_case)]
+extern "C" fn Rust_InjectableNetworkSender_SendTcp(
+    sender: *mut Box<dyn PacketSender>,
+    source: RffiIpPort,
+    dest: RffiIpPort,
+    data: *const u8,
+    size: usize,
+) {
+    debug!("Send TCP {:?} => {:?} of size {}", source, dest, size);
+    send_packet(sender, Protocol::Tcp, source, dest, data, size);
+}
+
+fn send_packet(
+    sender: *mut Box<dyn PacketSender>,
+    protocol: Protocol,
+    source: RffiIpPort,
+    dest: RffiIpPort,
+    data: *const u8,
+    size: usize,
+) {
     let sender = unsafe { &*sender };
     // Copy the data because it won't be valid any more.
     let data = unsafe { std::slice::from_raw_parts(data, size) }.to_vec();
     let packet = Packet {
         source: source.socket_addr(),
         dest: dest.socket_addr(),
+        protocol,
         data,
     };
-    sender.send_udp(packet);
+    sender.send_packet(packet);
 }
 
 #[allow(non_snake_case)]
@@ -9,6 +9,7 @@ use std::ffi::c_void;
 use std::{ptr, slice};
 
 use crate::core::util::{ptr_as_mut, RustObject};
+use crate::webrtc::rtp;
 
 #[cfg(not(feature = "sim"))]
 use crate::webrtc::ffi::ref_count::release_ref;
@@ -24,11 +25,34 @@ use crate::webrtc::sim::stats_observer as stats;
 #[cfg(feature = "sim")]
 pub use crate::webrtc::sim::stats_observer::RffiStatsObserver;
 
+/// The audio levels found in a set of statistics,
+/// from 0.0 (silence) to 1.0 (the loudest possible).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AudioLevels {
+    /// The level of the audio being captured and sent.
+    pub sent:     f64,
+    /// The level of each audio stream being received, by SSRC.
+    pub received: Vec<(rtp::Ssrc, f64)>,
+}
+
+pub type BoxedAudioLevelsHandler = Box<dyn FnMut(AudioLevels) + Send>;
+
 /// Collector object for obtaining statistics.
-#[derive(Debug)]
 pub struct StatsObserver {
     /// Pointer to C++ webrtc::rffi::StatsObserverRffi object.
-    rffi_stats_observer: *const RffiStatsObserver,
+    rffi_stats_observer:  *const RffiStatsObserver,
+    /// If set, the statistics are only used for their audio levels and aren't logged,
+    /// since they are likely to be requested much more often.
+    audio_levels_handler: Option<BoxedAudioLevelsHandler>,
+}
+
+impl std::fmt::Debug for StatsObserver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StatsObserver")
+            .field("rffi_stats_observer", &self.rffi_stats_observer)
+            .field("audio_levels_handler", &self.audio_levels_handler.is_some())
+            .finish()
+    }
 }
 
 unsafe impl Send for StatsObserver {}
@@ -43,7 +67,14 @@ impl Drop for StatsObserver {
 
 impl StatsObserver {
     /// Create a new StatsObserver.
-    fn new() -> Self {
+    fn new(audio_levels_handler: Option<BoxedAudioLevelsHandler>) -> Self {
+        if audio_levels_handler.is_some() {
+            return Self {
+                rffi_stats_observer: ptr::null(),
+                audio_levels_handler,
+            };
+        }
+
         info!(
             "ringrtc_stats!,\
                 connection,\
@@ -120,11 +151,17 @@ impl StatsObserver {
 
         Self {
             rffi_stats_observer: ptr::null(),
+            audio_levels_handler,
         }
     }
 
     /// Invoked when statistics are received via the stats observer callback.
     fn on_stats_complete(&mut self, media_statistics: &MediaStatistics) {
+        if let Some(audio_levels_handler) = self.audio_levels_handler.as_mut() {
+            audio_levels_handler(Self::audio_levels(media_statistics));
+            return;
+        }
+
         info!(
             "ringrtc_stats!,connection,{},{:.3},{:.0}",
             media_statistics.timestamp_us,
@@ -254,6 +291,39 @@ impl StatsObserver {
         }
     }
 
+    fn audio_levels(media_statistics: &MediaStatistics) -> AudioLevels {
+        let audio_senders = unsafe {
+            if media_statistics.audio_sender_statistics.is_null() {
+                &[]
+            } else {
+                slice::from_raw_parts(
+                    media_statistics.audio_sender_statistics,
+                    media_statistics.audio_sender_statistics_size as usize,
+                )
+            }
+        };
+        let audio_receivers = unsafe {
+            if media_statistics.audio_receiver_statistics.is_null() {
+                &[]
+            } else {
+                slice::from_raw_parts(
+                    media_statistics.audio_receiver_statistics,
+                    media_statistics.audio_receiver_statistics_size as usize,
+                )
+            }
+        };
+        AudioLevels {
+            sent:     audio_senders
+                .iter()
+                .map(|audio_sender| audio_sender.audio_level)
+                .fold(0.0, f64::max),
+            received: audio_receivers
+                .iter()
+                .map(|audio_receiver| (audio_receiver.ssrc, audio_receiver.audio_level))
+                .collect(),
+        }
+    }
+
     /// Set the RFFI observer object.
     pub fn set_rffi_stats_observer(&mut self, rffi_stats_observer: *const RffiStatsObserver) {
         self.rffi_stats_observer = rffi_stats_observer
@@ -386,7 +456,19 @@ const STATS_OBSERVER_CBS_PTR: *const StatsObserverCallbacks = &STATS_OBSERVER_CB
 /// registering the collector callbacks to this module, and wraps the
 /// result in a Rust StatsObserver object.
 pub fn create_stats_observer() -> Box<StatsObserver> {
-    let stats_observer = Box::new(StatsObserver::new());
+    create(StatsObserver::new(None))
+}
+
+/// Create a new Rust StatsObserver object that passes the audio levels
+/// of the statistics to the given handler instead of logging them.
+pub fn create_audio_levels_stats_observer(
+    audio_levels_handler: BoxedAudioLevelsHandler,
+) -> Box<StatsObserver> {
+    create(StatsObserver::new(Some(audio_levels_handler)))
+}
+
+fn create(stats_observer: StatsObserver) -> Box<StatsObserver> {
+    let stats_observer = Box::new(stats_observer);
     let stats_observer_ptr = Box::into_raw(stats_observer);
     let rffi_stats_observer = unsafe {
         stats::Rust_createStatsObserver(
@@ -19,7 +19,7 @@ use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha20Rng;
 use simplelog::{Config, ConfigBuilder, SimpleLogger};
 
-use ringrtc::common::{ApplicationEvent, CallMediaType, DeviceId, FeatureLevel};
+use ringrtc::common::{ApplicationEvent, CallId, CallMediaType, DeviceId, FeatureLevel};
 use ringrtc::core::call::Call;
 use ringrtc::core::call_manager::CallManager;
 use ringrtc::core::connection::Connection;
@@ -138,6 +138,10 @@ impl TestContext {
         self.call_manager.active_call().unwrap()
     }
 
+    pub fn second_call(&self) -> Call<SimPlatform> {
+        self.call_manager.second_call().unwrap()
+    }
+
     pub fn active_connection(&self) -> Connection<SimPlatform> {
         let active_call = self.call_manager.active_call().unwrap();
         match active_call.active_connection() {
@@ -166,6 +170,11 @@ impl TestContext {
         platform.offers_sent()
     }
 
+    pub fn transfer_offers_sent(&self) -> usize {
+        let platform = self.call_manager.platform().unwrap();
+        platform.transfer_offers_sent()
+    }
+
     pub fn answers_sent(&self) -> usize {
         let platform = self.call_manager.platform().unwrap();
         platform.answers_sent()
@@ -206,6 +215,11 @@ impl TestContext {
         platform.busy_hangups_sent()
     }
 
+    pub fn transferred_hangups_sent(&self) -> usize {
+        let platform = self.call_manager.platform().unwrap();
+        platform.transferred_hangups_sent()
+    }
+
     pub fn error_count(&self) -> usize {
         let platform = self.call_manager.platform().unwrap();
         platform.error_count()
@@ -263,6 +277,38 @@ impl TestContext {
             ringrtc::webrtc::media::VideoTrack::unowned(std::ptr::null()),
         )
     }
+
+    pub fn request_escalation(
+        &self,
+        call_id: CallId,
+        remote_user_id: group_call::UserId,
+        group_id: group_call::GroupId,
+    ) -> Result<group_call::ClientId, failure::Error> {
+        self.cm().request_escalation(
+            call_id,
+            remote_user_id,
+            group_id,
+            "".to_owned(),
+            None,
+            ringrtc::webrtc::media::AudioTrack::unowned(std::ptr::null()),
+            ringrtc::webrtc::media::VideoTrack::unowned(std::ptr::null()),
+            false,
+        )
+    }
+
+    pub fn accept_escalation(
+        &self,
+        call_id: CallId,
+    ) -> Result<group_call::ClientId, failure::Error> {
+        self.cm().accept_escalation(
+            call_id,
+            "".to_owned(),
+            None,
+            ringrtc::webrtc::media::AudioTrack::unowned(std::ptr::null()),
+            ringrtc::webrtc::media::VideoTrack::unowned(std::ptr::null()),
+            false,
+        )
+    }
 }
 
 pub fn random_received_offer(_prng: &Prng, age: Duration) -> signaling::ReceivedOffer {
@@ -18,6 +18,7 @@ use ringrtc::common::{ApplicationEvent, CallId, CallState, ConnectionState, Devi
 use ringrtc::core::bandwidth_mode::BandwidthMode;
 use ringrtc::core::call_manager::MAX_MESSAGE_AGE;
 use ringrtc::core::group_call;
+use ringrtc::core::ring_store::{FileRingStore, MemoryRingStore, OutstandingGroupRing, RingStore};
 use ringrtc::core::signaling;
 use ringrtc::protobuf;
 use ringrtc::webrtc::data_channel::DataChannel;
@@ -614,7 +615,7 @@ fn group_call_ring_busy_in_direct_call() {
                 &ringrtc::sim::sim_platform::GroupCallRingUpdate {
                     group_id: group_id.clone(),
                     ring_id,
-                    sender,
+                    sender: sender.clone(),
                     update: group_call::RingUpdate::BusyLocally
                 },
                 update
@@ -623,12 +624,15 @@ fn group_call_ring_busy_in_direct_call() {
         _ => panic!("unexpected ring updates: {:?}", ring_updates),
     }
 
+    // The ringer is told too.
     let messages = cm
         .platform()
         .expect(error_line!())
         .take_outgoing_call_messages();
     match &messages[..] {
-        [message] => {
+        [to_ringer, message] => {
+            assert_eq!(&sender[..], &to_ringer.recipient[..]);
+            assert_eq!(to_ringer.message, message.message);
             assert_eq!(&self_uuid[..], &message.recipient[..]);
             assert_eq!(
                 group_call::SignalingMessageUrgency::HandleImmediately,
@@ -703,7 +707,7 @@ fn group_call_ring_busy_in_group_call() {
                 &ringrtc::sim::sim_platform::GroupCallRingUpdate {
                     group_id: group_id.clone(),
                     ring_id,
-                    sender,
+                    sender: sender.clone(),
                     update: group_call::RingUpdate::BusyLocally
                 },
                 update
@@ -712,12 +716,15 @@ fn group_call_ring_busy_in_group_call() {
         _ => panic!("unexpected ring updates: {:?}", ring_updates),
     }
 
+    // The ringer is told too.
     let messages = cm
         .platform()
         .expect(error_line!())
         .take_outgoing_call_messages();
     match &messages[..] {
-        [message] => {
+        [to_ringer, message] => {
+            assert_eq!(&sender[..], &to_ringer.recipient[..]);
+            assert_eq!(to_ringer.message, message.message);
             assert_eq!(&self_uuid[..], &message.recipient[..]);
             assert_eq!(
                 group_call::SignalingMessageUrgency::HandleImmediately,
@@ -926,3 +933,915 @@ fn group_call_ring_timeout() {
         &messages[..]
     );
 }
+
+#[test]
+fn group_call_ring_declined() {
+    use protobuf::signaling::call_message::ring_intention::Type as IntentionType;
+    use protobuf::signaling::call_message::ring_response::Type as ResponseType;
+
+    test_init();
+
+    let context = TestContext::new();
+    let mut cm = context.cm();
+
+    let self_uuid = vec![1, 0, 1];
+    cm.set_self_uuid(self_uuid.clone()).expect(error_line!());
+
+    let group_id = vec![1, 1, 1];
+    let sender = vec![1, 2, 3];
+    let ring_id = group_call::RingId::from(42);
+
+    cm.received_call_message(
+        sender.clone(),
+        1,
+        2,
+        ring_intention_message(&group_id, ring_id, IntentionType::Ring),
+        Duration::ZERO,
+    )
+    .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+    cm.cancel_group_ring(
+        group_id.clone(),
+        ring_id,
+        Some(group_call::RingCancelReason::DeclinedByUser),
+    )
+    .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    // Both the ringer and our other devices are told.
+    let messages = cm
+        .platform()
+        .expect(error_line!())
+        .take_outgoing_call_messages();
+    let expected_message = protobuf::signaling::CallMessage {
+        ring_response: Some(protobuf::signaling::call_message::RingResponse {
+            group_id: Some(group_id),
+            ring_id:  Some(ring_id.into()),
+            r#type:   Some(ResponseType::Declined.into()),
+        }),
+        ..Default::default()
+    };
+    let recipients: Vec<&[u8]> = messages
+        .iter()
+        .map(|message| {
+            assert_eq!(
+                expected_message,
+                protobuf::signaling::CallMessage::decode(&message.message[..])
+                    .expect(error_line!())
+            );
+            &message.recipient[..]
+        })
+        .collect();
+    assert_eq!(vec![&sender[..], &self_uuid[..]], recipients);
+}
+
+fn ring_intention_message(
+    group_id: &[u8],
+    ring_id: group_call::RingId,
+    ring_type: protobuf::signaling::call_message::ring_intention::Type,
+) -> Vec<u8> {
+    let message = protobuf::signaling::CallMessage {
+        ring_intention: Some(protobuf::signaling::call_message::RingIntention {
+            group_id: Some(group_id.to_vec()),
+            ring_id:  Some(ring_id.into()),
+            r#type:   Some(ring_type.into()),
+        }),
+        ..Default::default()
+    };
+    let mut buf = Vec::new();
+    message
+        .encode(&mut buf)
+        .expect("cannot fail encoding to Vec");
+    buf
+}
+
+#[test]
+fn group_call_ring_replayed() {
+    use protobuf::signaling::call_message::ring_intention::Type as IntentionType;
+
+    test_init();
+
+    let context = TestContext::new();
+    let mut cm = context.cm();
+
+    let group_id = vec![1, 1, 1];
+    let sender = vec![1, 2, 3];
+    let ring_id = group_call::RingId::from(42);
+    let cancelled_ring_id = group_call::RingId::from(43);
+
+    for _ in 0..2 {
+        cm.received_call_message(
+            sender.clone(),
+            1,
+            2,
+            ring_intention_message(&group_id, ring_id, IntentionType::Ring),
+            Duration::ZERO,
+        )
+        .expect(error_line!());
+    }
+    // A ring that arrives after its cancellation doesn't ring either.
+    cm.received_call_message(
+        sender.clone(),
+        1,
+        2,
+        ring_intention_message(&group_id, cancelled_ring_id, IntentionType::Cancelled),
+        Duration::ZERO,
+    )
+    .expect(error_line!());
+    cm.received_call_message(
+        sender.clone(),
+        1,
+        2,
+        ring_intention_message(&group_id, cancelled_ring_id, IntentionType::Ring),
+        Duration::ZERO,
+    )
+    .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    let ring_updates = cm
+        .platform()
+        .expect(error_line!())
+        .take_group_call_ring_updates();
+    assert_eq!(
+        &[
+            ringrtc::sim::sim_platform::GroupCallRingUpdate {
+                group_id: group_id.clone(),
+                ring_id,
+                sender: sender.clone(),
+                update: group_call::RingUpdate::Requested
+            },
+            ringrtc::sim::sim_platform::GroupCallRingUpdate {
+                group_id,
+                ring_id: cancelled_ring_id,
+                sender,
+                update: group_call::RingUpdate::CancelledByRinger
+            },
+        ],
+        &ring_updates[..]
+    );
+}
+
+#[test]
+fn group_call_ring_restored() {
+    use protobuf::signaling::call_message::ring_intention::Type as IntentionType;
+
+    test_init();
+
+    let path = std::env::temp_dir().join(format!("ringrtc-ring-store-{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let group_id = vec![1, 1, 1];
+    let sender = vec![1, 2, 3];
+    let ring_id = group_call::RingId::from(42);
+    let expected_update = |update| ringrtc::sim::sim_platform::GroupCallRingUpdate {
+        group_id: group_id.clone(),
+        ring_id,
+        sender: sender.clone(),
+        update,
+    };
+
+    {
+        let context = TestContext::new();
+        let mut cm = context.cm();
+        cm.set_ring_store(Box::new(FileRingStore::open(&path).expect(error_line!())))
+            .expect(error_line!());
+        cm.received_call_message(
+            sender.clone(),
+            1,
+            2,
+            ring_intention_message(&group_id, ring_id, IntentionType::Ring),
+            Duration::ZERO,
+        )
+        .expect(error_line!());
+        cm.synchronize().expect(error_line!());
+
+        let ring_updates = cm
+            .platform()
+            .expect(error_line!())
+            .take_group_call_ring_updates();
+        assert_eq!(
+            &[expected_update(group_call::RingUpdate::Requested)],
+            &ring_updates[..]
+        );
+    }
+
+    // After a "restart", the ring is still there, and replaying it doesn't ring again.
+    let context = TestContext::new();
+    let mut cm = context.cm();
+    cm.set_ring_store(Box::new(FileRingStore::open(&path).expect(error_line!())))
+        .expect(error_line!());
+    cm.received_call_message(
+        sender.clone(),
+        1,
+        2,
+        ring_intention_message(&group_id, ring_id, IntentionType::Ring),
+        Duration::ZERO,
+    )
+    .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    let ring_updates = cm
+        .platform()
+        .expect(error_line!())
+        .take_group_call_ring_updates();
+    assert_eq!(
+        &[expected_update(group_call::RingUpdate::Requested)],
+        &ring_updates[..]
+    );
+    let _ = std::fs::remove_file(&path);
+
+    // A ring that expired while the app wasn't running is reported as expired.
+    let mut ring_store = MemoryRingStore::default();
+    ring_store
+        .insert_ring(
+            group_id.clone(),
+            OutstandingGroupRing {
+                ring_id,
+                sender_uuid: sender.clone(),
+                received: std::time::SystemTime::now() - Duration::from_secs(600),
+            },
+        )
+        .expect(error_line!());
+    cm.set_ring_store(Box::new(ring_store))
+        .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    let ring_updates = cm
+        .platform()
+        .expect(error_line!())
+        .take_group_call_ring_updates();
+    assert_eq!(
+        &[expected_update(group_call::RingUpdate::ExpiredRequest)],
+        &ring_updates[..]
+    );
+}
+
+// Keeps everything in memory, but can't save any of it.
+#[derive(Default)]
+struct UnsavableRingStore(MemoryRingStore);
+
+impl RingStore for UnsavableRingStore {
+    fn rings(&self) -> Vec<(group_call::GroupId, OutstandingGroupRing)> {
+        self.0.rings()
+    }
+
+    fn ring(&self, group_id: group_call::GroupIdRef) -> Option<OutstandingGroupRing> {
+        self.0.ring(group_id)
+    }
+
+    fn insert_ring(
+        &mut self,
+        group_id: group_call::GroupId,
+        ring: OutstandingGroupRing,
+    ) -> ringrtc::common::Result<()> {
+        self.0.insert_ring(group_id, ring)?;
+        Err(failure::format_err!("can't save the ring"))
+    }
+
+    fn remove_ring(&mut self, group_id: group_call::GroupIdRef) -> ringrtc::common::Result<()> {
+        self.0.remove_ring(group_id)?;
+        Err(failure::format_err!("can't save the removal"))
+    }
+
+    fn has_seen(&self, ring_id: group_call::RingId) -> bool {
+        self.0.has_seen(ring_id)
+    }
+
+    fn mark_seen(
+        &mut self,
+        ring_id: group_call::RingId,
+        seen: std::time::SystemTime,
+    ) -> ringrtc::common::Result<()> {
+        self.0.mark_seen(ring_id, seen)?;
+        Err(failure::format_err!("can't save the seen ring"))
+    }
+
+    fn forget_seen_before(&mut self, time: std::time::SystemTime) -> ringrtc::common::Result<()> {
+        self.0.forget_seen_before(time)?;
+        Err(failure::format_err!("can't save the forgotten rings"))
+    }
+}
+
+#[test]
+fn group_call_ring_store_failures() {
+    use protobuf::signaling::call_message::ring_intention::Type as IntentionType;
+
+    test_init();
+
+    let context = TestContext::new();
+    let mut cm = context.cm();
+    cm.set_ring_store(Box::new(UnsavableRingStore::default()))
+        .expect(error_line!());
+
+    let group_id = vec![1, 1, 1];
+    let sender = vec![1, 2, 3];
+    let ring_id = group_call::RingId::from(42);
+    let expected_update = |update| ringrtc::sim::sim_platform::GroupCallRingUpdate {
+        group_id: group_id.clone(),
+        ring_id,
+        sender: sender.clone(),
+        update,
+    };
+
+    // The ring still rings, and is still remembered as seen.
+    for _ in 0..2 {
+        cm.received_call_message(
+            sender.clone(),
+            1,
+            2,
+            ring_intention_message(&group_id, ring_id, IntentionType::Ring),
+            Duration::ZERO,
+        )
+        .expect(error_line!());
+    }
+    cm.synchronize().expect(error_line!());
+    assert_eq!(
+        &[expected_update(group_call::RingUpdate::Requested)],
+        &cm.platform()
+            .expect(error_line!())
+            .take_group_call_ring_updates()[..]
+    );
+
+    cm.received_call_message(
+        sender.clone(),
+        1,
+        2,
+        ring_intention_message(&group_id, ring_id, IntentionType::Cancelled),
+        Duration::ZERO,
+    )
+    .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+    assert_eq!(
+        &[expected_update(group_call::RingUpdate::CancelledByRinger)],
+        &cm.platform()
+            .expect(error_line!())
+            .take_group_call_ring_updates()[..]
+    );
+    assert_eq!(0, context.error_count());
+}
+
+// With call waiting enabled, receive a second call during an accepted call
+// and bring it up to the ringing state, leaving the first call active.
+fn receive_waiting_call(context: &TestContext) {
+    let mut cm = context.cm();
+    let active_call_id = context.active_call().call_id();
+
+    let remote_peer = format!("REMOTE_PEER-{}", context.prng.gen::<u16>()).to_owned();
+    let call_id = CallId::new(context.prng.gen::<u64>());
+    cm.received_offer(
+        remote_peer,
+        call_id,
+        random_received_offer(&context.prng, Duration::from_secs(0)),
+    )
+    .expect(error_line!());
+
+    cm.synchronize().expect(error_line!());
+
+    assert_eq!(context.start_incoming_count(), 2);
+    assert_eq!(context.busys_sent(), 0);
+    assert_eq!(
+        context.event_count(ApplicationEvent::ReceivedOfferWhileActive),
+        0
+    );
+    assert_eq!(context.active_call().call_id(), active_call_id);
+
+    let waiting_call = context.second_call();
+    assert_eq!(waiting_call.call_id(), call_id);
+    assert_eq!(
+        waiting_call.state().expect(error_line!()),
+        CallState::WaitingToProceed
+    );
+
+    cm.proceed(
+        call_id,
+        format!("CONTEXT-{}", context.prng.gen::<u16>()).to_owned(),
+        BandwidthMode::Normal,
+    )
+    .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    cm.received_ice(call_id, random_received_ice_candidate(&context.prng))
+        .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    let mut connection = waiting_call
+        .get_connection(1 as DeviceId)
+        .expect(error_line!());
+    connection.inject_ice_connected().expect(error_line!());
+    let data_channel = unsafe { DataChannel::new(ptr::null()) };
+    connection
+        .inject_received_signaling_data_channel(data_channel)
+        .expect(error_line!());
+    connection
+        .handle_received_incoming_media(MediaStream::new(ptr::null()))
+        .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    assert_eq!(
+        waiting_call.state().expect(error_line!()),
+        CallState::ConnectedWithDataChannelBeforeAccepted
+    );
+    assert_eq!(context.event_count(ApplicationEvent::LocalRinging), 2);
+    assert_eq!(context.error_count(), 0);
+    assert_eq!(context.ended_count(), 0);
+}
+
+#[test]
+fn call_waiting_hold_and_swap() {
+    test_init();
+
+    let context = connect_inbound_call();
+    let mut cm = context.cm();
+    cm.set_call_waiting_enabled(true).expect(error_line!());
+    let first_call = context.active_call();
+    let first_connection = context.active_connection();
+
+    receive_waiting_call(&context);
+    let second_call = context.second_call();
+    let second_connection = second_call
+        .get_connection(1 as DeviceId)
+        .expect(error_line!());
+
+    info!("test: holding the first call and accepting the second");
+    cm.accept_waiting_call(second_call.call_id())
+        .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    assert_eq!(context.active_call().call_id(), second_call.call_id());
+    assert_eq!(context.second_call().call_id(), first_call.call_id());
+    assert_eq!(first_call.state().expect(error_line!()), CallState::OnHold);
+    assert_eq!(
+        first_connection.state().expect(error_line!()),
+        ConnectionState::OnHold
+    );
+    assert_eq!(
+        false,
+        first_connection
+            .app_connection()
+            .unwrap()
+            .outgoing_audio_enabled(),
+    );
+    assert_eq!(
+        Some(protobuf::data_channel::SenderStatus {
+            id:             Some(first_call.call_id().into()),
+            video_enabled:  None,
+            sharing_screen: None,
+            held:           Some(true),
+        }),
+        first_connection.last_sent_sender_status()
+    );
+    assert_eq!(
+        second_call.state().expect(error_line!()),
+        CallState::ConnectedAndAccepted
+    );
+    assert_eq!(
+        true,
+        second_connection
+            .app_connection()
+            .unwrap()
+            .outgoing_audio_enabled(),
+    );
+    assert_eq!(context.event_count(ApplicationEvent::LocalAccepted), 2);
+
+    info!("test: swapping back to the first call");
+    cm.swap_calls().expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    assert_eq!(context.active_call().call_id(), first_call.call_id());
+    assert_eq!(
+        first_call.state().expect(error_line!()),
+        CallState::ConnectedAndAccepted
+    );
+    assert_eq!(
+        true,
+        first_connection
+            .app_connection()
+            .unwrap()
+            .outgoing_audio_enabled(),
+    );
+    assert_eq!(
+        Some(false),
+        first_connection
+            .last_sent_sender_status()
+            .and_then(|status| status.held)
+    );
+    assert_eq!(second_call.state().expect(error_line!()), CallState::OnHold);
+    assert_eq!(
+        false,
+        second_connection
+            .app_connection()
+            .unwrap()
+            .outgoing_audio_enabled(),
+    );
+
+    info!("test: remote hangup of the call on hold");
+    cm.received_hangup(
+        second_call.call_id(),
+        signaling::ReceivedHangup {
+            sender_device_id: 1 as DeviceId,
+            hangup:           signaling::Hangup::Normal,
+        },
+    )
+    .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    assert_eq!(context.event_count(ApplicationEvent::EndedRemoteHangup), 1);
+    assert!(cm.second_call().is_err());
+    assert_eq!(context.active_call().call_id(), first_call.call_id());
+    assert_eq!(
+        first_call.state().expect(error_line!()),
+        CallState::ConnectedAndAccepted
+    );
+
+    cm.hangup().expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    assert_eq!(context.event_count(ApplicationEvent::EndedLocalHangup), 1);
+    assert!(!cm.call_active().expect(error_line!()));
+    assert_eq!(context.error_count(), 0);
+}
+
+#[test]
+fn call_waiting_resume_while_ice_disconnected() {
+    test_init();
+
+    let context = connect_inbound_call();
+    let mut cm = context.cm();
+    cm.set_call_waiting_enabled(true).expect(error_line!());
+    let first_call = context.active_call();
+    let mut first_connection = context.active_connection();
+
+    receive_waiting_call(&context);
+    let second_call = context.second_call();
+
+    info!("test: holding the first call and accepting the second");
+    cm.accept_waiting_call(second_call.call_id())
+        .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+    assert_eq!(first_call.state().expect(error_line!()), CallState::OnHold);
+
+    info!("test: ICE disconnects on the call on hold");
+    first_connection
+        .inject_ice_disconnected()
+        .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    assert_eq!(first_call.state().expect(error_line!()), CallState::OnHold);
+    assert_eq!(
+        first_connection.state().expect(error_line!()),
+        ConnectionState::OnHold
+    );
+    assert_eq!(context.event_count(ApplicationEvent::Reconnecting), 0);
+
+    info!("test: swapping back to the first call");
+    cm.swap_calls().expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    assert_eq!(context.active_call().call_id(), first_call.call_id());
+    assert_eq!(
+        first_call.state().expect(error_line!()),
+        CallState::ReconnectingAfterAccepted
+    );
+    assert_eq!(
+        first_connection.state().expect(error_line!()),
+        ConnectionState::ReconnectingAfterAccepted
+    );
+    assert_eq!(
+        false,
+        first_connection
+            .app_connection()
+            .unwrap()
+            .outgoing_audio_enabled(),
+    );
+    assert_eq!(context.event_count(ApplicationEvent::Reconnecting), 1);
+
+    info!("test: ICE reconnects on the resumed call");
+    first_connection
+        .inject_ice_connected()
+        .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    assert_eq!(
+        first_call.state().expect(error_line!()),
+        CallState::ConnectedAndAccepted
+    );
+    assert_eq!(
+        first_connection.state().expect(error_line!()),
+        ConnectionState::ConnectedAndAccepted
+    );
+    assert_eq!(
+        true,
+        first_connection
+            .app_connection()
+            .unwrap()
+            .outgoing_audio_enabled(),
+    );
+    assert_eq!(context.event_count(ApplicationEvent::Reconnecting), 1);
+    assert_eq!(context.event_count(ApplicationEvent::Reconnected), 1);
+    assert_eq!(context.error_count(), 0);
+}
+
+#[test]
+fn call_waiting_decline() {
+    test_init();
+
+    let context = connect_inbound_call();
+    let mut cm = context.cm();
+    cm.set_call_waiting_enabled(true).expect(error_line!());
+    let first_call = context.active_call();
+
+    receive_waiting_call(&context);
+
+    cm.hangup_second_call().expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    assert_eq!(context.event_count(ApplicationEvent::EndedLocalHangup), 1);
+    assert_eq!(context.normal_hangups_sent(), 1);
+    assert!(cm.second_call().is_err());
+    assert_eq!(context.active_call().call_id(), first_call.call_id());
+    assert_eq!(
+        first_call.state().expect(error_line!()),
+        CallState::ConnectedAndAccepted
+    );
+    assert_eq!(context.error_count(), 0);
+}
+
+#[test]
+fn call_waiting_becomes_active_after_hangup() {
+    test_init();
+
+    let context = connect_inbound_call();
+    let mut cm = context.cm();
+    cm.set_call_waiting_enabled(true).expect(error_line!());
+
+    receive_waiting_call(&context);
+    let waiting_call = context.second_call();
+
+    cm.hangup().expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    assert_eq!(context.event_count(ApplicationEvent::EndedLocalHangup), 1);
+    assert!(cm.second_call().is_err());
+    assert_eq!(context.active_call().call_id(), waiting_call.call_id());
+
+    // Still busy, so another offer gets a busy response.
+    let remote_peer = format!("REMOTE_PEER-{}", context.prng.gen::<u16>()).to_owned();
+    cm.received_offer(
+        remote_peer,
+        CallId::new(context.prng.gen::<u64>()),
+        random_received_offer(&context.prng, Duration::from_secs(0)),
+    )
+    .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+    assert_eq!(context.busys_sent(), 1);
+
+    cm.accept_call(waiting_call.call_id()).expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    assert_eq!(
+        waiting_call.state().expect(error_line!()),
+        CallState::ConnectedAndAccepted
+    );
+    assert_eq!(context.error_count(), 0);
+}
+
+fn random_received_transfer_offer(
+    context: &TestContext,
+    sender_device_id: DeviceId,
+) -> signaling::ReceivedOffer {
+    let mut received = random_received_offer(&context.prng, Duration::from_secs(0));
+    received.offer = received.offer.to_transfer().expect(error_line!());
+    received.sender_device_id = sender_device_id;
+    received
+}
+
+#[test]
+fn call_transferred_to_another_remote_device() {
+    test_init();
+
+    let context = connect_inbound_call();
+    let mut cm = context.cm();
+    let active_call = context.active_call();
+    let answers_sent = context.answers_sent();
+
+    info!("test: receiving transfer offer from device 2");
+    let remote_peer = active_call.remote_peer().expect(error_line!()).clone();
+    cm.received_offer(
+        remote_peer,
+        active_call.call_id(),
+        random_received_transfer_offer(&context, 2 as DeviceId),
+    )
+    .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    assert_eq!(context.start_incoming_count(), 1);
+    assert_eq!(context.answers_sent(), answers_sent + 1);
+    assert_eq!(
+        active_call.transfer_device_id().expect(error_line!()),
+        Some(2 as DeviceId)
+    );
+
+    let mut transfer_connection = active_call
+        .get_connection(2 as DeviceId)
+        .expect(error_line!());
+    transfer_connection
+        .inject_ice_connected()
+        .expect(error_line!());
+    let data_channel = unsafe { DataChannel::new(ptr::null()) };
+    transfer_connection
+        .inject_received_signaling_data_channel(data_channel)
+        .expect(error_line!());
+    transfer_connection
+        .handle_received_incoming_media(MediaStream::new(ptr::null()))
+        .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    assert_eq!(context.event_count(ApplicationEvent::RemoteTransferred), 1);
+    assert_eq!(context.event_count(ApplicationEvent::LocalRinging), 1);
+    assert_eq!(context.transferred_hangups_sent(), 1);
+    assert_eq!(
+        active_call.active_device_id().expect(error_line!()),
+        2 as DeviceId
+    );
+    assert_eq!(active_call.transfer_device_id().expect(error_line!()), None);
+    assert_eq!(
+        active_call.state().expect(error_line!()),
+        CallState::ConnectedAndAccepted
+    );
+    assert_eq!(
+        transfer_connection.state().expect(error_line!()),
+        ConnectionState::ConnectedAndAccepted
+    );
+    assert!(active_call.get_connection(1 as DeviceId).is_err());
+    assert_eq!(context.error_count(), 0);
+    assert_eq!(context.ended_count(), 0);
+}
+
+#[test]
+fn call_transferred_to_another_remote_device_while_on_hold() {
+    test_init();
+
+    let context = connect_inbound_call();
+    let mut cm = context.cm();
+    let active_call = context.active_call();
+
+    info!("test: holding the call");
+    cm.hold_call(active_call.call_id()).expect(error_line!());
+    cm.synchronize().expect(error_line!());
+    assert_eq!(active_call.state().expect(error_line!()), CallState::OnHold);
+
+    info!("test: receiving transfer offer from device 2");
+    let remote_peer = active_call.remote_peer().expect(error_line!()).clone();
+    cm.received_offer(
+        remote_peer,
+        active_call.call_id(),
+        random_received_transfer_offer(&context, 2 as DeviceId),
+    )
+    .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    assert_eq!(
+        active_call.transfer_device_id().expect(error_line!()),
+        Some(2 as DeviceId)
+    );
+
+    let mut transfer_connection = active_call
+        .get_connection(2 as DeviceId)
+        .expect(error_line!());
+    transfer_connection
+        .inject_ice_connected()
+        .expect(error_line!());
+    let data_channel = unsafe { DataChannel::new(ptr::null()) };
+    transfer_connection
+        .inject_received_signaling_data_channel(data_channel)
+        .expect(error_line!());
+    transfer_connection
+        .handle_received_incoming_media(MediaStream::new(ptr::null()))
+        .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    assert_eq!(context.event_count(ApplicationEvent::RemoteTransferred), 1);
+    assert_eq!(
+        active_call.active_device_id().expect(error_line!()),
+        2 as DeviceId
+    );
+    assert_eq!(active_call.state().expect(error_line!()), CallState::OnHold);
+    assert_eq!(
+        transfer_connection.state().expect(error_line!()),
+        ConnectionState::OnHold
+    );
+    assert_eq!(
+        false,
+        transfer_connection
+            .app_connection()
+            .unwrap()
+            .outgoing_audio_enabled(),
+    );
+    assert_eq!(
+        Some(true),
+        transfer_connection
+            .last_sent_sender_status()
+            .and_then(|status| status.held)
+    );
+
+    info!("test: a late timeout for the completed transfer is ignored");
+    let mut call = active_call.clone();
+    call.inject_transfer_timeout(2 as DeviceId)
+        .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    assert!(active_call.get_connection(2 as DeviceId).is_ok());
+    assert_eq!(active_call.state().expect(error_line!()), CallState::OnHold);
+    assert_eq!(context.error_count(), 0);
+    assert_eq!(context.ended_count(), 0);
+}
+
+#[test]
+fn call_transfer_times_out() {
+    test_init();
+
+    let context = connect_inbound_call();
+    let mut cm = context.cm();
+    let mut active_call = context.active_call();
+
+    info!("test: receiving transfer offer from device 2");
+    let remote_peer = active_call.remote_peer().expect(error_line!()).clone();
+    cm.received_offer(
+        remote_peer,
+        active_call.call_id(),
+        random_received_transfer_offer(&context, 2 as DeviceId),
+    )
+    .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    assert!(active_call.get_connection(2 as DeviceId).is_ok());
+
+    info!("test: the transfer connection never connects");
+    active_call
+        .inject_transfer_timeout(2 as DeviceId)
+        .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    assert_eq!(active_call.transfer_device_id().expect(error_line!()), None);
+    assert!(active_call.get_connection(2 as DeviceId).is_err());
+    assert_eq!(
+        active_call.active_device_id().expect(error_line!()),
+        1 as DeviceId
+    );
+    assert_eq!(
+        active_call.state().expect(error_line!()),
+        CallState::ConnectedAndAccepted
+    );
+    assert_eq!(context.event_count(ApplicationEvent::RemoteTransferred), 0);
+    assert_eq!(context.error_count(), 0);
+    assert_eq!(context.ended_count(), 0);
+}
+
+#[test]
+fn call_transferred_to_another_local_device() {
+    test_init();
+
+    let context = connect_inbound_call();
+    let mut cm = context.cm();
+    let active_call = context.active_call();
+
+    cm.received_hangup(
+        active_call.call_id(),
+        signaling::ReceivedHangup {
+            sender_device_id: 1 as DeviceId,
+            hangup:           signaling::Hangup::TransferredToAnotherDevice(2 as DeviceId),
+        },
+    )
+    .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    assert_eq!(
+        context.event_count(ApplicationEvent::EndedTransferredToAnotherDevice),
+        1
+    );
+    assert_eq!(context.ended_count(), 1);
+    assert_eq!(context.error_count(), 0);
+}
+
+#[test]
+fn transfer_offer_for_unknown_call_ignored() {
+    test_init();
+
+    let context = TestContext::new();
+    let mut cm = context.cm();
+
+    let remote_peer = format!("REMOTE_PEER-{}", context.prng.gen::<u16>()).to_owned();
+    cm.received_offer(
+        remote_peer,
+        CallId::new(context.prng.gen::<u64>()),
+        random_received_transfer_offer(&context, 2 as DeviceId),
+    )
+    .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    assert_eq!(context.start_incoming_count(), 0);
+    assert_eq!(context.call_concluded_count(), 1);
+    assert_eq!(context.answers_sent(), 0);
+    assert_eq!(context.ended_count(), 0);
+    assert_eq!(context.error_count(), 0);
+}
@@ -25,6 +25,7 @@ use ringrtc::common::{
     DeviceId,
 };
 use ringrtc::core::bandwidth_mode::BandwidthMode;
+use ringrtc::core::escalation::EscalationUpdate;
 use ringrtc::core::{group_call, signaling};
 use ringrtc::protobuf;
 use ringrtc::sim::error::SimError;
@@ -624,6 +625,7 @@ fn update_sender_status() {
         .update_sender_status(signaling::SenderStatus {
             video_enabled:  Some(false),
             sharing_screen: None,
+            held:           None,
         })
         .expect(error_line!());
 
@@ -635,6 +637,7 @@ fn update_sender_status() {
             id:             Some(active_connection.call_id().into()),
             video_enabled:  Some(false),
             sharing_screen: None,
+            held:           None,
         }),
         active_connection.last_sent_sender_status()
     );
@@ -643,6 +646,7 @@ fn update_sender_status() {
         .update_sender_status(signaling::SenderStatus {
             video_enabled:  Some(true),
             sharing_screen: None,
+            held:           None,
         })
         .expect(error_line!());
 
@@ -650,6 +654,7 @@ fn update_sender_status() {
         .update_sender_status(signaling::SenderStatus {
             video_enabled:  None,
             sharing_screen: Some(true),
+            held:           None,
         })
         .expect(error_line!());
 
@@ -661,6 +666,7 @@ fn update_sender_status() {
             id:             Some(active_connection.call_id().into()),
             video_enabled:  Some(true),
             sharing_screen: Some(true),
+            held:           None,
         }),
         active_connection.last_sent_sender_status()
     );
@@ -669,6 +675,7 @@ fn update_sender_status() {
         .update_sender_status(signaling::SenderStatus {
             video_enabled:  None,
             sharing_screen: Some(false),
+            held:           None,
         })
         .expect(error_line!());
 
@@ -680,6 +687,7 @@ fn update_sender_status() {
             id:             Some(active_connection.call_id().into()),
             video_enabled:  Some(true),
             sharing_screen: Some(false),
+            held:           None,
         }),
         active_connection.last_sent_sender_status()
     );
@@ -1138,6 +1146,7 @@ fn received_remote_video_status() {
                 signaling::SenderStatus {
                     video_enabled:  Some(enable),
                     sharing_screen: None,
+                    held:           None,
                 },
                 Some(i),
             )
@@ -1176,6 +1185,7 @@ fn received_remote_video_status() {
             signaling::SenderStatus {
                 video_enabled:  Some(true),
                 sharing_screen: None,
+                held:           None,
             },
             Some(1),
         )
@@ -1187,6 +1197,7 @@ fn received_remote_video_status() {
             signaling::SenderStatus {
                 video_enabled:  Some(false),
                 sharing_screen: None,
+                held:           None,
             },
             Some(2),
         )
@@ -1231,6 +1242,7 @@ fn received_remote_sharing_screen_status() {
                 signaling::SenderStatus {
                     video_enabled:  None,
                     sharing_screen: Some(enable),
+                    held:           None,
                 },
                 Some(i),
             )
@@ -1263,6 +1275,7 @@ fn received_remote_sharing_screen_status() {
             signaling::SenderStatus {
                 video_enabled:  None,
                 sharing_screen: Some(true),
+                held:           None,
             },
             Some(1),
         )
@@ -1274,6 +1287,7 @@ fn received_remote_sharing_screen_status() {
             signaling::SenderStatus {
                 video_enabled:  None,
                 sharing_screen: Some(false),
+                held:           None,
             },
             Some(2),
         )
@@ -1308,6 +1322,7 @@ fn received_remote_multiple_status() {
             signaling::SenderStatus {
                 video_enabled:  Some(false),
                 sharing_screen: Some(true),
+                held:           None,
             },
             Some(1),
         )
@@ -1319,6 +1334,7 @@ fn received_remote_multiple_status() {
             signaling::SenderStatus {
                 video_enabled:  Some(true),
                 sharing_screen: Some(false),
+                held:           None,
             },
             Some(2),
         )
@@ -1336,6 +1352,39 @@ fn received_remote_multiple_status() {
     assert_eq!(context.event_count(ApplicationEvent::RemoteVideoEnable), 1);
     assert_eq!(context.event_count(ApplicationEvent::RemoteVideoDisable), 1);
 }
+
+#[test]
+fn received_remote_hold_status() {
+    test_init();
+
+    let context = connect_outbound_call();
+    let mut cm = context.cm();
+    let active_call = context.active_call();
+    let mut active_connection = context.active_connection();
+
+    for (sequence_number, held) in [true, false].iter().enumerate() {
+        active_connection
+            .inject_received_sender_status_via_data_channel(
+                active_call.call_id(),
+                signaling::SenderStatus {
+                    video_enabled:  None,
+                    sharing_screen: None,
+                    held:           Some(*held),
+                },
+                Some(sequence_number as u64 + 1),
+            )
+            .expect(error_line!());
+        cm.synchronize().expect(error_line!());
+    }
+
+    assert_eq!(context.event_count(ApplicationEvent::RemoteHoldEnable), 1);
+    assert_eq!(context.event_count(ApplicationEvent::RemoteHoldDisable), 1);
+    assert_eq!(context.event_count(ApplicationEvent::RemoteVideoEnable), 0);
+    assert_eq!(
+        context.event_count(ApplicationEvent::RemoteSharingScreenEnable),
+        0
+    );
+}
 #[test]
 fn call_timeout_before_connect() {
     test_init();
@@ -2471,3 +2520,278 @@ fn group_call_ring_cancelled_by_another_device_before_join() {
         &messages[..]
     );
 }
+
+fn escalation_message(
+    group_id: &[u8],
+    call_id: CallId,
+    escalation_type: protobuf::signaling::call_message::escalation::Type,
+) -> Vec<u8> {
+    let message = protobuf::signaling::CallMessage {
+        escalation: Some(protobuf::signaling::call_message::Escalation {
+            group_id: Some(group_id.to_vec()),
+            call_id:  Some(u64::from(call_id)),
+            r#type:   Some(escalation_type.into()),
+        }),
+        ..Default::default()
+    };
+    let mut buf = Vec::new();
+    message
+        .encode(&mut buf)
+        .expect("cannot fail encoding to Vec");
+    buf
+}
+
+fn take_escalation_updates(context: &TestContext) -> Vec<EscalationUpdate> {
+    context
+        .cm()
+        .platform()
+        .expect(error_line!())
+        .take_escalation_updates()
+        .into_iter()
+        .map(|event| event.update)
+        .collect()
+}
+
+#[test]
+fn escalation_requested_and_declined() {
+    use protobuf::signaling::call_message::escalation::Type as EscalationType;
+
+    test_init();
+
+    let context = connect_outbound_call();
+    let mut cm = context.cm();
+    let call_id = context.active_call().call_id();
+    let remote_uuid = vec![1, 2, 3];
+    let group_id = vec![1, 1, 1];
+
+    context
+        .request_escalation(call_id, remote_uuid.clone(), group_id.clone())
+        .expect(error_line!());
+    assert!(context
+        .request_escalation(call_id, remote_uuid.clone(), group_id.clone())
+        .is_err());
+    cm.synchronize().expect(error_line!());
+
+    let messages = cm
+        .platform()
+        .expect(error_line!())
+        .take_outgoing_call_messages();
+    assert_eq!(1, messages.len());
+    assert_eq!(remote_uuid, messages[0].recipient);
+    assert_eq!(
+        escalation_message(&group_id, call_id, EscalationType::Request),
+        messages[0].message
+    );
+
+    cm.received_call_message(
+        remote_uuid.clone(),
+        1,
+        2,
+        escalation_message(&group_id, call_id, EscalationType::Decline),
+        Duration::ZERO,
+    )
+    .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+    assert_eq!(
+        vec![EscalationUpdate::Declined],
+        take_escalation_updates(&context)
+    );
+    assert!(cm.call_is_active(call_id).expect(error_line!()));
+
+    // Now the other user asks, and we decline.
+    cm.received_call_message(
+        remote_uuid.clone(),
+        1,
+        2,
+        escalation_message(&group_id, call_id, EscalationType::Request),
+        Duration::ZERO,
+    )
+    .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+    assert_eq!(
+        vec![EscalationUpdate::Requested],
+        take_escalation_updates(&context)
+    );
+
+    cm.decline_escalation(call_id).expect(error_line!());
+    assert!(cm.decline_escalation(call_id).is_err());
+    let messages = cm
+        .platform()
+        .expect(error_line!())
+        .take_outgoing_call_messages();
+    assert_eq!(1, messages.len());
+    assert_eq!(
+        escalation_message(&group_id, call_id, EscalationType::Decline),
+        messages[0].message
+    );
+    assert!(cm.call_is_active(call_id).expect(error_line!()));
+}
+
+#[test]
+fn escalation_fails_when_direct_call_ends_first() {
+    test_init();
+
+    let context = connect_outbound_call();
+    let mut cm = context.cm();
+    let call_id = context.active_call().call_id();
+
+    context
+        .request_escalation(call_id, vec![1, 2, 3], vec![1, 1, 1])
+        .expect(error_line!());
+    cm.hangup().expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    assert_eq!(
+        vec![EscalationUpdate::Failed],
+        take_escalation_updates(&context)
+    );
+    assert_eq!(context.event_count(ApplicationEvent::EndedLocalHangup), 1);
+}
+
+#[test]
+fn escalation_accepted_keeps_busy_for_group_call() {
+    use protobuf::signaling::call_message::escalation::Type as EscalationType;
+    use protobuf::signaling::call_message::ring_intention::Type as IntentionType;
+
+    test_init();
+
+    let context = connect_outbound_call();
+    let mut cm = context.cm();
+    cm.set_self_uuid(vec![1, 0, 1]).expect(error_line!());
+    let call_id = context.active_call().call_id();
+    let remote_uuid = vec![1, 2, 3];
+    let group_id = vec![1, 1, 1];
+
+    // Accepting without a request fails.
+    assert!(context.accept_escalation(call_id).is_err());
+
+    cm.received_call_message(
+        remote_uuid.clone(),
+        1,
+        2,
+        escalation_message(&group_id, call_id, EscalationType::Request),
+        Duration::ZERO,
+    )
+    .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+    assert_eq!(
+        vec![EscalationUpdate::Requested],
+        take_escalation_updates(&context)
+    );
+
+    let client_id = context.accept_escalation(call_id).expect(error_line!());
+    cm.synchronize().expect(error_line!());
+    let messages = cm
+        .platform()
+        .expect(error_line!())
+        .take_outgoing_call_messages();
+    assert_eq!(1, messages.len());
+    assert_eq!(
+        escalation_message(&group_id, call_id, EscalationType::Accept),
+        messages[0].message
+    );
+
+    // The other user finished first and hung up the direct call, but the
+    // group call keeps the busy state.
+    cm.received_hangup(
+        call_id,
+        signaling::ReceivedHangup {
+            sender_device_id: 1,
+            hangup:           signaling::Hangup::Normal,
+        },
+    )
+    .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+    assert_eq!(context.event_count(ApplicationEvent::EndedRemoteHangup), 1);
+    assert_eq!(
+        Vec::<EscalationUpdate>::new(),
+        take_escalation_updates(&context)
+    );
+
+    let ring = protobuf::signaling::CallMessage {
+        ring_intention: Some(protobuf::signaling::call_message::RingIntention {
+            group_id: Some(vec![2, 2, 2]),
+            ring_id:  Some(42),
+            r#type:   Some(IntentionType::Ring.into()),
+        }),
+        ..Default::default()
+    };
+    let mut buf = Vec::new();
+    ring.encode(&mut buf).expect("cannot fail encoding to Vec");
+    cm.received_call_message(vec![4, 5, 6], 1, 2, buf, Duration::ZERO)
+        .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+    let ring_updates = cm
+        .platform()
+        .expect(error_line!())
+        .take_group_call_ring_updates();
+    assert_eq!(1, ring_updates.len());
+    assert_eq!(group_call::RingUpdate::BusyLocally, ring_updates[0].update);
+
+    // Giving up on the group call ends the escalation.
+    cm.delete_group_call_client(client_id);
+    assert_eq!(
+        vec![EscalationUpdate::Failed],
+        take_escalation_updates(&context)
+    );
+}
+
+#[test]
+fn take_over_call() {
+    test_init();
+
+    let context = TestContext::new();
+    let mut cm = context.cm();
+
+    let remote_peer = format!("REMOTE_PEER-{}", context.prng.gen::<u16>()).to_owned();
+    let call_id = CallId::new(context.prng.gen::<u64>());
+    cm.take_over_call(remote_peer, call_id, CallMediaType::Audio, 2 as DeviceId)
+        .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    let active_call = context.active_call();
+    assert_eq!(active_call.call_id(), call_id);
+    assert_eq!(context.start_outgoing_count(), 1);
+
+    cm.proceed(
+        call_id,
+        format!("CONTEXT-{}", context.prng.gen::<u16>()).to_owned(),
+        BandwidthMode::Normal,
+    )
+    .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    assert_eq!(context.offers_sent(), 1);
+    assert_eq!(context.transfer_offers_sent(), 1);
+
+    cm.received_answer(call_id, random_received_answer(&context.prng, 1))
+        .expect(error_line!());
+    cm.received_ice(call_id, random_received_ice_candidate(&context.prng))
+        .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    let mut active_connection = context.active_connection();
+    active_connection
+        .inject_ice_connected()
+        .expect(error_line!());
+    active_connection
+        .inject_received_incoming_media(MediaStream::new(ptr::null()))
+        .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    assert_eq!(context.event_count(ApplicationEvent::RemoteRinging), 0);
+
+    active_connection
+        .inject_received_accepted_via_data_channel(call_id)
+        .expect(error_line!());
+    cm.synchronize().expect(error_line!());
+
+    assert_eq!(
+        active_call.state().expect(error_line!()),
+        CallState::ConnectedAndAccepted
+    );
+    assert_eq!(context.event_count(ApplicationEvent::RemoteAccepted), 1);
+    assert_eq!(context.accepted_hangups_sent(), 0);
+    assert_eq!(context.error_count(), 0);
+    assert_eq!(context.ended_count(), 0);
+}